@@ -22,6 +22,7 @@ enum SupportedModules {
     Elf,
     Pe,
     Dotnet,
+    Dex,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -100,6 +101,9 @@ pub fn exec_dump(args: &ArgMatches) -> anyhow::Result<()> {
         // those that weren't explicitly asked for.
         let requested_modules: Vec<_> = modules.collect();
 
+        if !requested_modules.contains(&&SupportedModules::Dex) {
+            module_output.dex = MessageField::none()
+        }
         if !requested_modules.contains(&&SupportedModules::Dotnet) {
             module_output.dotnet = MessageField::none()
         }
@@ -118,6 +122,9 @@ pub fn exec_dump(args: &ArgMatches) -> anyhow::Result<()> {
     } else {
         // Module was not specified, only show those that produced meaningful
         // results, the rest are cleared out.
+        if !module_output.dex.is_dex() {
+            module_output.dex = MessageField::none()
+        }
         if !module_output.dotnet.is_dotnet() {
             module_output.dotnet = MessageField::none()
         }
@@ -365,6 +365,7 @@ impl<'a> Token<'a> {
             | GrammarRule::k_IEQUALS
             | GrammarRule::k_IMPORT
             | GrammarRule::k_IN
+            | GrammarRule::k_INCLUDE
             | GrammarRule::k_ISTARTSWITH
             | GrammarRule::k_MATCHES
             | GrammarRule::k_META
@@ -380,6 +381,7 @@ impl<'a> Token<'a> {
             | GrammarRule::k_THEM
             | GrammarRule::k_TRUE
             | GrammarRule::k_WIDE
+            | GrammarRule::k_WITH
             | GrammarRule::k_XOR => Token::Keyword(src),
             // Punctuation.
             GrammarRule::ASTERISK
@@ -67,6 +67,7 @@ use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec, ToSmallVec};
 
 pub(crate) use crate::compiler::atoms::mask::ByteMaskCombinator;
+pub(crate) use crate::compiler::atoms::quality::atom_quality;
 pub(crate) use crate::compiler::atoms::quality::best_atom_in_bytes;
 pub(crate) use crate::compiler::atoms::quality::best_range_in_bytes;
 pub(crate) use crate::compiler::atoms::quality::best_range_in_masked_bytes;
@@ -1,6 +1,6 @@
 use itertools::Itertools;
 use std::mem::size_of;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use yara_x_parser::report::ReportBuilder;
 
@@ -24,7 +24,7 @@ pub(in crate::compiler) struct CompileContext<'a, 'src, 'sym> {
     /// Symbol table for the currently active type. When this contains some
     /// value, symbols are looked up in this table, and the main symbol table
     /// (i.e: `symbol_table`) is ignored.
-    pub current_symbol_table: Option<Rc<dyn SymbolLookup + 'a>>,
+    pub current_symbol_table: Option<Arc<dyn SymbolLookup + 'a>>,
 
     /// Information about the rules compiled so far.
     pub rules: &'a Vec<RuleInfo>,
@@ -33,6 +33,10 @@ pub(in crate::compiler) struct CompileContext<'a, 'src, 'sym> {
     /// in the current rule.
     pub current_rule_patterns: &'a mut Vec<ir::PatternInRule<'src>>,
 
+    /// Reference to a vector where the IDs of the rules referenced by the
+    /// current rule's condition are stored.
+    pub current_rule_dependencies: &'a mut Vec<RuleId>,
+
     /// Warnings generated during the compilation.
     pub warnings: &'a mut Warnings,
 
@@ -45,6 +49,10 @@ pub(in crate::compiler) struct CompileContext<'a, 'src, 'sym> {
 
     /// Allow invalid escape sequences in regular expressions.
     pub relaxed_re_syntax: bool,
+
+    /// Reduce expressions whose value is known at compile time to their
+    /// final value (see [`crate::OptLevel`]).
+    pub constant_folding: bool,
 }
 
 impl<'a, 'src, 'sym> CompileContext<'a, 'src, 'sym> {
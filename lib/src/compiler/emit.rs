@@ -7,7 +7,7 @@ expressions or language constructs.
  */
 
 use std::mem::size_of;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use bstr::ByteSlice;
 use itertools::Itertools;
@@ -22,7 +22,7 @@ use walrus::{FunctionId, InstrSeqBuilder, ValType};
 use crate::compiler::context::VarStack;
 use crate::compiler::ir::{
     Expr, ForIn, ForOf, Iterable, MatchAnchor, Of, OfItems, PatternIdx,
-    Quantifier,
+    Quantifier, With,
 };
 use crate::compiler::{
     LiteralId, PatternId, RegexpId, RuleId, RuleInfo, Var, VarStackFrame,
@@ -125,7 +125,10 @@ macro_rules! emit_shift_op {
                 // 1 << 64 == 0, but in WebAssembly 1 << 64 == 1.
                 // In general, X << Y behaves as X << (Y mod 64) in
                 // WebAssembly, while in YARA the result is always 0 for
-                // every Y >= 64. The sames applies for X >> Y.
+                // every Y >= 64. The sames applies for X >> Y. Negative
+                // values of Y, which can't be known at compile time, also
+                // produce 0, which is achieved by comparing Y with 64 as
+                // an unsigned integer.
                 //
                 // For that reason shift operations require some additional
                 // code. The code for shift-left goes like this:
@@ -137,7 +140,7 @@ macro_rules! emit_shift_op {
                 //  push 0
                 //  push rhs (from tmp)
                 //  push 64
-                //  is rhs less than 64?
+                //  is rhs less than 64? (unsigned comparison)
                 //  if true                               ┐
                 //     push result form shift operation   │  select
                 //  else                                  │
@@ -148,7 +151,7 @@ macro_rules! emit_shift_op {
                 $instr.i64_const(0);
                 $instr.local_get($ctx.wasm_symbols.i64_tmp);
                 $instr.i64_const(64);
-                $instr.binop(BinaryOp::I64LtS);
+                $instr.binop(BinaryOp::I64LtU);
                 $instr.select(Some(I64));
             }
             _ => unreachable!(),
@@ -261,65 +264,112 @@ pub(super) fn emit_rule_condition(
         builder.new_rule()
     };
 
-    // When the "logging" feature is enabled, print a log before the starting
-    // evaluating the rule's condition. In case of error during the evaluation
-    // this helps in determining the rule causing the issue.
-    #[cfg(feature = "logging")]
-    {
-        instr.i32_const(rule_id.0);
-        instr.call(
-            ctx.function_id(wasm::export__log_rule_eval_start.mangled_name),
-        );
-    }
-
-    // Emit WASM code for the rule's condition.
-    catch_undef(
-        ctx,
-        I32,
-        &mut instr,
-        |ctx, instr| {
-            emit_bool_expr(ctx, instr, condition);
-        },
-        |_, instr| {
-            instr.i32_const(0);
+    // Check if the rule was disabled with `Scanner::disable_rule` or any of
+    // its variants. Disabled rules are skipped entirely, their conditions are
+    // not evaluated. The bitmap of disabled rules has one bit per rule, the
+    // N-th bit is set if rule with RuleId = N is disabled.
+    instr.global_get(ctx.wasm_symbols.disabled_rules_bitmap_base);
+    instr.load(
+        ctx.wasm_symbols.main_memory,
+        LoadKind::I32_8 { kind: ZeroExtend },
+        MemArg {
+            align: size_of::<i8>() as u32,
+            offset: (rule_id.0 / 8) as u32,
         },
     );
-
-    // Check if the result from the condition is zero (false).
+    instr.i32_const(1 << (rule_id.0 % 8));
+    instr.binop(BinaryOp::I32And);
     instr.unop(UnaryOp::I32Eqz);
     instr.if_else(
         None,
-        |then_| {
-            // The condition is false. For normal rules we don't do anything,
-            // but for global rules we must call `global_rule_no_match` and
-            // return 1.
-            //
-            // By returning 1 the function that contains the logic for this
-            // rule exits immediately, preventing any other rule (both global
-            // and non-global) in the same namespace is executed, and therefore
-            // they will remain false.
-            //
-            // This guarantees that any global rule that returns false, forces
-            // the non-global rules in the same namespace to be false. There
-            // may be some global rules that matched before, though. The
-            // purpose of `global_rule_no_match` is reverting those previous
-            // matches.
-            if ctx.current_rule.is_global {
-                // Call `global_rule_no_match`.
-                then_.i32_const(rule_id.0);
-                then_.call(ctx.function_id(
-                    wasm::export__global_rule_no_match.mangled_name,
+        |enabled| {
+            // When the "logging" feature is enabled, print a log before the
+            // starting evaluating the rule's condition. In case of error
+            // during the evaluation this helps in determining the rule
+            // causing the issue.
+            #[cfg(feature = "logging")]
+            {
+                enabled.i32_const(rule_id.0);
+                enabled.call(ctx.function_id(
+                    wasm::export__log_rule_eval_start.mangled_name,
                 ));
-                // Return 1.
-                then_.i32_const(1);
-                then_.return_();
             }
+
+            // When the "rules-profiling" feature is enabled, measure the
+            // time spent evaluating the rule's condition.
+            #[cfg(feature = "rules-profiling")]
+            {
+                enabled.i32_const(rule_id.0);
+                enabled.call(
+                    ctx.function_id(
+                        wasm::export__rule_eval_start.mangled_name,
+                    ),
+                );
+            }
+
+            // Emit WASM code for the rule's condition.
+            catch_undef(
+                ctx,
+                I32,
+                enabled,
+                |ctx, instr| {
+                    emit_bool_expr(ctx, instr, condition);
+                },
+                |_, instr| {
+                    instr.i32_const(0);
+                },
+            );
+
+            #[cfg(feature = "rules-profiling")]
+            {
+                enabled.i32_const(rule_id.0);
+                enabled.call(
+                    ctx.function_id(wasm::export__rule_eval_end.mangled_name),
+                );
+            }
+
+            // Check if the result from the condition is zero (false).
+            enabled.unop(UnaryOp::I32Eqz);
+            enabled.if_else(
+                None,
+                |then_| {
+                    // The condition is false. For normal rules we don't do
+                    // anything, but for global rules we must call
+                    // `global_rule_no_match` and return 1.
+                    //
+                    // By returning 1 the function that contains the logic
+                    // for this rule exits immediately, preventing any other
+                    // rule (both global and non-global) in the same
+                    // namespace is executed, and therefore they will remain
+                    // false.
+                    //
+                    // This guarantees that any global rule that returns
+                    // false, forces the non-global rules in the same
+                    // namespace to be false. There may be some global rules
+                    // that matched before, though. The purpose of
+                    // `global_rule_no_match` is reverting those previous
+                    // matches.
+                    if ctx.current_rule.is_global {
+                        // Call `global_rule_no_match`.
+                        then_.i32_const(rule_id.0);
+                        then_.call(ctx.function_id(
+                            wasm::export__global_rule_no_match.mangled_name,
+                        ));
+                        // Return 1.
+                        then_.i32_const(1);
+                        then_.return_();
+                    }
+                },
+                |else_| {
+                    // The condition is true, call `rule_match`.
+                    else_.i32_const(rule_id.0);
+                    else_.call(
+                        ctx.function_id(wasm::export__rule_match.mangled_name),
+                    );
+                },
+            );
         },
-        |else_| {
-            // The condition is true, call `rule_match`.
-            else_.i32_const(rule_id.0);
-            else_.call(ctx.function_id(wasm::export__rule_match.mangled_name));
-        },
+        |_| {},
     );
 }
 
@@ -679,6 +729,10 @@ fn emit_expr(
             }
         },
 
+        Expr::With(with) => {
+            emit_with(ctx, instr, with);
+        }
+
         Expr::FuncCall(fn_call) => {
             // Emit the arguments first.
             for expr in fn_call.args.iter_mut() {
@@ -918,11 +972,65 @@ fn emit_div(
             // In integer division make sure that the divisor is not
             // zero, if that's the case the result is undefined.
             throw_undef_if_zero(ctx, instr);
-            instr.binop(BinaryOp::I64DivS);
+            emit_i64_div(ctx, instr);
         }
     }
 }
 
+/// Emits the code for a signed integer division where the divisor is known
+/// to be non-zero.
+///
+/// The only other case where `i64.div_s` traps is `i64::MIN / -1`, because
+/// the result doesn't fit in an `i64`. In that case the result is undefined,
+/// like in libyara. This is achieved by dividing by 1 instead of -1, and
+/// negating the result afterwards, which is `i64::MIN` only if the dividend
+/// was `i64::MIN`.
+fn emit_i64_div(ctx: &mut EmitContext, instr: &mut InstrSeqBuilder) {
+    let tmp = ctx.wasm_symbols.i64_tmp;
+    let is_minus_one = ctx.wasm_symbols.i32_tmp;
+
+    // Take the divisor from the stack, and check if it is -1.
+    instr.local_set(tmp);
+    instr.local_get(tmp);
+    instr.i64_const(-1);
+    instr.binop(BinaryOp::I64Eq);
+    instr.local_set(is_minus_one);
+
+    // Put back the divisor into the stack, or 1 if the divisor was -1.
+    instr.i64_const(1);
+    instr.local_get(tmp);
+    instr.local_get(is_minus_one);
+    instr.select(Some(I64));
+    instr.binop(BinaryOp::I64DivS);
+
+    instr.local_set(tmp);
+    instr.local_get(is_minus_one);
+    instr.if_else(
+        I64,
+        |then| {
+            // The divisor was -1, negate the result.
+            then.i64_const(0);
+            then.local_get(tmp);
+            then.binop(BinaryOp::I64Sub);
+            then.local_tee(tmp);
+            then.i64_const(i64::MIN);
+            then.binop(BinaryOp::I64Eq);
+            then.if_else(
+                I64,
+                |overflow| {
+                    throw_undef(ctx, overflow);
+                },
+                |no_overflow| {
+                    no_overflow.local_get(tmp);
+                },
+            );
+        },
+        |else_| {
+            else_.local_get(tmp);
+        },
+    );
+}
+
 /// Emits the code for `mod` operations.
 fn emit_mod(
     ctx: &mut EmitContext,
@@ -1299,7 +1407,7 @@ fn emit_check_for_pattern_match(
 fn emit_array_indexing(
     ctx: &mut EmitContext,
     instr: &mut InstrSeqBuilder,
-    array: &Rc<Array>,
+    array: &Arc<Array>,
 ) {
     let func = match array.as_ref() {
         Array::Integers(_) => &wasm::export__array_indexing_integer,
@@ -1328,7 +1436,7 @@ fn emit_array_indexing(
 fn emit_map_lookup_by_index(
     ctx: &mut EmitContext,
     instr: &mut InstrSeqBuilder,
-    map: &Rc<Map>,
+    map: &Arc<Map>,
 ) {
     let func = match map.as_ref() {
         Map::IntegerKeys { deputy, .. } => {
@@ -1386,7 +1494,7 @@ fn emit_map_lookup_by_index(
 fn emit_map_lookup(
     ctx: &mut EmitContext,
     instr: &mut InstrSeqBuilder,
-    map: &Rc<Map>,
+    map: &Arc<Map>,
 ) {
     match map.as_ref() {
         Map::IntegerKeys { deputy, .. } => {
@@ -1891,6 +1999,37 @@ fn emit_for_in_expr_tuple(
     );
 }
 
+/// Emits the code for a `with` statement.
+///
+/// Each declaration is evaluated in order and its result is stored in the
+/// corresponding variable, then the condition is evaluated. If the expression
+/// in a declaration is undefined, the variable is flagged as undefined, and
+/// any attempt to use it in the condition will produce an undefined result.
+fn emit_with(
+    ctx: &mut EmitContext,
+    instr: &mut InstrSeqBuilder,
+    with: &mut With,
+) {
+    for (var, expr) in with.declarations.iter_mut() {
+        let var = *var;
+        catch_undef(
+            ctx,
+            None,
+            instr,
+            |ctx, instr| {
+                set_var(ctx, instr, var, |ctx, instr| {
+                    emit_expr(ctx, instr, expr);
+                });
+            },
+            move |ctx, instr| {
+                set_var_undef(ctx, instr, var, true);
+            },
+        );
+    }
+
+    emit_bool_expr(ctx, instr, &mut with.condition);
+}
+
 /// Emits a `for` loop.
 ///
 /// This function allows creating different types of `for` loops by receiving
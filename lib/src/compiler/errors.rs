@@ -9,6 +9,7 @@ use yara_x_parser::ast::Span;
 use yara_x_parser::report::Level;
 use yara_x_parser::report::ReportBuilder;
 use yara_x_parser::Error as ParseError;
+use yara_x_parser::Warning;
 
 /// Errors returned while serializing/deserializing compiled rules.
 #[derive(Error, Debug)]
@@ -16,6 +17,16 @@ pub enum SerializationError {
     #[error("not a YARA-X compiled rules file")]
     InvalidFormat,
 
+    /// The rules were serialized with a version of the serialization
+    /// format that is not supported by this version of YARA-X.
+    #[error("unsupported format version {version}, expected {expected}")]
+    UnsupportedVersion { version: u32, expected: u32 },
+
+    /// The checksum of the serialized rules doesn't match their content,
+    /// which means that the data is corrupt.
+    #[error("checksum mismatch, the compiled rules are corrupt")]
+    ChecksumMismatch,
+
     #[error("invalid YARA-X compiled rules file")]
     InvalidEncoding(#[from] bincode::Error),
 
@@ -40,6 +51,11 @@ pub enum Error {
 
     #[error(transparent)]
     VariableError(#[from] VariableError),
+
+    /// A warning raised while [`crate::Compiler::warnings_as_errors`] is
+    /// enabled.
+    #[error(transparent)]
+    WarningAsError(Box<Warning>),
 }
 
 /// An error occurred during the compilation process.
@@ -120,6 +136,10 @@ pub enum CompileError {
     #[label("module `{identifier}` not found", span)]
     UnknownModule { detailed_report: String, identifier: String, span: Span },
 
+    #[error("module `{identifier}` can't be used")]
+    #[label("module `{identifier}` imported here", span)]
+    BannedModule { detailed_report: String, identifier: String, span: Span },
+
     #[error("invalid range")]
     #[label("higher bound must be greater or equal than lower bound", span)]
     InvalidRange { detailed_report: String, span: Span },
@@ -203,6 +223,27 @@ pub enum CompileError {
         note: Option<String>,
     },
 
+    #[error("can not include `{file_name}`")]
+    #[label("{error}", span)]
+    IncludeError {
+        detailed_report: String,
+        file_name: String,
+        error: String,
+        span: Span,
+    },
+
+    #[error("circular include")]
+    #[label("`{file_name}` is already being included", span)]
+    CircularInclude { detailed_report: String, file_name: String, span: Span },
+
+    #[error("too many nested includes")]
+    #[label("the maximum include depth is {max_depth}", span)]
+    IncludeDepthExceeded {
+        detailed_report: String,
+        max_depth: usize,
+        span: Span,
+    },
+
     #[error("`entrypoint` is unsupported`")]
     #[label("the `entrypoint` keyword is not supported anymore", span)]
     #[note(note)]
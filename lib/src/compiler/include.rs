@@ -0,0 +1,112 @@
+/*! Resolution of `include` statements.
+
+When the compiler finds an `include "file.yar"` statement it asks an
+[`IncludeResolver`] for the source code of the included file. The default
+resolver is [`FilesystemIncludeResolver`], which reads files from disk,
+while [`MemoryIncludeResolver`] serves files from memory, which is useful
+when rules are embedded in an application.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Trait implemented by types that resolve `include` statements.
+pub trait IncludeResolver {
+    /// Returns the source code for the file included with
+    /// `include "<file_name>"`.
+    ///
+    /// `parent` is the origin of the source code that contains the `include`
+    /// statement (see [`crate::SourceCode::with_origin`]), if any. This
+    /// allows resolving file names relative to the including file.
+    ///
+    /// The result is a tuple `(origin, source_code)`, where `origin` is a
+    /// string that uniquely identifies the included file, like its canonical
+    /// path. The origin is used for detecting circular includes, and appears
+    /// in error messages referring to the included file.
+    fn resolve(
+        &self,
+        file_name: &str,
+        parent: Option<&str>,
+    ) -> io::Result<(String, String)>;
+}
+
+/// An [`IncludeResolver`] that reads included files from the filesystem.
+///
+/// Relative paths are resolved relative to the directory of the including
+/// file, or relative to the current directory if the including source code
+/// doesn't have an origin.
+#[derive(Debug, Default)]
+pub struct FilesystemIncludeResolver;
+
+impl IncludeResolver for FilesystemIncludeResolver {
+    fn resolve(
+        &self,
+        file_name: &str,
+        parent: Option<&str>,
+    ) -> io::Result<(String, String)> {
+        let path = match parent.and_then(|parent| Path::new(parent).parent()) {
+            Some(dir) => dir.join(file_name),
+            None => Path::new(file_name).to_path_buf(),
+        };
+
+        let path = fs::canonicalize(path)?;
+        let source = fs::read_to_string(&path)?;
+
+        Ok((path.to_string_lossy().into_owned(), source))
+    }
+}
+
+/// An [`IncludeResolver`] that serves included files from memory.
+///
+/// # Example
+///
+/// ```rust
+/// # use yara_x::{Compiler, MemoryIncludeResolver};
+/// let resolver = MemoryIncludeResolver::new()
+///     .add_file("common.yar", "rule common { condition: true }");
+///
+/// let mut compiler = Compiler::new();
+///
+/// compiler
+///     .include_resolver(resolver)
+///     .add_source(r#"
+///         include "common.yar"
+///         rule test { condition: common }"#)?;
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryIncludeResolver {
+    files: HashMap<String, String>,
+}
+
+impl MemoryIncludeResolver {
+    /// Creates a new resolver with no files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with the given name and content.
+    pub fn add_file(mut self, file_name: &str, source: &str) -> Self {
+        self.files.insert(file_name.to_owned(), source.to_owned());
+        self
+    }
+}
+
+impl IncludeResolver for MemoryIncludeResolver {
+    fn resolve(
+        &self,
+        file_name: &str,
+        _parent: Option<&str>,
+    ) -> io::Result<(String, String)> {
+        match self.files.get(file_name) {
+            Some(source) => Ok((file_name.to_owned(), source.clone())),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("file `{}` not found", file_name),
+            )),
+        }
+    }
+}
@@ -15,7 +15,7 @@ use crate::compiler::ir::hex2hir::hex_pattern_hir_from_ast;
 use crate::compiler::ir::{
     Expr, ForIn, ForOf, FuncCall, Iterable, LiteralPattern, Lookup,
     MatchAnchor, Of, OfItems, Pattern, PatternFlagSet, PatternFlags,
-    PatternIdx, PatternInRule, Quantifier, Range, RegexpPattern,
+    PatternIdx, PatternInRule, Quantifier, Range, RegexpPattern, With,
 };
 use crate::compiler::{CompileContext, CompileError};
 use crate::modules::BUILTIN_MODULES;
@@ -193,15 +193,17 @@ pub(in crate::compiler) fn regexp_pattern_from_ast<'src>(
     // matches that start at the same offset are found while scanning backwards
     // (right-to-left). However, if the regexp contains a mix of greedy and
     // non-greedy repetitions the decision becomes impossible.
-    let hir = re::parser::Parser::new()
+    let (hir, re_warnings) = re::parser::Parser::new()
         .force_case_insensitive(flags.contains(PatternFlags::Nocase))
         .allow_mixed_greediness(false)
         .relaxed_re_syntax(ctx.relaxed_re_syntax)
-        .parse(&pattern.regexp)
+        .parse_with_warnings(&pattern.regexp)
         .map_err(|err| {
             re_error_to_compile_error(ctx.report_builder, &pattern.regexp, err)
         })?;
 
+    warn_relaxed_regexp(ctx, &pattern.regexp, re_warnings);
+
     // TODO: raise warning when .* used, propose using the non-greedy
     // variant .*?
 
@@ -248,12 +250,14 @@ pub(in crate::compiler) fn expr_from_ast(
             TypeValue::const_string_from(literal.value.as_bytes()))),
 
         ast::Expr::Regexp(regexp) => {
-            re::parser::Parser::new()
+            let (_, re_warnings) = re::parser::Parser::new()
                 .relaxed_re_syntax(ctx.relaxed_re_syntax)
-                .parse(regexp.as_ref())
+                .parse_with_warnings(regexp.as_ref())
                 .map_err(|err| { re_error_to_compile_error(ctx.report_builder, regexp, err)
             })?;
 
+            warn_relaxed_regexp(ctx, regexp, re_warnings);
+
             Ok(Expr::Const(TypeValue::Regexp(Some(Regexp::new(
                     regexp.literal,
                 ))),
@@ -305,6 +309,7 @@ pub(in crate::compiler) fn expr_from_ast(
         ast::Expr::Of(of) => of_expr_from_ast(ctx, of),
         ast::Expr::ForOf(for_of) => for_of_expr_from_ast(ctx, for_of),
         ast::Expr::ForIn(for_in) => for_in_expr_from_ast(ctx, for_in),
+        ast::Expr::With(with) => with_expr_from_ast(ctx, with),
         ast::Expr::FuncCall(fn_call) => func_call_from_ast(ctx, fn_call),
 
         ast::Expr::FieldAccess(expr) => {
@@ -333,11 +338,12 @@ pub(in crate::compiler) fn expr_from_ast(
 
             // If the last operand is constant, the whole expression is
             // constant.
-            #[cfg(feature = "constant-folding")]
-            if let Expr::Const(type_value) = last_operand {
-                // A constant always have a defined value.
-                assert!(type_value.is_const());
-                return Ok(Expr::Const(type_value));
+            if ctx.constant_folding {
+                if let Expr::Const(type_value) = last_operand {
+                    // A constant always have a defined value.
+                    assert!(type_value.is_const());
+                    return Ok(Expr::Const(type_value));
+                }
             }
 
             operands.push(last_operand);
@@ -395,6 +401,7 @@ pub(in crate::compiler) fn expr_from_ast(
             // A global rule can depend on another global rule. And non-global
             // rules can depend both on global rules and non-global ones.
             if let SymbolKind::Rule(rule_id) = symbol.kind() {
+                ctx.current_rule_dependencies.push(*rule_id);
                 let current_rule = ctx.get_current_rule();
                 let used_rule = ctx.get_rule(*rule_id);
                 if current_rule.is_global && !used_rule.is_global {
@@ -413,8 +420,7 @@ pub(in crate::compiler) fn expr_from_ast(
                 }
             }
 
-            #[cfg(feature = "constant-folding")]
-            {
+            if ctx.constant_folding {
                 let type_value = symbol.type_value();
                 if type_value.is_const() {
                     return Ok(Expr::Const(type_value.clone()));
@@ -879,6 +885,65 @@ fn for_in_expr_from_ast(
     })))
 }
 
+fn with_expr_from_ast(
+    ctx: &mut CompileContext,
+    with: &ast::With,
+) -> Result<Expr, Box<CompileError>> {
+    // Create stack frame with capacity for the declared variables.
+    let mut stack_frame = ctx.vars.new_frame(with.declarations.len() as i32);
+
+    let mut declarations = Vec::with_capacity(with.declarations.len());
+
+    // Each variable is put into scope right after its declaration, so that
+    // it can be used in the declarations that follow, but not in its own.
+    for declaration in with.declarations.iter() {
+        let span = declaration.expression.span();
+        let expr = expr_from_ast(ctx, &declaration.expression)?;
+
+        check_type(
+            ctx,
+            expr.ty(),
+            span,
+            &[
+                Type::Integer,
+                Type::Float,
+                Type::Bool,
+                Type::String,
+                Type::Struct,
+                Type::Array,
+                Type::Map,
+            ],
+        )?;
+
+        // The actual value for the variable is not known until the
+        // expression is evaluated at scan time, that's why we use
+        // `clone_without_value`.
+        let type_value = expr.type_value().clone_without_value();
+        let var = stack_frame.new_var(type_value.ty());
+
+        let mut symbols = SymbolTable::new();
+
+        symbols.insert(
+            declaration.identifier.name,
+            Symbol::new(type_value, SymbolKind::Var(var)),
+        );
+
+        ctx.symbol_table.push(Rc::new(symbols));
+        declarations.push((var, expr));
+    }
+
+    let condition = bool_expr_from_ast(ctx, &with.condition)?;
+
+    // Leaving the condition's scope. Remove the declared variables.
+    for _ in 0..declarations.len() {
+        ctx.symbol_table.pop();
+    }
+
+    ctx.vars.unwind(&stack_frame);
+
+    Ok(Expr::With(Box::new(With { declarations, condition })))
+}
+
 fn iterable_from_ast(
     ctx: &mut CompileContext,
     iter: &ast::Iterable,
@@ -1211,7 +1276,7 @@ fn matches_expr_from_ast(
 
     let expr = Expr::Matches { lhs, rhs };
 
-    if cfg!(feature = "constant-folding") {
+    if ctx.constant_folding {
         expr.fold(ctx, span)
     } else {
         Ok(expr)
@@ -1308,6 +1373,27 @@ fn re_error_to_compile_error(
     }
 }
 
+/// Produce a warning for each syntax error in `regexp` that was tolerated
+/// because of the relaxed regexp syntax.
+fn warn_relaxed_regexp(
+    ctx: &mut CompileContext,
+    regexp: &ast::Regexp,
+    errors: Vec<re::parser::Error>,
+) {
+    for err in errors {
+        if let Error::SyntaxError { msg, span, note } = err {
+            ctx.warnings.add(|| {
+                Warning::relaxed_regexp(
+                    ctx.report_builder,
+                    msg.clone(),
+                    regexp.span.subspan(span.start.offset, span.end.offset),
+                    note.clone(),
+                )
+            });
+        }
+    }
+}
+
 /// Produce a warning if the expression is not boolean.
 pub(in crate::compiler) fn warn_if_not_bool(
     ctx: &mut CompileContext,
@@ -1367,7 +1453,7 @@ macro_rules! gen_unary_op {
 
             let expr = Expr::$variant { operand };
 
-            if cfg!(feature = "constant-folding") {
+            if ctx.constant_folding {
                 expr.fold(ctx, span)
             } else {
                 Ok(expr)
@@ -1409,7 +1495,7 @@ macro_rules! gen_binary_op {
 
             let expr = Expr::$variant { lhs, rhs };
 
-            if cfg!(feature = "constant-folding") {
+            if ctx.constant_folding {
                 expr.fold(ctx, span)
             } else {
                 Ok(expr)
@@ -1443,7 +1529,7 @@ macro_rules! gen_string_op {
 
             let expr = Expr::$variant { lhs, rhs };
 
-            if cfg!(feature = "constant-folding") {
+            if ctx.constant_folding {
                 expr.fold(ctx, span)
             } else {
                 Ok(expr)
@@ -1512,7 +1598,7 @@ macro_rules! gen_n_ary_operation {
 
             let expr = Expr::$variant { operands: operands_hir };
 
-            if cfg!(feature = "constant-folding") {
+            if ctx.constant_folding {
                 expr.fold(ctx, span)
             } else {
                 Ok(expr)
@@ -29,6 +29,7 @@ allows using the same regexp engine for matching both types of patterns.
 [Hir]: regex_syntax::hir::Hir
 */
 
+use std::cmp::Ordering;
 use std::hash::Hash;
 use std::ops::RangeInclusive;
 
@@ -521,6 +522,9 @@ pub(in crate::compiler) enum Expr {
     /// A `for <quantifier> <vars> in ...` expression. (e.g. `for all i in (1..100) : ( ... )`)
     ForIn(Box<ForIn>),
 
+    /// A `with <declarations> : ...` expression. (e.g. `with foo = 1 + 1 : ( ... )`)
+    With(Box<With>),
+
     /// Array or dictionary lookup expression (e.g. `array[1]`, `dict["key"]`)
     Lookup(Box<Lookup>),
 }
@@ -579,6 +583,15 @@ pub(in crate::compiler) struct ForIn {
     pub stack_frame: VarStackFrame,
 }
 
+/// A `with` expression (e.g `with foo = 1 + 1 : (foo == 2)`)
+#[derive(Debug)]
+pub(in crate::compiler) struct With {
+    /// Each declaration is a variable and the expression that produces its
+    /// value. Declarations are evaluated in order before the condition.
+    pub declarations: Vec<(Var, Expr)>,
+    pub condition: Expr,
+}
+
 /// A quantifier used in `for` and `of` expressions.
 #[derive(Debug)]
 pub(in crate::compiler) enum Quantifier {
@@ -669,7 +682,8 @@ impl Expr {
             | Expr::PatternMatchVar { .. }
             | Expr::Of(_)
             | Expr::ForOf(_)
-            | Expr::ForIn(_) => Type::Bool,
+            | Expr::ForIn(_)
+            | Expr::With(_) => Type::Bool,
 
             Expr::Minus { operand, .. } => match operand.ty() {
                 Type::Integer => Type::Integer,
@@ -738,7 +752,8 @@ impl Expr {
             | Expr::PatternMatchVar { .. }
             | Expr::Of(_)
             | Expr::ForOf(_)
-            | Expr::ForIn(_) => TypeValue::Bool(Value::Unknown),
+            | Expr::ForIn(_)
+            | Expr::With(_) => TypeValue::Bool(Value::Unknown),
 
             Expr::Minus { operand, .. } => match operand.ty() {
                 Type::Integer => TypeValue::Integer(Value::Unknown),
@@ -782,6 +797,45 @@ impl Expr {
         }
     }
 
+    /// If this expression is true as soon as any of the patterns in some
+    /// set matches, no matter what else is found in the scanned data,
+    /// returns that set of patterns. This is the case of expressions like
+    /// `$a`, `$a or $b`, `any of them` or `1 of ($a, $b)`. Returns `None`
+    /// for any other expression.
+    pub fn sufficient_patterns(&self) -> Option<Vec<PatternIdx>> {
+        match self {
+            Expr::PatternMatch { pattern, anchor: MatchAnchor::None } => {
+                Some(vec![*pattern])
+            }
+            Expr::Or { operands } => {
+                let mut patterns = Vec::new();
+                for operand in operands {
+                    patterns.extend(operand.sufficient_patterns()?);
+                }
+                Some(patterns)
+            }
+            Expr::Of(of) => {
+                let is_any = match &of.quantifier {
+                    Quantifier::Any => true,
+                    Quantifier::Expr(expr) => {
+                        let value = expr.type_value();
+                        value.is_const() && value.try_as_integer() == Some(1)
+                    }
+                    _ => false,
+                };
+                match (&of.items, &of.anchor) {
+                    (OfItems::PatternSet(patterns), MatchAnchor::None)
+                        if is_any =>
+                    {
+                        Some(patterns.clone())
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     pub fn fold(
         self,
         ctx: &mut CompileContext,
@@ -811,6 +865,13 @@ impl Expr {
                     return Ok(Expr::Const(TypeValue::const_bool_from(false)));
                 }
 
+                // Comparisons between `filesize` and constants that can't be
+                // satisfied at the same time, like in `filesize < 10 and
+                // filesize > 100`, also make the result false.
+                if !Self::filesize_range_is_satisfiable(&operands) {
+                    return Ok(Expr::Const(TypeValue::const_bool_from(false)));
+                }
+
                 Ok(Expr::And { operands })
             }
             Expr::Or { mut operands } => {
@@ -845,7 +906,13 @@ impl Expr {
                     return Ok(Expr::Add { operands });
                 }
 
-                Self::fold_arithmetic(ctx, span, operands, |acc, x| acc + x)
+                Self::fold_arithmetic(
+                    ctx,
+                    span,
+                    operands,
+                    |acc, x| acc + x,
+                    |acc, x| acc.checked_add(x),
+                )
             }
             Expr::Sub { operands } => {
                 // If not all operands are constant, there's nothing to fold.
@@ -853,7 +920,13 @@ impl Expr {
                     return Ok(Expr::Sub { operands });
                 }
 
-                Self::fold_arithmetic(ctx, span, operands, |acc, x| acc - x)
+                Self::fold_arithmetic(
+                    ctx,
+                    span,
+                    operands,
+                    |acc, x| acc - x,
+                    |acc, x| acc.checked_sub(x),
+                )
             }
             Expr::Mul { operands } => {
                 // If not all operands are constant, there's nothing to fold.
@@ -861,51 +934,137 @@ impl Expr {
                     return Ok(Expr::Mul { operands });
                 }
 
-                Self::fold_arithmetic(ctx, span, operands, |acc, x| acc * x)
+                Self::fold_arithmetic(
+                    ctx,
+                    span,
+                    operands,
+                    |acc, x| acc * x,
+                    |acc, x| acc.checked_mul(x),
+                )
             }
             _ => Ok(self),
         }
     }
 
-    pub fn fold_arithmetic<F>(
+    /// Given the operands of an `and` expression, returns false if the
+    /// comparisons between `filesize` and integer constants among them
+    /// restrict `filesize` to an empty range.
+    fn filesize_range_is_satisfiable(operands: &[Expr]) -> bool {
+        // `filesize` is never negative.
+        let mut min = 0_i64;
+        let mut max = i64::MAX;
+
+        for operand in operands {
+            let (lhs, rhs, op) = match operand {
+                Expr::Eq { lhs, rhs } => (lhs, rhs, Ordering::Equal),
+                Expr::Lt { lhs, rhs } | Expr::Le { lhs, rhs } => {
+                    (lhs, rhs, Ordering::Less)
+                }
+                Expr::Gt { lhs, rhs } | Expr::Ge { lhs, rhs } => {
+                    (lhs, rhs, Ordering::Greater)
+                }
+                _ => continue,
+            };
+
+            let inclusive = matches!(
+                operand,
+                Expr::Eq { .. } | Expr::Le { .. } | Expr::Ge { .. }
+            );
+
+            // Put the comparison in the form `filesize <op> value`.
+            let (op, value) = match (lhs.as_ref(), rhs.as_ref()) {
+                (Expr::Filesize, other) => (op, other.type_value()),
+                (other, Expr::Filesize) => (op.reverse(), other.type_value()),
+                _ => continue,
+            };
+
+            let value = match value {
+                TypeValue::Integer(Value::Const(value)) => value,
+                _ => continue,
+            };
+
+            match (op, inclusive) {
+                (Ordering::Equal, _) => {
+                    min = min.max(value);
+                    max = max.min(value);
+                }
+                (Ordering::Less, true) => max = max.min(value),
+                (Ordering::Less, false) => {
+                    max = max.min(value.saturating_sub(1))
+                }
+                (Ordering::Greater, true) => min = min.max(value),
+                (Ordering::Greater, false) => {
+                    min = min.max(value.saturating_add(1))
+                }
+            }
+        }
+
+        min <= max
+    }
+
+    /// Folds an arithmetic operation where all the operands are constant.
+    ///
+    /// `f` is used when any of the operands is a float, and `g` when all of
+    /// them are integers. Integer operations are performed with 64-bits
+    /// integers, not floats, because floats can't represent every 64-bits
+    /// integer exactly. `g` returns `None` when the operation overflows, which
+    /// is reported as an error, like libyara does.
+    pub fn fold_arithmetic<F, G>(
         ctx: &mut CompileContext,
         span: Span,
         operands: Vec<Expr>,
         f: F,
+        mut g: G,
     ) -> Result<Self, Box<CompileError>>
     where
         F: FnMut(f64, f64) -> f64,
+        G: FnMut(i64, i64) -> Option<i64>,
     {
         debug_assert!(!operands.is_empty());
 
-        let mut is_float = false;
-
-        let result = operands
-            .iter()
-            .map(|operand| match operand.type_value() {
-                TypeValue::Integer(Value::Const(v)) => v as f64,
-                TypeValue::Float(Value::Const(v)) => {
-                    is_float = true;
-                    v
-                }
-                _ => unreachable!(),
-            })
-            .reduce(f)
-            // It's safe to call unwrap because there must be at least
-            // one iterator.
-            .unwrap();
+        let is_float =
+            operands.iter().any(|operand| matches!(operand.ty(), Type::Float));
 
         if is_float {
-            Ok(Expr::Const(TypeValue::const_float_from(result)))
-        } else if result >= i64::MIN as f64 && result <= i64::MAX as f64 {
-            Ok(Expr::Const(TypeValue::const_integer_from(result as i64)))
-        } else {
-            Err(Box::new(CompileError::number_out_of_range(
-                ctx.report_builder,
-                i64::MIN,
-                i64::MAX,
-                span,
-            )))
+            let result = operands
+                .iter()
+                .map(|operand| match operand.type_value() {
+                    TypeValue::Integer(Value::Const(v)) => v as f64,
+                    TypeValue::Float(Value::Const(v)) => v,
+                    _ => unreachable!(),
+                })
+                .reduce(f)
+                // It's safe to call unwrap because there must be at least
+                // one iterator.
+                .unwrap();
+
+            return Ok(Expr::Const(TypeValue::const_float_from(result)));
         }
+
+        let mut operands =
+            operands.iter().map(|operand| match operand.type_value() {
+                TypeValue::Integer(Value::Const(v)) => v,
+                _ => unreachable!(),
+            });
+
+        // It's safe to call unwrap because there must be at least one
+        // operand.
+        let mut result = operands.next().unwrap();
+
+        for operand in operands {
+            match g(result, operand) {
+                Some(r) => result = r,
+                None => {
+                    return Err(Box::new(CompileError::number_out_of_range(
+                        ctx.report_builder,
+                        i64::MIN,
+                        i64::MAX,
+                        span,
+                    )))
+                }
+            }
+        }
+
+        Ok(Expr::Const(TypeValue::const_integer_from(result)))
     }
 }
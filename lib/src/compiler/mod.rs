@@ -7,10 +7,14 @@ module implements the YARA compiler.
 use std::cell::RefCell;
 use std::collections::hash_map::Entry;
 use std::collections::HashSet;
+use std::mem;
 use std::ops::RangeInclusive;
 use std::path::Path;
 use std::rc::Rc;
-#[cfg(feature = "logging")]
+use std::sync::Arc;
+#[cfg(feature = "rules-profiling")]
+use std::time::Duration;
+#[cfg(any(feature = "logging", feature = "rules-profiling"))]
 use std::time::Instant;
 use std::{fmt, iter, u32};
 
@@ -26,8 +30,8 @@ use serde::{Deserialize, Serialize};
 use walrus::FunctionId;
 
 use yara_x_parser::ast;
-use yara_x_parser::ast::{HasSpan, Ident, Import, RuleFlag, Span};
-use yara_x_parser::report::ReportBuilder;
+use yara_x_parser::ast::{HasSpan, Ident, Import, Include, RuleFlag, Span};
+use yara_x_parser::report::{Level, ReportBuilder};
 use yara_x_parser::warnings::{Warning, Warnings};
 use yara_x_parser::{Parser, SourceCode};
 
@@ -52,6 +56,9 @@ pub(crate) use crate::compiler::ir::*;
 #[doc(inline)]
 pub use crate::compiler::errors::*;
 
+#[doc(inline)]
+pub use crate::compiler::include::*;
+
 #[doc(inline)]
 pub use crate::compiler::rules::*;
 use crate::re;
@@ -61,6 +68,7 @@ mod atoms;
 mod context;
 mod emit;
 mod errors;
+mod include;
 mod ir;
 mod rules;
 
@@ -133,11 +141,18 @@ struct Namespace {
 /// ```
 ///
 pub struct Compiler<'a> {
+    /// Optimization level used while compiling the rules.
+    opt_level: OptLevel,
+
     /// Mimics YARA behaviour with respect to regular expressions, allowing
     /// some constructs that are invalid in YARA-X by default, like invalid
     /// escape sequences.
     relaxed_re_syntax: bool,
 
+    /// If true, patterns that are not used in the condition produce warnings
+    /// instead of errors.
+    unused_patterns_as_warnings: bool,
+
     /// Used for generating error and warning reports.
     report_builder: ReportBuilder,
 
@@ -158,6 +173,10 @@ pub struct Compiler<'a> {
     /// contain any new rules added via a call to `add_sources`.
     current_namespace: Namespace,
 
+    /// Origin of the source code being compiled, as an ID in `lit_pool`.
+    /// `None` if the source code doesn't have an origin.
+    current_origin: Option<LiteralId>,
+
     /// Pool that contains all the identifiers used in the rules. Each
     /// identifier appears only once, even if they are used by multiple
     /// rules. For example, the pool contains a single copy of the common
@@ -239,6 +258,10 @@ pub struct Compiler<'a> {
     /// module is ignored.
     ignored_modules: Vec<String>,
 
+    /// Names of modules that can't be used. Importing one of these modules
+    /// produces an error, even if the module exists.
+    banned_modules: Vec<String>,
+
     /// Keys in this map are the name of rules that will be ignored because they
     /// depend on unsupported modules, either directly or indirectly. Values are
     /// the names of the unsupported modules they depend on.
@@ -251,6 +274,25 @@ pub struct Compiler<'a> {
 
     /// Warnings generated while compiling the rules.
     warnings: Warnings,
+
+    /// If true, any warning that is not suppressed makes the compilation
+    /// fail with [`Error::WarningAsError`].
+    warnings_as_errors: bool,
+
+    /// Resolver used for obtaining the source code of files included with
+    /// `include` statements.
+    include_resolver: Box<dyn IncludeResolver + 'a>,
+
+    /// Maximum number of nested includes.
+    max_include_depth: usize,
+
+    /// Origins of the files that are currently being included, from the
+    /// outermost to the innermost. Used for detecting circular includes.
+    include_stack: Vec<String>,
+
+    /// Time spent in each compilation phase.
+    #[cfg(feature = "rules-profiling")]
+    compilation_profile: Vec<(&'static str, Duration)>,
 }
 
 impl<'a> Compiler<'a> {
@@ -267,7 +309,7 @@ impl<'a> Compiler<'a> {
             // Get only the public exports not belonging to a YARA module.
             .filter(|e| e.public && e.builtin())
         {
-            let func = Rc::new(Func::from_mangled_name(export.mangled_name));
+            let func = Arc::new(Func::from_mangled_name(export.mangled_name));
 
             let symbol = Symbol::new(
                 TypeValue::Func(func.clone()),
@@ -309,11 +351,15 @@ impl<'a> Compiler<'a> {
             wasm_mod,
             wasm_symbols,
             wasm_exports,
+            opt_level: OptLevel::default(),
             relaxed_re_syntax: false,
+            unused_patterns_as_warnings: false,
             next_pattern_id: PatternId(0),
             current_pattern_id: PatternId(0),
             current_namespace: default_namespace,
+            current_origin: None,
             warnings: Warnings::default(),
+            warnings_as_errors: false,
             rules: Vec::new(),
             sub_patterns: Vec::new(),
             anchored_sub_patterns: Vec::new(),
@@ -321,18 +367,28 @@ impl<'a> Compiler<'a> {
             re_code: Vec::new(),
             imported_modules: Vec::new(),
             ignored_modules: Vec::new(),
+            banned_modules: Vec::new(),
             ignored_rules: FxHashMap::default(),
             root_struct: Struct::new().make_root(),
             report_builder: ReportBuilder::new(),
             lit_pool: BStringPool::new(),
             regexp_pool: StringPool::new(),
             patterns: FxHashMap::default(),
+            include_resolver: Box::new(FilesystemIncludeResolver),
+            max_include_depth: 16,
+            include_stack: Vec::new(),
+            #[cfg(feature = "rules-profiling")]
+            compilation_profile: Vec::new(),
         }
     }
 
     /// Adds a YARA source code to be compiled.
     ///
     /// This function can be called multiple times.
+    ///
+    /// Files included with `include` statements are obtained from the
+    /// [`IncludeResolver`] set with [`Compiler::include_resolver`], and
+    /// their rules are compiled before the rules in `src`.
     pub fn add_source<'src, S>(&mut self, src: S) -> Result<&mut Self, Error>
     where
         S: Into<SourceCode<'src>>,
@@ -341,11 +397,42 @@ impl<'a> Compiler<'a> {
         // else, like a &str.
         let src = src.into();
 
+        let warnings_len = self.warnings.len();
+
+        // The origin of this source code, used for resolving the files
+        // included by it.
+        let parent = src.origin().map(|origin| origin.to_owned());
+
+        #[cfg(feature = "rules-profiling")]
+        let start = Instant::now();
+
         // Parse the source code and build the Abstract Syntax Tree.
-        let ast = Parser::new()
+        let mut ast = Parser::new()
             .set_report_builder(&self.report_builder)
+            .unused_patterns_as_warnings(self.unused_patterns_as_warnings)
             .build_ast(src)?;
 
+        // When warnings are treated as errors, the first warning produced by
+        // the parser that is not suppressed is returned as an error.
+        if self.warnings_as_errors {
+            if let Some(warning) = Vec::from(mem::take(&mut ast.warnings))
+                .into_iter()
+                .find(|w| !self.warnings.is_suppressed(w))
+            {
+                return Err(Error::WarningAsError(Box::new(warning)));
+            }
+        }
+
+        #[cfg(feature = "rules-profiling")]
+        let start = {
+            crate::profiling::add_phase_time(
+                &mut self.compilation_profile,
+                "parse",
+                Instant::elapsed(&start),
+            );
+            Instant::now()
+        };
+
         let mut already_imported = FxHashMap::default();
 
         // Process import statements. Checks that all imported modules
@@ -371,6 +458,18 @@ impl<'a> Compiler<'a> {
             self.c_import(import)?;
         }
 
+        self.check_warnings(warnings_len)?;
+
+        // Process include statements. The rules in the included files are
+        // added to the current namespace.
+        for include in &ast.includes {
+            self.c_include(include, parent.as_deref())?;
+        }
+
+        self.current_origin = parent
+            .as_deref()
+            .map(|origin| self.lit_pool.get_or_intern(origin));
+
         // Iterate over the list of declared rules and verify that their
         // conditions are semantically valid. For each rule add a symbol
         // to the current namespace.
@@ -381,6 +480,13 @@ impl<'a> Compiler<'a> {
         // Transfer the warnings generated by the parser to the compiler
         self.warnings.append(ast.warnings);
 
+        #[cfg(feature = "rules-profiling")]
+        crate::profiling::add_phase_time(
+            &mut self.compilation_profile,
+            "compile",
+            Instant::elapsed(&start),
+        );
+
         Ok(self)
     }
 
@@ -394,7 +500,11 @@ impl<'a> Compiler<'a> {
     ///
     /// `T` can be any type that implements [`TryInto<Variable>`], which
     /// includes: `i64`, `i32`, `i16`, `i8`, `u32`, `u16`, `u8`, `f64`, `f32`,
-    /// `bool`, `&str`, `String` and [`serde_json::Value`].
+    /// `bool`, `&str`, `String`, [`serde_json::Value`] and
+    /// `&dyn` [`protobuf::MessageDyn`]. JSON objects and protobuf messages
+    /// are converted into structures, which allows passing structured data
+    /// that can contain arrays and nested structures. Protobuf messages can
+    /// also contain maps.
     ///
     /// ```
     /// # use yara_x::Compiler;
@@ -486,11 +596,41 @@ impl<'a> Compiler<'a> {
     ///
     /// This function consumes the compiler and returns an instance of
     /// [`Rules`].
-    pub fn build(self) -> Rules {
+    pub fn build(mut self) -> Rules {
+        // Vectors grow by doubling their capacity while rules are being
+        // compiled, which means that up to half of the allocated memory can
+        // be unused once the compilation is done. With large rule sets this
+        // is a lot of memory, so the excess capacity is released here.
+        for rule in self.rules.iter_mut() {
+            rule.tags.shrink_to_fit();
+            rule.metadata.shrink_to_fit();
+            rule.patterns.shrink_to_fit();
+            rule.dependencies.shrink_to_fit();
+            if let Some(patterns) = rule.sufficient_patterns.as_mut() {
+                patterns.shrink_to_fit();
+            }
+        }
+
+        self.rules.shrink_to_fit();
+        self.sub_patterns.shrink_to_fit();
+        self.anchored_sub_patterns.shrink_to_fit();
+        self.atoms.shrink_to_fit();
+        self.re_code.shrink_to_fit();
+
+        #[cfg(feature = "rules-profiling")]
+        let start = Instant::now();
+
         // Finish building the WASM module.
         let wasm_mod = self.wasm_mod.build().emit_wasm();
 
-        #[cfg(feature = "logging")]
+        #[cfg(feature = "rules-profiling")]
+        crate::profiling::add_phase_time(
+            &mut self.compilation_profile,
+            "emit_wasm",
+            Instant::elapsed(&start),
+        );
+
+        #[cfg(any(feature = "logging", feature = "rules-profiling"))]
         let start = Instant::now();
 
         // Compile the WASM module for the current platform. This panics
@@ -506,6 +646,13 @@ impl<'a> Compiler<'a> {
         #[cfg(feature = "logging")]
         info!("WASM module build time: {:?}", Instant::elapsed(&start));
 
+        #[cfg(feature = "rules-profiling")]
+        crate::profiling::add_phase_time(
+            &mut self.compilation_profile,
+            "compile_wasm",
+            Instant::elapsed(&start),
+        );
+
         // The structure that contains the global variables is serialized before
         // being passed to the `Rules` struct. This is because we want `Rules`
         // to be `Send`, so that it can be shared with scanners running in
@@ -524,6 +671,7 @@ impl<'a> Compiler<'a> {
         let mut rules = Rules {
             serialized_globals,
             relaxed_re_syntax: self.relaxed_re_syntax,
+            constant_folding: self.opt_level == OptLevel::Full,
             wasm_mod: compiled_wasm_mod,
             ac: None,
             num_patterns: self.next_pattern_id.0 as usize,
@@ -537,6 +685,8 @@ impl<'a> Compiler<'a> {
             atoms: self.atoms,
             re_code: self.re_code,
             warnings: self.warnings.into(),
+            #[cfg(feature = "rules-profiling")]
+            compilation_profile: self.compilation_profile,
         };
 
         rules.build_ac_automaton();
@@ -555,6 +705,25 @@ impl<'a> Compiler<'a> {
         self
     }
 
+    /// Tell the compiler that a YARA module can't be used.
+    ///
+    /// Import statements for banned modules produce an error, even if the
+    /// module exists. This is useful for preventing the use of modules that
+    /// are too expensive or not available in the environment where the
+    /// rules will be used.
+    ///
+    /// ```
+    /// # use yara_x::Compiler;
+    /// assert!(Compiler::new()
+    ///     .ban_module("hash")
+    ///     .add_source(r#"import "hash" rule test { condition: true }"#)
+    ///     .is_err());
+    /// ```
+    pub fn ban_module<M: Into<String>>(&mut self, module: M) -> &mut Self {
+        self.banned_modules.push(module.into());
+        self
+    }
+
     /// Specifies whether the compiler should produce colorful error messages.
     ///
     /// Colorized error messages contain ANSI escape sequences that make them
@@ -575,7 +744,9 @@ impl<'a> Compiler<'a> {
     /// `{0,1}`).
     ///
     /// This setting controls whether the compiler should mimic YARA's behavior,
-    /// allowing constructs that YARA-X doesn't accept by default.
+    /// allowing constructs that YARA-X doesn't accept by default. Each of
+    /// these constructs produces a `relaxed_regexp` warning, which helps in
+    /// fixing legacy rules progressively.
     ///
     /// This should be called before any rule is added to the compiler.
     ///
@@ -590,12 +761,198 @@ impl<'a> Compiler<'a> {
         self
     }
 
+    /// Sets the optimization level used while compiling the rules.
+    ///
+    /// [`OptLevel::None`] compiles faster, which is convenient for
+    /// interactive use, while [`OptLevel::Full`] produces rules that
+    /// are faster to evaluate. The default level is [`OptLevel::Full`],
+    /// unless the `constant-folding` feature is disabled.
+    ///
+    /// This should be called before any rule is added to the compiler.
+    ///
+    /// # Panics
+    ///
+    /// If called after adding rules to the compiler.
+    pub fn optimization_level(&mut self, level: OptLevel) -> &mut Self {
+        if !self.rules.is_empty() {
+            panic!("calling optimization_level in non-empty compiler")
+        }
+        self.opt_level = level;
+        self
+    }
+
+    /// Specifies whether patterns that are not used in the rule's condition
+    /// produce warnings instead of errors.
+    ///
+    /// By default, declaring a pattern that is not used in the condition is
+    /// an error. With this setting enabled the rule is compiled, and an
+    /// `unused_pattern` warning is issued instead. In both cases patterns
+    /// with identifiers that start with underscore (e.g. `$_a`) are allowed
+    /// to remain unused.
+    pub fn unused_patterns_as_warnings(&mut self, yes: bool) -> &mut Self {
+        self.unused_patterns_as_warnings = yes;
+        self
+    }
+
+    /// Specifies whether warnings should be treated as errors.
+    ///
+    /// When enabled, [`Compiler::add_source`] fails with
+    /// [`Error::WarningAsError`] as soon as a warning is raised, and the
+    /// rule that produced the warning is not added to the compiler. Warnings
+    /// suppressed with [`Compiler::suppress_warning`] are not affected by
+    /// this setting. The default setting is `false`.
+    pub fn warnings_as_errors(&mut self, yes: bool) -> &mut Self {
+        self.warnings_as_errors = yes;
+        self
+    }
+
+    /// Suppresses the warnings with the given code.
+    ///
+    /// Warnings are identified by the code returned by [`Warning::code`],
+    /// like `slow_pattern` or `duplicate_import`. Suppressed warnings are
+    /// discarded, so they don't appear in [`Compiler::warnings`], and they
+    /// don't produce errors when [`Compiler::warnings_as_errors`] is enabled.
+    ///
+    /// ```
+    /// # use yara_x::Compiler;
+    /// let mut compiler = Compiler::new();
+    ///
+    /// compiler
+    ///     .warnings_as_errors(true)
+    ///     .suppress_warning("non_boolean_as_boolean")
+    ///     .add_source("rule test { condition: 1 }")?;
+    ///
+    /// assert!(compiler.warnings().is_empty());
+    /// # Ok::<(), yara_x::Error>(())
+    /// ```
+    pub fn suppress_warning<C: Into<String>>(&mut self, code: C) -> &mut Self {
+        self.warnings.suppress(code);
+        self
+    }
+
+    /// Sets the [`IncludeResolver`] used for obtaining the source code of
+    /// files included with `include` statements.
+    ///
+    /// By default the compiler uses a [`FilesystemIncludeResolver`].
+    pub fn include_resolver<R: IncludeResolver + 'a>(
+        &mut self,
+        resolver: R,
+    ) -> &mut Self {
+        self.include_resolver = Box::new(resolver);
+        self
+    }
+
+    /// Sets the maximum number of nested includes.
+    ///
+    /// Including a file that exceeds this limit produces an error. The
+    /// default limit is 16.
+    pub fn max_include_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_include_depth = depth;
+        self
+    }
+
     /// Returns the warnings emitted by the compiler.
+    ///
+    /// Besides the detailed report returned by their [`Display`]
+    /// implementation, warnings provide structured information through
+    /// [`Warning::code`], [`Warning::title`], [`Warning::span`] and
+    /// [`Warning::level`].
+    ///
+    /// [`Display`]: std::fmt::Display
     #[inline]
     pub fn warnings(&self) -> &[Warning] {
         self.warnings.as_slice()
     }
 
+    /// Returns the warnings emitted by the compiler as a JSON array.
+    ///
+    /// See [`Compiler::error_json`] for a description of the objects in
+    /// the array.
+    pub fn warnings_json(&self) -> serde_json::Value {
+        serde_json::Value::Array(
+            self.warnings
+                .as_slice()
+                .iter()
+                .map(|w| {
+                    self.diagnostic_json(
+                        w.level(),
+                        w.code(),
+                        w.title(),
+                        Some(w.span()),
+                        w.to_string(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns a JSON representation of an error returned by this compiler.
+    ///
+    /// The result is an object like the one below, where `location` tells
+    /// the origin of the source code (see [`SourceCode::with_origin`]) and
+    /// the position of the code that caused the error. Lines and columns
+    /// start at 1, while `start` and `end` are byte offsets. `location` is
+    /// `null` for errors that are not associated to any source code, like
+    /// errors returned by [`Compiler::define_global`]. Lines and columns are
+    /// `null` if they can't be determined. `text` contains the
+    /// same detailed report returned by the error's [`Display`]
+    /// implementation.
+    ///
+    /// ```json
+    /// {
+    ///   "type": "error",
+    ///   "code": "unknown_identifier",
+    ///   "title": "unknown identifier `foo`",
+    ///   "location": {
+    ///     "origin": "rules.yar",
+    ///     "start": 23,
+    ///     "end": 26,
+    ///     "line": 1,
+    ///     "column": 24,
+    ///     "end_line": 1,
+    ///     "end_column": 27
+    ///   },
+    ///   "text": "error: unknown identifier `foo` ..."
+    /// }
+    /// ```
+    ///
+    /// [`Display`]: std::fmt::Display
+    pub fn error_json(&self, err: &Error) -> serde_json::Value {
+        match err {
+            Error::ParseError(err) => {
+                let info = err.info();
+                self.diagnostic_json(
+                    info.level(),
+                    info.code(),
+                    info.title(),
+                    Some(info.span()),
+                    err.to_string(),
+                )
+            }
+            Error::CompileError(err) => self.diagnostic_json(
+                err.level(),
+                err.code(),
+                err.title(),
+                Some(err.span()),
+                err.to_string(),
+            ),
+            Error::VariableError(err) => self.diagnostic_json(
+                Level::Error,
+                "variable_error",
+                err.to_string(),
+                None,
+                err.to_string(),
+            ),
+            Error::WarningAsError(err) => self.diagnostic_json(
+                Level::Error,
+                err.code(),
+                err.title(),
+                Some(err.span()),
+                err.to_string(),
+            ),
+        }
+    }
+
     /// Emits a `.wasm` file with the WASM module generated by the compiler.
     ///
     /// This file can be inspected and converted to WASM text format by using
@@ -710,14 +1067,29 @@ impl<'a> Compiler<'a> {
         self.atoms.truncate(snapshot.atoms_len);
         self.symbol_table.truncate(snapshot.symbol_table_len);
     }
+
+    /// Checks the warnings raised after the first `since` ones, returning
+    /// the first of them as an error if warnings are treated as errors.
+    ///
+    /// In that case the warnings are removed from the list, as they are
+    /// reported as an error instead.
+    fn check_warnings(&mut self, since: usize) -> Result<(), Error> {
+        if self.warnings_as_errors && self.warnings.len() > since {
+            let warning = self.warnings.split_off(since).into_iter().next();
+            return Err(Error::WarningAsError(Box::new(warning.unwrap())));
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Compiler<'a> {
-    fn c_rule(&mut self, rule: &ast::Rule) -> Result<(), Box<CompileError>> {
+    fn c_rule(&mut self, rule: &ast::Rule) -> Result<(), Error> {
         // Check if another rule, module or variable has the same identifier
         // and return an error in that case.
         self.check_for_existing_identifier(&rule.identifier)?;
 
+        let warnings_len = self.warnings.len();
+
         // Take snapshot of the current compiler state. In case of error
         // compiling the current rule this snapshot allows restoring the
         // compiler to the state it had before starting compiling the rule.
@@ -737,26 +1109,58 @@ impl<'a> Compiler<'a> {
         // and determines which patterns are anchored, because this information
         // is required for detecting duplicate patterns that can share the same
         // PatternId.
+        let metadata = rule
+            .meta
+            .iter()
+            .flatten()
+            .map(|meta| {
+                (
+                    self.ident_pool.get_or_intern(meta.identifier.name),
+                    match &meta.value {
+                        ast::MetaValue::Bool(b) => MetaValue::Bool(*b),
+                        ast::MetaValue::Integer(i) => MetaValue::Integer(*i),
+                        ast::MetaValue::Float(f) => MetaValue::Float(*f),
+                        ast::MetaValue::String(s) => MetaValue::String(
+                            self.lit_pool.get_or_intern(s.as_ref()),
+                        ),
+                    },
+                )
+            })
+            .collect();
+
         self.rules.push(RuleInfo {
             namespace_id: self.current_namespace.id,
             namespace_ident_id: self.current_namespace.ident_id,
             ident_id: self.ident_pool.get_or_intern(rule.identifier.name),
             ident_span: rule.identifier.span,
+            origin: self.current_origin,
+            tags: rule
+                .tags
+                .iter()
+                .flatten()
+                .map(|tag| self.ident_pool.get_or_intern(tag.name))
+                .collect(),
+            metadata,
             patterns: vec![],
             is_global: rule.flags.contains(RuleFlag::Global),
             is_private: rule.flags.contains(RuleFlag::Private),
+            dependencies: vec![],
+            sufficient_patterns: None,
         });
 
         let mut rule_patterns = Vec::new();
+        let mut rule_dependencies = Vec::new();
 
         let mut ctx = CompileContext {
             relaxed_re_syntax: self.relaxed_re_syntax,
+            constant_folding: self.opt_level == OptLevel::Full,
             current_symbol_table: None,
             symbol_table: &mut self.symbol_table,
             ident_pool: &mut self.ident_pool,
             report_builder: &self.report_builder,
             rules: &self.rules,
             current_rule_patterns: &mut rule_patterns,
+            current_rule_dependencies: &mut rule_dependencies,
             warnings: &mut self.warnings,
             vars: VarStack::new(),
         };
@@ -766,7 +1170,7 @@ impl<'a> Compiler<'a> {
         if let Err(err) = patterns_from_ast(&mut ctx, rule.patterns.as_ref()) {
             drop(ctx);
             self.restore_snapshot(snapshot);
-            return Err(Box::new(*err));
+            return Err(err.into());
         };
 
         // Convert the rule condition's AST to the intermediate representation
@@ -816,14 +1220,39 @@ impl<'a> Compiler<'a> {
                         .insert(rule.identifier.name.to_string(), identifier);
                 }
 
-                return Ok(());
+                return self.check_warnings(warnings_len);
             }
             Err(err) => {
                 self.restore_snapshot(snapshot);
-                return Err(Box::new(err));
+                return Err(Box::new(err).into());
             }
         };
 
+        // When the condition is known to be false at compile time the rule
+        // never matches. A warning is raised unless the condition is a
+        // literal like `false` or `0`, which is used for disabling rules on
+        // purpose.
+        let condition_value = condition.type_value().cast_to_bool();
+        let is_dead = condition_value.is_const() && !condition_value.as_bool();
+
+        if is_dead
+            && !matches!(
+                rule.condition,
+                ast::Expr::False { .. }
+                    | ast::Expr::LiteralInteger(_)
+                    | ast::Expr::LiteralFloat(_)
+                    | ast::Expr::LiteralString(_)
+            )
+        {
+            self.warnings.add(|| {
+                Warning::dead_rule(
+                    &self.report_builder,
+                    rule.identifier.name.to_string(),
+                    rule.condition.span(),
+                )
+            });
+        }
+
         // Create a new symbol of bool type for the rule.
         let new_symbol = Symbol::new(
             TypeValue::Bool(Value::Unknown),
@@ -847,6 +1276,11 @@ impl<'a> Compiler<'a> {
 
         let current_rule = self.rules.last_mut().unwrap();
 
+        rule_dependencies.sort();
+        rule_dependencies.dedup();
+
+        current_rule.dependencies = rule_dependencies;
+
         for pattern in &rule_patterns {
             // Check if this pattern has been declared before, in this rule or
             // in some other rule. In such cases the pattern ID is re-used, and
@@ -877,6 +1311,14 @@ impl<'a> Compiler<'a> {
             pattern_ids.push(pattern_id);
         }
 
+        current_rule.sufficient_patterns =
+            condition.sufficient_patterns().map(|patterns| {
+                patterns
+                    .into_iter()
+                    .map(|pattern| pattern_ids[pattern.as_usize()])
+                    .collect()
+            });
+
         // Process the patterns in the rule. This extract the best atoms
         // from each pattern, adding them to the `self.atoms` vector, it
         // also creates one or more sub-patterns per pattern and add them
@@ -898,7 +1340,7 @@ impl<'a> Compiler<'a> {
                             self.c_regexp_pattern(pattern, anchored_at, span)
                         {
                             self.restore_snapshot(snapshot);
-                            return Err(err);
+                            return Err(err.into());
                         }
                     }
                 };
@@ -906,6 +1348,26 @@ impl<'a> Compiler<'a> {
             }
         }
 
+        // If warnings are treated as errors, and compiling this rule raised
+        // some warning, the rule is discarded.
+        if let Err(err) = self.check_warnings(warnings_len) {
+            self.current_namespace
+                .symbols
+                .as_ref()
+                .borrow_mut()
+                .remove(rule.identifier.name);
+            self.restore_snapshot(snapshot);
+            return Err(err);
+        }
+
+        // Non-global rules that never match don't need any code at all, as
+        // the rule is considered false unless its code reports a match.
+        // Global rules must be emitted anyways, because they prevent other
+        // rules in the namespace from matching.
+        if is_dead && !rule.flags.contains(RuleFlag::Global) {
+            return Ok(());
+        }
+
         // The last step is emitting the WASM code corresponding to the rule's
         // condition. This is done after every fallible function has been called
         // because once the code is emitted it cannot be undone, which means
@@ -937,8 +1399,102 @@ impl<'a> Compiler<'a> {
         Ok(())
     }
 
+    fn diagnostic_json(
+        &self,
+        level: Level,
+        code: &str,
+        title: String,
+        span: Option<Span>,
+        text: String,
+    ) -> serde_json::Value {
+        let location = span.map(|span| {
+            let source_id = span.source_id();
+            // Line and column are `null` if they can't be determined, for
+            // instance because the source code is not available anymore.
+            let start =
+                self.report_builder.line_and_column(source_id, span.start());
+            let end =
+                self.report_builder.line_and_column(source_id, span.end());
+            serde_json::json!({
+                "origin": self.report_builder.origin(source_id),
+                "start": span.start(),
+                "end": span.end(),
+                "line": start.map(|(line, _)| line),
+                "column": start.map(|(_, column)| column),
+                "end_line": end.map(|(line, _)| line),
+                "end_column": end.map(|(_, column)| column),
+            })
+        });
+
+        serde_json::json!({
+            "type": if level == Level::Error { "error" } else { "warning" },
+            "code": code,
+            "title": title,
+            "location": location,
+            "text": text,
+        })
+    }
+
+    fn c_include(
+        &mut self,
+        include: &Include,
+        parent: Option<&str>,
+    ) -> Result<(), Error> {
+        if self.include_stack.len() >= self.max_include_depth {
+            return Err(Box::new(CompileError::include_depth_exceeded(
+                &self.report_builder,
+                self.max_include_depth,
+                include.span(),
+            ))
+            .into());
+        }
+
+        let (origin, source) = self
+            .include_resolver
+            .resolve(include.file_name.as_str(), parent)
+            .map_err(|err| {
+                Box::new(CompileError::include_error(
+                    &self.report_builder,
+                    include.file_name.clone(),
+                    err.to_string(),
+                    include.span(),
+                ))
+            })?;
+
+        if parent == Some(origin.as_str())
+            || self.include_stack.contains(&origin)
+        {
+            return Err(Box::new(CompileError::circular_include(
+                &self.report_builder,
+                include.file_name.clone(),
+                include.span(),
+            ))
+            .into());
+        }
+
+        self.include_stack.push(origin);
+
+        let src = SourceCode::from(source.as_str())
+            .with_origin(self.include_stack.last().unwrap());
+
+        let result = self.add_source(src).map(|_| ());
+
+        self.include_stack.pop();
+
+        result
+    }
+
     fn c_import(&mut self, import: &Import) -> Result<(), Box<CompileError>> {
         let module_name = import.module_name.as_str();
+
+        if self.banned_modules.iter().any(|m| m == module_name) {
+            return Err(Box::new(CompileError::banned_module(
+                &self.report_builder,
+                module_name.to_string(),
+                import.span(),
+            )));
+        }
+
         let module = BUILTIN_MODULES.get(module_name);
 
         // Does a module with the given name actually exist? ...
@@ -995,7 +1551,7 @@ impl<'a> Compiler<'a> {
                 // Insert the functions in the module's struct.
                 for (name, export) in functions.drain() {
                     if module_struct
-                        .add_field(name, TypeValue::Func(Rc::new(export)))
+                        .add_field(name, TypeValue::Func(Arc::new(export)))
                         .is_some()
                     {
                         panic!("duplicate function `{}`", name)
@@ -1010,7 +1566,7 @@ impl<'a> Compiler<'a> {
                 .root_struct
                 .add_field(
                     module_name,
-                    TypeValue::Struct(Rc::new(module_struct)),
+                    TypeValue::Struct(Arc::new(module_struct)),
                 )
                 .is_some()
             {
@@ -1694,9 +2250,50 @@ impl From<LiteralId> for u64 {
 #[serde(transparent)]
 pub(crate) struct NamespaceId(i32);
 
+/// Optimization level used by the [`Compiler`].
+///
+/// See [`Compiler::optimization_level`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimizations. Every expression in a condition produces code that
+    /// computes its value at scan time.
+    None,
+    /// Expressions whose value can be determined at compile time, like
+    /// `2+2+2` or `true or false`, are reduced to their final value. This
+    /// also allows detecting rules that never match.
+    Full,
+}
+
+impl Default for OptLevel {
+    fn default() -> Self {
+        if cfg!(feature = "constant-folding") {
+            OptLevel::Full
+        } else {
+            OptLevel::None
+        }
+    }
+}
+
 /// ID associated to each rule.
-#[derive(Copy, Clone, Debug)]
-pub(crate) struct RuleId(i32);
+///
+/// Rules are numbered in the order in which they were added to the compiler,
+/// starting at 0. The ID of a rule doesn't change when the compiled rules are
+/// serialized and deserialized, so it can be used for storing results in a
+/// compact way, and resolving them later with [`Rules::rule_name`].
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Eq,
+    Hash,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct RuleId(i32);
 
 impl From<i32> for RuleId {
     #[inline]
@@ -1,24 +1,28 @@
-use std::fmt;
 use std::io::{BufWriter, Read, Write};
+#[cfg(feature = "rules-profiling")]
+use std::time::Duration;
 #[cfg(feature = "logging")]
 use std::time::Instant;
+use std::{fmt, iter, mem, slice};
 
 use aho_corasick::AhoCorasick;
 use bincode::Options;
 #[cfg(feature = "logging")]
 use log::*;
 use regex_automata::meta::Regex;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use yara_x_parser::ast::Span;
 use yara_x_parser::Warning;
 
-use crate::compiler::atoms::Atom;
+use crate::compiler::atoms::{atom_quality, Atom};
 use crate::compiler::{
     IdentId, Imports, LiteralId, NamespaceId, PatternId, RegexpId, RuleId,
     SubPattern, SubPatternId,
 };
 use crate::re::{BckCodeLoc, FwdCodeLoc, RegexpAtom};
+use crate::scanner::{Metadata, Tags};
 use crate::string_pool::{BStringPool, StringPool};
 use crate::{re, types, SerializationError};
 
@@ -42,6 +46,11 @@ pub struct Rules {
     /// contain invalid escape sequences.
     pub(in crate::compiler) relaxed_re_syntax: bool,
 
+    /// If `true`, the rules were compiled with constant folding, and the
+    /// values of enums were resolved at compile time. Otherwise, the scanner
+    /// must provide the values of enums at scan time.
+    pub(in crate::compiler) constant_folding: bool,
+
     /// Pool with literal strings used in the rules. Each literal has its
     /// own [`LiteralId`], which can be used for retrieving the literal
     /// string as `&BStr`.
@@ -114,9 +123,24 @@ pub struct Rules {
     /// serialized rules won't have any warnings.
     #[serde(skip)]
     pub(in crate::compiler) warnings: Vec<Warning>,
+
+    /// Time spent in each compilation phase. Like warnings, this is not
+    /// serialized, but building the Aho-Corasick automaton after
+    /// deserialization is recorded here too.
+    #[cfg(feature = "rules-profiling")]
+    #[serde(skip)]
+    pub(in crate::compiler) compilation_profile: Vec<(&'static str, Duration)>,
 }
 
 impl Rules {
+    /// Magic bytes at the start of serialized rules.
+    const MAGIC: &'static [u8] = b"YARA-X";
+
+    /// Version of the format used for serializing rules. Must be incremented
+    /// every time the format changes in a way that makes rules serialized
+    /// by previous versions unreadable.
+    const FORMAT_VERSION: u32 = 5;
+
     /// An iterator that yields the name of the modules imported by the
     /// rules.
     pub fn imports(&self) -> Imports {
@@ -126,15 +150,235 @@ impl Rules {
         }
     }
 
+    /// Returns true if the rules import the module with the given name.
+    ///
+    /// Modules that were ignored with [`crate::Compiler::ignore_module`]
+    /// are not considered imported. This allows checking that the modules
+    /// required by a set of rules are available before using them.
+    ///
+    /// ```
+    /// # use yara_x::compile;
+    /// let rules = compile(r#"import "math" rule test { condition: true }"#)?;
+    ///
+    /// assert!(rules.requires_module("math"));
+    /// assert!(!rules.requires_module("pe"));
+    /// # Ok::<(), yara_x::Error>(())
+    /// ```
+    pub fn requires_module(&self, module_name: &str) -> bool {
+        self.imports().any(|name| name == module_name)
+    }
+
+    /// Returns an iterator over all the rules, in the order in which they
+    /// were added to the compiler.
+    ///
+    /// This allows inspecting the compiled rules without scanning any data,
+    /// private rules included.
+    ///
+    /// ```
+    /// # use yara_x::compile;
+    /// let rules = compile(r#"
+    ///     rule foo : bar { meta: author = "me" strings: $a = "a" condition: $a }
+    /// "#)?;
+    ///
+    /// let rule = rules.iter().next().unwrap();
+    ///
+    /// assert_eq!(rule.identifier(), "foo");
+    /// assert_eq!(rule.tags().collect::<Vec<_>>(), vec!["bar"]);
+    /// assert_eq!(rule.patterns().collect::<Vec<_>>(), vec!["$a"]);
+    /// # Ok::<(), yara_x::Error>(())
+    /// ```
+    pub fn iter(&self) -> RulesIter<'_> {
+        RulesIter { rules: self, iterator: self.rules.iter().enumerate() }
+    }
+
+    /// Returns the IDs of the rules needed for evaluating the given rule.
+    ///
+    /// The result contains `rule_id` itself, and all the rules it depends
+    /// on, directly or indirectly (see [`CompiledRule::dependencies`]).
+    /// As rules can only reference rules that were declared before them,
+    /// the result, which is sorted by ID, is also a valid evaluation order.
+    ///
+    /// ```
+    /// # use yara_x::compile;
+    /// let rules = compile(r#"
+    ///     rule a { condition: true }
+    ///     rule b { condition: a }
+    ///     rule c { condition: b }
+    ///     rule d { condition: true }
+    /// "#)?;
+    ///
+    /// let c = rules.rule_by_name("default", "c").unwrap();
+    /// let required = rules.required_rules(c);
+    ///
+    /// assert_eq!(
+    ///     required.into_iter().map(|id| rules.rule_name(id).unwrap().1).collect::<Vec<_>>(),
+    ///     vec!["a", "b", "c"]
+    /// );
+    /// # Ok::<(), yara_x::Error>(())
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `rule_id` doesn't correspond to any rule.
+    pub fn required_rules(&self, rule_id: RuleId) -> Vec<RuleId> {
+        let mut required = FxHashSet::default();
+        let mut pending = vec![rule_id];
+
+        while let Some(rule_id) = pending.pop() {
+            if required.insert(rule_id) {
+                pending.extend(self.get(rule_id).dependencies.iter());
+            }
+        }
+
+        let mut required: Vec<_> = required.into_iter().collect();
+        required.sort();
+        required
+    }
+
+    /// Returns the ID of the rule with the given namespace and name, or
+    /// `None` if no such rule exists.
+    ///
+    /// Rules added to the compiler without creating a namespace belong to
+    /// the `default` namespace.
+    pub fn rule_by_name(&self, namespace: &str, name: &str) -> Option<RuleId> {
+        let namespace_ident_id = self.ident_pool.get_id(namespace)?;
+        let ident_id = self.ident_pool.get_id(name)?;
+
+        self.rules
+            .iter()
+            .position(|rule| {
+                rule.ident_id == ident_id
+                    && rule.namespace_ident_id == namespace_ident_id
+            })
+            .map(RuleId::from)
+    }
+
+    /// Returns the namespace and name of the rule with the given ID, or
+    /// `None` if the ID doesn't correspond to any rule.
+    pub fn rule_name(&self, rule_id: RuleId) -> Option<(&str, &str)> {
+        let rule = self.rules.get(usize::try_from(rule_id.0).ok()?)?;
+        Some((
+            self.ident_pool.get(rule.namespace_ident_id).unwrap(),
+            self.ident_pool.get(rule.ident_id).unwrap(),
+        ))
+    }
+
     /// Warnings produced while compiling these rules.
     pub fn warnings(&self) -> &[Warning] {
         self.warnings.as_slice()
     }
 
+    /// Returns an estimation of the memory used by these rules.
+    ///
+    /// The result is a breakdown of the heap memory used by each of the
+    /// components of the compiled rules. The numbers are approximate, as
+    /// they don't account for the overhead of allocators and hash tables,
+    /// but they are good enough for tracking how the memory footprint
+    /// evolves as rules are added.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let wasm_image = self.wasm_mod.image_range();
+
+        MemoryUsage {
+            rules: self.rules.capacity() * mem::size_of::<RuleInfo>()
+                + self.rules.iter().map(RuleInfo::heap_size).sum::<usize>(),
+            metadata: self
+                .rules
+                .iter()
+                .map(|r| {
+                    r.metadata.capacity()
+                        * mem::size_of::<(IdentId, MetaValue)>()
+                })
+                .sum(),
+            sub_patterns: self.sub_patterns.capacity()
+                * mem::size_of::<(PatternId, SubPattern)>()
+                + self.anchored_sub_patterns.capacity()
+                    * mem::size_of::<SubPatternId>(),
+            atoms: self.atoms.capacity() * mem::size_of::<SubPatternAtom>(),
+            re_code: self.re_code.capacity(),
+            identifiers: self.ident_pool.size(),
+            literals: self.lit_pool.size(),
+            regexps: self.regexp_pool.size(),
+            globals: self.serialized_globals.capacity(),
+            ac_automaton: self
+                .ac
+                .as_ref()
+                .map(|ac| ac.memory_usage())
+                .unwrap_or(0),
+            wasm_code: wasm_image.end as usize - wasm_image.start as usize,
+        }
+    }
+
+    /// Returns a report about the atoms extracted from each pattern.
+    ///
+    /// Atoms are short sequences of bytes extracted from each pattern, which
+    /// are searched for in the scanned data. The rest of the pattern is
+    /// verified only when one of its atoms is found. Patterns with short or
+    /// very common atoms (e.g: `00 00`) produce many false hits, and can
+    /// slow down scanning significantly. This report helps in identifying
+    /// such patterns.
+    ///
+    /// The result contains one [`PatternReport`] per pattern declared by
+    /// each rule, in the order in which rules and patterns were declared.
+    /// Patterns that are identical in multiple rules share the same atoms.
+    ///
+    /// ```
+    /// # use yara_x::compile;
+    /// let rules = compile(r#"rule test { strings: $a = "foobar" condition: $a }"#)?;
+    /// let report = rules.pattern_report();
+    ///
+    /// assert_eq!(report[0].pattern, "$a");
+    /// assert_eq!(report[0].atoms[0].bytes, b"obar");
+    /// # Ok::<(), yara_x::Error>(())
+    /// ```
+    pub fn pattern_report(&self) -> Vec<PatternReport<'_>> {
+        let mut atoms_per_pattern: FxHashMap<PatternId, Vec<AtomReport>> =
+            FxHashMap::default();
+
+        for atom in &self.atoms {
+            let (pattern_id, _) = self.get_sub_pattern(atom.sub_pattern_id);
+            atoms_per_pattern.entry(*pattern_id).or_default().push(
+                AtomReport {
+                    bytes: atom.as_slice().to_vec(),
+                    quality: atom_quality(atom.as_slice()),
+                },
+            );
+        }
+
+        let mut report = Vec::new();
+
+        for rule in &self.rules {
+            let namespace =
+                self.ident_pool.get(rule.namespace_ident_id).unwrap();
+            let rule_name = self.ident_pool.get(rule.ident_id).unwrap();
+
+            for (ident_id, pattern_id) in &rule.patterns {
+                let atoms = atoms_per_pattern
+                    .get(pattern_id)
+                    .cloned()
+                    .unwrap_or_default();
+
+                report.push(PatternReport {
+                    namespace,
+                    rule: rule_name,
+                    pattern: self.ident_pool.get(*ident_id).unwrap(),
+                    quality: atoms.iter().map(|atom| atom.quality).min(),
+                    atoms,
+                });
+            }
+        }
+
+        report
+    }
+
     /// Serializes the rules as a sequence of bytes.
     ///
     /// The [`Rules`] can be restored back by passing the bytes to
-    /// [`Rules::deserialize`].
+    /// [`Rules::deserialize`]. The serialized rules include everything
+    /// needed for scanning, like the compiled WASM code, the string pools
+    /// and the patterns, so they can be loaded without compiling the rules
+    /// again. The data starts with a header that contains the version of
+    /// the serialization format and a CRC32 checksum of the rest of the
+    /// data.
     pub fn serialize(&self) -> Result<Vec<u8>, SerializationError> {
         let mut bytes = Vec::new();
         self.serialize_into(&mut bytes)?;
@@ -143,24 +387,50 @@ impl Rules {
 
     /// Deserializes the rules from a sequence of bytes produced by
     /// [`Rules::serialize`].
+    ///
+    /// Fails with [`SerializationError::InvalidFormat`] if the data doesn't
+    /// look like serialized rules at all, with
+    /// [`SerializationError::UnsupportedVersion`] if the rules were
+    /// serialized with a different version of the format, and with
+    /// [`SerializationError::ChecksumMismatch`] if the data is corrupt.
     pub fn deserialize<B>(bytes: B) -> Result<Self, SerializationError>
     where
         B: AsRef<[u8]>,
     {
-        let bytes = bytes.as_ref();
-        let magic = b"YARA-X";
+        let bytes = bytes
+            .as_ref()
+            .strip_prefix(Self::MAGIC)
+            .ok_or(SerializationError::InvalidFormat)?;
 
-        if bytes.len() < magic.len() || &bytes[0..magic.len()] != magic {
+        if bytes.len() < 8 {
             return Err(SerializationError::InvalidFormat);
         }
 
+        let (version, bytes) = bytes.split_at(4);
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+
+        if version != Self::FORMAT_VERSION {
+            return Err(SerializationError::UnsupportedVersion {
+                version,
+                expected: Self::FORMAT_VERSION,
+            });
+        }
+
+        let (checksum, bytes) = bytes.split_at(4);
+
+        if u32::from_le_bytes(checksum.try_into().unwrap())
+            != crc32fast::hash(bytes)
+        {
+            return Err(SerializationError::ChecksumMismatch);
+        }
+
         #[cfg(feature = "logging")]
         let start = Instant::now();
 
-        // Skip the magic and deserialize the remaining data.
+        // Deserialize the data after the header.
         let mut rules = bincode::DefaultOptions::new()
             .with_varint_encoding()
-            .deserialize::<Self>(&bytes[magic.len()..])?;
+            .deserialize::<Self>(bytes)?;
 
         #[cfg(feature = "logging")]
         info!("Deserialization time: {:?}", Instant::elapsed(&start));
@@ -178,15 +448,23 @@ impl Rules {
     where
         W: Write,
     {
+        // Serialize rules. The serialized data is kept in memory because
+        // its checksum goes in the header.
+        let data = bincode::DefaultOptions::new()
+            .with_varint_encoding()
+            .serialize(self)?;
+
         let mut writer = BufWriter::new(writer);
 
         // Write file header.
-        writer.write_all(b"YARA-X")?;
+        writer.write_all(Self::MAGIC)?;
+        writer.write_all(Self::FORMAT_VERSION.to_le_bytes().as_slice())?;
+        writer.write_all(crc32fast::hash(&data).to_le_bytes().as_slice())?;
 
-        // Serialize rules.
-        Ok(bincode::DefaultOptions::new()
-            .with_varint_encoding()
-            .serialize_into(writer, self)?)
+        writer.write_all(&data)?;
+        writer.flush()?;
+
+        Ok(())
     }
 
     /// Deserializes the rules from a `reader`.
@@ -227,8 +505,17 @@ impl Rules {
         // Set a size limit for the NFA automata. The default limit (10MB) is
         // too small for certain regexps seen in YARA rules in the wild, see:
         // https://github.com/VirusTotal/yara-x/issues/85
+        //
+        // Full DFAs are disabled. By default, they are built upfront for
+        // small regexps, and their size grows exponentially with the size
+        // of the regexp. The lazy DFA builds its states on demand instead,
+        // and memoizes them in a cache of bounded size. The cache lives as
+        // long as the compiled regexp, which is kept by the scanner across
+        // scans, so hot regexps don't build the same states over and over.
         let config = regex_automata::meta::Config::new()
-            .nfa_size_limit(Some(50 * 1024 * 1024));
+            .nfa_size_limit(Some(50 * 1024 * 1024))
+            .dfa(false)
+            .hybrid_cache_capacity(4 * 1024 * 1024);
 
         regex_automata::meta::Builder::new()
             .configure(config)
@@ -269,7 +556,13 @@ impl Rules {
         None
     }
 
+    /// Returns the time spent in each compilation phase.
     #[cfg(feature = "rules-profiling")]
+    #[inline]
+    pub(crate) fn compilation_profile(&self) -> &[(&'static str, Duration)] {
+        self.compilation_profile.as_slice()
+    }
+
     #[inline]
     pub(crate) fn rules(&self) -> &[RuleInfo] {
         self.rules.as_slice()
@@ -355,6 +648,13 @@ impl Rules {
                 .expect("failed to build Aho-Corasick automaton"),
         );
 
+        #[cfg(feature = "rules-profiling")]
+        crate::profiling::add_phase_time(
+            &mut self.compilation_profile,
+            "build_ac_automaton",
+            Instant::elapsed(&start),
+        );
+
         #[cfg(feature = "logging")]
         {
             info!(
@@ -378,6 +678,11 @@ impl Rules {
         }
     }
 
+    #[inline]
+    pub(crate) fn constant_folding(&self) -> bool {
+        self.constant_folding
+    }
+
     #[inline]
     pub(crate) fn lit_pool(&self) -> &BStringPool<LiteralId> {
         &self.lit_pool
@@ -453,6 +758,209 @@ impl fmt::Debug for Rules {
     }
 }
 
+/// Memory used by a set of compiled [`Rules`].
+///
+/// Each field contains the approximate number of bytes used by one of the
+/// components of the compiled rules. This is the result from
+/// [`Rules::memory_usage`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Memory used by the information about each rule, including its tags,
+    /// patterns and dependencies, but not its metadata.
+    pub rules: usize,
+    /// Memory used by the metadata entries of all rules. String values are
+    /// interned, and accounted for in [`MemoryUsage::literals`].
+    pub metadata: usize,
+    /// Memory used by sub-patterns.
+    pub sub_patterns: usize,
+    /// Memory used by the atoms extracted from patterns.
+    pub atoms: usize,
+    /// Memory used by the code of regexp and hex patterns.
+    pub re_code: usize,
+    /// Memory used by identifiers (rule names, pattern names, etc).
+    pub identifiers: usize,
+    /// Memory used by literal strings, including the literal patterns.
+    pub literals: usize,
+    /// Memory used by regular expressions used in conditions.
+    pub regexps: usize,
+    /// Memory used by global variables.
+    pub globals: usize,
+    /// Memory used by the Aho-Corasick automaton.
+    pub ac_automaton: usize,
+    /// Memory used by the native code produced for rule conditions.
+    pub wasm_code: usize,
+}
+
+impl MemoryUsage {
+    /// Total number of bytes used by the compiled rules.
+    pub fn total(&self) -> usize {
+        self.rules
+            + self.metadata
+            + self.sub_patterns
+            + self.atoms
+            + self.re_code
+            + self.identifiers
+            + self.literals
+            + self.regexps
+            + self.globals
+            + self.ac_automaton
+            + self.wasm_code
+    }
+}
+
+/// An iterator over the rules in [`Rules`], see [`Rules::iter`].
+pub struct RulesIter<'r> {
+    rules: &'r Rules,
+    iterator: iter::Enumerate<slice::Iter<'r, RuleInfo>>,
+}
+
+impl<'r> Iterator for RulesIter<'r> {
+    type Item = CompiledRule<'r>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, rule_info) = self.iterator.next()?;
+        Some(CompiledRule {
+            rules: self.rules,
+            rule_id: RuleId::from(id),
+            rule_info,
+        })
+    }
+}
+
+impl<'r> ExactSizeIterator for RulesIter<'r> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.iterator.len()
+    }
+}
+
+/// A rule in [`Rules`], as returned by [`Rules::iter`].
+///
+/// Unlike [`crate::Rule`], which is returned as part of the scan results,
+/// this type describes the rule itself, without any information about its
+/// matches.
+pub struct CompiledRule<'r> {
+    rules: &'r Rules,
+    rule_id: RuleId,
+    rule_info: &'r RuleInfo,
+}
+
+impl<'r> CompiledRule<'r> {
+    /// Returns the rule's ID. See [`RuleId`].
+    pub fn id(&self) -> RuleId {
+        self.rule_id
+    }
+
+    /// Returns the rule's name.
+    pub fn identifier(&self) -> &'r str {
+        self.rules.ident_pool().get(self.rule_info.ident_id).unwrap()
+    }
+
+    /// Returns the rule's namespace.
+    pub fn namespace(&self) -> &'r str {
+        self.rules.ident_pool().get(self.rule_info.namespace_ident_id).unwrap()
+    }
+
+    /// Returns the origin of the source code that contained the rule, if
+    /// any. See [`crate::Rule::origin`].
+    pub fn origin(&self) -> Option<&'r str> {
+        self.rule_info
+            .origin
+            .map(|id| self.rules.lit_pool().get_str(id).unwrap())
+    }
+
+    /// Returns true if the rule is private.
+    pub fn is_private(&self) -> bool {
+        self.rule_info.is_private
+    }
+
+    /// Returns true if the rule is global.
+    pub fn is_global(&self) -> bool {
+        self.rule_info.is_global
+    }
+
+    /// Returns the tags associated to this rule.
+    pub fn tags(&self) -> Tags<'r> {
+        Tags::new(self.rules, self.rule_info)
+    }
+
+    /// Returns the metadata associated to this rule.
+    pub fn metadata(&self) -> Metadata<'r> {
+        Metadata::new(self.rules, self.rule_info)
+    }
+
+    /// Returns the IDs of the rules referenced by this rule's condition,
+    /// sorted by ID.
+    ///
+    /// Only direct references are returned, use [`Rules::required_rules`]
+    /// for obtaining indirect ones. Notice that global rules can prevent
+    /// other rules in the same namespace from matching, but they are not
+    /// considered dependencies unless they are explicitly referenced.
+    pub fn dependencies(&self) -> &'r [RuleId] {
+        self.rule_info.dependencies.as_slice()
+    }
+
+    /// Returns the identifiers of the patterns defined by this rule (e.g:
+    /// `$a`, `$b`), in the order in which they were declared.
+    pub fn patterns(&self) -> PatternIdents<'r> {
+        PatternIdents {
+            rules: self.rules,
+            iterator: self.rule_info.patterns.iter(),
+        }
+    }
+}
+
+/// An iterator that returns the identifiers of the patterns defined by a
+/// rule, see [`CompiledRule::patterns`].
+pub struct PatternIdents<'r> {
+    rules: &'r Rules,
+    iterator: slice::Iter<'r, (IdentId, PatternId)>,
+}
+
+impl<'r> Iterator for PatternIdents<'r> {
+    type Item = &'r str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (ident_id, _) = self.iterator.next()?;
+        Some(self.rules.ident_pool().get(*ident_id).unwrap())
+    }
+}
+
+impl<'r> ExactSizeIterator for PatternIdents<'r> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.iterator.len()
+    }
+}
+
+/// Atoms extracted from a pattern, as returned by [`Rules::pattern_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternReport<'r> {
+    /// Namespace of the rule that contains the pattern.
+    pub namespace: &'r str,
+    /// Identifier of the rule that contains the pattern.
+    pub rule: &'r str,
+    /// Identifier of the pattern (e.g: `$a`).
+    pub pattern: &'r str,
+    /// Atoms extracted from the pattern. Empty for literal patterns that
+    /// are anchored with `at`, which are not searched for with atoms but
+    /// verified only at the offset where they are anchored.
+    pub atoms: Vec<AtomReport>,
+    /// Quality of the worst atom, or `None` if the pattern has no atoms.
+    pub quality: Option<i32>,
+}
+
+/// An atom extracted from a pattern, see [`Rules::pattern_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomReport {
+    /// The bytes that are searched for in the scanned data.
+    pub bytes: Vec<u8>,
+    /// A score that grows with the length of the atom and the diversity
+    /// of its bytes. Atoms with common bytes like `00`, `20`, `FF` or
+    /// `CC` have lower scores.
+    pub quality: i32,
+}
+
 /// Information about each of the individual rules included in [`Rules`].
 #[derive(Serialize, Deserialize)]
 pub(crate) struct RuleInfo {
@@ -467,12 +975,55 @@ pub(crate) struct RuleInfo {
     /// compilation phase, but not during the scan phase.
     #[serde(skip)]
     pub(crate) ident_span: Span,
+    /// Origin of the source code that contained the rule (see
+    /// [`SourceCode::with_origin`]), as an ID in the literals pool.
+    ///
+    /// [`SourceCode::with_origin`]: yara_x_parser::SourceCode::with_origin
+    pub(crate) origin: Option<LiteralId>,
+    /// Tags of this rule, in the order in which they appear in the source
+    /// code.
+    pub(crate) tags: Vec<IdentId>,
+    /// Metadata entries defined by this rule, in the order in which they
+    /// appear in the source code.
+    pub(crate) metadata: Vec<(IdentId, MetaValue)>,
     /// Vector with all the patterns defined by this rule.
     pub(crate) patterns: Vec<(IdentId, PatternId)>,
     /// True if the rule is global.
     pub(crate) is_global: bool,
     /// True if the rule is private.
     pub(crate) is_private: bool,
+    /// Rules referenced by the condition of this rule, sorted by ID and
+    /// without duplicates.
+    pub(crate) dependencies: Vec<RuleId>,
+    /// Patterns such that a single match of any of them is enough for the
+    /// rule's condition to be true. `None` if the condition doesn't have
+    /// this property. Used for stopping the search for patterns early, see
+    /// [`crate::Scanner::early_termination`].
+    pub(crate) sufficient_patterns: Option<Vec<PatternId>>,
+}
+
+impl RuleInfo {
+    /// Returns the number of bytes allocated in the heap by this rule,
+    /// excluding its metadata.
+    fn heap_size(&self) -> usize {
+        self.tags.capacity() * mem::size_of::<IdentId>()
+            + self.patterns.capacity() * mem::size_of::<(IdentId, PatternId)>()
+            + self.dependencies.capacity() * mem::size_of::<RuleId>()
+            + self
+                .sufficient_patterns
+                .as_ref()
+                .map_or(0, |p| p.capacity() * mem::size_of::<PatternId>())
+    }
+}
+
+/// Value of a metadata entry in a rule. String values are stored in the
+/// literals pool.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum MetaValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(LiteralId),
 }
 
 /// Represents an atom extracted from a pattern and added to the Aho-Corasick
@@ -3,13 +3,19 @@ use serde_json::json;
 use std::fs;
 use std::io::Write;
 use std::mem::size_of;
-use yara_x_parser::Parser;
+use yara_x_parser::ast::Span;
+use yara_x_parser::report::Level;
+use yara_x_parser::{Parser, SourceCode};
 
 use crate::compiler::{
-    SerializationError, SubPattern, Var, VarStack, VariableError,
+    IdentId, PatternId, RegexpId, RuleInfo, SerializationError, SubPattern,
+    SubPatternAtom, Var, VarStack, VariableError,
 };
 use crate::types::Type;
-use crate::{compile, Compiler, Error, Rules, Scanner};
+use crate::{
+    compile, Compiler, Error, MemoryIncludeResolver, MetaValue, OptLevel,
+    RuleId, Rules, ScanError, Scanner,
+};
 
 #[test]
 fn serialization() {
@@ -20,6 +26,20 @@ fn serialization() {
 
     assert!(matches!(
         Rules::deserialize(b"YARA-X").err().unwrap(),
+        SerializationError::InvalidFormat
+    ));
+
+    assert!(matches!(
+        Rules::deserialize(b"YARA-X\x00\x00\x00\x00\x00\x00\x00\x00")
+            .err()
+            .unwrap(),
+        SerializationError::UnsupportedVersion { version: 0, expected: 5 }
+    ));
+
+    assert!(matches!(
+        Rules::deserialize(b"YARA-X\x05\x00\x00\x00\x00\x00\x00\x00")
+            .err()
+            .unwrap(),
         SerializationError::InvalidEncoding(_)
     ));
 
@@ -28,6 +48,15 @@ fn serialization() {
         .serialize()
         .unwrap();
 
+    // Flipping any bit in the serialized data is detected.
+    let mut corrupt = rules.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+
+    assert!(matches!(
+        Rules::deserialize(corrupt).err().unwrap(),
+        SerializationError::ChecksumMismatch
+    ));
+
     let rules = Rules::deserialize(rules).unwrap();
 
     let mut scanner = Scanner::new(&rules);
@@ -43,6 +72,297 @@ fn serialization() {
     assert_eq!(size_of::<SubPattern>(), 24);
 }
 
+#[test]
+fn memory_usage() {
+    let rules = compile(
+        r#"
+        rule test_1 { strings: $a = "foo" condition: $a }
+        rule test_2 : tag {
+          meta:
+            author = "someone"
+            version = 2
+          strings:
+            $a = "foo"
+            $b = /ba[rz]/
+          condition:
+            $a and $b and test_1 and "foo" matches /fo+/
+        }
+        "#,
+    )
+    .unwrap();
+
+    let usage = rules.memory_usage();
+
+    // Two rules, with one tag, three patterns and one dependency between
+    // them. The single pattern in `test_1` is enough for its condition to
+    // be true.
+    assert_eq!(
+        usage.rules,
+        2 * size_of::<RuleInfo>()
+            + size_of::<IdentId>()
+            + 3 * size_of::<(IdentId, PatternId)>()
+            + size_of::<RuleId>()
+            + size_of::<PatternId>()
+    );
+
+    assert_eq!(
+        usage.metadata,
+        2 * size_of::<(IdentId, crate::compiler::MetaValue)>()
+    );
+
+    // "foo" is shared by both rules, so there are only two sub-patterns.
+    assert_eq!(usage.sub_patterns, 2 * size_of::<(PatternId, SubPattern)>());
+
+    // "foo", "bar" and "baz".
+    assert_eq!(usage.atoms, 3 * size_of::<SubPatternAtom>());
+
+    // "test_1", "test_2", "default", "$a", "$b", "tag", "author" and
+    // "version".
+    assert_eq!(usage.identifiers, 39);
+
+    // "someone" and "foo".
+    assert_eq!(usage.literals, 10);
+
+    // "/fo+/"
+    assert_eq!(usage.regexps, 5);
+
+    assert_eq!(usage.re_code, rules.re_code.len());
+    assert!(usage.ac_automaton > 0);
+    assert!(usage.wasm_code > 0);
+
+    // Adding a rule increases the memory used by each component.
+    let more_rules = compile(
+        r#"
+        rule test_1 { strings: $a = "foo" condition: $a }
+        rule test_2 : tag {
+          meta:
+            author = "someone"
+            version = 2
+          strings:
+            $a = "foo"
+            $b = /ba[rz]/
+          condition:
+            $a and $b and test_1 and "foo" matches /fo+/
+        }
+        rule test_3 { strings: $c = "qux" condition: $c }
+        "#,
+    )
+    .unwrap();
+
+    let more_usage = more_rules.memory_usage();
+
+    assert_eq!(
+        more_usage.rules - usage.rules,
+        size_of::<RuleInfo>()
+            + size_of::<(IdentId, PatternId)>()
+            + size_of::<PatternId>()
+    );
+
+    assert_eq!(more_usage.metadata, usage.metadata);
+
+    assert_eq!(
+        more_usage.sub_patterns - usage.sub_patterns,
+        size_of::<(PatternId, SubPattern)>()
+    );
+
+    // "test_3" and "$c".
+    assert_eq!(more_usage.identifiers - usage.identifiers, 8);
+
+    // "qux".
+    assert_eq!(more_usage.literals - usage.literals, 3);
+}
+
+#[test]
+fn scan_unknown_module() {
+    let mut rules =
+        compile(r#"import "test_proto2" rule test { condition: true }"#)
+            .unwrap();
+
+    // Simulate rules compiled by a version of YARA-X that has a module that
+    // doesn't exist in this one.
+    let ident_id = rules.ident_pool.get_or_intern("foo");
+    rules.imported_modules.push(ident_id);
+
+    let mut scanner = Scanner::new(&rules);
+
+    assert!(matches!(
+        scanner.scan(b""),
+        Err(ScanError::UnknownModule { module }) if module == "foo"
+    ));
+}
+
+#[test]
+fn rules_iter() {
+    let mut compiler = Compiler::new();
+
+    compiler
+        .add_source(
+            r#"
+            rule foo : t1 t2 {
+              meta:
+                author = "someone"
+                version = 2
+              strings:
+                $a = "foo"
+                $b = "bar"
+              condition:
+                $a and $b
+            }
+            private global rule bar { condition: true }"#,
+        )
+        .unwrap()
+        .new_namespace("other")
+        .add_source("rule baz { condition: true }")
+        .unwrap();
+
+    let rules = compiler.build();
+
+    assert_eq!(rules.iter().len(), 3);
+
+    assert_eq!(
+        rules
+            .iter()
+            .map(|r| (r.id(), r.namespace(), r.identifier()))
+            .collect::<Vec<_>>(),
+        vec![
+            (RuleId::from(0), "default", "foo"),
+            (RuleId::from(1), "default", "bar"),
+            (RuleId::from(2), "other", "baz"),
+        ]
+    );
+
+    let foo = rules.iter().next().unwrap();
+
+    assert!(!foo.is_private());
+    assert!(!foo.is_global());
+    assert_eq!(foo.tags().collect::<Vec<_>>(), vec!["t1", "t2"]);
+    assert_eq!(foo.patterns().collect::<Vec<_>>(), vec!["$a", "$b"]);
+    assert_eq!(
+        foo.metadata().collect::<Vec<_>>(),
+        vec![
+            ("author", MetaValue::String("someone")),
+            ("version", MetaValue::Integer(2))
+        ]
+    );
+
+    let bar = rules.iter().nth(1).unwrap();
+
+    assert!(bar.is_private());
+    assert!(bar.is_global());
+    assert_eq!(bar.patterns().len(), 0);
+}
+
+#[test]
+fn rule_dependencies() {
+    let rules = compile(
+        r#"
+        rule a { condition: true }
+        rule b { condition: a and a }
+        rule c { condition: b or true }
+        rule d { condition: a and c }
+        rule e { condition: true }
+        "#,
+    )
+    .unwrap();
+
+    let deps = |name| {
+        let rule = rules.rule_by_name("default", name).unwrap();
+        rules
+            .iter()
+            .nth(rule.into())
+            .unwrap()
+            .dependencies()
+            .iter()
+            .map(|id| rules.rule_name(*id).unwrap().1)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(deps("a").is_empty());
+    assert_eq!(deps("b"), vec!["a"]);
+    // References are kept even if constant folding removes them from the
+    // condition.
+    assert_eq!(deps("c"), vec!["b"]);
+    assert_eq!(deps("d"), vec!["a", "c"]);
+
+    let d = rules.rule_by_name("default", "d").unwrap();
+
+    assert_eq!(
+        rules
+            .required_rules(d)
+            .into_iter()
+            .map(|id| rules.rule_name(id).unwrap().1)
+            .collect::<Vec<_>>(),
+        vec!["a", "b", "c", "d"]
+    );
+
+    let rules = Rules::deserialize(rules.serialize().unwrap()).unwrap();
+
+    assert_eq!(rules.required_rules(d).len(), 4);
+}
+
+#[test]
+fn pattern_report() {
+    let rules = compile(
+        r#"
+        rule test_1 { strings: $a = "foobar" $b = { 00 00 00 00 } condition: $a and $b }
+        rule test_2 { strings: $a = "foobar" condition: $a }
+        "#,
+    )
+    .unwrap();
+
+    let report = rules.pattern_report();
+
+    assert_eq!(
+        report
+            .iter()
+            .map(|p| (p.rule, p.pattern, p.atoms.len()))
+            .collect::<Vec<_>>(),
+        vec![("test_1", "$a", 1), ("test_1", "$b", 1), ("test_2", "$a", 1)]
+    );
+
+    // Both rules share the same pattern, and therefore the same atoms.
+    assert_eq!(report[0].atoms, report[2].atoms);
+    assert_eq!(report[0].atoms[0].bytes, b"obar");
+
+    // Atoms with zeroes have lower quality.
+    assert!(report[1].quality < report[0].quality);
+}
+
+#[test]
+fn hex_pattern_atoms() {
+    let rules = compile(
+        r#"
+        rule test {
+          strings:
+            $a = { 00 00 [-] 4D 5A 90 00 [2-4] ( 01 | 02 ) }
+            $b = { ( AA BB CC DD | 11 22 33 44 ) }
+          condition:
+            $a and $b
+        }
+        "#,
+    )
+    .unwrap();
+
+    let report = rules.pattern_report();
+
+    // Unbounded jumps split the pattern in two chained pieces, each one with
+    // its own atom. The bounded jump and the alternative don't prevent the
+    // second piece from using its best four bytes.
+    assert_eq!(
+        report[0].atoms.iter().map(|a| a.bytes.as_slice()).collect::<Vec<_>>(),
+        vec![[0x00, 0x00].as_slice(), [0x4D, 0x5A, 0x90, 0x00].as_slice()]
+    );
+
+    // Each alternative gets its own atom.
+    assert_eq!(
+        report[1].atoms.iter().map(|a| a.bytes.as_slice()).collect::<Vec<_>>(),
+        vec![
+            [0xAA, 0xBB, 0xCC, 0xDD].as_slice(),
+            [0x11, 0x22, 0x33, 0x44].as_slice()
+        ]
+    );
+}
+
 #[test]
 fn namespaces() {
     // `foo` and `bar` are both in the default namespace, this compiles
@@ -65,6 +385,13 @@ fn namespaces() {
         .new_namespace("bar")
         .add_source("rule bar {condition: foo}")
         .is_err());
+
+    // Rules must be declared before they are used.
+    let mut compiler = Compiler::new();
+
+    assert!(compiler
+        .add_source("rule bar {condition: foo} rule foo {condition: true}")
+        .is_err());
 }
 
 #[test]
@@ -490,6 +817,60 @@ fn globals_json() {
     );
 }
 
+#[cfg(feature = "test_proto2-module")]
+#[test]
+fn globals_protobuf() {
+    use protobuf::MessageDyn;
+
+    use crate::modules::protos::test_proto2::{NestedProto2, TestProto2};
+
+    let mut msg = TestProto2::new();
+
+    msg.set_int64_one(1);
+    msg.array_int64.push(2);
+    msg.map_string_int64.insert("foo".to_string(), 3);
+
+    let mut nested = NestedProto2::new();
+    nested.set_nested_bool(true);
+    msg.map_int64_struct.insert(4, nested.clone());
+
+    let mut compiler = Compiler::new();
+
+    // Repeated fields of message type can't be empty.
+    assert_eq!(
+        compiler.define_global("ctx", &msg as &dyn MessageDyn).err(),
+        Some(VariableError::InvalidArray.into())
+    );
+
+    msg.array_struct.push(nested);
+
+    compiler
+        .define_global("ctx", &msg as &dyn MessageDyn)
+        .unwrap()
+        .add_source(
+            r#"
+            rule foo {
+              condition:
+                ctx.int64_one == 1 and
+                ctx.array_int64[0] == 2 and
+                ctx.map_string_int64["foo"] == 3 and
+                ctx.map_int64_struct[4].nested_bool and
+                ctx.Enumeration.ITEM_1 == 1
+            }"#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 1);
+
+    msg.set_int64_one(2);
+    scanner.set_global("ctx", &msg as &dyn MessageDyn).unwrap();
+
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 0);
+}
+
 #[test]
 fn relaxed_re_syntax() {
     let mut compiler = Compiler::new();
@@ -501,8 +882,38 @@ fn relaxed_re_syntax() {
         .add_source(r#"rule test_2 { strings: $a = /xyz{/ condition: $a }"#)
         .unwrap()
         .add_source(r#"rule test_3 { strings: $a = /xyz[\>]/ condition: $a }"#)
+        .unwrap()
+        .add_source(r#"rule test_4 { strings: $a = /ab++c/ condition: $a }"#)
         .unwrap();
 
+    // Each invalid construct accepted in relaxed mode produces a warning.
+    assert_eq!(
+        compiler
+            .warnings()
+            .iter()
+            .map(|w| (w.code(), w.span().start(), w.span().end()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("relaxed_regexp", 29, 31),
+            ("relaxed_regexp", 31, 33),
+            ("relaxed_regexp", 33, 35),
+            ("relaxed_regexp", 32, 33),
+            ("relaxed_regexp", 33, 35),
+            ("relaxed_regexp", 30, 33),
+        ]
+    );
+
+    assert_eq!(
+        compiler.warnings().last().unwrap().to_string(),
+        r#"warning: invalid regular expression accepted in relaxed mode
+ --> line:1:31
+  |
+1 | rule test_4 { strings: $a = /ab++c/ condition: $a }
+  |                               --- nested quantifier
+  |
+  = note: `b++` is interpreted as `(?:b+)+`"#
+    );
+
     let rules = compiler.build();
 
     assert_eq!(
@@ -531,6 +942,58 @@ fn relaxed_re_syntax() {
             .len(),
         1
     );
+
+    assert_eq!(
+        Scanner::new(&rules)
+            .scan(b"abbbc")
+            .expect("scan should not fail")
+            .matching_rules()
+            .len(),
+        1
+    );
+}
+
+#[test]
+#[cfg(feature = "test_proto2-module")]
+fn optimization_level() {
+    let src = r#"
+import "test_proto2"
+rule test_1 {
+  condition:
+    test_proto2.TopLevelEnumeration.ITEM_0x1000 == 0x1000 and 2 + 2 == 4
+}
+rule test_2 {
+  condition:
+    filesize < 10 and filesize > 100
+}"#;
+
+    for level in [OptLevel::None, OptLevel::Full] {
+        let mut compiler = Compiler::new();
+
+        compiler.optimization_level(level).add_source(src).unwrap();
+
+        // Rules that never match can be detected only when constant
+        // folding is enabled.
+        assert_eq!(
+            compiler.warnings().iter().any(|w| w.code() == "dead_rule"),
+            level == OptLevel::Full
+        );
+
+        // Enums must be resolved at scan time when constant folding is
+        // disabled, also when the rules are serialized and deserialized.
+        let rules =
+            Rules::deserialize(compiler.build().serialize().unwrap()).unwrap();
+
+        assert_eq!(
+            Scanner::new(&rules)
+                .scan(&[])
+                .expect("scan should not fail")
+                .matching_rules()
+                .map(|r| r.identifier())
+                .collect::<Vec<_>>(),
+            vec!["test_1"]
+        );
+    }
 }
 
 #[test]
@@ -561,6 +1024,122 @@ fn unsupported_modules() {
     );
 }
 
+#[test]
+fn unused_patterns() {
+    let src = r#"
+rule test {
+  strings:
+    $b = "bar"
+    $a = "foo"
+    $_c = "baz"
+  condition:
+    true
+}"#;
+
+    assert!(Compiler::new()
+        .add_source(src)
+        .unwrap_err()
+        .to_string()
+        .starts_with("error: unused pattern `$b`"));
+
+    let mut compiler = Compiler::new();
+
+    compiler.unused_patterns_as_warnings(true).add_source(src).unwrap();
+
+    assert_eq!(
+        compiler
+            .warnings()
+            .iter()
+            .map(|w| (w.code(), w.title()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("unused_pattern", "unused pattern `$b`".to_string()),
+            ("unused_pattern", "unused pattern `$a`".to_string()),
+        ]
+    );
+
+    let rules = compiler.build();
+
+    assert_eq!(
+        Scanner::new(&rules)
+            .scan(&[])
+            .expect("scan should not fail")
+            .matching_rules()
+            .len(),
+        1
+    );
+}
+
+#[test]
+#[cfg(feature = "test_proto2-module")]
+fn warnings_as_errors() {
+    let mut compiler = Compiler::new();
+
+    compiler.warnings_as_errors(true);
+
+    let err = compiler
+        .add_source(
+            "rule test_1 { condition: 1 } rule test_2 { condition: true }",
+        )
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        Error::WarningAsError(ref w) if w.code() == "non_boolean_as_boolean"
+    ));
+
+    // The rule that produced the warning was discarded, so another rule
+    // with the same name can be added.
+    compiler.add_source("rule test_1 { condition: true }").unwrap();
+
+    assert!(compiler
+        .add_source(r#"import "test_proto2" import "test_proto2""#)
+        .is_err());
+
+    compiler
+        .suppress_warning("non_boolean_as_boolean")
+        .suppress_warning("duplicate_import")
+        .add_source(
+            r#"
+import "test_proto2"
+import "test_proto2"
+rule test_2 { condition: 1 }"#,
+        )
+        .unwrap();
+
+    assert!(compiler.warnings().is_empty());
+
+    let rules = compiler.build();
+
+    assert_eq!(
+        Scanner::new(&rules)
+            .scan(&[])
+            .expect("scan should not fail")
+            .matching_rules()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn banned_modules() {
+    let mut compiler = Compiler::new();
+
+    compiler.ban_module("foo_module").ignore_module("foo_module");
+
+    assert_eq!(
+        compiler.add_source(r#"import "foo_module""#).unwrap_err().to_string(),
+        "error: module `foo_module` can't be used
+ --> line:1:1
+  |
+1 | import \"foo_module\"
+  | ^^^^^^^^^^^^^^^^^^^ module `foo_module` imported here
+  |"
+    );
+
+    assert!(compiler.add_source(r#"rule test { condition: true }"#).is_ok());
+}
+
 #[cfg(feature = "test_proto2-module")]
 #[test]
 fn import_modules() {
@@ -594,6 +1173,23 @@ fn import_modules() {
             rule bar {condition: test_proto2.int32_zero == 0}"#
         )
         .is_ok());
+
+    let mut compiler = Compiler::new();
+    compiler
+        .ignore_module("unsupported_module")
+        .add_source(
+            r#"
+            import "test_proto2"
+            import "unsupported_module"
+            rule foo {condition: test_proto2.int32_zero == 0}"#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+
+    assert_eq!(rules.imports().collect::<Vec<_>>(), vec!["test_proto2"]);
+    assert!(rules.requires_module("test_proto2"));
+    assert!(!rules.requires_module("unsupported_module"));
 }
 
 #[test]
@@ -634,6 +1230,127 @@ fn continue_after_error() {
     assert!(compiler.add_source(r#"rule test { condition: true }"#).is_ok());
 }
 
+#[test]
+fn includes() {
+    let resolver = MemoryIncludeResolver::new()
+        .add_file("common.yar", r#"rule common { condition: true }"#)
+        .add_file(
+            "nested.yar",
+            r#"
+            include "common.yar"
+            rule nested { condition: common }"#,
+        )
+        .add_file("cycle_a.yar", r#"include "cycle_b.yar""#)
+        .add_file("cycle_b.yar", r#"include "cycle_a.yar""#)
+        .add_file("error.yar", "rule error { condition: foo }");
+
+    let mut compiler = Compiler::new();
+
+    compiler
+        .include_resolver(resolver)
+        .add_source(
+            r#"
+            include "nested.yar"
+            rule test { condition: common and nested }"#,
+        )
+        .unwrap();
+
+    assert_eq!(
+        compiler
+            .add_source(r#"include "missing.yar""#)
+            .unwrap_err()
+            .to_string(),
+        "error: can not include `missing.yar`
+ --> line:1:1
+  |
+1 | include \"missing.yar\"
+  | ^^^^^^^^^^^^^^^^^^^^^ file `missing.yar` not found
+  |"
+    );
+
+    assert_eq!(
+        compiler
+            .add_source(r#"include "cycle_a.yar""#)
+            .unwrap_err()
+            .to_string(),
+        "error: circular include
+ --> cycle_b.yar:1:1
+  |
+1 | include \"cycle_a.yar\"
+  | ^^^^^^^^^^^^^^^^^^^^^ `cycle_a.yar` is already being included
+  |"
+    );
+
+    assert_eq!(
+        compiler.add_source(r#"include "error.yar""#).unwrap_err().to_string(),
+        "error: unknown identifier `foo`
+ --> error.yar:1:25
+  |
+1 | rule error { condition: foo }
+  |                         ^^^ this identifier has not been declared
+  |"
+    );
+
+    let rules = compiler.build();
+
+    assert_eq!(
+        Scanner::new(&rules)
+            .scan(&[])
+            .expect("scan should not fail")
+            .matching_rules()
+            .len(),
+        3
+    );
+
+    let resolver = MemoryIncludeResolver::new()
+        .add_file("a.yar", r#"include "b.yar""#)
+        .add_file("b.yar", r#"include "c.yar""#)
+        .add_file("c.yar", r#"rule c { condition: true }"#);
+
+    assert!(Compiler::new()
+        .include_resolver(resolver)
+        .max_include_depth(2)
+        .add_source(r#"include "a.yar""#)
+        .unwrap_err()
+        .to_string()
+        .starts_with("error: too many nested includes"));
+}
+
+#[test]
+fn includes_from_filesystem() {
+    let dir = std::env::temp_dir()
+        .join(format!("yara-x-includes-{}", std::process::id()));
+
+    fs::create_dir_all(dir.join("inc")).unwrap();
+    fs::write(dir.join("inc/common.yar"), "rule common { condition: true }")
+        .unwrap();
+    fs::write(dir.join("main.yar"), r#"include "inc/common.yar""#).unwrap();
+
+    let main = dir.join("main.yar");
+    let src = fs::read_to_string(&main).unwrap();
+
+    let mut compiler = Compiler::new();
+
+    let result = compiler.add_source(
+        SourceCode::from(src.as_str()).with_origin(&main.to_string_lossy()),
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(result.is_ok());
+
+    let rules = compiler.build();
+
+    assert_eq!(
+        Scanner::new(&rules)
+            .scan(&[])
+            .expect("scan should not fail")
+            .matching_rules()
+            .len(),
+        1
+    );
+}
+
 #[test]
 fn errors_2() {
     assert_eq!(
@@ -713,6 +1430,117 @@ fn utf8_errors() {
     );
 }
 
+#[cfg(feature = "test_proto2-module")]
+#[test]
+fn structured_warnings() {
+    let mut compiler = Compiler::new();
+
+    compiler
+        .add_source(
+            r#"
+import "test_proto2"
+import "test_proto2"
+rule test { condition: 0 }"#,
+        )
+        .unwrap();
+
+    let warnings = compiler.warnings();
+
+    assert_eq!(warnings.len(), 2);
+
+    assert_eq!(warnings[0].code(), "duplicate_import");
+    assert_eq!(warnings[0].title(), "duplicate import statement");
+    assert_eq!(warnings[0].level(), Level::Warning);
+    assert_eq!(warnings[0].span().start(), 22);
+    assert_eq!(warnings[0].span().end(), 42);
+
+    assert_eq!(warnings[1].code(), "non_boolean_as_boolean");
+    assert_eq!(warnings[1].title(), "non-boolean expression used as boolean");
+    assert_eq!(warnings[1].level(), Level::Warning);
+    assert_eq!(warnings[1].span().start(), 66);
+    assert_eq!(warnings[1].span().end(), 67);
+
+    let err = Compiler::new()
+        .add_source("rule test { condition: foo }")
+        .unwrap_err();
+
+    let Error::CompileError(err) = err else { panic!() };
+
+    assert_eq!(err.code(), "unknown_identifier");
+    assert_eq!(err.title(), "unknown identifier `foo`");
+    assert_eq!(err.level(), Level::Error);
+    assert_eq!(err.span().start(), 23);
+    assert_eq!(err.span().end(), 26);
+}
+
+#[test]
+fn diagnostics_json() {
+    let mut compiler = Compiler::new();
+
+    let err = compiler
+        .add_source(
+            SourceCode::from("rule test {\n  condition: foo\n}")
+                .with_origin("test.yar"),
+        )
+        .unwrap_err();
+
+    let json = compiler.error_json(&err);
+
+    assert_eq!(json["type"], "error");
+    assert_eq!(json["code"], "unknown_identifier");
+    assert_eq!(json["title"], "unknown identifier `foo`");
+    assert_eq!(
+        json["location"],
+        json!({
+            "origin": "test.yar",
+            "start": 25,
+            "end": 28,
+            "line": 2,
+            "column": 14,
+            "end_line": 2,
+            "end_column": 17,
+        })
+    );
+    assert_eq!(json["text"], err.to_string());
+
+    let err = compiler.add_source("rule test {").unwrap_err();
+    let json = compiler.error_json(&err);
+
+    assert_eq!(json["code"], "syntax_error");
+    assert_eq!(json["location"]["origin"], serde_json::Value::Null);
+    assert_eq!(json["location"]["line"], 1);
+
+    let err = compiler.define_global("1foo", 1).unwrap_err();
+    let json = compiler.error_json(&err);
+
+    assert_eq!(json["code"], "variable_error");
+    assert_eq!(json["location"], serde_json::Value::Null);
+
+    compiler.add_source("rule foo { condition: 0 }").unwrap();
+
+    let json = compiler.warnings_json();
+
+    assert_eq!(json[0]["type"], "warning");
+    assert_eq!(json[0]["code"], "non_boolean_as_boolean");
+    assert_eq!(json[0]["location"]["column"], 23);
+
+    // Lines and columns are null when the source code for the span is not
+    // known.
+    let compiler = Compiler::new();
+    let json = compiler.diagnostic_json(
+        Level::Error,
+        "some_error",
+        "some error".to_string(),
+        Some(Span::default()),
+        String::new(),
+    );
+
+    assert_eq!(json["location"]["line"], serde_json::Value::Null);
+    assert_eq!(json["location"]["column"], serde_json::Value::Null);
+    assert_eq!(json["location"]["end_line"], serde_json::Value::Null);
+    assert_eq!(json["location"]["end_column"], serde_json::Value::Null);
+}
+
 #[test]
 fn test_errors() {
     let mut mint = goldenfile::Mint::new(".");
@@ -779,3 +1607,26 @@ fn test_warnings() {
         }
     }
 }
+
+#[test]
+fn regexp_lazy_dfa() {
+    // Each additional repetition doubles the number of states in a full DFA
+    // for this regexp, but regexps are compiled into lazy DFAs that build
+    // their states on demand, so the memory used by the compiled regexp
+    // grows linearly.
+    let memory_usage: Vec<usize> = (1..=5)
+        .map(|n| {
+            let rules = crate::compile(
+                format!(r#"rule t {{ condition: "x" matches /[ab]*a[ab]{{{n}}}/ }}"#)
+                    .as_str(),
+            )
+            .unwrap();
+            rules.get_regexp(RegexpId::from(0)).memory_usage()
+        })
+        .collect();
+
+    let deltas: Vec<usize> =
+        memory_usage.windows(2).map(|w| w[1] - w[0]).collect();
+
+    assert!(deltas.iter().all(|delta| *delta == deltas[0]), "{:?}", deltas);
+}
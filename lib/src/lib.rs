@@ -42,31 +42,62 @@ assert_eq!(results.matching_rules().len(), 1);
 */
 
 pub use compiler::compile;
+pub use compiler::AtomReport;
 pub use compiler::CompileError;
+pub use compiler::CompiledRule;
 pub use compiler::Compiler;
 pub use compiler::Error;
+pub use compiler::FilesystemIncludeResolver;
+pub use compiler::IncludeResolver;
+pub use compiler::MemoryIncludeResolver;
+pub use compiler::MemoryUsage;
+pub use compiler::OptLevel;
+pub use compiler::PatternIdents;
+pub use compiler::PatternReport;
+pub use compiler::RuleId;
 pub use compiler::Rules;
+pub use compiler::RulesIter;
 pub use compiler::SerializationError;
 
+pub use scanner::CancelHandle;
+pub use scanner::DataProvider;
+pub use scanner::DirScanOptions;
 pub use scanner::Match;
 pub use scanner::Matches;
 pub use scanner::MatchingRules;
+pub use scanner::MemoryPolicy;
+pub use scanner::MemoryRegion;
+pub use scanner::MetaValue;
+pub use scanner::Metadata;
 pub use scanner::ModuleOutputs;
 pub use scanner::NonMatchingRules;
 pub use scanner::Pattern;
 pub use scanner::Patterns;
+pub use scanner::PooledScanner;
 pub use scanner::Rule;
 pub use scanner::ScanError;
+pub use scanner::ScanOptions;
 pub use scanner::ScanResults;
+pub use scanner::ScanStats;
 pub use scanner::Scanner;
+pub use scanner::ScannerPool;
+pub use scanner::Tags;
 
 pub use modules::mods;
 
+#[cfg(feature = "rules-profiling")]
+pub use profiling::{PatternProfile, ProfilingData, RuleProfile};
+
+#[cfg(feature = "pooling-allocator")]
+pub use wasm::{set_pooling_limits, PoolingLimits};
+
 pub use variables::Variable;
 pub use variables::VariableError;
 
 mod compiler;
 mod modules;
+#[cfg(feature = "rules-profiling")]
+mod profiling;
 mod re;
 mod scanner;
 mod string_pool;
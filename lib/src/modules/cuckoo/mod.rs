@@ -0,0 +1,174 @@
+/*! YARA module for writing rules based on the behavior of a file.
+
+The behavior is described by a report produced by a sandbox (e.g: [Cuckoo][1])
+while running the scanned file. This module doesn't analyze the scanned data,
+nor does it run the file in any way. The report must be provided by the user
+with [`crate::Scanner::set_module_output`] before each scan, if no report is
+provided all the functions in this module return false. Reports in Cuckoo's
+JSON format can be converted with [`Cuckoo::from_json`].
+
+[1]: https://cuckoosandbox.org
+ */
+
+use crate::compiler::RegexpId;
+use crate::modules::prelude::*;
+use crate::modules::protos::cuckoo::*;
+
+mod report;
+
+#[cfg(test)]
+mod tests;
+
+#[module_main]
+fn main(_data: &[u8]) -> Cuckoo {
+    // The report is provided by the user with `Scanner::set_module_output`,
+    // when it's not provided the output is an empty report.
+    Cuckoo::new()
+}
+
+/// Returns true if any of the DNS lookups performed by the file was for a
+/// hostname that matches the regular expression.
+#[module_export(name = "network.dns_lookup")]
+fn network_dns_lookup(ctx: &ScanContext, hostname: RegexpId) -> Option<bool> {
+    let network = &ctx.module_output::<Cuckoo>()?.network;
+
+    Some(network.dns_lookups.iter().any(|lookup| {
+        lookup
+            .hostname
+            .as_ref()
+            .is_some_and(|h| ctx.regexp_matches(hostname, h.as_bytes()))
+    }))
+}
+
+/// Returns true if the file issued an HTTP request with any method to an URI
+/// that matches the regular expression.
+#[module_export(name = "network.http_request")]
+fn network_http_request(ctx: &ScanContext, uri: RegexpId) -> Option<bool> {
+    http_request_impl(ctx, None, uri)
+}
+
+/// Returns true if the file issued an HTTP GET request to an URI that matches
+/// the regular expression.
+#[module_export(name = "network.http_get")]
+fn network_http_get(ctx: &ScanContext, uri: RegexpId) -> Option<bool> {
+    http_request_impl(ctx, Some("GET"), uri)
+}
+
+/// Returns true if the file issued an HTTP POST request to an URI that
+/// matches the regular expression.
+#[module_export(name = "network.http_post")]
+fn network_http_post(ctx: &ScanContext, uri: RegexpId) -> Option<bool> {
+    http_request_impl(ctx, Some("POST"), uri)
+}
+
+/// Returns true if the file issued an HTTP request with a User-Agent header
+/// that matches the regular expression.
+#[module_export(name = "network.http_user_agent")]
+fn network_http_user_agent(
+    ctx: &ScanContext,
+    user_agent: RegexpId,
+) -> Option<bool> {
+    let network = &ctx.module_output::<Cuckoo>()?.network;
+
+    Some(network.http_requests.iter().any(|request| {
+        request
+            .user_agent
+            .as_ref()
+            .is_some_and(|ua| ctx.regexp_matches(user_agent, ua.as_bytes()))
+    }))
+}
+
+/// Returns true if the file contacted a host with an IP address that matches
+/// the regular expression.
+#[module_export(name = "network.host")]
+fn network_host(ctx: &ScanContext, host: RegexpId) -> Option<bool> {
+    let network = &ctx.module_output::<Cuckoo>()?.network;
+
+    Some(network.hosts.iter().any(|h| ctx.regexp_matches(host, h.as_bytes())))
+}
+
+/// Returns true if the file established a TCP connection with a host that
+/// matches the regular expression, at the given port.
+#[module_export(name = "network.tcp")]
+fn network_tcp(ctx: &ScanContext, dst: RegexpId, port: i64) -> Option<bool> {
+    let network = &ctx.module_output::<Cuckoo>()?.network;
+    Some(connection_impl(ctx, &network.tcp_connections, dst, port))
+}
+
+/// Returns true if the file sent an UDP packet to a host that matches the
+/// regular expression, at the given port.
+#[module_export(name = "network.udp")]
+fn network_udp(ctx: &ScanContext, dst: RegexpId, port: i64) -> Option<bool> {
+    let network = &ctx.module_output::<Cuckoo>()?.network;
+    Some(connection_impl(ctx, &network.udp_connections, dst, port))
+}
+
+/// Returns true if the file accessed a registry key that matches the regular
+/// expression.
+#[module_export(name = "registry.key_access")]
+fn registry_key_access(ctx: &ScanContext, key: RegexpId) -> Option<bool> {
+    let registry = &ctx.module_output::<Cuckoo>()?.registry;
+
+    Some(registry.keys.iter().any(|k| ctx.regexp_matches(key, k.as_bytes())))
+}
+
+/// Returns true if the file accessed a file with a path that matches the
+/// regular expression.
+#[module_export(name = "filesystem.file_access")]
+fn filesystem_file_access(ctx: &ScanContext, path: RegexpId) -> Option<bool> {
+    let filesystem = &ctx.module_output::<Cuckoo>()?.filesystem;
+
+    Some(
+        filesystem
+            .files
+            .iter()
+            .any(|f| ctx.regexp_matches(path, f.as_bytes())),
+    )
+}
+
+/// Returns true if the file opened or created a mutex with a name that
+/// matches the regular expression.
+#[module_export(name = "sync.mutex")]
+fn sync_mutex(ctx: &ScanContext, name: RegexpId) -> Option<bool> {
+    let sync = &ctx.module_output::<Cuckoo>()?.sync;
+
+    Some(sync.mutexes.iter().any(|m| ctx.regexp_matches(name, m.as_bytes())))
+}
+
+fn http_request_impl(
+    ctx: &ScanContext,
+    method: Option<&str>,
+    uri: RegexpId,
+) -> Option<bool> {
+    let network = &ctx.module_output::<Cuckoo>()?.network;
+
+    Some(network.http_requests.iter().any(|request| {
+        let method_matches = method.map_or(true, |method| {
+            request
+                .method
+                .as_ref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(method))
+        });
+
+        method_matches
+            && request
+                .uri
+                .as_ref()
+                .is_some_and(|u| ctx.regexp_matches(uri, u.as_bytes()))
+    }))
+}
+
+fn connection_impl(
+    ctx: &ScanContext,
+    connections: &[Connection],
+    dst: RegexpId,
+    port: i64,
+) -> bool {
+    connections.iter().any(|conn| {
+        conn.dst_port.is_some_and(|p| p as i64 == port)
+            && conn
+                .dst
+                .as_ref()
+                .is_some_and(|d| ctx.regexp_matches(dst, d.as_bytes()))
+    })
+}
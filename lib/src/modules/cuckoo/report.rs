@@ -0,0 +1,128 @@
+/*! Conversion of Cuckoo's JSON reports into the [`Cuckoo`] structure.
+
+The JSON report is the one produced by Cuckoo's `jsondump` reporting
+module (i.e: `report.json`). Only the fields used by this module are read,
+the rest are ignored. Fields with unexpected types are ignored too, instead
+of producing an error, as the format of the report has changed between
+versions of Cuckoo.
+ */
+
+use serde_json::{Map, Value};
+
+use crate::modules::protos::cuckoo::*;
+
+impl Cuckoo {
+    /// Creates a [`Cuckoo`] structure from a JSON report produced by
+    /// Cuckoo.
+    ///
+    /// The result can be passed to [`crate::Scanner::set_module_output`].
+    /// Returns an error if `report` is not valid JSON.
+    pub fn from_json(report: &[u8]) -> Result<Self, serde_json::Error> {
+        let report: Value = serde_json::from_slice(report)?;
+        let mut cuckoo = Cuckoo::new();
+
+        if let Some(network) = report.get("network") {
+            cuckoo.network = Some(parse_network(network)).into();
+        }
+
+        if let Some(summary) =
+            report.get("behavior").and_then(|b| b.get("summary"))
+        {
+            let mut registry = Registry::new();
+            registry.keys = strings(summary.get("keys"));
+            cuckoo.registry = Some(registry).into();
+
+            let mut filesystem = Filesystem::new();
+            filesystem.files = strings(summary.get("files"));
+            cuckoo.filesystem = Some(filesystem).into();
+
+            let mut sync = Sync::new();
+            sync.mutexes = strings(summary.get("mutexes"));
+            cuckoo.sync = Some(sync).into();
+        }
+
+        Ok(cuckoo)
+    }
+}
+
+fn parse_network(network: &Value) -> Network {
+    let mut result = Network::new();
+
+    for http in objects(network.get("http")) {
+        let mut request = HttpRequest::new();
+        request.method = string(http.get("method"));
+        request.uri = string(http.get("uri"));
+        request.user_agent = string(http.get("user-agent"));
+        result.http_requests.push(request);
+    }
+
+    // Older versions of Cuckoo report DNS lookups as `hostname` and `ip`,
+    // newer versions use `request` and a list of `answers`.
+    for dns in objects(network.get("dns")) {
+        let mut lookup = DnsLookup::new();
+        lookup.hostname =
+            string(dns.get("hostname")).or_else(|| string(dns.get("request")));
+        lookup.ip = string(dns.get("ip")).or_else(|| {
+            objects(dns.get("answers"))
+                .find(|answer| {
+                    answer.get("type").and_then(Value::as_str) == Some("A")
+                })
+                .and_then(|answer| string(answer.get("data")))
+        });
+        result.dns_lookups.push(lookup);
+    }
+
+    // Hosts are either IP addresses, or objects with an `ip` field.
+    if let Some(hosts) = network.get("hosts").and_then(Value::as_array) {
+        result.hosts = hosts
+            .iter()
+            .filter_map(|host| {
+                string(Some(host)).or_else(|| string(host.get("ip")))
+            })
+            .collect();
+    }
+
+    result.tcp_connections = connections(network.get("tcp"));
+    result.udp_connections = connections(network.get("udp"));
+
+    result
+}
+
+fn connections(value: Option<&Value>) -> Vec<Connection> {
+    objects(value)
+        .map(|conn| {
+            let mut connection = Connection::new();
+            connection.dst = string(conn.get("dst"));
+            connection.dst_port = conn
+                .get("dport")
+                .and_then(Value::as_u64)
+                .and_then(|port| u32::try_from(port).ok());
+            connection
+        })
+        .collect()
+}
+
+/// Returns the objects in a JSON array, ignoring any other value.
+fn objects(
+    value: Option<&Value>,
+) -> impl Iterator<Item = &Map<String, Value>> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+}
+
+/// Returns the strings in a JSON array, ignoring any other value.
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|s| string(Some(s)))
+        .collect()
+}
+
+fn string(value: Option<&Value>) -> Option<String> {
+    value.and_then(Value::as_str).map(String::from)
+}
@@ -0,0 +1,211 @@
+use crate::mods::cuckoo::{Connection, Cuckoo, DnsLookup, HttpRequest};
+use crate::{Compiler, Scanner};
+
+fn report() -> Cuckoo {
+    let mut report = Cuckoo::new();
+
+    let mut request = HttpRequest::new();
+    request.set_method("POST".to_string());
+    request.set_uri("http://evil.example.com/gate.php".to_string());
+    request.set_user_agent("Mozilla/4.0 (compatible; MSIE 6.0)".to_string());
+    report.network.mut_or_insert_default().http_requests.push(request);
+
+    let mut lookup = DnsLookup::new();
+    lookup.set_hostname("evil.example.com".to_string());
+    lookup.set_ip("192.0.2.1".to_string());
+    report.network.mut_or_insert_default().dns_lookups.push(lookup);
+
+    report.network.mut_or_insert_default().hosts.push("192.0.2.1".to_string());
+
+    let mut conn = Connection::new();
+    conn.set_dst("192.0.2.1".to_string());
+    conn.set_dst_port(443);
+    report.network.mut_or_insert_default().tcp_connections.push(conn);
+
+    let mut conn = Connection::new();
+    conn.set_dst("198.51.100.7".to_string());
+    conn.set_dst_port(53);
+    report.network.mut_or_insert_default().udp_connections.push(conn);
+
+    report
+        .registry
+        .mut_or_insert_default()
+        .keys
+        .push(r"HKEY_CURRENT_USER\Software\Microsoft\Windows\CurrentVersion\Run\updater".to_string());
+
+    report
+        .filesystem
+        .mut_or_insert_default()
+        .files
+        .push(r"C:\Users\user\AppData\Roaming\updater.exe".to_string());
+
+    report
+        .sync
+        .mut_or_insert_default()
+        .mutexes
+        .push("Global\\evil_mutex".to_string());
+
+    report
+}
+
+fn matches(condition: &str, report: Option<Cuckoo>) -> bool {
+    let mut compiler = Compiler::new();
+
+    compiler
+        .add_source(
+            format!(
+                r#"import "cuckoo" rule test {{ condition: {} }}"#,
+                condition
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+
+    if let Some(report) = report {
+        scanner.set_module_output(Box::new(report)).unwrap();
+    }
+
+    scanner.scan(b"").unwrap().matching_rules().len() == 1
+}
+
+#[test]
+fn network() {
+    assert!(matches(
+        r#"cuckoo.network.http_request(/evil\.example\.com/)"#,
+        Some(report())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.http_post(/gate\.php$/)"#,
+        Some(report())
+    ));
+    assert!(!matches(
+        r#"cuckoo.network.http_get(/gate\.php$/)"#,
+        Some(report())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.http_user_agent(/MSIE 6\.0/)"#,
+        Some(report())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.dns_lookup(/^evil\./)"#,
+        Some(report())
+    ));
+    assert!(!matches(
+        r#"cuckoo.network.dns_lookup(/^good\./)"#,
+        Some(report())
+    ));
+    assert!(matches(r#"cuckoo.network.host(/^192\.0\.2\./)"#, Some(report())));
+    assert!(matches(
+        r#"cuckoo.network.tcp(/^192\.0\.2\.1$/, 443)"#,
+        Some(report())
+    ));
+    assert!(!matches(
+        r#"cuckoo.network.tcp(/^192\.0\.2\.1$/, 80)"#,
+        Some(report())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.udp(/^198\.51\.100\.7$/, 53)"#,
+        Some(report())
+    ));
+    assert!(!matches(
+        r#"cuckoo.network.udp(/^192\.0\.2\.1$/, 443)"#,
+        Some(report())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.dns_lookups[0].ip == "192.0.2.1""#,
+        Some(report())
+    ));
+}
+
+#[test]
+fn registry_filesystem_and_sync() {
+    assert!(matches(
+        r#"cuckoo.registry.key_access(/\\CurrentVersion\\Run\\/i)"#,
+        Some(report())
+    ));
+    assert!(matches(
+        r#"cuckoo.filesystem.file_access(/\\AppData\\Roaming\\.*\.exe$/)"#,
+        Some(report())
+    ));
+    assert!(matches(r#"cuckoo.sync.mutex(/evil_mutex/)"#, Some(report())));
+    assert!(!matches(r#"cuckoo.sync.mutex(/good_mutex/)"#, Some(report())));
+}
+
+#[test]
+fn no_report() {
+    // Without a report the functions return false, not undefined.
+    assert!(matches(r#"not cuckoo.network.http_request(/./)"#, None));
+    assert!(matches(r#"not cuckoo.registry.key_access(/./)"#, None));
+    assert!(matches(r#"not cuckoo.filesystem.file_access(/./)"#, None));
+    assert!(matches(r#"not cuckoo.sync.mutex(/./)"#, None));
+}
+
+#[test]
+fn json_report() {
+    let json = br#"{
+        "network": {
+            "http": [{
+                "method": "POST",
+                "uri": "http://evil.example.com/gate.php",
+                "user-agent": "Mozilla/4.0 (compatible; MSIE 6.0)"
+            }],
+            "dns": [{"hostname": "evil.example.com", "ip": "192.0.2.1"}],
+            "hosts": ["192.0.2.1"],
+            "tcp": [{"dst": "192.0.2.1", "dport": 443}],
+            "udp": [{"dst": "198.51.100.7", "dport": 53}]
+        },
+        "behavior": {
+            "summary": {
+                "keys": ["HKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Run\\updater"],
+                "files": ["C:\\Users\\user\\AppData\\Roaming\\updater.exe"],
+                "mutexes": ["Global\\evil_mutex"]
+            }
+        }
+    }"#;
+
+    assert_eq!(Cuckoo::from_json(json).unwrap(), report());
+
+    // Newer versions of Cuckoo use a different format for DNS lookups and
+    // hosts. Values with unexpected types are ignored.
+    let json = br#"{
+        "network": {
+            "dns": [{
+                "request": "evil.example.com",
+                "answers": [
+                    {"type": "CNAME", "data": "cdn.example.com"},
+                    {"type": "A", "data": "192.0.2.1"}
+                ]
+            }],
+            "hosts": [{"ip": "192.0.2.1"}, 1234],
+            "tcp": [{"dst": "192.0.2.1", "dport": "443"}]
+        }
+    }"#;
+
+    let report = Cuckoo::from_json(json).unwrap();
+
+    assert!(matches(
+        r#"cuckoo.network.dns_lookup(/^evil\./)"#,
+        Some(report.clone())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.dns_lookups[0].ip == "192.0.2.1""#,
+        Some(report.clone())
+    ));
+    assert!(matches(
+        r#"cuckoo.network.host(/^192\.0\.2\.1$/)"#,
+        Some(report.clone())
+    ));
+    assert!(matches(
+        r#"not defined cuckoo.network.hosts[1]"#,
+        Some(report.clone())
+    ));
+    assert!(matches(
+        r#"not cuckoo.network.tcp(/^192\.0\.2\.1$/, 443)"#,
+        Some(report)
+    ));
+
+    assert!(Cuckoo::from_json(b"not json").is_err());
+}
@@ -0,0 +1,490 @@
+/*! Parser for APK files.
+
+APK files are ZIP archives that contain, among other things, the compiled
+code of an Android application (`classes.dex`) and its manifest
+(`AndroidManifest.xml`), which is stored in Android's binary XML format.
+Signed APKs also contain an [`APK Signing Block`][1], located right before
+the ZIP central directory.
+
+[1]: https://source.android.com/docs/security/features/apksigning/v2
+ */
+
+use std::borrow::Cow;
+
+use nom::bytes::complete::{tag, take};
+use nom::multi::length_data;
+use nom::number::complete::{le_u16, le_u32, le_u64, u8};
+use nom::sequence::tuple;
+use nom::IResult;
+use sha2::{Digest, Sha256};
+
+/// An APK file.
+pub struct ApkFile<'a> {
+    data: &'a [u8],
+    /// Offset where the ZIP central directory starts.
+    cd_offset: usize,
+    /// Entries in the ZIP central directory.
+    entries: Vec<ZipEntry<'a>>,
+}
+
+struct ZipEntry<'a> {
+    name: &'a [u8],
+    compression_method: u16,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+impl<'a> ApkFile<'a> {
+    /// Maximum size of the files that will be extracted from the APK. This
+    /// prevents excessive memory usage with ZIP bombs.
+    const MAX_ENTRY_SIZE: usize = 64 * 1024 * 1024;
+
+    const APK_SIGNATURE_SCHEME_V2_BLOCK_ID: u32 = 0x7109871a;
+    const APK_SIGNATURE_SCHEME_V3_BLOCK_ID: u32 = 0xf05368c0;
+
+    /// Parses the ZIP central directory of an APK file. Returns `None` if
+    /// the data is not a ZIP archive, or if the archive doesn't contain
+    /// neither `AndroidManifest.xml` nor `classes.dex`.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if !data.starts_with(b"PK\x03\x04") {
+            return None;
+        }
+
+        // The end of central directory record is at the end of the file,
+        // followed by a comment of up to 65535 bytes.
+        let tail_start = data.len().saturating_sub(22 + 0xffff);
+        let eocd_offset = tail_start
+            + memchr::memmem::rfind(&data[tail_start..], b"PK\x05\x06")?;
+
+        let (_, (num_entries, cd_offset)) =
+            Self::parse_eocd(&data[eocd_offset..]).ok()?;
+
+        let mut input = data.get(cd_offset..)?;
+        let mut entries = Vec::new();
+
+        for _ in 0..num_entries {
+            match Self::parse_cd_entry(input) {
+                Ok((remainder, entry)) => {
+                    entries.push(entry);
+                    input = remainder;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if !entries.iter().any(|entry| {
+            entry.name == b"AndroidManifest.xml"
+                || entry.name == b"classes.dex"
+        }) {
+            return None;
+        }
+
+        Some(Self { data, cd_offset, entries })
+    }
+
+    /// Returns the content of the file with the given name. Returns `None`
+    /// if the file doesn't exist, is corrupt, is compressed with a method
+    /// other than deflate, or is larger than [`ApkFile::MAX_ENTRY_SIZE`].
+    pub fn read(&self, name: &str) -> Option<Cow<'a, [u8]>> {
+        let entry =
+            self.entries.iter().find(|entry| entry.name == name.as_bytes())?;
+
+        let local_header = self.data.get(entry.local_header_offset..)?;
+
+        // The data starts after the local header, which has a fixed size
+        // of 30 bytes followed by the file name and extra field.
+        let (_, name_and_extra_len) =
+            Self::parse_local_header(local_header).ok()?;
+
+        let start = 30 + name_and_extra_len;
+        let compressed = local_header
+            .get(start..start.checked_add(entry.compressed_size)?)?;
+
+        match entry.compression_method {
+            // Stored.
+            0 if compressed.len() <= Self::MAX_ENTRY_SIZE => {
+                Some(Cow::Borrowed(compressed))
+            }
+            // Deflate.
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(
+                compressed,
+                entry.uncompressed_size.min(Self::MAX_ENTRY_SIZE),
+            )
+            .ok()
+            .map(Cow::Owned),
+            _ => None,
+        }
+    }
+
+    /// Returns the package name declared in `AndroidManifest.xml`.
+    pub fn package_name(&self) -> Option<String> {
+        manifest_package(&self.read("AndroidManifest.xml")?)
+    }
+
+    /// Returns the SHA-256 digests of the signers' certificates, as found
+    /// in the APK Signature Scheme v2 and v3 blocks. Each digest appears
+    /// only once, even if the same signer appears in both blocks.
+    pub fn signer_digests(&self) -> Vec<String> {
+        let mut digests: Vec<String> = Vec::new();
+
+        for (id, value) in self.signing_block_pairs() {
+            if id != Self::APK_SIGNATURE_SCHEME_V2_BLOCK_ID
+                && id != Self::APK_SIGNATURE_SCHEME_V3_BLOCK_ID
+            {
+                continue;
+            }
+            for certificate in signer_certificates(value) {
+                let digest = format!("{:x}", Sha256::digest(certificate));
+                if !digests.contains(&digest) {
+                    digests.push(digest);
+                }
+            }
+        }
+
+        digests
+    }
+
+    /// Returns the ID-value pairs in the APK Signing Block, or an empty
+    /// vector if the APK doesn't have a signing block.
+    fn signing_block_pairs(&self) -> Vec<(u32, &'a [u8])> {
+        let mut pairs = Vec::new();
+
+        // The block ends with its size, excluding the size field at the
+        // start of the block, followed by a magic.
+        let footer_start = match self.cd_offset.checked_sub(24) {
+            Some(footer_start) => footer_start,
+            None => return pairs,
+        };
+
+        let size = match Self::parse_signing_block_footer(
+            &self.data[footer_start..self.cd_offset],
+        ) {
+            Ok((_, size)) => size,
+            Err(_) => return pairs,
+        };
+
+        let mut input = match usize::try_from(size)
+            .ok()
+            .and_then(|size| size.checked_add(8))
+            .and_then(|size| self.cd_offset.checked_sub(size))
+            .and_then(|start| self.data.get(start + 8..footer_start))
+        {
+            Some(input) => input,
+            None => return pairs,
+        };
+
+        while let Ok((remainder, (id, value))) = Self::parse_pair(input) {
+            pairs.push((id, value));
+            input = remainder;
+        }
+
+        pairs
+    }
+
+    fn parse_signing_block_footer(input: &[u8]) -> IResult<&[u8], u64> {
+        let (input, (size, _)) =
+            tuple((le_u64, tag(b"APK Sig Block 42")))(input)?;
+        Ok((input, size))
+    }
+
+    /// Parses an ID-value pair in the APK Signing Block, which is prefixed
+    /// by its length as a 64-bit integer.
+    fn parse_pair(input: &'a [u8]) -> IResult<&'a [u8], (u32, &'a [u8])> {
+        let (input, pair) = length_data(le_u64)(input)?;
+        let (value, id) = le_u32(pair)?;
+        Ok((input, (id, value)))
+    }
+
+    /// Parses the end of central directory record, returning the number
+    /// of entries in the central directory and its offset.
+    fn parse_eocd(input: &[u8]) -> IResult<&[u8], (u16, usize)> {
+        let (input, (_, _, _, _, num_entries, _, cd_offset)) =
+            tuple((
+                tag(b"PK\x05\x06"),
+                le_u16, // number of this disk
+                le_u16, // disk where the central directory starts
+                le_u16, // number of entries in this disk
+                le_u16, // total number of entries
+                le_u32, // size of the central directory
+                le_u32, // offset of the central directory
+            ))(input)?;
+        Ok((input, (num_entries, cd_offset as usize)))
+    }
+
+    fn parse_local_header(input: &[u8]) -> IResult<&[u8], usize> {
+        let (input, (_, _, name_len, extra_len)) = tuple((
+            tag(b"PK\x03\x04"),
+            take(22_usize), // version, flags, compression, time, date, sizes
+            le_u16,         // file name length
+            le_u16,         // extra field length
+        ))(input)?;
+        Ok((input, name_len as usize + extra_len as usize))
+    }
+
+    fn parse_cd_entry(input: &'a [u8]) -> IResult<&'a [u8], ZipEntry<'a>> {
+        let (
+            input,
+            (
+                _,
+                _,
+                compression_method,
+                _,
+                compressed_size,
+                uncompressed_size,
+                name_len,
+                extra_len,
+                comment_len,
+                _,
+                local_header_offset,
+            ),
+        ) = tuple((
+            tag(b"PK\x01\x02"),
+            take(6_usize), // version made by, version needed, flags
+            le_u16,        // compression method
+            take(8_usize), // time, date, crc32
+            le_u32,        // compressed size
+            le_u32,        // uncompressed size
+            le_u16,        // file name length
+            le_u16,        // extra field length
+            le_u16,        // comment length
+            take(8_usize), // disk number, internal and external attributes
+            le_u32,        // offset of the local header
+        ))(input)?;
+
+        let (input, name) = take(name_len)(input)?;
+        let (input, _) =
+            take(extra_len as usize + comment_len as usize)(input)?;
+
+        Ok((
+            input,
+            ZipEntry {
+                name,
+                compression_method,
+                compressed_size: compressed_size as usize,
+                uncompressed_size: uncompressed_size as usize,
+                local_header_offset: local_header_offset as usize,
+            },
+        ))
+    }
+}
+
+/// Returns the certificate of each signer in the value of an APK Signature
+/// Scheme v2 or v3 block.
+///
+/// The value is a length-prefixed sequence of length-prefixed signers. In
+/// both versions each signer starts with the length-prefixed signed data,
+/// which contains a length-prefixed sequence of digests, followed by a
+/// length-prefixed sequence of certificates. The first certificate is the
+/// signer's certificate.
+fn signer_certificates(value: &[u8]) -> Vec<&[u8]> {
+    let mut certificates = Vec::new();
+
+    let mut signers = match length_prefixed(value) {
+        Ok((_, signers)) => signers,
+        Err(_) => return certificates,
+    };
+
+    while let Ok((remainder, signer)) = length_prefixed(signers) {
+        let certificate = length_prefixed(signer)
+            .and_then(|(_, signed_data)| {
+                tuple((length_prefixed, length_prefixed))(signed_data)
+            })
+            .and_then(|(_, (_digests, certificates))| {
+                length_prefixed(certificates)
+            });
+
+        if let Ok((_, certificate)) = certificate {
+            certificates.push(certificate);
+        }
+
+        signers = remainder;
+    }
+
+    certificates
+}
+
+/// Parses a sequence of bytes prefixed by its length as a 32-bit integer.
+fn length_prefixed(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    length_data(le_u32)(input)
+}
+
+/// Chunk types in Android's binary XML format.
+const RES_STRING_POOL_TYPE: u16 = 0x0001;
+const RES_XML_TYPE: u16 = 0x0003;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+
+/// Value type for attributes whose value is a string.
+const TYPE_STRING: u8 = 0x03;
+
+/// Returns the value of the `package` attribute in the `<manifest>` element
+/// of an `AndroidManifest.xml` file in binary XML format.
+fn manifest_package(xml: &[u8]) -> Option<String> {
+    let (_, (chunk_type, header_size, _)) = chunk_header(xml).ok()?;
+
+    if chunk_type != RES_XML_TYPE {
+        return None;
+    }
+
+    let mut offset = header_size as usize;
+    let mut string_pool = None;
+
+    while let Some(chunk) = xml.get(offset..) {
+        let (_, (chunk_type, header_size, size)) = chunk_header(chunk).ok()?;
+        let chunk = chunk.get(..size as usize)?;
+
+        match chunk_type {
+            RES_STRING_POOL_TYPE => string_pool = Some(StringPool(chunk)),
+            // The first element is `<manifest>`.
+            RES_XML_START_ELEMENT_TYPE => {
+                return manifest_element_package(
+                    chunk,
+                    header_size as usize,
+                    string_pool.as_ref()?,
+                );
+            }
+            _ => {}
+        }
+
+        if size < 8 {
+            return None;
+        }
+
+        offset += size as usize;
+    }
+
+    None
+}
+
+/// Returns the value of the `package` attribute in a start element chunk,
+/// if the element is `<manifest>`.
+fn manifest_element_package(
+    chunk: &[u8],
+    header_size: usize,
+    strings: &StringPool,
+) -> Option<String> {
+    let (_, (_, name, attr_start, attr_size, attr_count)) =
+        tuple((
+            le_u32::<_, nom::error::Error<_>>, // namespace
+            le_u32,                            // name
+            le_u16,                            // attribute start
+            le_u16,                            // attribute size
+            le_u16,                            // attribute count
+        ))(chunk.get(header_size..)?)
+        .ok()?;
+
+    if strings.get(name)? != "manifest" {
+        return None;
+    }
+
+    let attrs = chunk.get(header_size + attr_start as usize..)?;
+
+    for i in 0..attr_count as usize {
+        let attr = attrs.get(i * attr_size as usize..)?;
+        let (_, (_, name, raw_value, _, _, data_type, data)) = tuple((
+            le_u32::<_, nom::error::Error<_>>, // namespace
+            le_u32,                            // name
+            le_u32,                            // raw value
+            le_u16,                            // value size
+            u8,                                // reserved
+            u8,                                // value data type
+            le_u32,                            // value data
+        ))(attr)
+        .ok()?;
+
+        if strings.get(name).as_deref() != Some("package") {
+            continue;
+        }
+
+        return if raw_value != u32::MAX {
+            strings.get(raw_value)
+        } else if data_type == TYPE_STRING {
+            strings.get(data)
+        } else {
+            None
+        };
+    }
+
+    None
+}
+
+/// Parses the header that all chunks in a binary XML file start with.
+/// Returns the chunk type, the size of the header and the size of the
+/// whole chunk.
+fn chunk_header(input: &[u8]) -> IResult<&[u8], (u16, u16, u32)> {
+    tuple((le_u16, le_u16, le_u32))(input)
+}
+
+/// String pool chunk in a binary XML file.
+struct StringPool<'a>(&'a [u8]);
+
+impl StringPool<'_> {
+    /// Flag indicating that strings are encoded as UTF-8 instead of UTF-16.
+    const UTF8_FLAG: u32 = 1 << 8;
+
+    /// Returns the string with the given index.
+    fn get(&self, idx: u32) -> Option<String> {
+        let (_, (_, header_size, _, count, _, flags, strings_start, _)) =
+            tuple((
+                le_u16::<_, nom::error::Error<_>>, // type
+                le_u16,                            // header size
+                le_u32,                            // size
+                le_u32,                            // string count
+                le_u32,                            // style count
+                le_u32,                            // flags
+                le_u32,                            // strings start
+                le_u32,                            // styles start
+            ))(self.0)
+            .ok()?;
+
+        if idx >= count {
+            return None;
+        }
+
+        let (_, offset) = le_u32::<_, nom::error::Error<_>>(
+            self.0.get(header_size as usize + idx as usize * 4..)?,
+        )
+        .ok()?;
+
+        let input = self
+            .0
+            .get((strings_start as usize).checked_add(offset as usize)?..)?;
+
+        if flags & Self::UTF8_FLAG != 0 {
+            // The length in UTF-16 code units, followed by the length in
+            // bytes. Each length is encoded in one or two bytes.
+            let (input, _) = Self::utf8_len(input).ok()?;
+            let (input, len) = Self::utf8_len(input).ok()?;
+            let bytes = input.get(..len)?;
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        } else {
+            // The length in UTF-16 code units, encoded in one or two 16-bit
+            // words.
+            let (input, len) =
+                le_u16::<_, nom::error::Error<_>>(input).ok()?;
+            let (input, len) = if len & 0x8000 != 0 {
+                let (input, low) =
+                    le_u16::<_, nom::error::Error<_>>(input).ok()?;
+                (input, ((len as usize & 0x7fff) << 16) | low as usize)
+            } else {
+                (input, len as usize)
+            };
+            let units: Vec<u16> = input
+                .get(..len.checked_mul(2)?)?
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+    }
+
+    fn utf8_len(input: &[u8]) -> IResult<&[u8], usize> {
+        let (input, len) = u8(input)?;
+        if len & 0x80 != 0 {
+            let (input, low) = u8(input)?;
+            Ok((input, ((len as usize & 0x7f) << 8) | low as usize))
+        } else {
+            Ok((input, len as usize))
+        }
+    }
+}
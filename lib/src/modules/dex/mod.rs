@@ -0,0 +1,116 @@
+/*! YARA module that parses DEX files.
+
+DEX (Dalvik Executable) files contain the compiled code of Android
+applications. This module parses the tables in the DEX file (strings, types,
+method prototypes, fields, methods and classes) and exposes them to YARA
+rules, resolving the indexes used by the DEX format into the actual names.
+
+APK files are also accepted. In that case the module parses the
+`classes.dex` file inside the APK, and additionally exposes the package name
+declared in the manifest and the digests of the signers' certificates.
+
+This module is based on the [`DEX format specification`][1].
+
+[1]: https://source.android.com/docs/core/runtime/dex-format
+ */
+
+use protobuf::MessageField;
+
+use crate::modules::prelude::*;
+use crate::modules::protos::dex::*;
+
+mod apk;
+pub mod parser;
+
+#[cfg(test)]
+mod tests;
+
+#[module_main]
+fn main(data: &[u8]) -> Dex {
+    let apk = apk::ApkFile::parse(data);
+
+    let mut dex = match apk.as_ref() {
+        Some(apk) => apk.read("classes.dex").and_then(|classes| {
+            parser::DexParser::new().parse(classes.as_ref()).ok()
+        }),
+        None => parser::DexParser::new().parse(data).ok(),
+    }
+    .unwrap_or_else(|| {
+        let mut dex = Dex::new();
+        dex.is_dex = Some(false);
+        dex
+    });
+
+    if let Some(apk) = apk {
+        let mut info = Apk::new();
+        info.package_name = apk.package_name();
+        info.signer_digests = apk.signer_digests();
+        dex.apk = MessageField::some(info);
+    }
+
+    dex
+}
+
+/// Returns true if the DEX file contains the string `s` in its string
+/// table.
+#[module_export]
+fn has_string(ctx: &ScanContext, s: RuntimeString) -> Option<bool> {
+    let dex = ctx.module_output::<Dex>()?;
+    let expected = s.as_bstr(ctx);
+
+    Some(dex.string_table.iter().any(|s| expected == s.as_bytes()))
+}
+
+/// Returns true if the DEX file references a method named `name`, in any
+/// class.
+#[module_export(name = "has_method")]
+fn has_method(ctx: &ScanContext, name: RuntimeString) -> Option<bool> {
+    let dex = ctx.module_output::<Dex>()?;
+    let expected_name = name.as_bstr(ctx);
+
+    Some(dex.methods.iter().any(|method| {
+        method
+            .name
+            .as_ref()
+            .is_some_and(|name| expected_name == name.as_bytes())
+    }))
+}
+
+/// Returns true if the DEX file references a method named `name` in the
+/// class with descriptor `class` (e.g: "Ljava/lang/Object;").
+#[module_export(name = "has_method")]
+fn has_class_method(
+    ctx: &ScanContext,
+    class: RuntimeString,
+    name: RuntimeString,
+) -> Option<bool> {
+    let dex = ctx.module_output::<Dex>()?;
+    let expected_class = class.as_bstr(ctx);
+    let expected_name = name.as_bstr(ctx);
+
+    Some(dex.methods.iter().any(|method| {
+        method
+            .class
+            .as_ref()
+            .is_some_and(|class| expected_class == class.as_bytes())
+            && method
+                .name
+                .as_ref()
+                .is_some_and(|name| expected_name == name.as_bytes())
+    }))
+}
+
+/// Returns true if the DEX file defines a class with descriptor `class`
+/// (e.g: "Lcom/example/Main;").
+#[module_export]
+fn has_class(ctx: &ScanContext, class: RuntimeString) -> Option<bool> {
+    let dex = ctx.module_output::<Dex>()?;
+    let expected_class = class.as_bstr(ctx);
+
+    Some(dex.class_defs.iter().any(|class_def| {
+        class_def
+            .class
+            .as_ref()
+            .is_some_and(|class| expected_class == class.as_bytes())
+    }))
+}
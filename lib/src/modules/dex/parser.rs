@@ -0,0 +1,614 @@
+use std::mem;
+
+use rustc_hash::FxHashMap;
+
+use nom::bytes::complete::{tag, take, take_till};
+use nom::combinator::{map, map_res, verify};
+use nom::number::complete::{le_u16, le_u32, u8};
+use nom::sequence::tuple;
+use nom::{Err, IResult, Parser};
+use protobuf::{EnumOrUnknown, MessageField};
+
+use crate::modules::protos::dex::{
+    ClassItem, Dex, DexHeader, FieldItem, MapItem, MapList, MethodItem,
+    ProtoItem, TypeCode,
+};
+
+/// A DEX file parser.
+pub struct DexParser {
+    result: Dex,
+}
+
+impl DexParser {
+    /// Maximum number of items that will be parsed from each of the tables
+    /// in the file (strings, types, methods, etc). This prevents excessive
+    /// memory usage with corrupted files.
+    const MAX_ITEMS: usize = 500_000;
+
+    /// Value used in some index fields for indicating the absence of a
+    /// value.
+    const NO_INDEX: u32 = 0xffffffff;
+
+    /// Creates a new parser for DEX files.
+    pub fn new() -> Self {
+        Self { result: Dex::default() }
+    }
+
+    /// Parses a DEX file and produces a [`Dex`] protobuf containing metadata
+    /// extracted from the file.
+    pub fn parse<'a>(
+        &mut self,
+        data: &'a [u8],
+    ) -> Result<Dex, Err<nom::error::Error<&'a [u8]>>> {
+        let (_, header) = Self::parse_header(data)?;
+
+        self.result.is_dex = Some(true);
+
+        // Each table is parsed independently. If a table is truncated or
+        // corrupt, the items that could be parsed are kept and the rest of
+        // the tables are parsed anyway.
+        let strings = Self::parse_strings(data, &header);
+        let types = Self::parse_types(data, &header, &strings);
+        let mut type_lists = TypeLists::new(data, &types);
+
+        let protos =
+            Self::parse_protos(data, &header, &strings, &mut type_lists);
+
+        self.result.fields =
+            Self::parse_fields(data, &header, &strings, &types);
+
+        self.result.methods = Self::parse_methods(
+            data,
+            &header,
+            &strings,
+            &types,
+            &protos,
+            &mut type_lists,
+        );
+
+        self.result.class_defs = Self::parse_class_defs(
+            data,
+            &header,
+            &strings,
+            &types,
+            &mut type_lists,
+        );
+
+        self.result.map_list = Self::parse_map_list(data, &header)
+            .map_or_else(MessageField::none, MessageField::some);
+
+        self.result.string_table =
+            strings.into_iter().map(|s| s.unwrap_or_default()).collect();
+
+        self.result.types =
+            types.into_iter().map(|t| t.unwrap_or_default()).collect();
+
+        self.result.protos = protos;
+        self.result.header = MessageField::some(header);
+
+        Ok(mem::take(&mut self.result))
+    }
+}
+
+impl DexParser {
+    fn parse_header(input: &[u8]) -> IResult<&[u8], DexHeader> {
+        let (
+            remainder,
+            (
+                (_, version, _),
+                checksum,
+                signature,
+                file_size,
+                header_size,
+                endian_tag,
+                (link_size, link_off),
+                map_off,
+                (string_ids_size, string_ids_off),
+                (type_ids_size, type_ids_off),
+                (proto_ids_size, proto_ids_off),
+                (field_ids_size, field_ids_off),
+                (method_ids_size, method_ids_off),
+                (class_defs_size, class_defs_off),
+                (data_size, data_off),
+            ),
+        ) = tuple((
+            // The magic is "dex\n" followed by the version number as three
+            // ASCII digits and a null character (e.g: "dex\n035\0").
+            tuple((
+                tag(b"dex\n"),
+                map_res(
+                    verify(take(3_usize), |v: &[u8]| {
+                        v.iter().all(u8::is_ascii_digit)
+                    }),
+                    |v: &[u8]| std::str::from_utf8(v).unwrap().parse::<u32>(),
+                ),
+                tag(b"\0"),
+            )),
+            le_u32,                  // checksum
+            take(20_usize),          // signature
+            le_u32,                  // file_size
+            le_u32,                  // header_size
+            le_u32,                  // endian_tag
+            tuple((le_u32, le_u32)), // link_size, link_off
+            le_u32,                  // map_off
+            tuple((le_u32, le_u32)), // string_ids_size, string_ids_off
+            tuple((le_u32, le_u32)), // type_ids_size, type_ids_off
+            tuple((le_u32, le_u32)), // proto_ids_size, proto_ids_off
+            tuple((le_u32, le_u32)), // field_ids_size, field_ids_off
+            tuple((le_u32, le_u32)), // method_ids_size, method_ids_off
+            tuple((le_u32, le_u32)), // class_defs_size, class_defs_off
+            tuple((le_u32, le_u32)), // data_size, data_off
+        ))(input)?;
+
+        let mut header = DexHeader::new();
+
+        header.version = Some(version);
+        header.checksum = Some(checksum);
+        header.signature =
+            Some(signature.iter().map(|b| format!("{:02x}", b)).collect());
+        header.file_size = Some(file_size);
+        header.header_size = Some(header_size);
+        header.endian_tag = Some(endian_tag);
+        header.link_size = Some(link_size);
+        header.link_off = Some(link_off);
+        header.map_off = Some(map_off);
+        header.string_ids_size = Some(string_ids_size);
+        header.string_ids_off = Some(string_ids_off);
+        header.type_ids_size = Some(type_ids_size);
+        header.type_ids_off = Some(type_ids_off);
+        header.proto_ids_size = Some(proto_ids_size);
+        header.proto_ids_off = Some(proto_ids_off);
+        header.field_ids_size = Some(field_ids_size);
+        header.field_ids_off = Some(field_ids_off);
+        header.method_ids_size = Some(method_ids_size);
+        header.method_ids_off = Some(method_ids_off);
+        header.class_defs_size = Some(class_defs_size);
+        header.class_defs_off = Some(class_defs_off);
+        header.data_size = Some(data_size);
+        header.data_off = Some(data_off);
+
+        Ok((remainder, header))
+    }
+
+    /// Parses the items in a table that starts at `offset` and contains
+    /// `size` items. Parsing stops at the first item that can't be parsed,
+    /// the items parsed so far are returned.
+    fn parse_table<'a, T, P>(
+        data: &'a [u8],
+        offset: u32,
+        size: u32,
+        mut parser: P,
+    ) -> Vec<T>
+    where
+        P: Parser<&'a [u8], T, nom::error::Error<&'a [u8]>>,
+    {
+        let mut items = Vec::new();
+        let mut input = match data.get(offset as usize..) {
+            Some(input) => input,
+            None => return items,
+        };
+
+        for _ in 0..(size as usize).min(Self::MAX_ITEMS) {
+            match parser.parse(input) {
+                Ok((remainder, item)) => {
+                    items.push(item);
+                    input = remainder;
+                }
+                Err(_) => break,
+            }
+        }
+
+        items
+    }
+
+    /// Parses the string table.
+    ///
+    /// The result contains one entry per item in the string table, entries
+    /// are `None` for strings that couldn't be parsed.
+    fn parse_strings(data: &[u8], header: &DexHeader) -> Vec<Option<String>> {
+        Self::parse_table(
+            data,
+            header.string_ids_off(),
+            header.string_ids_size(),
+            le_u32,
+        )
+        .into_iter()
+        .map(|string_data_off| {
+            data.get(string_data_off as usize..)
+                .and_then(|input| Self::parse_string_data(input).ok())
+                .map(|(_, s)| s)
+        })
+        .collect()
+    }
+
+    /// Parses the type table. Each type is an index within the string table
+    /// that points to the type's descriptor.
+    fn parse_types(
+        data: &[u8],
+        header: &DexHeader,
+        strings: &[Option<String>],
+    ) -> Vec<Option<String>> {
+        Self::parse_table(
+            data,
+            header.type_ids_off(),
+            header.type_ids_size(),
+            le_u32,
+        )
+        .into_iter()
+        .map(|descriptor_idx| Self::get(strings, descriptor_idx))
+        .collect()
+    }
+
+    fn parse_protos(
+        data: &[u8],
+        header: &DexHeader,
+        strings: &[Option<String>],
+        type_lists: &mut TypeLists,
+    ) -> Vec<ProtoItem> {
+        Self::parse_table(
+            data,
+            header.proto_ids_off(),
+            header.proto_ids_size(),
+            tuple((
+                le_u32, // shorty_idx
+                le_u32, // return_type_idx
+                le_u32, // parameters_off
+            )),
+        )
+        .into_iter()
+        .map(|(shorty_idx, return_type_idx, parameters_off)| {
+            let mut proto = ProtoItem::new();
+            proto.shorty = Self::get(strings, shorty_idx);
+            proto.return_type = Self::get(type_lists.types, return_type_idx);
+            proto.parameters = type_lists.get(parameters_off);
+            proto.parameters_count =
+                Some(proto.parameters.len().try_into().unwrap());
+            proto
+        })
+        .collect()
+    }
+
+    fn parse_fields(
+        data: &[u8],
+        header: &DexHeader,
+        strings: &[Option<String>],
+        types: &[Option<String>],
+    ) -> Vec<FieldItem> {
+        Self::parse_table(
+            data,
+            header.field_ids_off(),
+            header.field_ids_size(),
+            tuple((
+                le_u16, // class_idx
+                le_u16, // type_idx
+                le_u32, // name_idx
+            )),
+        )
+        .into_iter()
+        .map(|(class_idx, type_idx, name_idx)| {
+            let mut field = FieldItem::new();
+            field.class = Self::get(types, class_idx.into());
+            field.type_ = Self::get(types, type_idx.into());
+            field.name = Self::get(strings, name_idx);
+            field
+        })
+        .collect()
+    }
+
+    fn parse_methods(
+        data: &[u8],
+        header: &DexHeader,
+        strings: &[Option<String>],
+        types: &[Option<String>],
+        protos: &[ProtoItem],
+        type_lists: &mut TypeLists,
+    ) -> Vec<MethodItem> {
+        Self::parse_table(
+            data,
+            header.method_ids_off(),
+            header.method_ids_size(),
+            tuple((
+                le_u16, // class_idx
+                le_u16, // proto_idx
+                le_u32, // name_idx
+            )),
+        )
+        .into_iter()
+        .map(|(class_idx, proto_idx, name_idx)| {
+            let mut method = MethodItem::new();
+            method.class = Self::get(types, class_idx.into());
+            method.name = Self::get(strings, name_idx);
+            method.proto = protos
+                .get(proto_idx as usize)
+                .map(|proto| {
+                    // The parameters are not cloned with the rest of the
+                    // proto, as they count towards the limit of type list
+                    // entries.
+                    let mut method_proto = ProtoItem::new();
+                    method_proto.shorty.clone_from(&proto.shorty);
+                    method_proto.return_type.clone_from(&proto.return_type);
+                    method_proto.parameters =
+                        type_lists.take(&proto.parameters);
+                    method_proto.parameters_count = Some(
+                        method_proto.parameters.len().try_into().unwrap(),
+                    );
+                    method_proto
+                })
+                .map_or_else(MessageField::none, MessageField::some);
+            method
+        })
+        .collect()
+    }
+
+    fn parse_class_defs(
+        data: &[u8],
+        header: &DexHeader,
+        strings: &[Option<String>],
+        types: &[Option<String>],
+        type_lists: &mut TypeLists,
+    ) -> Vec<ClassItem> {
+        Self::parse_table(
+            data,
+            header.class_defs_off(),
+            header.class_defs_size(),
+            tuple((
+                le_u32, // class_idx
+                le_u32, // access_flags
+                le_u32, // superclass_idx
+                le_u32, // interfaces_off
+                le_u32, // source_file_idx
+                le_u32, // annotations_off
+                le_u32, // class_data_off
+                le_u32, // static_values_off
+            )),
+        )
+        .into_iter()
+        .map(
+            |(
+                class_idx,
+                access_flags,
+                superclass_idx,
+                interfaces_off,
+                source_file_idx,
+                annotations_off,
+                class_data_off,
+                static_values_off,
+            )| {
+                let mut class = ClassItem::new();
+                class.class = Self::get(types, class_idx);
+                class.access_flags = Some(access_flags);
+                class.superclass = Self::get(types, superclass_idx);
+                class.interfaces = type_lists.get(interfaces_off);
+                class.source_file = Self::get(strings, source_file_idx);
+                class.annotations_off = Some(annotations_off);
+                class.class_data_off = Some(class_data_off);
+                class.static_values_off = Some(static_values_off);
+                class
+            },
+        )
+        .collect()
+    }
+
+    fn parse_map_list(data: &[u8], header: &DexHeader) -> Option<MapList> {
+        let map_off = header.map_off();
+
+        // An offset of zero means that there is no map list.
+        if map_off == 0 {
+            return None;
+        }
+
+        let (_, size) =
+            le_u32::<_, nom::error::Error<_>>(data.get(map_off as usize..)?)
+                .ok()?;
+
+        let mut map_list = MapList::new();
+
+        map_list.size = Some(size);
+        map_list.map_items = Self::parse_table(
+            data,
+            map_off.saturating_add(4),
+            size,
+            tuple((
+                le_u16, // type
+                le_u16, // unused
+                le_u32, // size
+                le_u32, // offset
+            )),
+        )
+        .into_iter()
+        .map(|(type_, unused, size, offset)| {
+            let mut item = MapItem::new();
+            item.type_ =
+                Some(EnumOrUnknown::<TypeCode>::from_i32(type_.into()));
+            item.unused = Some(unused.into());
+            item.size = Some(size);
+            item.offset = Some(offset);
+            item
+        })
+        .collect();
+
+        Some(map_list)
+    }
+
+    /// Parses a `string_data_item`, which consists of the string length in
+    /// UTF-16 code units encoded as ULEB128, followed by the string encoded
+    /// as MUTF-8 and terminated by a null character.
+    fn parse_string_data(input: &[u8]) -> IResult<&[u8], String> {
+        let (remainder, (_utf16_size, s, _)) = tuple((
+            uleb128,
+            map(take_till(|b| b == 0), decode_mutf8),
+            tag(b"\0"),
+        ))(input)?;
+
+        Ok((remainder, s))
+    }
+
+    /// Returns the item at index `idx`, or `None` if the index is out of
+    /// bounds, equal to [`DexParser::NO_INDEX`], or the item couldn't be
+    /// parsed.
+    fn get<T: Clone>(items: &[Option<T>], idx: u32) -> Option<T> {
+        if idx == Self::NO_INDEX {
+            return None;
+        }
+        items.get(idx as usize).cloned().flatten()
+    }
+}
+
+/// Type lists referenced by method prototypes and class definitions.
+///
+/// Many prototypes and classes can reference the same `type_list`, so each
+/// list is parsed only once and cached by offset. Also, the total number of
+/// entries returned by [`TypeLists::get`] and [`TypeLists::take`] is limited
+/// to [`DexParser::MAX_ITEMS`]. Otherwise, a crafted file where lots of items
+/// reference a large list would require a huge amount of memory.
+struct TypeLists<'a> {
+    data: &'a [u8],
+    types: &'a [Option<String>],
+    cache: FxHashMap<u32, Vec<String>>,
+    remaining: usize,
+}
+
+impl<'a> TypeLists<'a> {
+    fn new(data: &'a [u8], types: &'a [Option<String>]) -> Self {
+        Self {
+            data,
+            types,
+            cache: FxHashMap::default(),
+            remaining: DexParser::MAX_ITEMS,
+        }
+    }
+
+    /// Returns the descriptors of the types in the `type_list` located at
+    /// `offset`. The list is truncated if the limit of entries is reached.
+    fn get(&mut self, offset: u32) -> Vec<String> {
+        // An offset of zero means that the list is empty.
+        if offset == 0 {
+            return Vec::new();
+        }
+
+        let list = match self.cache.get(&offset) {
+            Some(list) => list,
+            None => {
+                let list = self.parse(offset);
+                self.cache.entry(offset).or_insert(list)
+            }
+        };
+
+        let n = list.len().min(self.remaining);
+        self.remaining -= n;
+        list[..n].to_vec()
+    }
+
+    /// Returns a copy of a list previously returned by [`TypeLists::get`],
+    /// truncated if the limit of entries is reached.
+    fn take(&mut self, list: &[String]) -> Vec<String> {
+        let n = list.len().min(self.remaining);
+        self.remaining -= n;
+        list[..n].to_vec()
+    }
+
+    /// Parses the `type_list` located at `offset`. No more entries than
+    /// the ones that can be still returned are parsed.
+    fn parse(&self, offset: u32) -> Vec<String> {
+        let size = self
+            .data
+            .get(offset as usize..)
+            .and_then(|input| le_u32::<_, nom::error::Error<_>>(input).ok())
+            .map(|(_, size)| size)
+            .unwrap_or_default();
+
+        let size = size.min(self.remaining.try_into().unwrap_or(u32::MAX));
+
+        DexParser::parse_table(
+            self.data,
+            offset.saturating_add(4),
+            size,
+            le_u16,
+        )
+        .into_iter()
+        .map(|type_idx| {
+            DexParser::get(self.types, type_idx.into()).unwrap_or_default()
+        })
+        .collect()
+    }
+}
+
+/// Parses an unsigned LEB128 value of up to 32 bits.
+fn uleb128(input: &[u8]) -> IResult<&[u8], u32> {
+    let mut result: u32 = 0;
+    let mut input = input;
+
+    // A 32-bit value is encoded in at most 5 bytes.
+    for i in 0..5 {
+        let (remainder, byte) = u8(input)?;
+        result |= ((byte & 0x7f) as u32) << (7 * i);
+        input = remainder;
+        if byte & 0x80 == 0 {
+            return Ok((input, result));
+        }
+    }
+
+    Err(Err::Error(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::TooLarge,
+    )))
+}
+
+/// Decodes a MUTF-8 string, which is the encoding used for strings in DEX
+/// files.
+///
+/// MUTF-8 is like CESU-8, characters outside the Basic Multilingual Plane
+/// are encoded as surrogate pairs, each surrogate is encoded as a 3-byte
+/// sequence. Also, the null character is encoded as 0xC0 0x80. Invalid
+/// sequences are replaced with U+FFFD.
+fn decode_mutf8(input: &[u8]) -> String {
+    // Fast path for the most common case: strings that are pure ASCII.
+    if input.is_ascii() {
+        return String::from_utf8(input.to_vec()).unwrap();
+    }
+
+    let mut utf16 = Vec::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        let b0 = input[i] as u16;
+        let continuation = |n: usize| {
+            input
+                .get(i + n)
+                .filter(|b| *b & 0xc0 == 0x80)
+                .map(|b| (*b & 0x3f) as u16)
+        };
+        match b0 {
+            0x00..=0x7f => {
+                utf16.push(b0);
+                i += 1;
+            }
+            0xc0..=0xdf => match continuation(1) {
+                Some(b1) => {
+                    utf16.push(((b0 & 0x1f) << 6) | b1);
+                    i += 2;
+                }
+                None => {
+                    utf16.push(0xfffd);
+                    i += 1;
+                }
+            },
+            0xe0..=0xef => match (continuation(1), continuation(2)) {
+                (Some(b1), Some(b2)) => {
+                    utf16.push(((b0 & 0x0f) << 12) | (b1 << 6) | b2);
+                    i += 3;
+                }
+                _ => {
+                    utf16.push(0xfffd);
+                    i += 1;
+                }
+            },
+            _ => {
+                utf16.push(0xfffd);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf16_lossy(&utf16)
+}
@@ -0,0 +1,226 @@
+use pretty_assertions::assert_eq;
+
+use crate::modules::tests::create_binary_from_zipped_ihex;
+use crate::tests::rule_false;
+use crate::tests::rule_true;
+use crate::tests::test_rule;
+
+#[test]
+fn header_and_tables() {
+    let dex = create_binary_from_zipped_ihex(
+        "src/modules/dex/tests/testdata/hello.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            dex.is_dex and
+            dex.header.version == 35 and
+            dex.header.endian_tag == dex.ENDIAN_CONSTANT and
+            dex.header.file_size == filesize and
+            dex.header.checksum == 0x49fb57da and
+            dex.header.signature == "5f7645b6b7b7ad4fc55f26fb5fe59a9c93cb06d3" and
+            dex.header.string_ids_size == 14 and
+            dex.string_table[0] == "<init>" and
+            dex.string_table[10] == "café 😀" and
+            dex.string_table[13] == "run" and
+            dex.types[0] == "Lcom/example/Main;" and
+            dex.protos[1].shorty == "VL" and
+            dex.protos[1].return_type == "V" and
+            dex.protos[1].parameters_count == 1 and
+            dex.protos[1].parameters[0] == "[Ljava/lang/String;" and
+            dex.fields[0].class == "Lcom/example/Main;" and
+            dex.fields[0].type == "Ljava/lang/String;" and
+            dex.fields[0].name == "greeting" and
+            dex.methods[1].class == "Lcom/example/Main;" and
+            dex.methods[1].name == "main" and
+            dex.methods[1].proto.shorty == "VL" and
+            dex.class_defs[0].class == "Lcom/example/Main;" and
+            dex.class_defs[0].access_flags & dex.ACC_PUBLIC != 0 and
+            dex.class_defs[0].superclass == "Ljava/lang/Object;" and
+            dex.class_defs[0].interfaces[0] == "Ljava/lang/Runnable;" and
+            dex.class_defs[0].source_file == "Main.java" and
+            dex.map_list.size == 10 and
+            dex.map_list.map_items[0].type == dex.TypeCode.TYPE_HEADER_ITEM and
+            dex.map_list.map_items[9].type == dex.TypeCode.TYPE_MAP_LIST and
+            dex.map_list.map_items[9].offset == dex.header.map_off
+        }
+        "#,
+        &dex
+    );
+
+    // The file is truncated right before the method table. The header and
+    // the tables before the method table are still accessible, but names
+    // can't be resolved because the string data is missing.
+    rule_true!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            dex.is_dex and
+            dex.header.method_ids_size == 4 and
+            dex.header.method_ids_off == 0xe0 and
+            dex.string_table[0] == "" and
+            not defined dex.string_table[14] and
+            not defined dex.fields[0].name and
+            not defined dex.methods[0].name and
+            not defined dex.class_defs[0].class and
+            not defined dex.map_list.size
+        }
+        "#,
+        &dex[..0xe0]
+    );
+
+    rule_true!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            not dex.is_dex and
+            not defined dex.header.version
+        }
+        "#,
+        &dex[..0x60]
+    );
+}
+
+#[test]
+fn has_string_method_and_class() {
+    let dex = create_binary_from_zipped_ihex(
+        "src/modules/dex/tests/testdata/hello.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            dex.has_string("Hello") and
+            dex.has_method("run") and
+            dex.has_method("Ljava/lang/Object;", "<init>") and
+            dex.has_class("Lcom/example/Main;")
+        }
+        "#,
+        &dex
+    );
+
+    rule_false!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            dex.has_string("hello") or
+            dex.has_method("onCreate") or
+            dex.has_method("Ljava/lang/Object;", "run") or
+            dex.has_class("Ljava/lang/Object;")
+        }
+        "#,
+        &dex
+    );
+
+    rule_false!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            dex.has_string("Hello")
+        }
+        "#,
+        &[]
+    );
+}
+
+#[test]
+fn shared_type_lists() {
+    const NUM_CLASSES: u32 = 1000;
+    const LIST_SIZE: u32 = 600_000;
+
+    let class_defs_off = 0x80;
+    let type_list_off = class_defs_off + NUM_CLASSES * 32;
+
+    let mut dex = Vec::new();
+
+    // Header with one string, one type and NUM_CLASSES classes.
+    dex.extend_from_slice(b"dex\n035\0");
+    dex.resize(0x38, 0);
+    for value in [1, 0x70, 1, 0x74, 0, 0, 0, 0, 0, 0] {
+        dex.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    for value in [NUM_CLASSES, class_defs_off, 0, 0] {
+        dex.extend_from_slice(&u32::to_le_bytes(value));
+    }
+
+    // String table, type table and the data of the only string.
+    dex.extend_from_slice(&u32::to_le_bytes(0x78));
+    dex.extend_from_slice(&u32::to_le_bytes(0));
+    dex.extend_from_slice(b"\x05LFoo;\0");
+    dex.resize(class_defs_off as usize, 0);
+
+    // All the classes implement the interfaces in the same type list.
+    for _ in 0..NUM_CLASSES {
+        for value in [0, 0, 0, type_list_off, 0, 0, 0, 0] {
+            dex.extend_from_slice(&u32::to_le_bytes(value));
+        }
+    }
+
+    dex.extend_from_slice(&u32::to_le_bytes(LIST_SIZE));
+    dex.resize(dex.len() + LIST_SIZE as usize * 2, 0);
+
+    let result = crate::modules::dex::parser::DexParser::new()
+        .parse(dex.as_slice())
+        .unwrap();
+
+    assert_eq!(result.class_defs.len(), NUM_CLASSES as usize);
+    assert_eq!(result.class_defs[0].interfaces[0], "LFoo;");
+
+    // The number of interfaces across all classes is limited.
+    assert_eq!(
+        result
+            .class_defs
+            .iter()
+            .map(|class| class.interfaces.len())
+            .sum::<usize>(),
+        500_000
+    );
+}
+
+#[test]
+fn apk() {
+    let apk = create_binary_from_zipped_ihex(
+        "src/modules/dex/tests/testdata/app.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            dex.is_dex and
+            dex.header.signature == "5f7645b6b7b7ad4fc55f26fb5fe59a9c93cb06d3" and
+            dex.class_defs[0].class == "Lcom/example/Main;" and
+            dex.apk.package_name == "com.example.hello" and
+            dex.apk.signer_digests[0] == "c9432663a8bf5cd271dc00162af82abdff1a84b7cda3a64a8dc6e515c2d6d1c8" and
+            not defined dex.apk.signer_digests[1]
+        }
+        "#,
+        &apk
+    );
+
+    // Plain DEX files don't have APK information.
+    let dex = create_binary_from_zipped_ihex(
+        "src/modules/dex/tests/testdata/hello.in.zip",
+    );
+
+    rule_false!(
+        r#"
+        import "dex"
+        rule test {
+          condition:
+            defined dex.apk.package_name
+        }
+        "#,
+        &dex
+    );
+}
@@ -36,7 +36,7 @@ fn import_md5(ctx: &mut ScanContext) -> Option<RuntimeString> {
         elf.dynsym.iter()
     };
 
-    let comma_separated_names = symbols
+    let names = symbols
         .filter_map(|sym| match (sym.shndx, sym.name.as_ref()) {
             (Some(shndx), Some(name)) if shndx == 0 && !name.is_empty() => {
                 Some(name.to_lowercase())
@@ -44,10 +44,15 @@ fn import_md5(ctx: &mut ScanContext) -> Option<RuntimeString> {
             _ => None,
         })
         .sorted()
-        .join(",");
+        .collect::<Vec<_>>();
+
+    // Files without imported symbols don't have an import hash.
+    if names.is_empty() {
+        return None;
+    }
 
     let mut hasher = Md5::new();
-    hasher.update(comma_separated_names.as_bytes());
+    hasher.update(names.join(",").as_bytes());
 
     let digest = format!("{:x}", hasher.finalize());
 
@@ -4,6 +4,93 @@ use crate::modules::tests::create_binary_from_zipped_ihex;
 use crate::tests::rule_true;
 use crate::tests::test_rule;
 
+#[test]
+fn headers_segments_and_sections() {
+    let elf = create_binary_from_zipped_ihex(
+        "src/modules/elf/tests/testdata/f040356a60cde1047266c841237b03e5d157c0628f5a348e64f63962265d1434.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            elf.type == elf.ET_EXEC and
+            elf.machine == elf.EM_SPARC and
+            elf.entry_point == 4348 and
+            elf.number_of_segments == 5 and
+            elf.number_of_sections == 24 and
+            elf.segments[0].type == elf.PT_PHDR and
+            elf.segments[0].file_size == 160 and
+            elf.dynamic_section_entries == 19 and
+            elf.dynamic[0].type == elf.DT_NEEDED and
+            elf.dynsym_entries == 109 and
+            elf.symtab_entries == 0 and
+            for any s in elf.sections : (
+              s.name == ".text" and
+              s.type == elf.SHT_PROGBITS and
+              s.offset == elf.entry_point
+            )
+        }
+        "#,
+        &elf
+    );
+
+    let elf = create_binary_from_zipped_ihex(
+        "src/modules/elf/tests/testdata/8bfe885838b4d1fba194b761ca900a0425aa892e4b358bf5a9bf4304e571df1b.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            elf.type == elf.ET_DYN and
+            elf.machine == elf.EM_X86_64 and
+            elf.symtab_entries == 40 and
+            elf.symtab[1].name == "crtstuff.c" and
+            elf.symtab[1].type == elf.STT_FILE and
+            elf.dynsym[1].name == "_ITM_deregisterTMCloneTable" and
+            elf.dynamic[0].type == elf.DT_NEEDED and
+            elf.dynamic[0].val == 242
+        }
+        "#,
+        &elf
+    );
+
+    // Only the ELF header is present, section and segment tables are
+    // beyond the end of the file.
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            elf.type == elf.ET_DYN and
+            elf.number_of_sections == 34 and
+            elf.number_of_segments == 11 and
+            not defined elf.sections[0].name and
+            not defined elf.segments[0].type and
+            elf.dynamic_section_entries == 0
+        }
+        "#,
+        &elf[..64]
+    );
+
+    // Truncated in the middle of the ELF header.
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            not defined elf.type and
+            not defined elf.entry_point and
+            not defined elf.number_of_sections
+        }
+        "#,
+        &elf[..32]
+    );
+}
+
 #[test]
 fn import_md5() {
     let elf = create_binary_from_zipped_ihex(
@@ -20,6 +107,33 @@ fn import_md5() {
         "#,
         &elf
     );
+
+    let elf = create_binary_from_zipped_ihex(
+        "src/modules/elf/tests/testdata/ac64f17c42eec4ec357e5fe9e58b51e4e1d3a026356da4043095f9b96ebce51c.in.zip",
+    );
+
+    // Files without imported symbols don't have an import hash.
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            not defined elf.import_md5()
+        }
+        "#,
+        &elf
+    );
+
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            not defined elf.import_md5()
+        }
+        "#,
+        &[]
+    );
 }
 
 #[test]
@@ -38,4 +152,15 @@ fn telfhash() {
         "#,
         &elf
     );
+
+    rule_true!(
+        r#"
+        import "elf"
+        rule test {
+          condition:
+            not defined elf.telfhash()
+        }
+        "#,
+        &[]
+    );
 }
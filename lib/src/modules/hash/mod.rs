@@ -57,7 +57,8 @@ fn md5_data(
         return cached;
     }
 
-    let range = offset.try_into().ok()?..(offset + size).try_into().ok()?;
+    let range =
+        offset.try_into().ok()?..offset.checked_add(size)?.try_into().ok()?;
     let data = ctx.scanned_data().get(range)?;
     let mut hasher = Md5::new();
 
@@ -97,7 +98,8 @@ fn sha1_data(
         return cached;
     }
 
-    let range = offset.try_into().ok()?..(offset + size).try_into().ok()?;
+    let range =
+        offset.try_into().ok()?..offset.checked_add(size)?.try_into().ok()?;
     let data = ctx.scanned_data().get(range)?;
     let mut hasher = Sha1::new();
 
@@ -137,7 +139,8 @@ fn sha256_data(
         return cached;
     }
 
-    let range = offset.try_into().ok()?..(offset + size).try_into().ok()?;
+    let range =
+        offset.try_into().ok()?..offset.checked_add(size)?.try_into().ok()?;
     let data = ctx.scanned_data().get(range)?;
     let mut hasher = Sha256::new();
 
@@ -173,7 +176,8 @@ fn crc_data(ctx: &ScanContext, offset: i64, size: i64) -> Option<i64> {
         return cached;
     }
 
-    let range = offset.try_into().ok()?..(offset + size).try_into().ok()?;
+    let range =
+        offset.try_into().ok()?..offset.checked_add(size)?.try_into().ok()?;
     let data = ctx.scanned_data().get(range)?;
     let crc = crc32fast::hash(data);
 
@@ -200,7 +204,8 @@ fn checksum_data(ctx: &ScanContext, offset: i64, size: i64) -> Option<i64> {
         return cached;
     }
 
-    let range = offset.try_into().ok()?..(offset + size).try_into().ok()?;
+    let range =
+        offset.try_into().ok()?..offset.checked_add(size)?.try_into().ok()?;
     let data = ctx.scanned_data().get(range)?;
     let mut checksum = 0_u32;
 
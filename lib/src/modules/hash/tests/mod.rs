@@ -82,3 +82,47 @@ fn test_hash_module() {
         b"TEST STRING"
     );
 }
+
+#[test]
+#[cfg(feature = "hash-module")]
+fn test_hash_module_invalid_ranges() {
+    rule_true!(
+        r#"
+        import "hash"
+        rule test {
+          condition:
+            not defined hash.md5(0, filesize + 1) and
+            not defined hash.sha1(-1, 2) and
+            not defined hash.sha256(2, -1) and
+            not defined hash.crc32(1, 0x7fffffffffffffff) and
+            not defined hash.checksum32(0x7fffffffffffffff, 1)
+        }
+        "#,
+        b"foobarbaz"
+    );
+}
+
+#[test]
+#[cfg(feature = "hash-module")]
+fn test_hash_module_cache() {
+    let rules = crate::compile(
+        r#"
+        import "hash"
+        rule test {
+          condition:
+            hash.md5(0, 3) == "acbd18db4cc2f85cedef654fccc4a4d8" and
+            hash.md5(0, 3) == hash.md5("foo") and
+            hash.crc32(0, 3) == hash.crc32("foo")
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = crate::scanner::Scanner::new(&rules);
+
+    // Results computed while scanning some data must not be reused when
+    // scanning different data with the same scanner.
+    assert_eq!(scanner.scan(b"foobarbaz").unwrap().matching_rules().len(), 1);
+    assert_eq!(scanner.scan(b"barbazfoo").unwrap().matching_rules().len(), 0);
+    assert_eq!(scanner.scan(b"foobarbaz").unwrap().matching_rules().len(), 1);
+}
@@ -89,8 +89,10 @@ pub struct MachO<'a> {
     archs: Vec<FatArch>,
     /// This array contains an entry per architecture included in the Mach-O
     /// file. For single-architecture binaries the array contains a single
-    /// entry.
-    files: Vec<MachOFile<'a>>,
+    /// entry. For FAT binaries the entry is `None` if the corresponding
+    /// architecture couldn't be parsed, so that indexes in this array match
+    /// the indexes in `archs`.
+    files: Vec<Option<MachOFile<'a>>>,
 }
 
 impl<'a> MachO<'a> {
@@ -106,7 +108,7 @@ impl<'a> MachO<'a> {
             Ok(Self {
                 fat_magic: None,
                 archs: Vec::new(),
-                files: vec![Self::parse_macho_file(data)?],
+                files: vec![Some(Self::parse_macho_file(data)?)],
             })
         }
     }
@@ -176,22 +178,25 @@ impl<'a> MachO<'a> {
         // binary. Errors that occur while parsing individual Mach-O files are
         // not propagated. If the FAT file is truncated for example, we may be
         // able to parse some of the Mach-O files while the rest can't be
-        // parsed, but we still consider that case a success.
+        // parsed, but we still consider that case a success. Files that can't
+        // be parsed are represented by `None`.
         for arch in &archs {
             let start = arch.offset as usize;
             let end = start.saturating_add(arch.size as usize);
 
-            if let Some(macho) = data.get(start..end) {
-                match Self::parse_macho_file(macho) {
-                    Ok(macho) => files.push(macho),
-                    #[cfg(feature = "logging")]
-                    Err(err) => {
-                        error!("Error parsing Mach-O file: {:?}", err);
-                    }
-                    #[cfg(not(feature = "logging"))]
-                    Err(_) => {}
+            let file = match data.get(start..end).map(Self::parse_macho_file) {
+                Some(Ok(macho)) => Some(macho),
+                #[cfg(feature = "logging")]
+                Some(Err(err)) => {
+                    error!("Error parsing Mach-O file: {:?}", err);
+                    None
                 }
+                #[cfg(not(feature = "logging"))]
+                Some(Err(_)) => None,
+                None => None,
             };
+
+            files.push(file);
         }
 
         Ok(MachO { fat_magic: Some(magic), archs, files })
@@ -1323,8 +1328,9 @@ impl From<MachO<'_>> for protos::macho::Macho {
         // at the top level of `protos::macho::Macho` structure. If it is a
         // multi-architecture binary (FAT binary) then fill the `fat_arch`
         // and `file` arrays.
-        if macho.files.len() == 1 {
-            let m = macho.files.first().unwrap();
+        if macho.fat_magic.is_none() {
+            // Single-architecture binaries always have exactly one file.
+            let m = macho.files.first().and_then(Option::as_ref).unwrap();
             result.set_magic(m.header.magic);
             result.set_ncmds(m.header.ncmds);
             result.set_cputype(m.header.cputype);
@@ -1398,7 +1404,11 @@ impl From<MachO<'_>> for protos::macho::Macho {
             result.fat_magic = macho.fat_magic;
             result.set_nfat_arch(macho.archs.len().try_into().unwrap());
             result.fat_arch.extend(macho.archs.iter().map(|arch| arch.into()));
-            result.file.extend(macho.files.iter().map(|file| file.into()));
+            // Architectures that couldn't be parsed are represented by an
+            // empty `File`, so that `file[i]` corresponds to `fat_arch[i]`.
+            result.file.extend(macho.files.iter().map(|file| {
+                file.as_ref().map(|file| file.into()).unwrap_or_default()
+            }));
         }
         result
     }
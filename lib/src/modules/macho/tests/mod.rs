@@ -311,4 +311,100 @@ fn test_macho_module() {
         "#,
         &chess_macho_data
     );
+
+    let segments_macho_data = create_binary_from_zipped_ihex(
+        "src/modules/macho/tests/testdata/01ac68a14f0ff5faa72bb33e768bfaae4d21de61f776e2405324c498ef52b21b.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "macho"
+        rule macho_test {
+            condition:
+                macho.segments[0].segname == "__TEXT" and
+                macho.segments[0].filesize == 24576 and
+                macho.segments[macho.number_of_segments - 1].filesize == 33600
+        }
+        "#,
+        &segments_macho_data
+    );
+
+    rule_true!(
+        r#"
+        import "macho"
+        rule macho_test {
+            condition:
+                not defined macho.segments[macho.number_of_segments].filesize
+        }
+        "#,
+        &segments_macho_data
+    );
+}
+
+#[test]
+fn fat_binary_files() {
+    let mut macho = create_binary_from_zipped_ihex(
+        "src/modules/macho/tests/testdata/tiny_universal.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "macho"
+        rule test {
+          condition:
+            not defined macho.magic and
+            macho.fat_magic == macho.FAT_MAGIC and
+            macho.nfat_arch == 2 and
+            macho.fat_arch[0].cputype == macho.CPU_TYPE_X86 and
+            macho.fat_arch[1].cputype == macho.CPU_TYPE_X86_64 and
+            macho.file[0].magic == macho.MH_CIGAM and
+            macho.file[0].cputype == macho.CPU_TYPE_X86 and
+            macho.file[1].magic == macho.MH_CIGAM_64 and
+            macho.file[1].cputype == macho.CPU_TYPE_X86_64 and
+            macho.file[1].number_of_segments == 4 and
+            macho.file[1].dylibs[0].name == "/usr/lib/libSystem.B.dylib" and
+            not defined macho.file[2].magic
+        }
+        "#,
+        &macho
+    );
+
+    // When the FAT binary is truncated the architectures that are fully
+    // contained in the file are still parsed, and the file is still
+    // considered a FAT binary.
+    rule_true!(
+        r#"
+        import "macho"
+        rule test {
+          condition:
+            not defined macho.magic and
+            macho.nfat_arch == 2 and
+            macho.file[0].cputype == macho.CPU_TYPE_X86 and
+            not defined macho.file[1].cputype and
+            macho.entry_point_for_arch(macho.CPU_TYPE_X86) == 0x1EE0 and
+            not defined macho.entry_point_for_arch(macho.CPU_TYPE_X86_64)
+        }
+        "#,
+        &macho[..16384 + 100]
+    );
+
+    // Corrupt the magic of the first architecture. The second architecture
+    // must still be accessible at `file[1]`.
+    macho[4096..4100].copy_from_slice(b"\0\0\0\0");
+
+    rule_true!(
+        r#"
+        import "macho"
+        rule test {
+          condition:
+            macho.nfat_arch == 2 and
+            not defined macho.file[0].magic and
+            macho.file[1].cputype == macho.CPU_TYPE_X86_64 and
+            macho.file_index_for_arch(macho.CPU_TYPE_X86_64) == 1 and
+            not defined macho.entry_point_for_arch(macho.CPU_TYPE_X86) and
+            macho.entry_point_for_arch(macho.CPU_TYPE_X86_64) == 0x4EE0
+        }
+        "#,
+        &macho
+    );
 }
@@ -37,4 +37,9 @@ fn e2e_test() {
     let results = scanner.scan(b"Maestro\r").unwrap();
 
     assert_eq!(results.matching_rules().len(), 1);
+
+    // The cached results must not be reused when scanning a different file.
+    let results = scanner.scan(b"foobar").unwrap();
+
+    assert_eq!(results.matching_rules().len(), 0);
 }
@@ -23,6 +23,9 @@ pub(crate) mod prelude {
 
 include!("modules.rs");
 
+#[cfg(any(feature = "math-module", feature = "pe-module"))]
+mod utils;
+
 /// Type of module's main function.
 type MainFn = fn(&[u8]) -> Box<dyn MessageDyn>;
 
@@ -139,6 +142,27 @@ pub mod mods {
     ```
      */
 
+    /// Data structures defined by the `cuckoo` module.
+    ///
+    /// The main structure used by the module is [`cuckoo::Cuckoo`]. This
+    /// module doesn't parse the scanned data, the structure must be filled
+    /// with the report produced by a sandbox, and passed to the scanner with
+    /// [`crate::Scanner::set_module_output`]. Cuckoo's JSON reports can be
+    /// converted with `Cuckoo::from_json`.
+    ///
+    pub use super::protos::cuckoo;
+    /// Data structure used by the `cuckoo` module.
+    pub use super::protos::cuckoo::Cuckoo;
+
+    /// Data structures defined by the `dex` module.
+    ///
+    /// The main structure produced by the module is [`dex::Dex`]. The rest of
+    /// them are used by one or more fields in the main structure.
+    ///
+    pub use super::protos::dex;
+    /// Data structure returned by the `dex` module.
+    pub use super::protos::dex::Dex;
+
     /// Data structures defined by the `dotnet` module.
     ///
     /// The main structure produced by the module is [`dotnet::Dotnet`]. The
@@ -184,6 +208,17 @@ pub mod mods {
     /// Data structure returned by the `pe` module.
     pub use super::protos::pe::PE;
 
+    /// Data structures defined by the `vt` module.
+    ///
+    /// The main structure used by the module is [`vt::LiveHuntData`]. This
+    /// module doesn't parse the scanned data, the structure must be filled
+    /// with the file metadata provided by VirusTotal, and passed to the
+    /// scanner with [`crate::Scanner::set_module_output`].
+    ///
+    pub use super::protos::vt;
+    /// Data structure used by the `vt` module.
+    pub use super::protos::vt::LiveHuntData;
+
     /// A data structure contains the data returned by all modules.
     pub use super::protos::mods::Modules;
 
@@ -254,6 +289,7 @@ pub mod mods {
         info.dotnet = protobuf::MessageField(invoke::<Dotnet>(data));
         info.macho = protobuf::MessageField(invoke::<Macho>(data));
         info.lnk = protobuf::MessageField(invoke::<Lnk>(data));
+        info.dex = protobuf::MessageField(invoke::<Dex>(data));
         info
     }
 }
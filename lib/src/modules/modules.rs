@@ -1,29 +1,33 @@
 // File generated automatically by build.rs. Do not edit.
-#[cfg(feature = "string-module")]
-mod string;
-#[cfg(feature = "macho-module")]
-mod macho;
-#[cfg(feature = "pe-module")]
-mod pe;
-#[cfg(feature = "elf-module")]
-mod elf;
-#[cfg(feature = "text-module")]
-mod text;
+#[cfg(feature = "console-module")]
+mod console;
+#[cfg(feature = "dex-module")]
+mod dex;
 #[cfg(feature = "dotnet-module")]
 mod dotnet;
+#[cfg(feature = "elf-module")]
+mod elf;
+#[cfg(feature = "pe-module")]
+mod pe;
 #[cfg(feature = "lnk-module")]
 mod lnk;
-#[cfg(feature = "hash-module")]
-mod hash;
-#[cfg(feature = "magic-module")]
-mod magic;
-#[cfg(feature = "math-module")]
-mod math;
-#[cfg(feature = "test_proto2-module")]
-mod test_proto2;
+#[cfg(feature = "macho-module")]
+mod macho;
 #[cfg(feature = "time-module")]
 mod time;
+#[cfg(feature = "test_proto2-module")]
+mod test_proto2;
 #[cfg(feature = "test_proto3-module")]
 mod test_proto3;
-#[cfg(feature = "console-module")]
-mod console;
\ No newline at end of file
+#[cfg(feature = "hash-module")]
+mod hash;
+#[cfg(feature = "math-module")]
+mod math;
+#[cfg(feature = "cuckoo-module")]
+mod cuckoo;
+#[cfg(feature = "magic-module")]
+mod magic;
+#[cfg(feature = "string-module")]
+mod string;
+#[cfg(feature = "text-module")]
+mod text;
\ No newline at end of file
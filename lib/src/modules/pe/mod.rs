@@ -4,8 +4,8 @@ This allows creating YARA rules based on PE metadata, including sections,
 imports and exports, resources, etc.
  */
 
-use std::rc::Rc;
 use std::slice::Iter;
+use std::sync::Arc;
 
 use bstr::BStr;
 use digest::Digest;
@@ -220,11 +220,14 @@ fn imphash(ctx: &mut ScanContext) -> Option<RuntimeString> {
     for import in &pe.import_details {
         let original_dll_name =
             import.library_name.as_deref().unwrap().to_lowercase();
-        let mut dll_name = original_dll_name.as_str();
-        // If extension is '.dll', '.sys' or '.ocx', remove it.
-        for extension in [".dll", ".sys", ".ocx"] {
-            dll_name = dll_name.trim_end_matches(extension);
-        }
+        // If extension is '.dll', '.sys' or '.ocx', remove it. Only the
+        // last extension is removed, and only once, which means that
+        // "foo.dll.dll" becomes "foo.dll" and "foo.sys.dll" becomes
+        // "foo.sys". This is what both pefile and libyara do.
+        let dll_name = [".dll", ".sys", ".ocx"]
+            .into_iter()
+            .find_map(|extension| original_dll_name.strip_suffix(extension))
+            .unwrap_or(original_dll_name.as_str());
         for func in &import.functions {
             if !first {
                 Digest::update(&mut md5_hash, ",".as_bytes())
@@ -278,8 +281,10 @@ fn rich_version_impl(
 ) -> Option<i64> {
     assert!(toolid.is_some() || version.is_some());
 
+    // If the file doesn't have a rich signature the result is undefined.
     let count = pe
         .rich_signature
+        .as_ref()?
         .tools
         .iter()
         .filter_map(|t| {
@@ -627,7 +632,7 @@ fn language(ctx: &ScanContext, lang: i64) -> Option<bool> {
 #[module_export(method_of = "pe.Signature")]
 fn valid_on(
     _ctx: &ScanContext,
-    signature: Rc<Struct>,
+    signature: Arc<Struct>,
     timestamp: i64,
 ) -> Option<bool> {
     let not_before = signature
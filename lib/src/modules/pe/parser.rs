@@ -22,12 +22,14 @@ use nom::number::complete::{le_u16, le_u32, le_u64, u8};
 use nom::sequence::tuple;
 use nom::{Err, IResult, Parser, ToUsize};
 use protobuf::{EnumOrUnknown, MessageField};
+use uuid::Uuid;
 
 use crate::modules::pe::authenticode::{
     AuthenticodeHasher, AuthenticodeParser, AuthenticodeSignature,
 };
 use crate::modules::pe::rva2off;
 use crate::modules::protos;
+use crate::modules::utils::entropy;
 
 type Error<'a> = nom::error::Error<&'a [u8]>;
 
@@ -77,8 +79,15 @@ pub struct PE<'a> {
     /// [`PE::get_dir_entries`] is called for the first time.
     dir_entries: OnceCell<Option<Vec<DirEntry>>>,
 
-    /// Path to PDB file containing debug information for the PE.
-    pdb_path: OnceCell<Option<&'a [u8]>>,
+    /// Information about the PDB file containing debug information for
+    /// the PE.
+    pdb_info: OnceCell<Option<PdbInfo<'a>>>,
+
+    /// Entries in the debug directory.
+    dbg_dir_entries: OnceCell<Option<Vec<DbgDirEntry>>>,
+
+    /// Virtual addresses of the TLS callbacks.
+    tls_callbacks: OnceCell<Option<Vec<u64>>>,
 
     /// Vector with the DLLs imported by this PE file. Each item in the vector
     /// is a tuple composed of a DLL name and a vector of [`ImportedFunc`] that
@@ -309,6 +318,20 @@ impl<'a> PE<'a> {
         self.sections.as_slice()
     }
 
+    /// Returns the raw data for the given section.
+    ///
+    /// If the section's data goes beyond the end of the file, the result
+    /// is truncated. Returns `None` if the data starts after the end of
+    /// the file.
+    pub fn get_section_data(&self, section: &Section) -> Option<&'a [u8]> {
+        let start = section.raw_data_offset as usize;
+        let end = min(
+            self.data.len(),
+            start.saturating_add(section.raw_data_size as usize),
+        );
+        self.data.get(start..end)
+    }
+
     /// Returns information about the rich header.
     ///
     /// The rich header is an undocumented chunk of data found between the DOS
@@ -355,7 +378,45 @@ impl<'a> PE<'a> {
     /// a CLSID. Is not clear what the CLSID means. Example:
     /// 6c2abf4b80a87e63eee2996e5cea8f004d49ec0c1806080fa72e960529cba14c
     pub fn get_pdb_path(&self) -> Option<&'a [u8]> {
-        *self.pdb_path.get_or_init(|| self.parse_dbg())
+        self.get_pdb_info().map(|pdb_info| pdb_info.path)
+    }
+
+    /// Returns information about the PDB file that contains debug
+    /// information for the PE file. See [`PE::get_pdb_path`] for details
+    /// about the cases in which the result is `None`.
+    pub fn get_pdb_info(&self) -> Option<&PdbInfo<'a>> {
+        self.pdb_info.get_or_init(|| self.parse_dbg()).as_ref()
+    }
+
+    /// Returns the entries in the debug directory.
+    pub fn get_dbg_dir_entries(&self) -> &[DbgDirEntry] {
+        self.dbg_dir_entries
+            .get_or_init(|| self.parse_dbg_dir())
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Returns the virtual addresses of the TLS callbacks.
+    ///
+    /// TLS callbacks are functions that are called by the loader when the
+    /// process or thread is started, before the entry point. They are listed
+    /// in a null-terminated array of virtual addresses pointed to by the
+    /// `AddressOfCallBacks` field in the TLS directory.
+    pub fn get_tls_callbacks(&self) -> &[u64] {
+        self.tls_callbacks
+            .get_or_init(|| self.parse_tls_callbacks())
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the PE was produced by a reproducible build. This is
+    /// indicated by the presence of an entry of type `IMAGE_DEBUG_TYPE_REPRO`
+    /// in the debug directory. In such files the timestamp in the PE header
+    /// is a hash of the file's content, not an actual timestamp.
+    pub fn is_reproducible_build(&self) -> bool {
+        self.get_dbg_dir_entries()
+            .iter()
+            .any(|entry| entry.type_ == Self::IMAGE_DEBUG_TYPE_REPRO)
     }
 
     /// Returns a slice of [`Resource`] structures, one per each resource
@@ -507,10 +568,12 @@ impl<'a> PE<'a> {
     pub const IMAGE_DIRECTORY_ENTRY_RESOURCE: usize = 2;
     pub const IMAGE_DIRECTORY_ENTRY_SECURITY: usize = 4;
     pub const IMAGE_DIRECTORY_ENTRY_DEBUG: usize = 6;
+    pub const IMAGE_DIRECTORY_ENTRY_TLS: usize = 9;
     pub const IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT: usize = 13;
     pub const IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR: usize = 14;
 
     const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+    const IMAGE_DEBUG_TYPE_REPRO: u32 = 16;
 
     const RICH_TAG: &'static [u8] = &[0x52_u8, 0x69, 0x63, 0x68];
     const DANS_TAG: u32 = 0x536e6144;
@@ -536,6 +599,7 @@ impl<'a> PE<'a> {
     const MAX_PE_IMPORTS: usize = 16384;
     const MAX_PE_EXPORTS: usize = 16384;
     const MAX_PE_RESOURCES: usize = 65536;
+    const MAX_PE_TLS_CALLBACKS: usize = 256;
     const MAX_DIR_ENTRIES: usize = 16;
 
     fn parse_dos_header(input: &[u8]) -> IResult<&[u8], DOSHeader> {
@@ -1558,16 +1622,21 @@ impl<'a> PE<'a> {
             .ok()
     }
 
-    /// Parses the PE debug information and extracts the PDB path.
-    fn parse_dbg(&self) -> Option<&'a [u8]> {
+    /// Parses the PE debug directory.
+    fn parse_dbg_dir(&self) -> Option<Vec<DbgDirEntry>> {
         let (_, _, dbg_section) =
             self.get_dir_entry_data(Self::IMAGE_DIRECTORY_ENTRY_DEBUG, true)?;
 
-        let entries = many0(Self::parse_dbg_dir_entry)(dbg_section)
+        many0(Self::parse_dbg_dir_entry)(dbg_section)
             .map(|(_, entries)| entries)
-            .ok()?;
+            .ok()
+    }
 
-        for entry in entries
+    /// Parses the PE debug information and extracts the PDB path, GUID and
+    /// age.
+    fn parse_dbg(&self) -> Option<PdbInfo<'a>> {
+        for entry in self
+            .get_dbg_dir_entries()
             .iter()
             .filter(|entry| entry.type_ == Self::IMAGE_DEBUG_TYPE_CODEVIEW)
         {
@@ -1611,13 +1680,21 @@ impl<'a> PE<'a> {
                 //   DWORD      age;
                 //   BYTE[..]   pdb_path;
                 //
-                tuple((
-                    verify(le_u32::<&[u8], Error>, |signature| {
-                        *signature == 0x53445352 // "RSDS"
-                    }),
-                    take(20_usize), // skip guid and age
-                    take_till(|c| c == 0),
-                )),
+                map(
+                    tuple((
+                        verify(le_u32::<&[u8], Error>, |signature| {
+                            *signature == 0x53445352 // "RSDS"
+                        }),
+                        take(16_usize), // guid
+                        le_u32,         // age
+                        take_till(|c| c == 0),
+                    )),
+                    |(_, guid, age, path)| PdbInfo {
+                        path,
+                        guid: Uuid::from_slice_le(guid).ok(),
+                        age: Some(age),
+                    },
+                ),
                 // "NB10" means that the debug information is stored in a
                 // PDB 2.0 file. The structure is:
                 //
@@ -1627,30 +1704,43 @@ impl<'a> PE<'a> {
                 //   DWORD      age;
                 //   BYTE[..]   pdb_path;
                 //
-                tuple((
-                    verify(le_u32::<&[u8], Error>, |signature| {
-                        *signature == 0x3031424e // "NB10"
-                    }),
-                    take(12_usize), // skip offset, timestamp, and age
-                    take_till(|c| c == 0),
-                )),
+                map(
+                    tuple((
+                        verify(le_u32::<&[u8], Error>, |signature| {
+                            *signature == 0x3031424e // "NB10"
+                        }),
+                        take(8_usize), // skip offset and timestamp
+                        le_u32,        // age
+                        take_till(|c| c == 0),
+                    )),
+                    |(_, _, age, path)| PdbInfo {
+                        path,
+                        guid: None,
+                        age: Some(age),
+                    },
+                ),
                 //
                 //   DWORD      signature;
                 //   BYTE[16]   guid;
                 //   BYTE[..]   pdb_path;
                 //
-                tuple((
-                    verify(le_u32::<&[u8], Error>, |signature| {
-                        *signature == 0x434f544d // "MTOC"
-                    }),
-                    take(16_usize), // skip guid
-                    take_till(|c| c == 0),
-                )),
+                map(
+                    tuple((
+                        verify(le_u32::<&[u8], Error>, |signature| {
+                            *signature == 0x434f544d // "MTOC"
+                        }),
+                        take(16_usize), // guid
+                        take_till(|c| c == 0),
+                    )),
+                    |(_, guid, path)| PdbInfo {
+                        path,
+                        guid: Uuid::from_slice_le(guid).ok(),
+                        age: None,
+                    },
+                ),
             ))(cv_info)
             {
-                Ok((_, (_signature, _padding, pdb_path))) => {
-                    return Some(pdb_path)
-                }
+                Ok((_, pdb_info)) => return Some(pdb_info),
                 Err(_) => continue,
             };
         }
@@ -1658,6 +1748,51 @@ impl<'a> PE<'a> {
         None
     }
 
+    /// Parses the TLS directory and returns the virtual addresses of the
+    /// TLS callbacks.
+    fn parse_tls_callbacks(&self) -> Option<Vec<u64>> {
+        let (_, _, tls_dir) =
+            self.get_dir_entry_data(Self::IMAGE_DIRECTORY_ENTRY_TLS, false)?;
+
+        // See the comment in `parse_imports` for the reason why `is_32_bits`
+        // is computed in this way.
+        let is_32_bits =
+            self.optional_hdr.magic != Self::IMAGE_NT_OPTIONAL_HDR64_MAGIC;
+
+        // The first fields in the IMAGE_TLS_DIRECTORY structure are virtual
+        // addresses, which are 32-bits or 64-bits long depending on whether
+        // this is a 64-bits PE file.
+        //
+        //   start_address_of_raw_data;
+        //   end_address_of_raw_data;
+        //   address_of_index;
+        //   address_of_callbacks;
+        //
+        let (_, (_, _, _, address_of_callbacks)) = tuple((
+            uint(is_32_bits),
+            uint(is_32_bits),
+            uint(is_32_bits),
+            uint(is_32_bits),
+        ))(tls_dir)
+        .ok()?;
+
+        if address_of_callbacks == 0 {
+            return None;
+        }
+
+        let callbacks_rva: u32 = address_of_callbacks
+            .checked_sub(self.optional_hdr.image_base)?
+            .try_into()
+            .ok()?;
+
+        let mut callbacks = iterator(
+            self.data_at_rva(callbacks_rva)?,
+            verify(uint(is_32_bits), |callback| *callback != 0),
+        );
+
+        Some(callbacks.take(Self::MAX_PE_TLS_CALLBACKS).collect())
+    }
+
     /// Parse the IMAGE_DEBUG_DIRECTORY structure.
     /// https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-image_debug_directory
     fn parse_dbg_dir_entry(input: &[u8]) -> IResult<&[u8], DbgDirEntry> {
@@ -2226,7 +2361,15 @@ impl From<PE<'_>> for protos::pe::PE {
         result.set_size_of_stack_commit(pe.optional_hdr.size_of_stack_commit);
         result.set_size_of_heap_reserve(pe.optional_hdr.size_of_heap_reserve);
         result.set_size_of_heap_commit(pe.optional_hdr.size_of_heap_commit);
-        result.pdb_path = pe.get_pdb_path().map(|path| path.to_vec());
+        if let Some(pdb_info) = pe.get_pdb_info() {
+            result.pdb_path = Some(pdb_info.path.to_vec());
+            result.pdb_guid = pdb_info.guid.map(|guid| guid.to_string());
+            result.pdb_age = pdb_info.age;
+        }
+
+        result.is_reproducible_build = Some(pe.is_reproducible_build());
+
+        result.tls_callbacks.extend(pe.get_tls_callbacks());
         result.set_number_of_rva_and_sizes(pe.optional_hdr.number_of_rva_and_sizes);
         result.set_image_base(pe.optional_hdr.image_base);
         result.set_size_of_image(pe.optional_hdr.size_of_image);
@@ -2262,9 +2405,11 @@ impl From<PE<'_>> for protos::pe::PE {
             .data_directories
             .extend(pe.get_dir_entries().iter().map(protos::pe::DirEntry::from));
 
-        result
-            .sections
-            .extend(pe.get_sections().iter().map(protos::pe::Section::from));
+        result.sections.extend(pe.get_sections().iter().map(|section| {
+            let mut sec = protos::pe::Section::from(section);
+            sec.entropy = pe.get_section_data(section).map(entropy);
+            sec
+        }));
 
         result
             .resources
@@ -2373,6 +2518,9 @@ impl From<PE<'_>> for protos::pe::PE {
 
         result.set_number_of_signatures(
             result.signatures.len().try_into().unwrap());
+
+        result.set_number_of_tls_callbacks(
+            result.tls_callbacks.len().try_into().unwrap());
         
         // The overlay offset is the offset where the last section ends. The
         // last section is not the last one in the section table, but the one
@@ -2702,6 +2850,18 @@ impl From<&Resource<'_>> for protos::pe::Resource {
     }
 }
 
+/// Information about the PDB file that contains debug information for a PE
+/// file.
+pub struct PdbInfo<'a> {
+    /// Path to the PDB file.
+    path: &'a [u8],
+    /// GUID that identifies the PDB file. Not present in PDB 2.0 files.
+    guid: Option<Uuid>,
+    /// Number of times that the PDB file has been updated. Not present
+    /// when the signature is "MTOC".
+    age: Option<u32>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct DbgDirEntry {
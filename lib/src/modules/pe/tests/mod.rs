@@ -1,6 +1,7 @@
 use pretty_assertions::assert_eq;
 
 use crate::modules::tests::create_binary_from_zipped_ihex;
+use crate::tests::rule_false;
 use crate::tests::rule_true;
 use crate::tests::test_rule;
 
@@ -26,6 +27,38 @@ fn rich_signature() {
         "#,
         &pe
     );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.rich_signature.offset == 128 and
+            pe.rich_signature.length == 96 and
+            pe.rich_signature.key == 0x09654322 and
+            uint32(pe.rich_signature.offset) ^ pe.rich_signature.key == 0x536e6144 and
+            pe.rich_signature.clear_data startswith "DanS\x00\x00\x00\x00" and
+            pe.rich_signature.raw_data startswith "f\"\x0bZ" and
+            pe.rich_signature.tools[5].toolid == 1 and
+            pe.rich_signature.tools[5].version == 0 and
+            pe.rich_signature.tools[5].times == 44
+        }
+        "#,
+        &pe
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            not defined pe.rich_signature.key and
+            not defined pe.rich_signature.clear_data and
+            not defined pe.rich_signature.toolid(157)
+        }
+        "#,
+        &[]
+    );
 }
 
 #[test]
@@ -259,6 +292,71 @@ fn exports() {
     );
 }
 
+#[test]
+fn imports_and_exports_details() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/23e72ce7e9cdbc80c0095484ebeb02f56b21e48fd67044e69e7a2ae76db631e5.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.number_of_imports == 2 and
+            pe.number_of_imported_functions == 24 and
+            pe.imports("msvcrt.dll") == 6 and
+            pe.imports("kernel32.dll", "GetProcAddress") and
+            not pe.imports("kernel32.dll", "CreateFileA") and
+            pe.import_details[1].library_name == "KERNEL32.dll" and
+            pe.import_details[1].functions[17].name == "GetCurrentProcessId"
+        }
+        "#,
+        &pe
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.number_of_exports == 4 and
+            pe.exports("DllGetClassObject") and
+            pe.exports(4) and
+            not pe.exports(5) and
+            pe.exports_index("SafeRef") == 3 and
+            pe.export_details[0].ordinal == 1 and
+            pe.export_details[0].offset == 1072 and
+            not defined pe.export_details[0].forward_name and
+            for 3 export in pe.export_details : (
+              export.forward_name startswith "COMSVCS." and
+              not defined export.offset
+            ) and
+            pe.export_details[3].forward_name == "COMSVCS.SafeRef"
+        }
+        "#,
+        &pe
+    );
+
+    // Truncate the file right after the headers, so that the import and
+    // export directories point to data that is not available.
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.is_pe and
+            pe.number_of_imports == 0 and
+            pe.number_of_exports == 0 and
+            not pe.imports("kernel32.dll", "GetProcAddress") and
+            not pe.exports("DllGetClassObject") and
+            not defined pe.export_details[0].name
+        }
+        "#,
+        &pe[..1024]
+    );
+}
+
 #[test]
 fn imphash() {
     let pe = create_binary_from_zipped_ihex(
@@ -291,6 +389,42 @@ fn imphash() {
         &pe
     );
 
+    let mut pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/0ba6042247d90a187919dd88dc2d55cd882c80e5afc511c4f7b2e0e193968f7f.in.zip",
+    );
+
+    // This file imports `sendto` from WS2_32.dll by ordinal, the name of the
+    // function is obtained from the ordinal, like pefile does.
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.imphash() == "90a500d58fff87de59862af5b6bb678a"
+        }
+        "#,
+        &pe
+    );
+
+    // Rename MSWSOCK.dll to MSW.dll.dll. Only the last extension is removed
+    // from DLL names, the imphash is computed as if the DLL was "msw.dll".
+    let pos =
+        pe.windows(12).position(|window| window == b"MSWSOCK.dll\0").unwrap();
+
+    pe[pos..pos + 11].copy_from_slice(b"MSW.dll.dll");
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.imports("msw.dll.dll", "s_perror") and
+            pe.imphash() == "4fd8c9dda1cdce5af3c949fabfc63187"
+        }
+        "#,
+        &pe
+    );
+
     let pe = create_binary_from_zipped_ihex(
         "src/modules/lnk/tests/testdata/lnk-overlay.in.zip",
     );
@@ -318,12 +452,29 @@ fn checksum() {
         import "pe"
         rule test {
           condition:
-            pe.calculate_checksum() == 0x3CE9BA
+            pe.calculate_checksum() == 0x3CE9BA and
+            pe.calculate_checksum() == pe.checksum
         }
         "#,
         &pe
     );
 
+    // Tampering with the file makes the checksum in the header wrong.
+    let mut tampered = pe.clone();
+    *tampered.last_mut().unwrap() ^= 0xff;
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.checksum == 0x3CE9BA and
+            pe.calculate_checksum() != pe.checksum
+        }
+        "#,
+        &tampered
+    );
+
     let pe = create_binary_from_zipped_ihex(
         "src/modules/lnk/tests/testdata/lnk-overlay.in.zip",
     );
@@ -454,6 +605,310 @@ fn section_index() {
     );
 }
 
+#[test]
+fn sections_and_version_info() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/079a472d22290a94ebb212aa8015cdc8dd28a968c6b4d3b88acdd58ce2d3b885.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            for any section in pe.sections : (section.name == ".text") and
+            for all section in pe.sections : (section.name startswith ".") and
+            not for any section in pe.sections : (section.name == ".tls")
+        }
+        "#,
+        &pe
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            for any k, v in pe.version_info : (
+              k == "CompanyName" and v == "Quicken Inc."
+            ) and
+            for 2 k, v in pe.version_info : (v == "27.1.9.33") and
+            for any item in pe.version_info_list : (
+              item.key == "ProductName" and item.value == "Quicken for Windows"
+            )
+        }
+        "#,
+        &pe
+    );
+}
+
+#[test]
+fn sections_indexing() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/079a472d22290a94ebb212aa8015cdc8dd28a968c6b4d3b88acdd58ce2d3b885.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.sections[0].name == ".text" and
+            pe.sections[pe.number_of_sections - 1].name == ".reloc" and
+            pe.sections[pe.number_of_sections \ 2].name == ".data" and
+            pe.sections[pe.number_of_sections - 2].raw_data_size == 1536 and
+            for all i in (0..pe.number_of_sections - 1) : (
+              pe.sections[i].raw_data_size > 0
+            )
+        }
+        "#,
+        &pe
+    );
+
+    // Indexes that are out of bounds produce undefined values.
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            not defined pe.sections[pe.number_of_sections].name and
+            not defined pe.sections[pe.number_of_sections + 100].raw_data_size and
+            not defined pe.sections[pe.number_of_sections - 6].name
+        }
+        "#,
+        &pe
+    );
+
+    rule_false!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.sections[pe.number_of_sections].name == ".reloc" or
+            pe.sections[pe.number_of_sections - 6].name == ".reloc"
+        }
+        "#,
+        &pe
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            not defined pe.sections[0].name and
+            not defined pe.sections[pe.number_of_sections - 1].name
+        }
+        "#,
+        &[]
+    );
+}
+
+#[test]
+fn resources_and_version_info() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/29eeeecf2c458ea3da1ce9d6d54742c0fad490cb2165f371f53b61941eedf072.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.number_of_resources == 72 and
+            pe.resources[5].type == pe.RESOURCE_TYPE_ICON and
+            pe.resources[5].id == 1 and
+            pe.resources[5].language == 0 and
+            pe.resources[5].offset == 388528 and
+            pe.resources[5].length == 1640 and
+            for 27 resource in pe.resources : (
+              resource.type == pe.RESOURCE_TYPE_DIALOG
+            ) and
+            for 1 resource in pe.resources : (
+              resource.type == pe.RESOURCE_TYPE_VERSION
+            )
+        }
+        "#,
+        &pe
+    );
+
+    // Resources with a type identified by a string instead of a number.
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            not defined pe.resources[0].type and
+            pe.resources[0].type_string == "A\x00F\x00X\x00_\x00D\x00I\x00A\x00L\x00O\x00G\x00_\x00L\x00A\x00Y\x00O\x00U\x00T\x00" and
+            pe.resources[0].id == 106 and
+            pe.resources[0].language == 1033 and
+            not defined pe.resources[0].name_string
+        }
+        "#,
+        &pe
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.version_info["CompanyName"] == "Ladislav Zezula" and
+            pe.version_info["OriginalFilename"] == "FileTest.exe" and
+            pe.version_info["FileVersion"] == "2, 7, 0, 601" and
+            not defined pe.version_info["Comments"]
+        }
+        "#,
+        &pe
+    );
+}
+
+#[test]
+fn sections_entropy() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/079a472d22290a94ebb212aa8015cdc8dd28a968c6b4d3b88acdd58ce2d3b885.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        import "math"
+        rule test {
+          condition:
+            pe.sections[0].entropy > 6.0 and
+            pe.sections[2].entropy < 1.0 and
+            for all section in pe.sections : (
+              section.entropy == math.entropy(
+                section.raw_data_offset, section.raw_data_size)
+            )
+        }
+        "#,
+        &pe
+    );
+
+    // Truncate the file in the middle of the second section. The entropy
+    // for the second section is computed with the bytes that are present
+    // in the file, the remaining sections don't have entropy.
+    let truncated = &pe[..6000];
+
+    rule_true!(
+        r#"
+        import "pe"
+        import "math"
+        rule test {
+          condition:
+            pe.sections[0].raw_data_offset + pe.sections[0].raw_data_size < filesize and
+            pe.sections[1].raw_data_offset + pe.sections[1].raw_data_size > filesize and
+            pe.sections[1].raw_data_offset < filesize and
+            pe.sections[1].entropy == math.entropy(
+              pe.sections[1].raw_data_offset,
+              filesize - pe.sections[1].raw_data_offset) and
+            not defined pe.sections[pe.number_of_sections - 1].entropy
+        }
+        "#,
+        truncated
+    );
+}
+
+#[test]
+fn pdb_info() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/1e435fea9ced78bd31ae8320a894df290cdf8a262ba1b50c9b116caa26983145.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.pdb_path == "d:\\Projects\\astroburn\\bin\\Release\\Core.pdb" and
+            pe.pdb_path iendswith "\\core.pdb" and
+            pe.pdb_guid == "f544b9e8-887c-45e0-80f3-bc71a82bc25d" and
+            pe.pdb_age == 1 and
+            not pe.is_reproducible_build
+        }
+        "#,
+        &pe
+    );
+
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/23e72ce7e9cdbc80c0095484ebeb02f56b21e48fd67044e69e7a2ae76db631e5.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.pdb_path == "mtxex.pdb" and
+            pe.pdb_guid == "d282c602-5c44-8281-75c9-b6e6cac15357" and
+            pe.is_reproducible_build
+        }
+        "#,
+        &pe
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            not defined pe.pdb_path and
+            not defined pe.pdb_guid and
+            not defined pe.pdb_age and
+            not defined pe.is_reproducible_build
+        }
+        "#,
+        &[]
+    );
+}
+
+#[test]
+fn tls_callbacks_and_delayed_imports() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/99df28014fae5f213c8decfde423b0eb69005158f981bc84560e9a5dde103d90.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.number_of_tls_callbacks == 2 and
+            pe.tls_callbacks[0] == 0x4018c0 and
+            pe.tls_callbacks[1] == 0x401870 and
+            for all cb in pe.tls_callbacks : (
+              defined pe.rva_to_offset(cb - pe.image_base)
+            ) and
+            pe.number_of_delayed_imports == 0
+        }
+        "#,
+        &pe
+    );
+
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/2775d97f8bdb3311ace960a42eee35dbec84b9d71a6abbacb26c14e83f5897e4.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.number_of_tls_callbacks == 0 and
+            pe.number_of_delayed_imports == 6 and
+            pe.delayed_import_details[0].library_name == "USER32.dll" and
+            pe.delayed_import_details[0].functions[0].name == "CreateMenu" and
+            pe.delayed_import_details[0].functions[0].rva == 4268 and
+            pe.imports(pe.IMPORT_DELAYED, "USER32.dll", "DestroyMenu") and
+            pe.imports(pe.IMPORT_DELAYED, "gdi32.dll", "DeleteObject") and
+            not pe.imports(pe.IMPORT_STANDARD, "USER32.dll", "CreateMenu")
+        }
+        "#,
+        &pe
+    );
+}
+
 #[test]
 fn image_directory_constants() {
     rule_true!(
@@ -488,6 +943,65 @@ fn rva_to_offset() {
     );
 }
 
+#[test]
+fn overlay_and_entry_point() {
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/c6f9709feccf42f2d9e22057182fe185f177fb9daaa2649b4669a24f2ee7e3ba.in.zip",
+    );
+
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          strings:
+            $jmp = { E9 81 04 00 00 }
+            $hdr = "HDR*"
+            $power = "PowerB" wide
+          condition:
+            pe.entry_point == 1360 and
+            pe.entry_point_raw == 4432 and
+            pe.rva_to_offset(pe.entry_point_raw) == pe.entry_point and
+            $jmp at pe.entry_point and
+            pe.overlay.offset == 217600 and
+            pe.overlay.size == 17408 and
+            pe.overlay.offset + pe.overlay.size == filesize and
+            $hdr at pe.overlay.offset and
+            $power in (pe.overlay.offset..pe.overlay.offset + pe.overlay.size)
+        }
+        "#,
+        &pe
+    );
+
+    // RVAs that are not mapped to any section don't have a file offset.
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            not defined pe.rva_to_offset(0x7fffffff) and
+            not defined pe.rva_to_offset(-1)
+        }
+        "#,
+        &pe
+    );
+
+    let pe = create_binary_from_zipped_ihex(
+        "src/modules/pe/tests/testdata/0885cff80dd6e14559c6429a89d724d9f5553a34e0716a660376728b8987c61b.in.zip",
+    );
+
+    // Files without overlay have an overlay with zero size and offset.
+    rule_true!(
+        r#"
+        import "pe"
+        rule test {
+          condition:
+            pe.overlay.offset == 0 and pe.overlay.size == 0
+        }
+        "#,
+        &pe
+    );
+}
+
 #[test]
 fn valid_on() {
     let pe = create_binary_from_zipped_ihex(
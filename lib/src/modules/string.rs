@@ -9,8 +9,7 @@ fn main(_data: &[u8]) -> String {
 
 #[module_export]
 fn to_int(ctx: &ScanContext, string: RuntimeString) -> Option<i64> {
-    let string = string.to_str(ctx).ok()?;
-    string.parse::<i64>().ok()
+    parse_int(string.as_bstr(ctx), 0)
 }
 
 #[module_export(name = "to_int")]
@@ -20,11 +19,55 @@ fn to_int_base(
     base: i64,
 ) -> Option<i64> {
     let base: u32 = base.try_into().ok()?;
-    if !(2..=36).contains(&base) {
+    if base != 0 && !(2..=36).contains(&base) {
         return None;
     }
-    let string = string.to_str(ctx).ok()?;
-    i64::from_str_radix(string, base).ok()
+    parse_int(string.as_bstr(ctx), base)
+}
+
+/// Parses a string as a signed integer in the given base, following the
+/// semantics of `strtoll` in C.
+///
+/// Leading whitespaces are ignored, and the number can be preceded by a
+/// `+` or `-` sign. If `base` is 16 the number can be prefixed with "0x".
+/// If `base` is 0 the base is determined by the number itself: base 16 if it
+/// starts with "0x", base 8 if it starts with "0", and base 10 otherwise.
+///
+/// Unlike `strtoll`, the whole string must be a valid number. Returns `None`
+/// if the string contains trailing characters or the number doesn't fit in
+/// an `i64`.
+fn parse_int(s: &[u8], base: u32) -> Option<i64> {
+    let start =
+        s.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(s.len());
+
+    let s = &s[start..];
+
+    let (sign, s) = match s.split_first() {
+        Some((b'-', rest)) => ("-", rest),
+        Some((b'+', rest)) => ("", rest),
+        _ => ("", s),
+    };
+
+    let has_hex_prefix = (s.starts_with(b"0x") || s.starts_with(b"0X"))
+        && s.get(2).is_some_and(u8::is_ascii_hexdigit);
+
+    let (base, digits) = match base {
+        0 if has_hex_prefix => (16, &s[2..]),
+        0 if s.starts_with(b"0") => (8, s),
+        0 => (10, s),
+        16 if has_hex_prefix => (16, &s[2..]),
+        base => (base, s),
+    };
+
+    // `from_str_radix` accepts a leading sign, make sure that the digits
+    // don't contain another one.
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_alphanumeric) {
+        return None;
+    }
+
+    let digits = std::str::from_utf8(digits).ok()?;
+
+    i64::from_str_radix(format!("{sign}{digits}").as_str(), base).ok()
 }
 
 #[module_export]
@@ -98,5 +141,46 @@ mod tests {
             rule test { condition: string.to_int("-011", 8) == -9 }"#,
             &[]
         );
+
+        rule_true!(
+            r#"
+            import "string"
+            rule test {
+              condition:
+                string.to_int("0x1A") == 26 and
+                string.to_int("-010") == -8 and
+                string.to_int("+12") == 12 and
+                string.to_int("  42") == 42 and
+                string.to_int("0") == 0 and
+                string.to_int("-011", 0) == -9 and
+                string.to_int("0x1A", 0) == 26 and
+                string.to_int("0x1A", 16) == 26 and
+                string.to_int("1A", 16) == 26 and
+                string.to_int("z", 36) == 35 and
+                string.to_int("9223372036854775807") == 9223372036854775807 and
+                string.to_int("-0x8000000000000000") == -9223372036854775807 - 1
+            }"#,
+            &[]
+        );
+
+        rule_true!(
+            r#"
+            import "string"
+            rule test {
+              condition:
+                not defined string.to_int("") and
+                not defined string.to_int("-") and
+                not defined string.to_int("12abc") and
+                not defined string.to_int("12 ") and
+                not defined string.to_int("+-12") and
+                not defined string.to_int("08") and
+                not defined string.to_int("0x") and
+                not defined string.to_int("9223372036854775808") and
+                not defined string.to_int("10", 1) and
+                not defined string.to_int("10", 37) and
+                not defined string.to_int("2", 2)
+            }"#,
+            &[]
+        );
     }
 }
@@ -2,7 +2,7 @@ use crate::modules::prelude::*;
 use crate::modules::protos::test_proto2::NestedProto2;
 use crate::modules::protos::test_proto2::TestProto2;
 use crate::types::Struct;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[cfg(test)]
 mod tests;
@@ -36,7 +36,7 @@ pub(crate) fn nested_func(_ctx: &mut ScanContext) -> bool {
 )]
 pub(crate) fn nested_method(
     _ctx: &mut ScanContext,
-    structure: Rc<Struct>,
+    structure: Arc<Struct>,
 ) -> bool {
     structure.field_by_name("nested_bool").unwrap().type_value.as_bool()
 }
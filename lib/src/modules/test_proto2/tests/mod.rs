@@ -63,6 +63,12 @@ fn test_proto2_module() {
     condition_true!(r#"test_proto2.string_bar iendswith "AR""#);
     condition_true!(r#"test_proto2.string_bar istartswith "BAR""#);
     condition_true!(r#"test_proto2.string_bar iequals "BAR""#);
+    condition_true!(
+        r#"for any s in test_proto2.array_string : (s iequals "BAZ")"#
+    );
+    condition_true!(
+        r#"for all s in test_proto2.array_string : (s icontains "A" or s istartswith "F")"#
+    );
 
     condition_true!(r#"test_proto2.array_int64[0] == 1"#);
     condition_true!(r#"test_proto2.array_int64[1] == 10"#);
@@ -238,6 +244,35 @@ fn test_proto2_module() {
           )"#
     );
 
+    // The key and value bound in each iteration belong to the same entry.
+    condition_true!(
+        r#"for all key, value in test_proto2.map_string_int64 : (
+                test_proto2.map_string_int64[key] == value
+          )"#
+    );
+
+    condition_true!(
+        r#"for all key, value in test_proto2.map_int64_string : (
+                test_proto2.map_int64_string[key] == value
+          )"#
+    );
+
+    condition_true!(
+        r#"for none key, value in test_proto2.map_string_string : (
+                key == value
+          )"#
+    );
+
+    // Loops over maps can be nested with loops over arrays, and the
+    // variables of the outer loop are visible in the inner one.
+    condition_true!(
+        r#"for any key, value in test_proto2.map_string_string : (
+                for any s in test_proto2.array_string : (
+                  s == key and value iequals s
+                )
+          )"#
+    );
+
     condition_true!(r#"test_proto2.get_foo() == "foo""#);
     condition_true!(r#"test_proto2.to_int("123") == 123"#);
 
@@ -2,8 +2,15 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+use protobuf::reflect::{
+    MessageDescriptor, RuntimeFieldType, RuntimeType, Syntax,
+};
+use protobuf::MessageDyn;
 use rayon::prelude::*;
 
+use crate::modules::BUILTIN_MODULES;
+use crate::types::Struct;
+
 /// Utility function that receives the content of an [`Intel HEX`][1] (ihex)
 /// file and returns the binary data contained in it.
 ///
@@ -147,3 +154,155 @@ fn test_modules() {
         yaml.serialize(output).unwrap();
     });
 }
+
+/// Walks the fields described by `msg_descriptor` and collects in `exprs`
+/// expressions that access fields that are missing from `msg`.
+///
+/// `prefix` is the expression that evaluates to the structure described by
+/// `msg_descriptor` (e.g: `pe.rich_signature`). When `msg` is `None` every
+/// field in the structure is considered missing. For arrays the expressions
+/// use an index that is out of bounds, and for maps they use a key that is
+/// not present in the map.
+///
+/// In proto3 scalar fields that are missing take their default values, so
+/// they are not undefined and therefore they are not collected.
+fn missing_fields(
+    prefix: &str,
+    msg_descriptor: &MessageDescriptor,
+    msg: Option<&dyn MessageDyn>,
+    depth: usize,
+    exprs: &mut Vec<String>,
+) {
+    // Protobuf messages can be recursive, stop at some reasonable depth.
+    if depth == 0 {
+        return;
+    }
+
+    let proto3 = msg_descriptor.file_descriptor().syntax() == Syntax::Proto3;
+
+    for fd in msg_descriptor.fields() {
+        if Struct::ignore_field(&fd) {
+            continue;
+        }
+
+        let expr = format!("{}.{}", prefix, Struct::field_name(&fd));
+
+        match fd.runtime_field_type() {
+            RuntimeFieldType::Singular(RuntimeType::Message(m)) => {
+                let sub_msg = msg
+                    .filter(|msg| fd.has_field(*msg))
+                    .and_then(|msg| fd.get_singular(msg))
+                    .map(|value| value.to_message().unwrap());
+                missing_fields(
+                    expr.as_str(),
+                    &m,
+                    sub_msg.as_deref(),
+                    depth - 1,
+                    exprs,
+                );
+            }
+            RuntimeFieldType::Singular(_) => {
+                if !proto3 && !msg.is_some_and(|msg| fd.has_field(msg)) {
+                    exprs.push(expr);
+                }
+            }
+            RuntimeFieldType::Repeated(ty) => {
+                let len = msg.map_or(0, |msg| fd.get_repeated(msg).len());
+                let expr = format!("{}[{}]", expr, len);
+                if let RuntimeType::Message(m) = ty {
+                    missing_fields(expr.as_str(), &m, None, depth - 1, exprs);
+                } else {
+                    exprs.push(expr);
+                }
+            }
+            RuntimeFieldType::Map(key_ty, value_ty) => {
+                let expr = match key_ty {
+                    RuntimeType::String => {
+                        format!(r#"{}["__missing_key__"]"#, expr)
+                    }
+                    _ => format!("{}[-1]", expr),
+                };
+                if let RuntimeType::Message(m) = value_ty {
+                    missing_fields(expr.as_str(), &m, None, depth - 1, exprs);
+                } else {
+                    exprs.push(expr);
+                }
+            }
+        }
+    }
+}
+
+/// Makes sure that accessing fields that are missing from the output of a
+/// module produces an undefined value, instead of a trap or any other
+/// error.
+///
+/// For each module the test scans an empty input, walks the module's
+/// protobuf descriptor looking for the fields that were not set in the
+/// module's output, and creates a rule with condition `not defined <field>`
+/// for each of them. All these rules must match.
+#[test]
+fn undefined_fields() {
+    for (module_name, module) in BUILTIN_MODULES.iter() {
+        // Scan an empty input in order to obtain the module's output.
+        let rules = crate::compile(
+            format!(
+                r#"import "{}" rule test {{ condition: false }}"#,
+                module_name
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+        let mut scanner = crate::scanner::Scanner::new(&rules);
+        let scan_results = scanner.scan(b"").expect("scan should not fail");
+
+        let mut exprs = Vec::new();
+
+        missing_fields(
+            module_name,
+            &module.root_struct_descriptor,
+            scan_results.module_output(module_name),
+            4,
+            &mut exprs,
+        );
+
+        if exprs.is_empty() {
+            continue;
+        }
+
+        let mut src = format!(r#"import "{}""#, module_name);
+
+        for (i, expr) in exprs.iter().enumerate() {
+            src.push_str(
+                format!("\nrule r{} {{ condition: not defined {} }}", i, expr)
+                    .as_str(),
+            );
+        }
+
+        let rules = crate::compile(src.as_str()).unwrap_or_else(|err| {
+            panic!("can not compile rules for `{}`: {}", module_name, err)
+        });
+
+        let mut scanner = crate::scanner::Scanner::new(&rules);
+        let scan_results = scanner.scan(b"").expect("scan should not fail");
+
+        let mut defined = vec![true; exprs.len()];
+
+        for rule in scan_results.matching_rules() {
+            let i: usize = rule.identifier()[1..].parse().unwrap();
+            defined[i] = false;
+        }
+
+        let defined: Vec<_> = exprs
+            .iter()
+            .zip(defined)
+            .filter_map(|(expr, defined)| defined.then_some(expr))
+            .collect();
+
+        assert!(
+            defined.is_empty(),
+            "missing fields should be undefined: {:?}",
+            defined
+        );
+    }
+}
@@ -0,0 +1,25 @@
+/*! Utility functions shared by multiple modules. */
+
+/// Computes the Shannon entropy of `data`, in bits per byte.
+///
+/// The result is a value between 0.0 and 8.0. Returns 0.0 for empty data.
+pub(crate) fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut distribution = [0u64; 256];
+    for byte in data {
+        distribution[*byte as usize] += 1;
+    }
+
+    let mut entropy: f64 = 0.0;
+    for value in &distribution {
+        if *value != 0 {
+            let x = *value as f64 / data.len() as f64;
+            entropy -= x * f64::log2(x);
+        }
+    }
+
+    entropy
+}
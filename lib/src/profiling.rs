@@ -0,0 +1,259 @@
+/*! Profiling information collected while compiling and scanning.
+
+When the `rules-profiling` feature is enabled, the compiler records the time
+spent in each compilation phase, and the scanner records the time spent in
+the main function of each module, in the evaluation of each rule's condition
+and in the verification of each pattern.
+This information can be obtained with [`crate::Scanner::profiling_data`] and
+exported in the folded stack format understood by tools like [inferno],
+[flamegraph.pl] and [speedscope], or in the [pprof] format.
+
+[inferno]: https://github.com/jonhoo/inferno
+[flamegraph.pl]: https://github.com/brendangregg/FlameGraph
+[speedscope]: https://www.speedscope.app/
+[pprof]: https://github.com/google/pprof
+ */
+
+use std::io;
+use std::time::Duration;
+
+use protobuf::CodedOutputStream;
+use rustc_hash::FxHashMap;
+
+/// Adds `time` to the time already accumulated for `phase`.
+pub(crate) fn add_phase_time(
+    phases: &mut Vec<(&'static str, Duration)>,
+    phase: &'static str,
+    time: Duration,
+) {
+    match phases.iter_mut().find(|(name, _)| *name == phase) {
+        Some((_, total)) => *total += time,
+        None => phases.push((phase, time)),
+    }
+}
+
+/// Profiling information collected while compiling rules and scanning data.
+///
+/// Times spent during scanning are accumulated across all the scans
+/// performed by the same [`crate::Scanner`].
+#[derive(Debug, Clone, Default)]
+pub struct ProfilingData<'r> {
+    /// Time spent in each compilation phase, in the order in which the
+    /// phases were executed for the first time.
+    pub compilation: Vec<(&'static str, Duration)>,
+    /// Time spent in the main function of each module.
+    pub modules: Vec<(&'r str, Duration)>,
+    /// Time spent in each rule, from the most expensive to the least
+    /// expensive one.
+    pub rules: Vec<RuleProfile<'r>>,
+    /// Time spent verifying each pattern, from the most expensive to the
+    /// least expensive one.
+    pub patterns: Vec<PatternProfile<'r>>,
+}
+
+/// Time spent in a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleProfile<'r> {
+    /// Namespace of the rule.
+    pub namespace: &'r str,
+    /// Identifier of the rule.
+    pub rule: &'r str,
+    /// Cumulative time spent evaluating the rule's condition, excluding the
+    /// time spent searching for patterns.
+    pub condition_time: Duration,
+    /// Cumulative time spent verifying the rule's patterns. The time spent
+    /// in patterns shared with other rules is split evenly among them.
+    pub pattern_time: Duration,
+}
+
+impl RuleProfile<'_> {
+    /// Total time spent in the rule, which is the sum of
+    /// [`RuleProfile::condition_time`] and [`RuleProfile::pattern_time`].
+    pub fn total_time(&self) -> Duration {
+        self.condition_time + self.pattern_time
+    }
+}
+
+/// Time spent verifying a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternProfile<'r> {
+    /// Namespace of the rule that contains the pattern.
+    pub namespace: &'r str,
+    /// Identifier of the rule that contains the pattern.
+    pub rule: &'r str,
+    /// Identifier of the pattern (e.g: `$a`).
+    pub pattern: &'r str,
+    /// Cumulative time spent verifying the pattern. Identical patterns are
+    /// verified only once, even if they appear in multiple rules, and in
+    /// that case the time is split evenly among those rules.
+    pub time: Duration,
+}
+
+impl ProfilingData<'_> {
+    /// Writes the profiling data in folded stack format.
+    ///
+    /// Each line contains a semicolon-separated stack of frames followed by
+    /// the number of microseconds spent in it. For example:
+    ///
+    /// ```text
+    /// compilation;parse 1520
+    /// scan;modules;pe 310
+    /// scan;conditions;default;my_rule 12
+    /// scan;patterns;default;my_rule;$a 45
+    /// ```
+    ///
+    /// Entries where the time spent is less than one microsecond are
+    /// omitted.
+    pub fn write_folded<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        for (frames, time) in self.stacks() {
+            let micros = time.as_micros();
+            if micros > 0 {
+                writeln!(w, "{} {}", frames.join(";"), micros)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the profiling data in [pprof] format.
+    ///
+    /// The profile contains the same stacks written by
+    /// [`ProfilingData::write_folded`], with the time spent in each of them
+    /// in nanoseconds. It is written as an uncompressed protobuf message,
+    /// which can be read by `go tool pprof` and other tools that support
+    /// the format.
+    ///
+    /// [pprof]: https://github.com/google/pprof/blob/main/proto/profile.proto
+    pub fn write_pprof<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        let profile = self.encode_pprof().map_err(io::Error::other)?;
+        w.write_all(profile.as_slice())
+    }
+
+    /// Returns the stacks of frames in which the time was spent, together
+    /// with the time spent in each of them.
+    fn stacks(&self) -> Vec<(Vec<&str>, Duration)> {
+        let mut stacks = Vec::new();
+        for (phase, time) in &self.compilation {
+            stacks.push((vec!["compilation", phase], *time));
+        }
+        for (module, time) in &self.modules {
+            stacks.push((vec!["scan", "modules", module], *time));
+        }
+        for r in &self.rules {
+            stacks.push((
+                vec!["scan", "conditions", r.namespace, r.rule],
+                r.condition_time,
+            ));
+        }
+        for p in &self.patterns {
+            stacks.push((
+                vec!["scan", "patterns", p.namespace, p.rule, p.pattern],
+                p.time,
+            ));
+        }
+        stacks
+    }
+
+    /// Encodes the profiling data as a pprof `Profile` message.
+    ///
+    /// Each distinct frame name is a function with a single location, and
+    /// both share the same ID. The string table starts with the empty
+    /// string, followed by the sample type, unit and function names.
+    fn encode_pprof(&self) -> protobuf::Result<Vec<u8>> {
+        const TIME: u64 = 1;
+        const NANOSECONDS: u64 = 2;
+
+        let mut functions: Vec<&str> = Vec::new();
+        let mut function_ids: FxHashMap<&str, u64> = FxHashMap::default();
+        let mut samples = Vec::new();
+
+        for (frames, time) in self.stacks() {
+            let nanos = time.as_nanos();
+            if nanos == 0 {
+                continue;
+            }
+            // Locations in a sample go from the leaf to the root.
+            let locations: Vec<u64> = frames
+                .iter()
+                .rev()
+                .map(|frame| {
+                    *function_ids.entry(frame).or_insert_with(|| {
+                        functions.push(frame);
+                        functions.len() as u64
+                    })
+                })
+                .collect();
+            samples
+                .push((locations, i64::try_from(nanos).unwrap_or(i64::MAX)));
+        }
+
+        let mut profile = Vec::new();
+        let mut os = CodedOutputStream::vec(&mut profile);
+
+        let value_type = message(|os| {
+            os.write_uint64(1, TIME)?;
+            os.write_uint64(2, NANOSECONDS)
+        })?;
+
+        // sample_type
+        os.write_bytes(1, &value_type)?;
+
+        // sample
+        for (locations, value) in &samples {
+            os.write_bytes(
+                2,
+                &message(|os| {
+                    for location in locations {
+                        os.write_uint64(1, *location)?;
+                    }
+                    os.write_int64(2, *value)
+                })?,
+            )?;
+        }
+
+        for id in 1..=functions.len() as u64 {
+            // location, with a single line that refers to the function.
+            os.write_bytes(
+                4,
+                &message(|os| {
+                    os.write_uint64(1, id)?;
+                    os.write_bytes(4, &message(|os| os.write_uint64(1, id))?)
+                })?,
+            )?;
+            // function, whose name is in the string table after the
+            // sample type and unit.
+            os.write_bytes(
+                5,
+                &message(|os| {
+                    os.write_uint64(1, id)?;
+                    os.write_uint64(2, NANOSECONDS + id)
+                })?,
+            )?;
+        }
+
+        // string_table
+        for s in ["", "time", "nanoseconds"].iter().chain(functions.iter()) {
+            os.write_string(6, s)?;
+        }
+
+        // period_type
+        os.write_bytes(11, &value_type)?;
+
+        os.flush()?;
+        drop(os);
+
+        Ok(profile)
+    }
+}
+
+/// Encodes a protobuf message whose fields are written by `f`.
+fn message<F>(f: F) -> protobuf::Result<Vec<u8>>
+where
+    F: FnOnce(&mut CodedOutputStream) -> protobuf::Result<()>,
+{
+    let mut buf = Vec::new();
+    let mut os = CodedOutputStream::vec(&mut buf);
+    f(&mut os)?;
+    os.flush()?;
+    drop(os);
+    Ok(buf)
+}
@@ -138,6 +138,18 @@ impl Parser {
 
     /// Parses the regexp and returns its HIR.
     pub fn parse(&self, regexp: &impl Regexp) -> Result<Hir, Error> {
+        self.parse_with_warnings(regexp).map(|(hir, _)| hir)
+    }
+
+    /// Like [`Parser::parse`], but also returns the syntax errors that were
+    /// tolerated because [`Parser::relaxed_re_syntax`] is enabled. These
+    /// errors are always [`Error::SyntaxError`], and their notes explain how
+    /// the offending construct was interpreted.
+    pub fn parse_with_warnings(
+        &self,
+        regexp: &impl Regexp,
+    ) -> Result<(Hir, Vec<Error>), Error> {
+        let mut warnings = Vec::new();
         let mut re_src = Cow::Borrowed(regexp.source());
         let mut span_delta = 0_isize;
 
@@ -191,6 +203,19 @@ impl Parser {
                         ErrorKind::EscapeUnrecognized
                         | ErrorKind::ClassEscapeInvalid => {
                             let span = err.span();
+                            let esc_seq =
+                                &re_src[span.start.offset..span.end.offset];
+                            warnings.push(Error::SyntaxError {
+                                msg: err.kind().to_string(),
+                                span: adjust_span(span, span_delta),
+                                note: Some(format!(
+                                    "`{}` is interpreted as `{}`",
+                                    esc_seq,
+                                    esc_seq
+                                        .strip_prefix('\\')
+                                        .unwrap_or(esc_seq)
+                                )),
+                            });
                             let mut s = re_src.into_owned();
                             // Remove the backslash (\) from the original regexp.
                             s.remove(span.start.offset);
@@ -215,6 +240,28 @@ impl Parser {
                                 [0..=span.start.offset]
                                 .rfind('{')
                                 .unwrap();
+                            warnings.push(Error::SyntaxError {
+                                msg: err.kind().to_string(),
+                                span: adjust_span(
+                                    &re::ast::Span::new(
+                                        re::ast::Position::new(
+                                            curly_brace,
+                                            span.start.line,
+                                            curly_brace + 1,
+                                        ),
+                                        re::ast::Position::new(
+                                            curly_brace + 1,
+                                            span.start.line,
+                                            curly_brace + 2,
+                                        ),
+                                    ),
+                                    span_delta,
+                                ),
+                                note: Some(
+                                    "`{` is interpreted as a literal"
+                                        .to_string(),
+                                ),
+                            });
                             let mut s = re_src.into_owned();
                             // Insert a backslash in front of the `{`.
                             s.insert(curly_brace, '\\');
@@ -255,7 +302,27 @@ impl Parser {
         })?;
 
         let ast = Transformer::new().transform(ast);
-        let greedy = Validator::new().validate(&ast);
+        let mut validator = Validator::new();
+        let greedy = validator.validate(&ast);
+
+        // A quantifier applied to another quantifier, like in `a**` or
+        // `a{2}{3}`, is accepted but it doesn't have the meaning that the
+        // user probably intended. For instance, `a++` is not a possessive
+        // quantifier, it's equivalent to `(?:a+)+`.
+        if self.relaxed_re_syntax {
+            for (outer, inner) in validator.nested_reps {
+                warnings.push(Error::SyntaxError {
+                    msg: "nested quantifier".to_string(),
+                    span: adjust_span(&outer, span_delta),
+                    note: Some(format!(
+                        "`{}` is interpreted as `(?:{}){}`",
+                        &re_src[outer.start.offset..outer.end.offset],
+                        &re_src[inner.start.offset..inner.end.offset],
+                        &re_src[inner.end.offset..outer.end.offset],
+                    )),
+                });
+            }
+        }
 
         // `greedy` is set to Some(true) if all regexp quantifiers are greedy,
         // Some(false) if all are non-greedy, and None if there's a mix of
@@ -290,17 +357,20 @@ impl Parser {
                 }
             })?;
 
-        Ok(Hir { inner: hir, greedy })
+        Ok((Hir { inner: hir, greedy }, warnings))
     }
 }
 
 struct Validator {
     first_rep: Option<(bool, re::ast::Span)>,
+    /// Spans of the repetitions whose operand is another repetition, each
+    /// one paired with the span of its operand.
+    nested_reps: Vec<(re::ast::Span, re::ast::Span)>,
 }
 
 impl Validator {
     fn new() -> Self {
-        Self { first_rep: None }
+        Self { first_rep: None, nested_reps: Vec::new() }
     }
 
     fn validate(&mut self, ast: &Ast) -> Result<Option<bool>, Error> {
@@ -318,6 +388,9 @@ impl re::ast::Visitor for &mut Validator {
 
     fn visit_pre(&mut self, ast: &Ast) -> Result<(), Self::Err> {
         if let Ast::Repetition(rep) = ast {
+            if let Ast::Repetition(inner) = rep.ast.as_ref() {
+                self.nested_reps.push((rep.span, inner.span));
+            }
             if let Some(first_rep) = self.first_rep {
                 if rep.greedy != first_rep.0 {
                     return Err(Error::MixedGreediness {
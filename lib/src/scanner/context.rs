@@ -1,15 +1,15 @@
 use std::cell::RefCell;
+use std::cmp;
 use std::collections::VecDeque;
 use std::ops::{Range, RangeInclusive};
 use std::ptr::NonNull;
-use std::rc::Rc;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[cfg(feature = "logging")]
 use log::*;
 #[cfg(feature = "rules-profiling")]
 use std::time::Duration;
-#[cfg(any(feature = "logging", feature = "rules-profiling"))]
 use std::time::Instant;
 
 use base64::Engine;
@@ -20,7 +20,7 @@ use indexmap::IndexMap;
 use protobuf::{MessageDyn, MessageFull};
 use regex_automata::meta::Regex;
 use rustc_hash::{FxHashMap, FxHashSet};
-use wasmtime::Store;
+use wasmtime::ResourceLimiter;
 
 use crate::compiler::{
     NamespaceId, PatternId, RegexpId, RuleId, Rules, SubPattern,
@@ -30,24 +30,122 @@ use crate::re::fast::fastvm::FastVM;
 use crate::re::thompson::pikevm::PikeVM;
 use crate::re::Action;
 use crate::scanner::matches::{Match, PatternMatches, UnconfirmedMatch};
-use crate::scanner::HEARTBEAT_COUNTER;
+use crate::scanner::{
+    OffsetTranslator, Rule, ScanStats, ScannedData, HEARTBEAT_COUNTER,
+};
 use crate::types::{Array, Map, Struct};
 use crate::wasm::MATCHING_RULES_BITMAP_BASE;
 use crate::ScanError;
 
+/// Size of the chunks in which the data is searched for patterns. The
+/// timeout and the cancellation are checked after each chunk.
+const SEARCH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Callback set with [`crate::Scanner::on_rule_match`].
+pub(crate) type RuleMatchCallback<'r> =
+    Box<dyn FnMut(&Rule<'_, 'r>) + Send + 'r>;
+
+/// Keeps track of the rules that are known to match while the search for
+/// patterns is in progress, for stopping the search once all rules match.
+/// See [`crate::Scanner::early_termination`].
+pub(crate) struct EarlyTermination {
+    /// For each pattern, the rules that match as soon as the pattern
+    /// matches.
+    rules_by_pattern: FxHashMap<PatternId, Vec<RuleId>>,
+    /// Rules that are known to match in the current scan.
+    matching_rules: FxHashSet<RuleId>,
+    /// Total number of rules.
+    num_rules: usize,
+}
+
+impl EarlyTermination {
+    /// Creates a new [`EarlyTermination`] for the given rules. Returns
+    /// `None` if some rule is not guaranteed to match once some of its
+    /// patterns matches, as in that case the search can't be stopped
+    /// early.
+    pub fn new(rules: &Rules) -> Option<Self> {
+        let mut rules_by_pattern: FxHashMap<PatternId, Vec<RuleId>> =
+            FxHashMap::default();
+
+        for (rule_id, rule) in rules.rules().iter().enumerate() {
+            for pattern_id in rule.sufficient_patterns.as_ref()? {
+                rules_by_pattern
+                    .entry(*pattern_id)
+                    .or_default()
+                    .push(rule_id.into());
+            }
+        }
+
+        Some(Self {
+            rules_by_pattern,
+            matching_rules: FxHashSet::default(),
+            num_rules: rules.num_rules(),
+        })
+    }
+
+    /// Called when some pattern matches.
+    #[inline]
+    pub fn pattern_matched(&mut self, pattern_id: PatternId) {
+        if let Some(rules) = self.rules_by_pattern.get(&pattern_id) {
+            self.matching_rules.extend(rules.iter().cloned());
+        }
+    }
+
+    /// Returns true if all rules are known to match.
+    #[inline]
+    pub fn all_rules_match(&self) -> bool {
+        self.matching_rules.len() == self.num_rules
+    }
+
+    /// Clears the rules that are known to match, making it ready for a new
+    /// scan.
+    pub fn reset(&mut self) {
+        self.matching_rules.clear();
+    }
+}
+
+/// Resource limiter that puts a cap on the size of the WASM memory.
+#[derive(Default)]
+pub(crate) struct MemoryLimiter {
+    /// Maximum size of the WASM memory in bytes, `None` means unlimited.
+    pub max_memory: Option<usize>,
+}
+
+impl ResourceLimiter for MemoryLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        match self.max_memory {
+            Some(max_memory) if desired > max_memory => {
+                Err(ScanError::OutOfMemory.into())
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
 /// Structure that holds information about the current scan.
 pub(crate) struct ScanContext<'r> {
-    /// Pointer to the WASM store.
-    pub wasm_store: NonNull<Store<ScanContext<'r>>>,
     /// Map where keys are object handles and keys are objects used during the
     /// evaluation of rule conditions. Handles are opaque integer values that
     /// can be passed to and received from WASM code. Each handle identify an
     /// object (string, struct, array or map).
     pub runtime_objects: IndexMap<RuntimeObjectHandle, RuntimeObject>,
-    /// Pointer to the data being scanned.
-    pub scanned_data: *const u8,
-    /// Length of data being scanned.
-    pub scanned_data_len: usize,
+    /// Pointer to the data being scanned, it is valid only while the scan
+    /// is in progress, the rest of the time it is null.
+    pub scanned_data: ScanPtr<[u8]>,
     /// Vector containing the IDs of the non-private rules that matched,
     /// including both global and non-global ones. Global rules are initially
     /// added to `global_matching_rules`, and once all the rules in the
@@ -59,6 +157,21 @@ pub(crate) struct ScanContext<'r> {
     pub private_matching_rules: Vec<RuleId>,
     /// Map containing the IDs of the global rules that matched.
     pub global_matching_rules: FxHashMap<NamespaceId, Vec<RuleId>>,
+    /// Statistics about the current scan.
+    pub stats: ScanStats,
+    /// Function that translates the ranges reported by matches, as set
+    /// with [`crate::ScanOptions::offset_translator`] for the current scan.
+    pub offset_translator: Option<Arc<OffsetTranslator>>,
+    /// Flag set by [`crate::CancelHandle::cancel`] for cancelling the scan
+    /// in progress.
+    pub cancelled: Arc<AtomicBool>,
+    /// Tracks the rules known to match during the search for patterns when
+    /// early termination is enabled.
+    pub early_termination: Option<EarlyTermination>,
+    /// Vector containing the IDs of the global rules that didn't match.
+    /// There's at most one of them per namespace, as the evaluation of a
+    /// namespace stops as soon as one of its global rules doesn't match.
+    pub failed_global_rules: Vec<RuleId>,
     /// Compiled rules for this scan.
     pub compiled_rules: &'r Rules,
     /// Structure that contains top-level symbols, like module names
@@ -68,7 +181,7 @@ pub(crate) struct ScanContext<'r> {
     pub root_struct: Struct,
     /// Currently active structure that overrides the `root_struct` if
     /// set.
-    pub current_struct: Option<Rc<Struct>>,
+    pub current_struct: Option<Arc<Struct>>,
     /// Module's main memory.
     pub main_memory: Option<wasmtime::Memory>,
     /// Hash map that contains the protobuf messages returned by YARA modules.
@@ -98,6 +211,13 @@ pub(crate) struct ScanContext<'r> {
     /// When [`HEARTBEAT_COUNTER`] is larger than this value, the scan is
     /// aborted due to a timeout.
     pub deadline: u64,
+    /// Offset of the data being scanned relative to the start of the whole
+    /// scanned data. This is zero except when the data is split into blocks,
+    /// like with [`crate::Scanner::scan_blocks`] or
+    /// [`crate::Scanner::scan_process`], in which case it is the base offset
+    /// of the current block. This value is added to the ranges of matches
+    /// found while searching for patterns.
+    pub scanned_data_base: usize,
     /// Hash map that serves as a cache for regexps used in expressions like
     /// `some_var matches /foobar/`. Compiling a regexp is a expensive
     /// operation. Instead of compiling the regexp each time the expression
@@ -105,14 +225,79 @@ pub(crate) struct ScanContext<'r> {
     /// map.
     pub regexp_cache: RefCell<FxHashMap<RegexpId, Regex>>,
     /// Callback invoked every time a YARA rule calls `console.log`.
-    pub console_log: Option<Box<dyn FnMut(String) + 'r>>,
+    pub console_log: Option<Box<dyn FnMut(String) + Send + 'r>>,
+    /// Callback invoked every time a non-private rule matches.
+    pub rule_match_callback: Option<RuleMatchCallback<'r>>,
+    /// Pointer to the data being scanned. This is required for passing a
+    /// [`Rule`] to `rule_match_callback`, and it's valid only while the
+    /// scan is in progress, the rest of the time it is null.
+    pub scanned_data_ref: ScanPtr<ScannedData<'static>>,
+    /// Limits the memory that can be used by the WASM store.
+    pub memory_limiter: MemoryLimiter,
     /// Hash map that tracks the time spend on each pattern. Keys are pattern
     /// PatternIds and values are the cumulative time spent on verifying each
     /// pattern.
     #[cfg(feature = "rules-profiling")]
     pub time_spent_in_pattern: FxHashMap<PatternId, Duration>,
+    /// Hash map that tracks the time spent in the main function of each
+    /// module. Keys are module names and values are the cumulative time
+    /// spent in the module's main function.
+    #[cfg(feature = "rules-profiling")]
+    pub time_spent_in_module: FxHashMap<&'r str, Duration>,
+    /// Hash map that tracks the time spent evaluating the condition of each
+    /// rule. Keys are rule IDs and values are the cumulative time spent in
+    /// the rule's condition, excluding the time spent searching for
+    /// patterns.
+    #[cfg(feature = "rules-profiling")]
+    pub time_spent_in_rule: FxHashMap<RuleId, Duration>,
+    /// Instant in which the evaluation of the current rule started, and the
+    /// time spent searching for patterns up to that moment.
+    #[cfg(feature = "rules-profiling")]
+    pub rule_eval_start: Option<(Instant, Duration)>,
+}
+
+/// Pointer to some data that is accessed by [`ScanContext`] only while a
+/// scan is in progress.
+///
+/// The data being scanned doesn't live as long as the [`ScanContext`], so
+/// the latter can't hold a reference to it. Instead, the scanner sets the
+/// pointer when the scan starts, and sets it back to null once the scan
+/// finishes.
+pub(crate) struct ScanPtr<T: ?Sized>(Option<NonNull<T>>);
+
+impl<T: ?Sized> ScanPtr<T> {
+    /// Creates a null pointer.
+    pub const fn null() -> Self {
+        Self(None)
+    }
+
+    /// Creates a pointer from a [`NonNull`].
+    pub fn new(ptr: NonNull<T>) -> Self {
+        Self(Some(ptr))
+    }
+
+    /// Returns a reference to the pointed data, or `None` if the pointer
+    /// is null.
+    ///
+    /// # Safety
+    ///
+    /// The data must be still alive, which is guaranteed only while the
+    /// scan that set the pointer is in progress.
+    pub unsafe fn as_ref<'a>(&self) -> Option<&'a T> {
+        self.0.map(|ptr| ptr.as_ref())
+    }
 }
 
+impl<T: ?Sized> From<&T> for ScanPtr<T> {
+    fn from(value: &T) -> Self {
+        Self::new(NonNull::from(value))
+    }
+}
+
+// SAFETY: `ScanPtr<T>` is used exactly as a `&T`, which is `Send` when `T` is
+// `Sync`.
+unsafe impl<T: ?Sized + Sync> Send for ScanPtr<T> {}
+
 #[cfg(feature = "rules-profiling")]
 impl<'r> ScanContext<'r> {
     pub fn most_expensive_rules(&self) -> Vec<(&'r str, &'r str, Duration)> {
@@ -147,12 +332,9 @@ impl<'r> ScanContext<'r> {
 impl ScanContext<'_> {
     /// Returns a slice with the data being scanned.
     pub(crate) fn scanned_data<'a>(&self) -> &'a [u8] {
-        unsafe {
-            std::slice::from_raw_parts::<u8>(
-                self.scanned_data,
-                self.scanned_data_len,
-            )
-        }
+        // SAFETY: the pointer is not null only while the scan is in
+        // progress, and the data outlives the scan.
+        unsafe { self.scanned_data.as_ref() }.unwrap_or_default()
     }
 
     /// Returns true of the regexp identified by the given [`RegexpId`]
@@ -208,6 +390,31 @@ impl ScanContext<'_> {
         info!("Started rule evaluation: {}:{}", rule_namespace, rule_name);
     }
 
+    /// Called before starting evaluating the condition for the rule
+    /// identified by `rule_id`.
+    #[cfg(feature = "rules-profiling")]
+    pub(crate) fn rule_eval_start(&mut self, _rule_id: RuleId) {
+        self.rule_eval_start =
+            Some((Instant::now(), self.stats.pattern_search_time));
+    }
+
+    /// Called after evaluating the condition for the rule identified by
+    /// `rule_id`, for updating the time spent in the rule.
+    ///
+    /// The first condition that depends on patterns triggers the search
+    /// for patterns, the time spent in that search is not accounted to the
+    /// rule.
+    #[cfg(feature = "rules-profiling")]
+    pub(crate) fn rule_eval_end(&mut self, rule_id: RuleId) {
+        if let Some((start, pattern_search_time)) = self.rule_eval_start.take()
+        {
+            let time_spent = Instant::elapsed(&start).saturating_sub(
+                self.stats.pattern_search_time - pattern_search_time,
+            );
+            *self.time_spent_in_rule.entry(rule_id).or_default() += time_spent;
+        }
+    }
+
     pub(crate) fn console_log(&mut self, message: String) {
         if let Some(console_log) = &mut self.console_log {
             console_log(message)
@@ -216,30 +423,33 @@ impl ScanContext<'_> {
 
     pub(crate) fn store_struct(
         &mut self,
-        s: Rc<Struct>,
+        s: Arc<Struct>,
     ) -> RuntimeObjectHandle {
-        let obj_ref = RuntimeObjectHandle(Rc::<Struct>::as_ptr(&s) as i64);
+        let obj_ref = RuntimeObjectHandle(Arc::<Struct>::as_ptr(&s) as i64);
         self.runtime_objects.insert_full(obj_ref, RuntimeObject::Struct(s));
         obj_ref
     }
 
-    pub(crate) fn store_array(&mut self, a: Rc<Array>) -> RuntimeObjectHandle {
-        let obj_ref = RuntimeObjectHandle(Rc::<Array>::as_ptr(&a) as i64);
+    pub(crate) fn store_array(
+        &mut self,
+        a: Arc<Array>,
+    ) -> RuntimeObjectHandle {
+        let obj_ref = RuntimeObjectHandle(Arc::<Array>::as_ptr(&a) as i64);
         self.runtime_objects.insert_full(obj_ref, RuntimeObject::Array(a));
         obj_ref
     }
 
-    pub(crate) fn store_map(&mut self, m: Rc<Map>) -> RuntimeObjectHandle {
-        let obj_ref = RuntimeObjectHandle(Rc::<Map>::as_ptr(&m) as i64);
+    pub(crate) fn store_map(&mut self, m: Arc<Map>) -> RuntimeObjectHandle {
+        let obj_ref = RuntimeObjectHandle(Arc::<Map>::as_ptr(&m) as i64);
         self.runtime_objects.insert_full(obj_ref, RuntimeObject::Map(m));
         obj_ref
     }
 
     pub(crate) fn store_string(
         &mut self,
-        s: Rc<BString>,
+        s: Arc<BString>,
     ) -> RuntimeObjectHandle {
-        let obj_ref = RuntimeObjectHandle(Rc::<BString>::as_ptr(&s) as i64);
+        let obj_ref = RuntimeObjectHandle(Arc::<BString>::as_ptr(&s) as i64);
         self.runtime_objects.insert_full(obj_ref, RuntimeObject::String(s));
         obj_ref
     }
@@ -247,13 +457,21 @@ impl ScanContext<'_> {
     /// Called during the scan process when a global rule didn't match.
     ///
     /// When this happens any other global rule in the same namespace that
-    /// matched previously is reset to a non-matching state.
-    pub(crate) fn track_global_rule_no_match(&mut self, rule_id: RuleId) {
+    /// matched previously is reset to a non-matching state. `main_memory`
+    /// is the WASM module's main memory, which contains the bitmap of
+    /// matching rules.
+    pub(crate) fn track_global_rule_no_match(
+        &mut self,
+        main_memory: &mut [u8],
+        rule_id: RuleId,
+    ) {
         let rule = self.compiled_rules.get(rule_id);
 
         // This function must be called only for global rules.
         debug_assert!(rule.is_global);
 
+        self.failed_global_rules.push(rule_id);
+
         // All the global rules that matched previously, and are in the same
         // namespace as the non-matching rule, must be removed from the
         // `global_matching_rules` map. Also, their corresponding bits in
@@ -261,14 +479,11 @@ impl ScanContext<'_> {
         if let Some(rules) =
             self.global_matching_rules.get_mut(&rule.namespace_id)
         {
-            let wasm_store = unsafe { self.wasm_store.as_mut() };
-            let main_mem = self.main_memory.unwrap().data_mut(wasm_store);
-
             let base = MATCHING_RULES_BITMAP_BASE as usize;
             let num_rules = self.compiled_rules.num_rules();
 
             let bits = BitSlice::<u8, Lsb0>::from_slice_mut(
-                &mut main_mem[base..base + num_rules.div_ceil(8)],
+                &mut main_memory[base..base + num_rules.div_ceil(8)],
             );
 
             for rule_id in rules.drain(0..) {
@@ -278,8 +493,13 @@ impl ScanContext<'_> {
     }
 
     /// Called during the scan process when a rule has matched for tracking
-    /// the matching rules.
-    pub(crate) fn track_rule_match(&mut self, rule_id: RuleId) {
+    /// the matching rules. `main_memory` is the WASM module's main memory,
+    /// which contains the bitmap of matching rules.
+    pub(crate) fn track_rule_match(
+        &mut self,
+        main_memory: &mut [u8],
+        rule_id: RuleId,
+    ) {
         let rule = self.compiled_rules.get(rule_id);
 
         #[cfg(feature = "logging")]
@@ -304,41 +524,85 @@ impl ScanContext<'_> {
             self.non_private_matching_rules.push(rule_id);
         }
 
-        let wasm_store = unsafe { self.wasm_store.as_mut() };
-        let mem = self.main_memory.unwrap().data_mut(wasm_store);
         let num_rules = self.compiled_rules.num_rules();
 
         let base = MATCHING_RULES_BITMAP_BASE as usize;
         let bits = BitSlice::<u8, Lsb0>::from_slice_mut(
-            &mut mem[base..base + num_rules.div_ceil(8)],
+            &mut main_memory[base..base + num_rules.div_ceil(8)],
         );
 
         // The RuleId-th bit in the `rule_matches` bit vector is set to 1.
         bits.set(rule_id.into(), true);
+
+        // Global rules are not notified until the scan finishes, as they can
+        // be discarded if some other global rule in the same namespace
+        // doesn't match.
+        if !rule.is_global && !rule.is_private {
+            self.notify_rule_match(rule_id);
+        }
+    }
+
+    /// Invokes the callback set with [`crate::Scanner::on_rule_match`], if
+    /// any, passing it the rule identified by `rule_id`.
+    pub(crate) fn notify_rule_match(&mut self, rule_id: RuleId) {
+        // SAFETY: `scanned_data_ref` is not null only while the scan is in
+        // progress, and it points to the data owned by `Scanner::scan_impl`.
+        let data = match unsafe { self.scanned_data_ref.as_ref() } {
+            Some(data) => data,
+            None => return,
+        };
+
+        if let Some(mut callback) = self.rule_match_callback.take() {
+            callback(&Rule {
+                ctx: self,
+                data,
+                rules: self.compiled_rules,
+                rule_id,
+                rule_info: self.compiled_rules.get(rule_id),
+            });
+
+            self.rule_match_callback = Some(callback);
+        }
     }
 
     /// Called during the scan process when a pattern has matched for tracking
     /// the matching patterns.
+    ///
+    /// The bitmap of matching patterns in the WASM main memory is not
+    /// updated here, see [`ScanContext::update_matching_patterns_bitmap`].
     pub(crate) fn track_pattern_match(
         &mut self,
         pattern_id: PatternId,
         match_: Match,
         replace: bool,
     ) {
-        let wasm_store = unsafe { self.wasm_store.as_mut() };
-        let mem = self.main_memory.unwrap().data_mut(wasm_store);
+        if let Some(early_termination) = &mut self.early_termination {
+            early_termination.pattern_matched(pattern_id);
+        }
+
+        if !self.pattern_matches.add(pattern_id, match_, replace) {
+            self.limit_reached.insert(pattern_id);
+        }
+    }
+
+    /// Sets the bits corresponding to the patterns that have matched in the
+    /// bitmap of matching patterns, which lives in the WASM module's main
+    /// memory. Must be called after searching for patterns and before the
+    /// bitmap is used by WASM code.
+    pub(crate) fn update_matching_patterns_bitmap(
+        &self,
+        main_memory: &mut [u8],
+    ) {
         let num_rules = self.compiled_rules.num_rules();
         let num_patterns = self.compiled_rules.num_patterns();
 
         let base = MATCHING_RULES_BITMAP_BASE as usize + num_rules.div_ceil(8);
         let bits = BitSlice::<u8, Lsb0>::from_slice_mut(
-            &mut mem[base..base + num_patterns.div_ceil(8)],
+            &mut main_memory[base..base + num_patterns.div_ceil(8)],
         );
 
-        bits.set(pattern_id.into(), true);
-
-        if !self.pattern_matches.add(pattern_id, match_, replace) {
-            self.limit_reached.insert(pattern_id);
+        for pattern_id in self.pattern_matches.matching_patterns() {
+            bits.set(pattern_id.into(), true);
         }
     }
 
@@ -354,6 +618,7 @@ impl ScanContext<'_> {
     /// without looking for any of the patterns. If it must be called, it will be
     /// called only once.
     pub(crate) fn search_for_patterns(&mut self) -> Result<(), ScanError> {
+        let scan_start = Instant::now();
         let scanned_data = self.scanned_data();
 
         // Verify the anchored pattern first. These are patterns that can match
@@ -368,228 +633,290 @@ impl ScanContext<'_> {
         };
 
         let atoms = self.compiled_rules.atoms();
-
-        #[cfg(feature = "logging")]
-        let scan_start = Instant::now();
-
-        #[cfg(feature = "logging")]
         let mut atom_matches = 0_usize;
 
-        for ac_match in ac.find_overlapping_iter(scanned_data) {
-            #[cfg(feature = "logging")]
-            {
-                atom_matches += 1;
-            }
+        // The data is searched in chunks, so that the timeout and the
+        // cancellation are checked periodically even if no atom is found.
+        // Each chunk is extended with enough bytes for finding the atoms
+        // that start in the chunk but end in the next one.
+        let overlap = ac.max_pattern_len().saturating_sub(1);
+        let mut chunk_start = 0;
 
-            if HEARTBEAT_COUNTER.load(Ordering::Relaxed) >= self.deadline {
+        'search: loop {
+            if let Err(err) = self.check_for_abort() {
                 #[cfg(feature = "logging")]
                 info!(
-                    "Scan timeout after: {:?}",
+                    "Scan aborted after: {:?}",
                     Instant::elapsed(&scan_start)
                 );
-                return Err(ScanError::Timeout);
+                self.stats.atom_matches += atom_matches;
+                self.stats.pattern_search_time +=
+                    Instant::elapsed(&scan_start);
+                return Err(err);
             }
 
-            let atom =
-                unsafe { atoms.get_unchecked(ac_match.pattern().as_usize()) };
+            let chunk_end =
+                cmp::min(chunk_start + SEARCH_CHUNK_SIZE, scanned_data.len());
 
-            // Subtract the backtrack value from the offset where the atom
-            // matched. If the result is negative the atom can't be inside
-            // the scanned data and therefore is not a possible match.
-            let atom_pos = if let Some(atom_pos) =
-                ac_match.start().checked_sub(atom.backtrack())
-            {
-                atom_pos
-            } else {
-                continue;
-            };
+            let input = aho_corasick::Input::new(scanned_data).span(
+                chunk_start..cmp::min(chunk_end + overlap, scanned_data.len()),
+            );
 
-            // Each atom belongs to a sub-pattern.
-            let sub_pattern_id = atom.sub_pattern_id();
+            for ac_match in ac.find_overlapping_iter(input) {
+                // If all rules are known to match, the search can stop here.
+                if self
+                    .early_termination
+                    .as_ref()
+                    .is_some_and(|e| e.all_rules_match())
+                {
+                    break 'search;
+                }
 
-            // Each sub-pattern belongs to a pattern.
-            let (pattern_id, sub_pattern) =
-                &self.compiled_rules.get_sub_pattern(sub_pattern_id);
+                // Atoms that start beyond the end of the current chunk are
+                // found while searching in the next one.
+                if ac_match.start() >= chunk_end {
+                    continue;
+                }
 
-            // Check if the potentially matching pattern has reached the
-            // maximum number of allowed matches. In that case continue without
-            // verifying the match. `get_unchecked` is used for performance
-            // reasons, the number of bits in the bit vector is guaranteed to
-            // be the number of patterns.
-            if self.limit_reached.contains(pattern_id) {
-                continue;
-            }
+                atom_matches += 1;
 
-            #[cfg(feature = "rules-profiling")]
-            let verification_start = Instant::now();
-
-            // If the atom is exact no further verification is needed, except
-            // for making sure that the fullword requirements are met. An exact
-            // atom is enough to guarantee that the whole sub-pattern matched.
-            #[cfg(feature = "exact-atoms")]
-            if atom.is_exact() {
-                let flags = match sub_pattern {
-                    SubPattern::Literal { flags, .. }
-                    | SubPattern::LiteralChainHead { flags, .. }
-                    | SubPattern::LiteralChainTail { flags, .. }
-                    | SubPattern::Regexp { flags, .. }
-                    | SubPattern::RegexpChainHead { flags, .. }
-                    | SubPattern::RegexpChainTail { flags, .. } => flags,
-                    _ => unreachable!(),
+                if let Err(err) = self.check_for_abort() {
+                    #[cfg(feature = "logging")]
+                    info!(
+                        "Scan aborted after: {:?}",
+                        Instant::elapsed(&scan_start)
+                    );
+                    self.stats.atom_matches += atom_matches;
+                    self.stats.pattern_search_time +=
+                        Instant::elapsed(&scan_start);
+                    return Err(err);
+                }
+
+                let atom = unsafe {
+                    atoms.get_unchecked(ac_match.pattern().as_usize())
                 };
 
-                let match_range = atom_pos..atom_pos + atom.len();
+                // Subtract the backtrack value from the offset where the atom
+                // matched. If the result is negative the atom can't be inside
+                // the scanned data and therefore is not a possible match.
+                let atom_pos = if let Some(atom_pos) =
+                    ac_match.start().checked_sub(atom.backtrack())
+                {
+                    atom_pos
+                } else {
+                    continue;
+                };
 
-                if verify_full_word(scanned_data, &match_range, *flags, None) {
-                    self.handle_sub_pattern_match(
-                        sub_pattern_id,
-                        sub_pattern,
-                        *pattern_id,
-                        Match { range: match_range, xor_key: None },
-                    );
-                }
+                // Each atom belongs to a sub-pattern.
+                let sub_pattern_id = atom.sub_pattern_id();
 
-                continue;
-            }
+                // Each sub-pattern belongs to a pattern.
+                let (pattern_id, sub_pattern) =
+                    &self.compiled_rules.get_sub_pattern(sub_pattern_id);
 
-            match sub_pattern {
-                SubPattern::Literal { pattern, flags, .. }
-                | SubPattern::LiteralChainHead { pattern, flags, .. }
-                | SubPattern::LiteralChainTail { pattern, flags, .. } => {
-                    if let Some(match_) = verify_literal_match(
-                        self.compiled_rules
-                            .lit_pool()
-                            .get_bytes(*pattern)
-                            .unwrap(),
+                // Check if the potentially matching pattern has reached the
+                // maximum number of allowed matches. In that case continue without
+                // verifying the match. `get_unchecked` is used for performance
+                // reasons, the number of bits in the bit vector is guaranteed to
+                // be the number of patterns.
+                if self.limit_reached.contains(pattern_id) {
+                    continue;
+                }
+
+                #[cfg(feature = "rules-profiling")]
+                let verification_start = Instant::now();
+
+                // If the atom is exact no further verification is needed, except
+                // for making sure that the fullword requirements are met. An exact
+                // atom is enough to guarantee that the whole sub-pattern matched.
+                #[cfg(feature = "exact-atoms")]
+                if atom.is_exact() {
+                    let flags = match sub_pattern {
+                        SubPattern::Literal { flags, .. }
+                        | SubPattern::LiteralChainHead { flags, .. }
+                        | SubPattern::LiteralChainTail { flags, .. }
+                        | SubPattern::Regexp { flags, .. }
+                        | SubPattern::RegexpChainHead { flags, .. }
+                        | SubPattern::RegexpChainTail { flags, .. } => flags,
+                        _ => unreachable!(),
+                    };
+
+                    let match_range = atom_pos..atom_pos + atom.len();
+
+                    if verify_full_word(
                         scanned_data,
-                        atom_pos,
+                        &match_range,
                         *flags,
+                        None,
                     ) {
                         self.handle_sub_pattern_match(
                             sub_pattern_id,
                             sub_pattern,
                             *pattern_id,
-                            match_,
+                            Match { range: match_range, xor_key: None },
                         );
                     }
+
+                    continue;
                 }
-                SubPattern::Regexp { flags, .. }
-                | SubPattern::RegexpChainHead { flags, .. }
-                | SubPattern::RegexpChainTail { flags, .. } => {
-                    verify_regexp_match(
-                        &mut vm,
-                        scanned_data,
-                        atom_pos,
-                        atom,
-                        *flags,
-                        |match_| {
+
+                match sub_pattern {
+                    SubPattern::Literal { pattern, flags, .. }
+                    | SubPattern::LiteralChainHead {
+                        pattern, flags, ..
+                    }
+                    | SubPattern::LiteralChainTail {
+                        pattern, flags, ..
+                    } => {
+                        if let Some(match_) = verify_literal_match(
+                            self.compiled_rules
+                                .lit_pool()
+                                .get_bytes(*pattern)
+                                .unwrap(),
+                            scanned_data,
+                            atom_pos,
+                            *flags,
+                        ) {
                             self.handle_sub_pattern_match(
                                 sub_pattern_id,
                                 sub_pattern,
                                 *pattern_id,
                                 match_,
                             );
-                        },
-                    )
-                }
+                        }
+                    }
+                    SubPattern::Regexp { flags, .. }
+                    | SubPattern::RegexpChainHead { flags, .. }
+                    | SubPattern::RegexpChainTail { flags, .. } => {
+                        verify_regexp_match(
+                            &mut vm,
+                            scanned_data,
+                            atom_pos,
+                            atom,
+                            *flags,
+                            |match_| {
+                                self.handle_sub_pattern_match(
+                                    sub_pattern_id,
+                                    sub_pattern,
+                                    *pattern_id,
+                                    match_,
+                                );
+                            },
+                        )
+                    }
 
-                SubPattern::Xor { pattern, flags } => {
-                    if let Some(match_) = verify_xor_match(
-                        self.compiled_rules
-                            .lit_pool()
-                            .get_bytes(*pattern)
-                            .unwrap(),
-                        scanned_data,
-                        atom_pos,
-                        atom,
-                        *flags,
-                    ) {
-                        self.handle_sub_pattern_match(
-                            sub_pattern_id,
-                            sub_pattern,
-                            *pattern_id,
-                            match_,
-                        );
+                    SubPattern::Xor { pattern, flags } => {
+                        if let Some(match_) = verify_xor_match(
+                            self.compiled_rules
+                                .lit_pool()
+                                .get_bytes(*pattern)
+                                .unwrap(),
+                            scanned_data,
+                            atom_pos,
+                            atom,
+                            *flags,
+                        ) {
+                            self.handle_sub_pattern_match(
+                                sub_pattern_id,
+                                sub_pattern,
+                                *pattern_id,
+                                match_,
+                            );
+                        }
                     }
-                }
 
-                SubPattern::Base64 { pattern, padding }
-                | SubPattern::Base64Wide { pattern, padding } => {
-                    if let Some(match_) = verify_base64_match(
-                        self.compiled_rules
-                            .lit_pool()
-                            .get_bytes(*pattern)
-                            .unwrap(),
-                        scanned_data,
-                        (*padding).into(),
-                        atom_pos,
-                        None,
-                        matches!(sub_pattern, SubPattern::Base64Wide { .. }),
-                    ) {
-                        self.handle_sub_pattern_match(
-                            sub_pattern_id,
-                            sub_pattern,
-                            *pattern_id,
-                            match_,
-                        );
+                    SubPattern::Base64 { pattern, padding }
+                    | SubPattern::Base64Wide { pattern, padding } => {
+                        if let Some(match_) = verify_base64_match(
+                            self.compiled_rules
+                                .lit_pool()
+                                .get_bytes(*pattern)
+                                .unwrap(),
+                            scanned_data,
+                            (*padding).into(),
+                            atom_pos,
+                            None,
+                            matches!(
+                                sub_pattern,
+                                SubPattern::Base64Wide { .. }
+                            ),
+                        ) {
+                            self.handle_sub_pattern_match(
+                                sub_pattern_id,
+                                sub_pattern,
+                                *pattern_id,
+                                match_,
+                            );
+                        }
                     }
-                }
 
-                SubPattern::CustomBase64 { pattern, alphabet, padding }
-                | SubPattern::CustomBase64Wide {
-                    pattern,
-                    alphabet,
-                    padding,
-                } => {
-                    let alphabet = self
-                        .compiled_rules
-                        .lit_pool()
-                        .get_str(*alphabet)
-                        .map(|alphabet| {
-                            // `Alphabet::new` validates the string again. This
-                            // is not really necessary as we already know that
-                            // the string represents a valid alphabet, it would
-                            // be better if could use the private function
-                            // `Alphabet::from_str_unchecked`
-                            base64::alphabet::Alphabet::new(alphabet).unwrap()
-                        });
-
-                    assert!(alphabet.is_some());
-
-                    if let Some(match_) = verify_base64_match(
-                        self.compiled_rules
-                            .lit_pool()
-                            .get_bytes(*pattern)
-                            .unwrap(),
-                        scanned_data,
-                        (*padding).into(),
-                        atom_pos,
+                    SubPattern::CustomBase64 {
+                        pattern,
                         alphabet,
-                        matches!(
-                            sub_pattern,
-                            SubPattern::CustomBase64Wide { .. }
-                        ),
-                    ) {
-                        self.handle_sub_pattern_match(
-                            sub_pattern_id,
-                            sub_pattern,
-                            *pattern_id,
-                            match_,
-                        );
+                        padding,
+                    }
+                    | SubPattern::CustomBase64Wide {
+                        pattern,
+                        alphabet,
+                        padding,
+                    } => {
+                        let alphabet = self
+                            .compiled_rules
+                            .lit_pool()
+                            .get_str(*alphabet)
+                            .map(|alphabet| {
+                                // `Alphabet::new` validates the string again. This
+                                // is not really necessary as we already know that
+                                // the string represents a valid alphabet, it would
+                                // be better if could use the private function
+                                // `Alphabet::from_str_unchecked`
+                                base64::alphabet::Alphabet::new(alphabet)
+                                    .unwrap()
+                            });
+
+                        assert!(alphabet.is_some());
+
+                        if let Some(match_) = verify_base64_match(
+                            self.compiled_rules
+                                .lit_pool()
+                                .get_bytes(*pattern)
+                                .unwrap(),
+                            scanned_data,
+                            (*padding).into(),
+                            atom_pos,
+                            alphabet,
+                            matches!(
+                                sub_pattern,
+                                SubPattern::CustomBase64Wide { .. }
+                            ),
+                        ) {
+                            self.handle_sub_pattern_match(
+                                sub_pattern_id,
+                                sub_pattern,
+                                *pattern_id,
+                                match_,
+                            );
+                        }
                     }
+                };
+
+                #[cfg(feature = "rules-profiling")]
+                {
+                    let time_spent = Instant::elapsed(&verification_start);
+                    self.time_spent_in_pattern
+                        .entry(*pattern_id)
+                        .and_modify(|t| {
+                            *t += time_spent;
+                        })
+                        .or_insert(time_spent);
                 }
-            };
+            }
 
-            #[cfg(feature = "rules-profiling")]
-            {
-                let time_spent = Instant::elapsed(&verification_start);
-                self.time_spent_in_pattern
-                    .entry(*pattern_id)
-                    .and_modify(|t| {
-                        *t += time_spent;
-                    })
-                    .or_insert(time_spent);
+            if chunk_end >= scanned_data.len() {
+                break;
             }
+
+            chunk_start = chunk_end;
         }
 
         #[cfg(feature = "logging")]
@@ -606,9 +933,26 @@ impl ScanContext<'_> {
                 }
             }
         }
+
+        self.stats.atom_matches += atom_matches;
+        self.stats.pattern_search_time += Instant::elapsed(&scan_start);
+
         Ok(())
     }
 
+    /// Returns [`ScanError::Cancelled`] if the scan was cancelled with
+    /// [`crate::CancelHandle::cancel`], or [`ScanError::Timeout`] if the
+    /// scan timed out.
+    fn check_for_abort(&self) -> Result<(), ScanError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(ScanError::Cancelled)
+        } else if HEARTBEAT_COUNTER.load(Ordering::Relaxed) >= self.deadline {
+            Err(ScanError::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+
     fn verify_anchored_patterns(&mut self) {
         for (sub_pattern_id, (pattern_id, sub_pattern)) in self
             .compiled_rules
@@ -623,13 +967,22 @@ impl ScanContext<'_> {
                     anchored_at: Some(offset),
                     ..
                 } => {
+                    // The anchor is relative to the start of the whole
+                    // scanned data, if the current block starts after the
+                    // anchor the pattern can't match in it.
+                    let offset =
+                        match offset.checked_sub(self.scanned_data_base) {
+                            Some(offset) => offset,
+                            None => continue,
+                        };
+
                     if let Some(match_) = verify_literal_match(
                         self.compiled_rules
                             .lit_pool()
                             .get_bytes(*pattern)
                             .unwrap(),
                         self.scanned_data(),
-                        *offset,
+                        offset,
                         *flags,
                     ) {
                         self.handle_sub_pattern_match(
@@ -650,8 +1003,15 @@ impl ScanContext<'_> {
         sub_pattern_id: SubPatternId,
         sub_pattern: &SubPattern,
         pattern_id: PatternId,
-        match_: Match,
+        mut match_: Match,
     ) {
+        // Make the match range relative to the start of the whole scanned
+        // data.
+        if self.scanned_data_base > 0 {
+            match_.range.start += self.scanned_data_base;
+            match_.range.end += self.scanned_data_base;
+        }
+
         match sub_pattern {
             SubPattern::Literal { .. }
             | SubPattern::Xor { .. }
@@ -1260,14 +1620,14 @@ struct VM<'r> {
 /// integers that can cross the WASM-Rust boundary, and used to retrieve the
 /// original object from [`ScanContext`].
 pub(crate) enum RuntimeObject {
-    Struct(Rc<Struct>),
-    Array(Rc<Array>),
-    Map(Rc<Map>),
-    String(Rc<BString>),
+    Struct(Arc<Struct>),
+    Array(Arc<Array>),
+    Map(Arc<Map>),
+    String(Arc<BString>),
 }
 
 impl RuntimeObject {
-    pub fn as_struct(&self) -> Rc<Struct> {
+    pub fn as_struct(&self) -> Arc<Struct> {
         if let Self::Struct(s) = self {
             s.clone()
         } else {
@@ -1277,7 +1637,7 @@ impl RuntimeObject {
         }
     }
 
-    pub fn as_array(&self) -> Rc<Array> {
+    pub fn as_array(&self) -> Arc<Array> {
         if let Self::Array(a) = self {
             a.clone()
         } else {
@@ -1286,7 +1646,7 @@ impl RuntimeObject {
             )
         }
     }
-    pub fn as_map(&self) -> Rc<Map> {
+    pub fn as_map(&self) -> Arc<Map> {
         if let Self::Map(m) = self {
             m.clone()
         } else {
@@ -0,0 +1,201 @@
+/*! Recursive scanning of directories.
+
+This module implements the logic used by [`crate::Scanner::scan_dir`] for
+walking a directory and deciding which files must be scanned.
+*/
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashSet;
+
+/// Options that control which files are scanned by
+/// [`crate::Scanner::scan_dir`].
+///
+/// # Example
+///
+/// ```rust
+/// # use yara_x::DirScanOptions;
+/// let options = DirScanOptions::new()
+///     .extension("exe")
+///     .extension("dll")
+///     .max_file_size(10 * 1024 * 1024)
+///     .max_depth(3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DirScanOptions {
+    extensions: Vec<String>,
+    max_file_size: Option<u64>,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+}
+
+impl DirScanOptions {
+    /// Creates a new set of options where all files are scanned, no matter
+    /// their extension or size, and symbolic links are not followed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an extension to the list of extensions of the files that will
+    /// be scanned.
+    ///
+    /// By default files are scanned regardless of their extension, but once
+    /// some extension is added, only files that have one of the added
+    /// extensions are scanned. The extension must not include the leading
+    /// dot, and the comparison is case-insensitive.
+    pub fn extension<E: Into<String>>(mut self, extension: E) -> Self {
+        self.extensions.push(extension.into());
+        self
+    }
+
+    /// Sets the maximum size of the scanned files, in bytes. Larger files
+    /// are ignored.
+    pub fn max_file_size(mut self, size: u64) -> Self {
+        self.max_file_size = Some(size);
+        self
+    }
+
+    /// Sets the maximum number of subdirectory levels that will be walked.
+    ///
+    /// With a maximum depth of 0 only the files directly contained in the
+    /// scanned directory are scanned. By default there's no limit.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Indicates whether symbolic links must be followed.
+    ///
+    /// By default symbolic links are ignored. When they are followed, each
+    /// directory is walked only once, even if it can be reached through
+    /// multiple links, which prevents infinite loops.
+    pub fn follow_symlinks(mut self, yes: bool) -> Self {
+        self.follow_symlinks = yes;
+        self
+    }
+
+    /// Returns true if the file with the given path and size must be
+    /// scanned according to these options.
+    fn accepts(&self, path: &Path, size: u64) -> bool {
+        if self.max_file_size.is_some_and(|max| size > max) {
+            return false;
+        }
+
+        if self.extensions.is_empty() {
+            return true;
+        }
+
+        path.extension().and_then(OsStr::to_str).is_some_and(|ext| {
+            self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext))
+        })
+    }
+}
+
+/// Walks the directory `root`, calling `f` for each file that must be
+/// scanned according to `options`.
+///
+/// Errors found while reading the content of subdirectories, or the
+/// metadata of some entry, are also passed to `f` together with the path
+/// that caused them. Only errors related to `root` itself are returned by
+/// this function. The files in each directory are visited in alphabetical
+/// order, before walking its subdirectories.
+pub(super) fn walk<F>(
+    root: &Path,
+    options: &DirScanOptions,
+    mut f: F,
+) -> io::Result<()>
+where
+    F: FnMut(&Path, io::Result<()>),
+{
+    let metadata = fs::metadata(root)?;
+
+    if !metadata.is_dir() {
+        if metadata.is_file() && options.accepts(root, metadata.len()) {
+            f(root, Ok(()));
+        }
+        return Ok(());
+    }
+
+    let mut visited_dirs = FxHashSet::default();
+
+    if options.follow_symlinks {
+        visited_dirs.insert(fs::canonicalize(root)?);
+    }
+
+    // Stack of directories pending to be walked, together with their
+    // depth. Entries are pushed in reverse order, so that they are popped
+    // in alphabetical order.
+    let mut pending: Vec<(PathBuf, usize)> = Vec::new();
+    let mut is_root = true;
+
+    pending.push((root.to_path_buf(), 0));
+
+    while let Some((dir, depth)) = pending.pop() {
+        let entries = match fs::read_dir(&dir)
+            .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        {
+            Ok(entries) => entries,
+            Err(err) if is_root => return Err(err),
+            Err(err) => {
+                f(&dir, Err(err));
+                continue;
+            }
+        };
+
+        is_root = false;
+
+        let mut paths: Vec<_> =
+            entries.into_iter().map(|entry| entry.path()).collect();
+
+        paths.sort();
+
+        let mut subdirs = Vec::new();
+
+        for path in paths {
+            let metadata = if options.follow_symlinks {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    f(&path, Err(err));
+                    continue;
+                }
+            };
+
+            if metadata.is_dir() {
+                if options.max_depth.is_some_and(|max| depth >= max) {
+                    continue;
+                }
+                if options.follow_symlinks {
+                    match fs::canonicalize(&path) {
+                        Ok(canonical) => {
+                            if !visited_dirs.insert(canonical) {
+                                continue;
+                            }
+                        }
+                        Err(err) => {
+                            f(&path, Err(err));
+                            continue;
+                        }
+                    }
+                }
+                subdirs.push((path, depth + 1));
+            } else if metadata.is_file()
+                && options.accepts(&path, metadata.len())
+            {
+                f(&path, Ok(()));
+            }
+        }
+
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(())
+}
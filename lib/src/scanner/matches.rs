@@ -208,6 +208,7 @@ pub struct UnconfirmedMatch {
 pub struct PatternMatches {
     matches: FxHashMap<PatternId, MatchList>,
     max_matches_per_pattern: usize,
+    max_retained_capacity: usize,
     capacity: usize,
 }
 
@@ -215,10 +216,15 @@ impl PatternMatches {
     /// The maximum number of matches accepted for each pattern by default.
     const DEFAULT_MAX_MATCHES_PER_PATTERN: usize = 1_000_000;
 
+    /// The maximum number of matches for which memory is retained between
+    /// scans by default.
+    pub(crate) const DEFAULT_MAX_RETAINED_CAPACITY: usize = 10_000;
+
     pub fn new() -> Self {
         Self {
             matches: FxHashMap::default(),
             max_matches_per_pattern: Self::DEFAULT_MAX_MATCHES_PER_PATTERN,
+            max_retained_capacity: Self::DEFAULT_MAX_RETAINED_CAPACITY,
             capacity: 0,
         }
     }
@@ -231,6 +237,20 @@ impl PatternMatches {
         self
     }
 
+    /// Sets the maximum number of matches for which memory is retained
+    /// by [`PatternMatches::clear`].
+    ///
+    /// The default value is [`Self::DEFAULT_MAX_RETAINED_CAPACITY`].
+    pub fn max_retained_capacity(&mut self, n: usize) -> &mut Self {
+        self.max_retained_capacity = n;
+        self
+    }
+
+    /// Returns the maximum number of matches per pattern.
+    pub fn get_max_matches_per_pattern(&self) -> usize {
+        self.max_matches_per_pattern
+    }
+
     /// Returns the list of matches for a given pattern.
     pub fn get(&self, pattern_id: PatternId) -> Option<&MatchList> {
         self.matches.get(&pattern_id)
@@ -241,6 +261,28 @@ impl PatternMatches {
         self.matches.is_empty()
     }
 
+    /// Returns an iterator that yields the IDs of the patterns that have
+    /// at least one match, in arbitrary order.
+    pub fn matching_patterns(&self) -> impl Iterator<Item = PatternId> + '_ {
+        self.matches
+            .iter()
+            .filter(|(_, matches)| !matches.is_empty())
+            .map(|(pattern_id, _)| *pattern_id)
+    }
+
+    /// Returns an iterator that yields the ranges of all matches, for all
+    /// patterns, in arbitrary order.
+    pub fn ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.matches
+            .values()
+            .flat_map(|matches| matches.iter().map(|m| m.range.clone()))
+    }
+
+    /// Returns the total number of matches, for all patterns.
+    pub fn num_matches(&self) -> usize {
+        self.matches.values().map(|matches| matches.len()).sum()
+    }
+
     /// Clears the matches for all patterns.
     ///
     /// To optimize performance, the memory allocated for storing matches
@@ -248,16 +290,16 @@ impl PatternMatches {
     /// allocated memory is retained and reused in subsequent scans. However,
     /// due to the potential volume of patterns and matches, persistently
     /// holding onto this memory can result in a significant memory footprint.
-    /// When the total capacity of stored data exceeds a defined threshold,
-    /// memory is deallocated to manage resource usage efficiently.
+    /// When the total capacity of stored data exceeds the threshold set
+    /// with [`PatternMatches::max_retained_capacity`], memory is deallocated
+    /// to manage resource usage efficiently.
     pub fn clear(&mut self) {
         // If the capacity goes above a certain threshold, completely
         // clear the matches, which frees the memory associated to the
         // list of matches. If not, clear the list of matches, but maintain
         // the memory allocated for them.
-        if self.capacity > 10000 {
-            self.matches.clear();
-            self.capacity = 0;
+        if self.capacity > self.max_retained_capacity {
+            self.release();
         } else {
             for (_, matches) in self.matches.iter_mut() {
                 matches.clear();
@@ -265,6 +307,13 @@ impl PatternMatches {
         }
     }
 
+    /// Clears the matches for all patterns, releasing all the memory
+    /// allocated for them.
+    pub fn release(&mut self) {
+        self.matches = FxHashMap::default();
+        self.capacity = 0;
+    }
+
     /// Adds a new match to the pattern identified by the given [`PatternId`]
     ///
     /// If a match at the same offset already exists, the `replace_if_longer`
@@ -307,7 +356,8 @@ impl PatternMatches {
 
 #[cfg(test)]
 mod test {
-    use crate::scanner::matches::{Match, MatchList};
+    use crate::compiler::PatternId;
+    use crate::scanner::matches::{Match, MatchList, PatternMatches};
     use std::ops::Range;
 
     #[test]
@@ -325,4 +375,31 @@ mod test {
             vec![(1..10), (2..10), (3..10), (4..10), (5..10)]
         )
     }
+
+    #[test]
+    fn pattern_matches_retained_capacity() {
+        let mut pm = PatternMatches::new();
+
+        pm.add(
+            PatternId::from(0_usize),
+            Match { range: (0..1), xor_key: None },
+            false,
+        );
+        pm.clear();
+
+        // The list of matches for the pattern is kept, but empty.
+        assert!(!pm.is_empty());
+        assert_eq!(pm.num_matches(), 0);
+
+        pm.max_retained_capacity(0);
+        pm.add(
+            PatternId::from(0_usize),
+            Match { range: (0..1), xor_key: None },
+            false,
+        );
+        pm.clear();
+
+        // The capacity is above the limit, the memory is released.
+        assert!(pm.is_empty());
+    }
 }
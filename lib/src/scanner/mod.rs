@@ -3,21 +3,24 @@
 The scanner takes the rules produces by the compiler and scans data with them.
 */
 
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::hash_map;
+use std::future::Future;
 use std::io::Read;
-use std::ops::{Deref, Range};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::pin::Pin;
+use std::pin::pin;
 use std::ptr::{null, NonNull};
-use std::rc::Rc;
 use std::slice::Iter;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Once;
-use std::time::Duration;
-use std::{cmp, fs, thread};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Once};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::{Duration, Instant};
+use std::{cmp, fs, mem, thread};
 
 use bitvec::prelude::*;
+use bstr::{BStr, ByteSlice};
 use fmmap::{MmapFile, MmapFileExt};
 use indexmap::IndexMap;
 use protobuf::{CodedInputStream, MessageDyn};
@@ -30,29 +33,76 @@ use wasmtime::{
 
 use crate::compiler::{IdentId, PatternId, RuleId, RuleInfo, Rules};
 use crate::modules::{Module, BUILTIN_MODULES};
+#[cfg(feature = "rules-profiling")]
+use crate::profiling::{PatternProfile, ProfilingData, RuleProfile};
 use crate::types::{Struct, TypeValue};
 use crate::variables::VariableError;
 use crate::wasm::{ENGINE, MATCHING_RULES_BITMAP_BASE};
-use crate::{modules, wasm, Variable};
+use crate::{compiler, modules, wasm, Variable};
 
 pub(crate) use crate::scanner::context::*;
 use crate::scanner::matches::PatternMatches;
+use crate::scanner::provider::ProviderData;
+
+pub use crate::scanner::dir::DirScanOptions;
+pub use crate::scanner::pool::{PooledScanner, ScannerPool};
+pub use crate::scanner::process::MemoryRegion;
+pub use crate::scanner::provider::DataProvider;
 
 mod context;
+mod dir;
 mod matches;
+mod pool;
+mod process;
+mod provider;
+
+#[cfg(feature = "serde-results")]
+mod serialize;
 
 #[cfg(test)]
 mod tests;
 
+/// Function that translates the ranges reported by matches. See
+/// [`ScanOptions::offset_translator`].
+pub(crate) type OffsetTranslator =
+    dyn Fn(Range<usize>) -> Range<usize> + Send + Sync;
+
 /// Error returned by [`Scanner::scan`] and [`Scanner::scan_file`].
 #[derive(Error, Debug)]
 pub enum ScanError {
     /// The scan was aborted after the timeout period.
     #[error("timeout")]
     Timeout,
+    /// The scan was cancelled with [`CancelHandle::cancel`].
+    #[error("scan cancelled")]
+    Cancelled,
+    /// The scan required more memory than the limit set with
+    /// [`Scanner::max_memory`].
+    #[error("memory limit exceeded")]
+    OutOfMemory,
     /// Could not open the scanned file.
     #[error("can not open `{path}`: {source}")]
     OpenError { path: PathBuf, source: std::io::Error },
+    /// The path passed to [`Scanner::scan_file`] is a directory.
+    #[error("`{path}` is a directory")]
+    IsDirectory { path: PathBuf },
+    /// The path passed to [`Scanner::scan_file`] is not a regular file,
+    /// but a FIFO, socket, or device.
+    #[error("`{path}` is not a regular file")]
+    SpecialFile { path: PathBuf },
+    /// The path passed to [`Scanner::scan_file`] is a symbolic link, and
+    /// the scanner was configured for not following symbolic links with
+    /// [`Scanner::follow_symlinks`].
+    #[error("`{path}` is a symbolic link")]
+    SymbolicLink { path: PathBuf },
+    /// Some global variable passed to [`Scanner::scan_with_globals`] or
+    /// [`ScanOptions::global`] is not valid.
+    #[error(transparent)]
+    VariableError(#[from] VariableError),
+    /// Could not read the data from the reader passed to
+    /// [`Scanner::scan_reader`].
+    #[error("can not read data: {source}")]
+    ReadError { source: std::io::Error },
     /// Could not map the scanned file into memory.
     #[error("can not map `{path}`: {source}")]
     MapError { path: PathBuf, source: fmmap::error::Error },
@@ -62,6 +112,46 @@ pub enum ScanError {
     /// The module is unknown.
     #[error("unknown module `{module}`")]
     UnknownModule { module: String },
+    /// Could not read the memory of the scanned process.
+    #[error("can not read memory of process {pid}: {source}")]
+    ProcessMemoryError { pid: u32, source: std::io::Error },
+    /// Error while executing the WASM code that evaluates the rule
+    /// conditions, like a trap.
+    #[error("error while evaluating conditions: {err}")]
+    WasmError { err: wasmtime::Error },
+    /// The scanner could not be created with [`Scanner::try_new`]. This
+    /// happens when the `pooling-allocator` feature is enabled and the
+    /// maximum number of scanners that can exist at the same time was
+    /// reached.
+    #[error("can not create scanner: {err}")]
+    InstantiationError { err: wasmtime::Error },
+}
+
+/// Obtains the output of a future that never suspends.
+///
+/// This is used for running the asynchronous functions implemented by the
+/// scanner from synchronous code. Those futures only return [`Poll::Pending`]
+/// when the WASM code yields to the async executor, and this happens only
+/// in scans started by [`Scanner::scan_async`]. In any other case the future
+/// is ready the first time it is polled.
+fn block_on<F: Future>(future: F) -> F::Output {
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable =
+            RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("synchronous scans never yield"),
+    }
 }
 
 /// Global counter that gets incremented every 1 second by a dedicated thread.
@@ -72,10 +162,60 @@ static HEARTBEAT_COUNTER: AtomicU64 = AtomicU64::new(0);
 /// Used for spawning the thread that increments `HEARTBEAT_COUNTER`.
 static INIT_HEARTBEAT: Once = Once::new();
 
+/// Handle that allows cancelling the scans performed by a [`Scanner`] from
+/// another thread.
+///
+/// Handles are obtained with [`Scanner::cancel_handle`], and they can be
+/// cloned and sent to other threads.
+///
+/// # Example
+///
+/// ```rust
+/// # use yara_x::{ScanError, Scanner};
+/// let rules = yara_x::compile(r#"
+///     rule slow {
+///         condition:
+///             for all i in (0..10000000000) : ( uint8(i % filesize) != 0xff )
+///     }"#).unwrap();
+///
+/// let mut scanner = Scanner::new(&rules);
+/// let handle = scanner.cancel_handle();
+///
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+///     handle.cancel();
+/// });
+///
+/// assert!(matches!(scanner.scan(b"foo"), Err(ScanError::Cancelled)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    /// Cancels the scan that is in progress, which fails with
+    /// [`ScanError::Cancelled`].
+    ///
+    /// If no scan is in progress, the next scan is cancelled as soon as it
+    /// starts. Once a scan is cancelled, subsequent scans are not affected.
+    ///
+    /// The search for patterns stops shortly after the scan is cancelled,
+    /// but the evaluation of conditions can take up to one second to stop,
+    /// as it checks for cancellations only once per second.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
 pub enum ScannedData<'a> {
     Slice(&'a [u8]),
     Vec(Vec<u8>),
     Mmap(MmapFile),
+    Blocks(Vec<(usize, Cow<'a, [u8]>)>),
+    Regions(Vec<(usize, Cow<'a, [u8]>)>),
+    Provider(ProviderData<'a>),
+    Process(ProviderData<'a>),
 }
 
 impl<'a> AsRef<[u8]> for ScannedData<'a> {
@@ -84,71 +224,351 @@ impl<'a> AsRef<[u8]> for ScannedData<'a> {
             ScannedData::Slice(s) => s,
             ScannedData::Vec(v) => v.as_ref(),
             ScannedData::Mmap(m) => m.as_slice(),
+            ScannedData::Blocks(_) | ScannedData::Regions(_) => &[],
+            ScannedData::Provider(_) | ScannedData::Process(_) => &[],
+        }
+    }
+}
+
+impl<'a> ScannedData<'a> {
+    /// Returns the data in the given range.
+    ///
+    /// When the data consists of multiple blocks, the range must be fully
+    /// contained in one of them.
+    fn get(&self, range: Range<usize>) -> Option<&[u8]> {
+        match self {
+            ScannedData::Blocks(blocks) | ScannedData::Regions(blocks) => {
+                let (base, block) = blocks.iter().find(|(base, block)| {
+                    range.start >= *base && range.end <= *base + block.len()
+                })?;
+                block.get(range.start - base..range.end - base)
+            }
+            ScannedData::Provider(provider)
+            | ScannedData::Process(provider) => provider.get(range),
+            _ => self.as_ref().get(range),
+        }
+    }
+
+    /// Returns the value of `filesize` for the data. When the data consists
+    /// of multiple blocks, this is the offset where the last block ends.
+    /// When the data consists of memory regions, whose offsets are virtual
+    /// addresses, this is the number of bytes scanned.
+    fn len(&self) -> usize {
+        match self {
+            ScannedData::Blocks(blocks) => blocks
+                .iter()
+                .map(|(base, block)| base + block.len())
+                .max()
+                .unwrap_or(0),
+            ScannedData::Regions(_) | ScannedData::Process(_) => {
+                self.scanned_bytes()
+            }
+            ScannedData::Provider(provider) => provider.len,
+            _ => self.as_ref().len(),
+        }
+    }
+
+    /// Returns the number of bytes scanned.
+    fn scanned_bytes(&self) -> usize {
+        match self {
+            ScannedData::Blocks(blocks) | ScannedData::Regions(blocks) => {
+                blocks.iter().map(|(_, block)| block.len()).sum()
+            }
+            ScannedData::Provider(provider) => provider.len,
+            ScannedData::Process(process) => {
+                process.regions.iter().map(|region| region.len()).sum()
+            }
+            _ => self.as_ref().len(),
+        }
+    }
+
+    /// Returns the data passed to modules. When the data consists of
+    /// multiple blocks or regions, modules receive the first one.
+    fn module_data(&self) -> &[u8] {
+        match self {
+            ScannedData::Blocks(blocks) | ScannedData::Regions(blocks) => {
+                blocks.first().map_or(&[], |(_, block)| block.as_ref())
+            }
+            ScannedData::Provider(provider)
+            | ScannedData::Process(provider) => provider.head.as_slice(),
+            _ => self.as_ref(),
+        }
+    }
+}
+
+/// Options for a single scan operation.
+///
+/// These options are passed to [`Scanner::scan_with_options`], and only
+/// affect that scan.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use yara_x::{ScanOptions, Scanner};
+/// let rules = yara_x::compile("rule test { condition: true }").unwrap();
+/// let mut scanner = Scanner::new(&rules);
+///
+/// let options = ScanOptions::new()
+///     .timeout(Duration::from_secs(5))
+///     .max_matches_per_pattern(100);
+///
+/// let results = scanner.scan_with_options(b"some data", options).unwrap();
+///
+/// assert_eq!(results.matching_rules().len(), 1);
+/// ```
+#[derive(Default)]
+pub struct ScanOptions<'a> {
+    timeout: Option<Duration>,
+    offset_translator: Option<Arc<OffsetTranslator>>,
+    max_matches_per_pattern: Option<usize>,
+    module_outputs: Vec<Box<dyn MessageDyn>>,
+    module_outputs_raw: Vec<(&'a str, &'a [u8])>,
+    globals: Vec<(&'a str, TypeValue)>,
+}
+
+impl<'a> ScanOptions<'a> {
+    /// Creates a new set of options where nothing is overridden.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a timeout for the scan. See [`Scanner::set_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of matches per pattern. See
+    /// [`Scanner::max_matches_per_pattern`].
+    pub fn max_matches_per_pattern(mut self, n: usize) -> Self {
+        self.max_matches_per_pattern = Some(n);
+        self
+    }
+
+    /// Sets the output for a module. See [`Scanner::set_module_output`].
+    pub fn module_output(mut self, data: Box<dyn MessageDyn>) -> Self {
+        self.module_outputs.push(data);
+        self
+    }
+
+    /// Sets the output for a module as raw protobuf data. See
+    /// [`Scanner::set_module_output_raw`].
+    pub fn module_output_raw(mut self, name: &'a str, data: &'a [u8]) -> Self {
+        self.module_outputs_raw.push((name, data));
+        self
+    }
+
+    /// Sets a function that translates the ranges reported by
+    /// [`Match::range`].
+    ///
+    /// The function receives the range within the scanned data where a match
+    /// occurred and returns the range that will be reported instead. This
+    /// allows rebasing matches to some other address space, for instance,
+    /// converting file offsets into RVAs using the section table of a PE
+    /// file. The translation doesn't affect the evaluation of conditions,
+    /// which always use offsets within the scanned data.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use yara_x::{ScanOptions, Scanner};
+    /// let rules = yara_x::compile(r#"rule t { strings: $a = "foo" condition: $a at 2 }"#).unwrap();
+    /// let mut scanner = Scanner::new(&rules);
+    ///
+    /// let options = ScanOptions::new()
+    ///     .offset_translator(|range| range.start + 0x1000..range.end + 0x1000);
+    ///
+    /// let results = scanner.scan_with_options(b"xxfoo", options).unwrap();
+    /// let rule = results.matching_rules().next().unwrap();
+    /// let pattern = rule.patterns().next().unwrap();
+    ///
+    /// assert_eq!(pattern.matches().next().unwrap().range(), 0x1002..0x1005);
+    /// ```
+    pub fn offset_translator<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Range<usize>) -> Range<usize> + Send + Sync + 'static,
+    {
+        self.offset_translator = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the value of a global variable for this scan only. Once the scan
+    /// finishes the variable recovers the value it had before. See
+    /// [`Scanner::set_global`].
+    pub fn global<T: TryInto<Variable>>(
+        mut self,
+        ident: &'a str,
+        value: T,
+    ) -> Result<Self, VariableError>
+    where
+        VariableError: From<<T as TryInto<Variable>>::Error>,
+    {
+        let variable: Variable = value.try_into()?;
+        self.globals.push((ident, variable.into()));
+        Ok(self)
+    }
+}
+
+/// Policy that controls how much memory is retained by a [`Scanner`] between
+/// scans.
+///
+/// For performance reasons, the scanner reuses the memory allocated for
+/// tracking matches and other objects during one scan in subsequent scans.
+/// This avoids allocating memory over and over, but the memory retained can
+/// be significant in long-running processes that scan many files with lots
+/// of matches. This policy is set with [`Scanner::set_memory_policy`].
+///
+/// # Example
+///
+/// ```rust
+/// # use yara_x::{MemoryPolicy, Scanner};
+/// let rules = yara_x::compile("rule test { condition: true }").unwrap();
+/// let mut scanner = Scanner::new(&rules);
+///
+/// scanner.set_memory_policy(
+///     MemoryPolicy::new().max_retained_matches(1000).trim_every(100),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryPolicy {
+    max_retained_matches: usize,
+    trim_every: Option<usize>,
+}
+
+impl Default for MemoryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retained_matches:
+                PatternMatches::DEFAULT_MAX_RETAINED_CAPACITY,
+            trim_every: None,
         }
     }
 }
 
+impl MemoryPolicy {
+    /// Creates the default policy, where memory for up to 10000 matches is
+    /// retained between scans, and the retained memory is never trimmed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of matches for which memory is retained
+    /// between scans. When some scan produces more matches than this
+    /// limit, the memory used for storing them is released before the
+    /// next scan.
+    pub fn max_retained_matches(mut self, n: usize) -> Self {
+        self.max_retained_matches = n;
+        self
+    }
+
+    /// Releases all the memory retained by the scanner every `scans` scans,
+    /// regardless of its size.
+    ///
+    /// The memory is released when the next scan starts, so the results of
+    /// the last scan are not affected.
+    pub fn trim_every(mut self, scans: usize) -> Self {
+        self.trim_every = Some(scans);
+        self
+    }
+}
+
 /// Scans data with already compiled YARA rules.
 ///
 /// The scanner receives a set of compiled [`Rules`] and scans data with those
 /// rules. The same scanner can be used for scanning multiple files or
 /// in-memory data sequentially, but you need multiple scanners for scanning in
 /// parallel.
+///
+/// Scanners are [`Send`], so they can be created in one thread and moved to
+/// another one, for instance, a worker thread that uses them for scanning.
+/// However, they are not [`Sync`], a scanner can be used by a single thread
+/// at a time.
 pub struct Scanner<'r> {
-    wasm_store: Pin<Box<Store<ScanContext<'r>>>>,
+    wasm_store: Store<ScanContext<'r>>,
     wasm_main_func: TypedFunc<(), i32>,
     filesize: Global,
     timeout: Option<Duration>,
+    mmap_threshold: Option<u64>,
+    follow_symlinks: bool,
+    partial_results: bool,
+    trim_every: Option<usize>,
+    scans_since_trim: usize,
 }
 
 impl<'r> Scanner<'r> {
     const DEFAULT_SCAN_TIMEOUT: u64 = 315_360_000;
 
+    /// Files with this size or larger are memory-mapped by
+    /// [`Scanner::scan_file`] by default. For smaller files reading the
+    /// whole file is faster than using a memory-mapped file.
+    const DEFAULT_MMAP_THRESHOLD: u64 = 500_000_000;
+
     /// Creates a new scanner.
+    ///
+    /// When the `pooling-allocator` feature is enabled, the number of
+    /// scanners that can exist at the same time is limited (1000 by
+    /// default, see [`crate::PoolingLimits`]), and this function panics if
+    /// the limit is exceeded. Use [`Scanner::try_new`] for handling that
+    /// case gracefully.
     pub fn new(rules: &'r Rules) -> Self {
+        Self::try_new(rules).unwrap()
+    }
+
+    /// Creates a new scanner, or returns [`ScanError::InstantiationError`]
+    /// if the scanner can't be created.
+    ///
+    /// This can fail only when the `pooling-allocator` feature is enabled,
+    /// if the maximum number of scanners that can exist at the same time
+    /// was reached.
+    pub fn try_new(rules: &'r Rules) -> Result<Self, ScanError> {
         let num_rules = rules.num_rules() as u32;
         let num_patterns = rules.num_patterns() as u32;
 
-        // The ScanContext structure belongs to the WASM store, but at the same
-        // time it must have a reference to the store because it is required
-        // for accessing the WASM memory from code that only has a reference
-        // to ScanContext. This kind of circular data structures are not
-        // natural to Rust, and they can be achieved either by using unsafe
-        // pointers, or by using Rc::Weak. In this case we are storing a
-        // pointer to the store in ScanContext. The store is put into a
-        // pinned box in order to make sure that it doesn't move from
-        // its original memory address and the pointer remains valid.
-        let mut wasm_store = Box::pin(Store::new(
+        let mut wasm_store = Store::new(
             &crate::wasm::ENGINE,
             ScanContext {
-                wasm_store: NonNull::dangling(),
                 runtime_objects: IndexMap::new(),
                 compiled_rules: rules,
                 console_log: None,
+                rule_match_callback: None,
+                scanned_data_ref: ScanPtr::null(),
+                memory_limiter: MemoryLimiter::default(),
                 current_struct: None,
                 root_struct: rules.globals().make_root(),
-                scanned_data: null(),
-                scanned_data_len: 0,
+                scanned_data: ScanPtr::null(),
                 private_matching_rules: Vec::new(),
                 non_private_matching_rules: Vec::new(),
                 global_matching_rules: FxHashMap::default(),
+                failed_global_rules: Vec::new(),
+                early_termination: None,
+                stats: ScanStats::default(),
+                offset_translator: None,
+                cancelled: Arc::new(AtomicBool::new(false)),
                 main_memory: None,
                 module_outputs: FxHashMap::default(),
                 user_provided_module_outputs: FxHashMap::default(),
                 pattern_matches: PatternMatches::new(),
                 unconfirmed_matches: FxHashMap::default(),
                 deadline: 0,
+                scanned_data_base: 0,
                 limit_reached: FxHashSet::default(),
                 regexp_cache: RefCell::new(FxHashMap::default()),
                 #[cfg(feature = "rules-profiling")]
                 time_spent_in_pattern: FxHashMap::default(),
+                #[cfg(feature = "rules-profiling")]
+                time_spent_in_module: FxHashMap::default(),
+                #[cfg(feature = "rules-profiling")]
+                time_spent_in_rule: FxHashMap::default(),
+                #[cfg(feature = "rules-profiling")]
+                rule_eval_start: None,
             },
-        ));
+        );
 
-        // Initialize the ScanContext.wasm_store pointer that was initially
-        // dangling.
-        wasm_store.data_mut().wasm_store =
-            NonNull::from(wasm_store.as_ref().deref());
+        // All the memory used by the WASM store, including the main memory
+        // created below, is subject to the limits imposed by the scanner's
+        // memory limiter.
+        wasm_store.limiter(|ctx| &mut ctx.memory_limiter);
 
         // Global variable that will hold the value for `filesize`. This is
         // initialized to 0 because the file size is not known until some
@@ -186,9 +606,17 @@ impl<'r> Scanner<'r> {
         let matching_patterns_bitmap_base =
             MATCHING_RULES_BITMAP_BASE as u32 + num_rules.div_ceil(8);
 
+        // Compute the base offset for the bitmap that indicates which rules
+        // are disabled. This bitmap has 1 bit per rule, the N-th bit is set
+        // if the rule with RuleId = N was disabled with any of the
+        // `Scanner::disable_*` functions. The bitmap starts right after the
+        // bitmap that contains matching information for patterns.
+        let disabled_rules_bitmap_base =
+            matching_patterns_bitmap_base + num_patterns.div_ceil(8);
+
         // Compute the required memory size in 64KB pages.
         let mem_size = u32::div_ceil(
-            matching_patterns_bitmap_base + num_patterns.div_ceil(8),
+            disabled_rules_bitmap_base + num_rules.div_ceil(8),
             65536,
         );
 
@@ -199,6 +627,13 @@ impl<'r> Scanner<'r> {
         )
         .unwrap();
 
+        let disabled_rules_bitmap_base = Global::new(
+            wasm_store.as_context_mut(),
+            GlobalType::new(ValType::I32, Mutability::Const),
+            Val::I32(disabled_rules_bitmap_base as i32),
+        )
+        .unwrap();
+
         // Create module's main memory.
         let main_memory = wasmtime::Memory::new(
             wasm_store.as_context_mut(),
@@ -209,7 +644,9 @@ impl<'r> Scanner<'r> {
         // Instantiate the module. This takes the wasm code provided by the
         // `wasm_mod` function and links its imported functions with the
         // implementations that YARA provides.
-        let wasm_instance = wasm::new_linker()
+        let mut linker = wasm::new_linker();
+
+        linker
             .define(wasm_store.as_context(), "yara_x", "filesize", filesize)
             .unwrap()
             .define(
@@ -233,16 +670,36 @@ impl<'r> Scanner<'r> {
                 matching_patterns_bitmap_base,
             )
             .unwrap()
+            .define(
+                wasm_store.as_context(),
+                "yara_x",
+                "disabled_rules_bitmap_base",
+                disabled_rules_bitmap_base,
+            )
+            .unwrap()
             .define(
                 wasm_store.as_context(),
                 "yara_x",
                 "main_memory",
                 main_memory,
             )
-            .unwrap()
-            .instantiate(wasm_store.as_context_mut(), rules.wasm_mod())
             .unwrap();
 
+        // When async support is enabled the module must be instantiated
+        // asynchronously, even if instantiating it never blocks.
+        #[cfg(not(feature = "async"))]
+        let wasm_instance = linker
+            .instantiate(wasm_store.as_context_mut(), rules.wasm_mod())
+            .map_err(|err| ScanError::InstantiationError { err })?;
+
+        #[cfg(feature = "async")]
+        let wasm_instance =
+            block_on(linker.instantiate_async(
+                wasm_store.as_context_mut(),
+                rules.wasm_mod(),
+            ))
+            .map_err(|err| ScanError::InstantiationError { err })?;
+
         // Obtain a reference to the "main" function exported by the module.
         let wasm_main_func = wasm_instance
             .get_typed_func::<(), i32>(wasm_store.as_context_mut(), "main")
@@ -250,7 +707,17 @@ impl<'r> Scanner<'r> {
 
         wasm_store.data_mut().main_memory = Some(main_memory);
 
-        Self { wasm_store, wasm_main_func, filesize, timeout: None }
+        Ok(Self {
+            wasm_store,
+            wasm_main_func,
+            filesize,
+            timeout: None,
+            mmap_threshold: Some(Self::DEFAULT_MMAP_THRESHOLD),
+            follow_symlinks: true,
+            partial_results: false,
+            trim_every: None,
+            scans_since_trim: 0,
+        })
     }
 
     /// Sets a timeout for scan operations.
@@ -266,6 +733,50 @@ impl<'r> Scanner<'r> {
         self
     }
 
+    /// Returns a handle that can be used for cancelling the scans performed
+    /// by this scanner from another thread. See [`CancelHandle`].
+    pub fn cancel_handle(&self) -> CancelHandle {
+        // Cancellations are detected by the epoch deadline callback, which
+        // is invoked only when the heartbeat thread increments the epoch.
+        Self::start_heartbeat();
+        CancelHandle { cancelled: self.wasm_store.data().cancelled.clone() }
+    }
+
+    /// Indicates whether scans that time out or are cancelled produce
+    /// partial results.
+    ///
+    /// By default, scans that time out or are cancelled fail with
+    /// [`ScanError::Timeout`] or [`ScanError::Cancelled`], and any match
+    /// found before that is lost. When partial results are enabled these
+    /// scans succeed, and the returned [`ScanResults`] contain the rules
+    /// that matched before the scan was interrupted. In that case
+    /// [`ScanResults::is_truncated`] returns true. Scans that are cancelled
+    /// before they start fail with [`ScanError::Cancelled`] anyway.
+    pub fn partial_results(&mut self, yes: bool) -> &mut Self {
+        self.partial_results = yes;
+        self
+    }
+
+    /// Indicates whether the search for patterns stops as soon as all rules
+    /// are known to match.
+    ///
+    /// This is useful for triage rules with conditions like `any of them`,
+    /// as the search for patterns can stop long before reaching the end of
+    /// large files. It only has effect when the condition of every rule is
+    /// true as soon as any of its patterns matches, like in `$a`, `$a or $b`,
+    /// `any of them` or `1 of ($a, $b)`. If some rule has a different
+    /// condition, all the data is searched as usual.
+    ///
+    /// When the search stops early, the matches reported for each pattern
+    /// are not necessarily all the matches in the data. This is disabled
+    /// by default.
+    pub fn early_termination(&mut self, yes: bool) -> &mut Self {
+        let ctx = self.wasm_store.data_mut();
+        ctx.early_termination =
+            if yes { EarlyTermination::new(ctx.compiled_rules) } else { None };
+        self
+    }
+
     /// Sets the maximum number of matches per pattern.
     ///
     /// When some pattern reaches the maximum number of patterns it won't
@@ -275,6 +786,64 @@ impl<'r> Scanner<'r> {
         self
     }
 
+    /// Sets the policy that controls how much memory is retained by the
+    /// scanner between scans. See [`MemoryPolicy`].
+    pub fn set_memory_policy(&mut self, policy: MemoryPolicy) -> &mut Self {
+        self.wasm_store
+            .data_mut()
+            .pattern_matches
+            .max_retained_capacity(policy.max_retained_matches);
+        self.trim_every = policy.trim_every;
+        self.scans_since_trim = 0;
+        self
+    }
+
+    /// Sets the size, in bytes, starting at which [`Scanner::scan_file`]
+    /// memory-maps files instead of reading them into memory.
+    ///
+    /// The default threshold is 500MB. Which value is best depends on the
+    /// storage where the files reside, for instance, memory-mapping files
+    /// in network filesystems is usually slower than reading them.
+    pub fn set_mmap_threshold(&mut self, bytes: u64) -> &mut Self {
+        self.mmap_threshold = Some(bytes);
+        self
+    }
+
+    /// Makes [`Scanner::scan_file`] memory-map all files, regardless of
+    /// their size.
+    pub fn always_mmap(&mut self) -> &mut Self {
+        self.mmap_threshold = Some(0);
+        self
+    }
+
+    /// Makes [`Scanner::scan_file`] read files into memory, regardless of
+    /// their size, instead of memory-mapping them.
+    pub fn never_mmap(&mut self) -> &mut Self {
+        self.mmap_threshold = None;
+        self
+    }
+
+    /// Indicates whether [`Scanner::scan_file`] follows symbolic links.
+    ///
+    /// Symbolic links are followed by default. When they are not followed,
+    /// scanning a symbolic link fails with [`ScanError::SymbolicLink`].
+    pub fn follow_symlinks(&mut self, yes: bool) -> &mut Self {
+        self.follow_symlinks = yes;
+        self
+    }
+
+    /// Sets the maximum amount of memory, in bytes, that can be used by the
+    /// WASM store.
+    ///
+    /// The WASM memory holds the bitmaps that track which rules and patterns
+    /// matched, so its size depends on the number of rules and patterns.
+    /// Scans fail with [`ScanError::OutOfMemory`] if the memory exceeds the
+    /// limit, or if it would exceed the limit while growing.
+    pub fn max_memory(&mut self, bytes: usize) -> &mut Self {
+        self.wasm_store.data_mut().memory_limiter.max_memory = Some(bytes);
+        self
+    }
+
     /// Sets a callback that is invoked every time a YARA rule calls the
     /// `console` module.
     ///
@@ -284,13 +853,105 @@ impl<'r> Scanner<'r> {
     /// ignored.
     pub fn console_log<F>(&mut self, callback: F) -> &mut Self
     where
-        F: FnMut(String) + 'r,
+        F: FnMut(String) + Send + 'r,
     {
         self.wasm_store.data_mut().console_log = Some(Box::new(callback));
         self
     }
 
+    /// Sets a callback that is invoked every time a rule matches.
+    ///
+    /// The callback is invoked while the scan is in progress, as soon as
+    /// the rule's condition is evaluated, instead of waiting for all the
+    /// rules to be evaluated. The callback receives the matching [`Rule`].
+    /// Private rules are not passed to the callback, and global rules are
+    /// passed only after the scan finishes, as they can be discarded if
+    /// any other global rule in the same namespace doesn't match.
+    ///
+    /// Notice that the pattern search phase is performed lazily, so the
+    /// matches of patterns that were not required for evaluating the
+    /// conditions of rules evaluated so far may not be available yet.
+    pub fn on_rule_match<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: FnMut(&Rule<'_, 'r>) + Send + 'r,
+    {
+        self.wasm_store.data_mut().rule_match_callback =
+            Some(Box::new(callback));
+        self
+    }
+
+    /// Scans all the files in a directory and its subdirectories.
+    ///
+    /// The `options` argument controls which files are scanned, see
+    /// [`DirScanOptions`] for details. For each scanned file `callback`
+    /// is invoked with the file's path and the result of scanning it.
+    /// Errors that occur while walking the directory, like subdirectories
+    /// that can't be read, are also passed to `callback`, together with the
+    /// path of the problematic entry, and don't stop the walk.
+    ///
+    /// This function returns an error only if `path` can't be read. When
+    /// `path` is a file instead of a directory, that file is scanned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use yara_x::{DirScanOptions, Scanner};
+    /// let rules = yara_x::compile("rule test { condition: true }").unwrap();
+    /// let mut scanner = Scanner::new(&rules);
+    ///
+    /// scanner
+    ///     .scan_dir("/some/dir", &DirScanOptions::new(), |path, results| {
+    ///         match results {
+    ///             Ok(results) => println!(
+    ///                 "{}: {} matching rules",
+    ///                 path.display(),
+    ///                 results.matching_rules().len()
+    ///             ),
+    ///             Err(err) => println!("{}: {}", path.display(), err),
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn scan_dir<P, F>(
+        &mut self,
+        path: P,
+        options: &DirScanOptions,
+        mut callback: F,
+    ) -> Result<(), ScanError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path, Result<ScanResults<'_, 'r>, ScanError>),
+    {
+        let root = path.as_ref();
+
+        dir::walk(root, options, |path, result| match result {
+            Ok(()) => callback(path, self.scan_file_impl(path, true)),
+            Err(err) => callback(
+                path,
+                Err(ScanError::OpenError {
+                    path: path.to_path_buf(),
+                    source: err,
+                }),
+            ),
+        })
+        .map_err(|err| ScanError::OpenError {
+            path: root.to_path_buf(),
+            source: err,
+        })
+    }
+
     /// Scans a file.
+    ///
+    /// Files are read into memory, unless their size reaches the threshold
+    /// set with [`Scanner::set_mmap_threshold`], in which case they are
+    /// memory-mapped. Files that report a size of zero are always read, as
+    /// some special filesystems like `/proc` report a zero size for files
+    /// that actually contain data.
+    ///
+    /// Only regular files can be scanned, this function returns
+    /// [`ScanError::IsDirectory`] for directories and
+    /// [`ScanError::SpecialFile`] for FIFOs, sockets and devices, which
+    /// could block the scan indefinitely.
     pub fn scan_file<'a, P>(
         &'a mut self,
         path: P,
@@ -298,24 +959,52 @@ impl<'r> Scanner<'r> {
     where
         P: AsRef<Path>,
     {
-        let path = path.as_ref();
+        self.scan_file_impl(path.as_ref(), self.follow_symlinks)
+    }
 
-        let mut file = fs::File::open(path).map_err(|err| {
-            ScanError::OpenError { path: path.to_path_buf(), source: err }
-        })?;
+    fn scan_file_impl<'a>(
+        &'a mut self,
+        path: &Path,
+        follow_symlinks: bool,
+    ) -> Result<ScanResults<'a, 'r>, ScanError> {
+        let open_error = |err| ScanError::OpenError {
+            path: path.to_path_buf(),
+            source: err,
+        };
+
+        // Check the file type before opening the file, as opening some
+        // special files, like FIFOs, blocks until there's a writer.
+        if !follow_symlinks
+            && fs::symlink_metadata(path)
+                .map_err(open_error)?
+                .file_type()
+                .is_symlink()
+        {
+            return Err(ScanError::SymbolicLink { path: path.to_path_buf() });
+        }
+
+        let metadata = fs::metadata(path).map_err(open_error)?;
+
+        if metadata.is_dir() {
+            return Err(ScanError::IsDirectory { path: path.to_path_buf() });
+        }
+
+        if !metadata.is_file() {
+            return Err(ScanError::SpecialFile { path: path.to_path_buf() });
+        }
 
-        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut file = fs::File::open(path).map_err(open_error)?;
+
+        let size = metadata.len();
 
         let mut buffered_file;
         let mapped_file;
 
-        // For files smaller than ~500MB reading the whole file is faster than
-        // using a memory-mapped file.
-        let data = if size < 500_000_000 {
+        let data = if size == 0
+            || self.mmap_threshold.map_or(true, |t| size < t)
+        {
             buffered_file = Vec::with_capacity(size as usize);
-            file.read_to_end(&mut buffered_file).map_err(|err| {
-                ScanError::OpenError { path: path.to_path_buf(), source: err }
-            })?;
+            file.read_to_end(&mut buffered_file).map_err(open_error)?;
             ScannedData::Vec(buffered_file)
         } else {
             mapped_file = MmapFile::open(path).map_err(|err| {
@@ -324,7 +1013,192 @@ impl<'r> Scanner<'r> {
             ScannedData::Mmap(mapped_file)
         };
 
-        self.scan_impl(data)
+        self.scan_impl(data, None)
+    }
+
+    /// Scans the data produced by a reader.
+    ///
+    /// This allows scanning data coming from sockets, pipes, decompression
+    /// streams and any other source that implements [`Read`], without the
+    /// caller having to buffer it first. The reader is consumed until it
+    /// reaches the end of the stream, and the scan starts once all the data
+    /// has been read, as the value of `filesize` and the information
+    /// produced by modules depend on the whole data.
+    ///
+    /// Notice that the whole data is kept in memory during the scan, so the
+    /// memory used grows with the size of the data. For scanning large data
+    /// with bounded memory use [`Scanner::scan_provider`] instead.
+    pub fn scan_reader<'a, R>(
+        &'a mut self,
+        mut reader: R,
+    ) -> Result<ScanResults<'a, 'r>, ScanError>
+    where
+        R: Read,
+    {
+        let mut buffer = Vec::new();
+
+        reader
+            .read_to_end(&mut buffer)
+            .map_err(|err| ScanError::ReadError { source: err })?;
+
+        self.scan_impl(ScannedData::Vec(buffer), None)
+    }
+
+    /// Scans the memory of a running process.
+    ///
+    /// All the readable memory regions of the process are scanned
+    /// independently, and the ranges reported by matches are virtual
+    /// addresses, so matches never span multiple regions. The virtual
+    /// address of each match is also returned by [`Match::address`], and
+    /// the regions that were scanned are returned by
+    /// [`ScanResults::memory_regions`].
+    ///
+    /// The memory of the process is not copied as a whole. Each region is
+    /// read in windows of 16MB that overlap by 64KB while searching for
+    /// patterns, as [`Scanner::scan_provider`] does, and only the fragments
+    /// that contain matches are read again once the search finishes. This
+    /// means that patterns longer than 64KB can be missed when they cross
+    /// the boundary between two windows. Modules receive the first window
+    /// of the first region, functions that read the scanned data, like
+    /// `uint8`, return undefined values, and `filesize` is the number of
+    /// bytes scanned.
+    ///
+    /// Only supported in Linux, where it requires permission for reading
+    /// `/proc/<pid>/mem`. In other platforms this function returns
+    /// [`ScanError::ProcessMemoryError`].
+    pub fn scan_process<'a>(
+        &'a mut self,
+        pid: u32,
+    ) -> Result<ScanResults<'a, 'r>, ScanError> {
+        let mut data = process::open_process_memory(pid).map_err(|err| {
+            ScanError::ProcessMemoryError { pid, source: err }
+        })?;
+
+        // The first window is read only if some module needs it.
+        if self.wasm_store.data().compiled_rules.imports().next().is_some() {
+            data.read_head()?;
+        }
+
+        self.scan_impl(ScannedData::Process(data), None)
+    }
+
+    /// Scans a set of memory regions, each one accompanied by the virtual
+    /// address where it starts.
+    ///
+    /// This is useful for scanning address spaces reconstructed from other
+    /// sources, like minidumps. The regions are scanned as independent
+    /// blocks without copying them, exactly as [`Scanner::scan_process`]
+    /// does with the memory of a process, so the ranges reported by matches
+    /// are virtual addresses. Empty regions, and regions that are not
+    /// addressable in the current platform, are ignored.
+    ///
+    /// The virtual address of each match is also returned by
+    /// [`Match::address`], and the regions that were scanned are returned
+    /// by [`ScanResults::memory_regions`]. Modules receive the first
+    /// region, functions that read the scanned data, like `uint8`, return
+    /// undefined values, and `filesize` is the number of bytes scanned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use yara_x::Scanner;
+    /// let rules = yara_x::compile(r#"rule t { strings: $a = "foo" condition: $a }"#).unwrap();
+    /// let mut scanner = Scanner::new(&rules);
+    ///
+    /// let results = scanner
+    ///     .scan_memory_regions([
+    ///         (0x1000, b"xxxxxxxx".as_slice()),
+    ///         (0x7000, b"xxfooxxx".as_slice()),
+    ///     ])
+    ///     .unwrap();
+    ///
+    /// let rule = results.matching_rules().next().unwrap();
+    /// let pattern = rule.patterns().next().unwrap();
+    /// let m = pattern.matches().next().unwrap();
+    ///
+    /// assert_eq!(m.range(), 0x7002..0x7005);
+    /// assert_eq!(m.address(), Some(0x7002));
+    /// ```
+    pub fn scan_memory_regions<'a, I>(
+        &'a mut self,
+        regions: I,
+    ) -> Result<ScanResults<'a, 'r>, ScanError>
+    where
+        I: IntoIterator<Item = (u64, &'a [u8])>,
+    {
+        self.scan_impl(
+            ScannedData::Regions(process::regions_to_blocks(regions)),
+            None,
+        )
+    }
+
+    /// Scans data that is split into multiple blocks.
+    ///
+    /// Each block is a slice of data accompanied by its base offset, which
+    /// is the offset of the block's first byte within the whole data. The
+    /// blocks don't need to be contiguous, so this can be used for scanning
+    /// large files or memory dumps without copying them into a single
+    /// buffer. Blocks are searched for patterns one by one, and the ranges
+    /// reported by matches are relative to the whole data (i.e: the base
+    /// offset of the block is added to them).
+    ///
+    /// There are some limitations with respect to [`Scanner::scan`]:
+    ///
+    /// * Patterns can't match across the boundary between two blocks, even
+    ///   if the blocks are contiguous. This includes hex patterns with
+    ///   jumps, whose fragments must be found in the same block.
+    /// * Functions that read the scanned data, like `uint8`, `uint16`, or
+    ///   the ones in the `math` module, return undefined values.
+    /// * Modules receive only the first block, so they can parse the
+    ///   headers of file formats, but any structure beyond that block is
+    ///   missing from their output.
+    /// * `filesize` is the offset where the last block ends.
+    pub fn scan_blocks<'a, I>(
+        &'a mut self,
+        blocks: I,
+    ) -> Result<ScanResults<'a, 'r>, ScanError>
+    where
+        I: IntoIterator<Item = (usize, &'a [u8])>,
+    {
+        self.scan_impl(
+            ScannedData::Blocks(
+                blocks
+                    .into_iter()
+                    .map(|(base, block)| (base, Cow::Borrowed(block)))
+                    .collect(),
+            ),
+            None,
+        )
+    }
+
+    /// Scans data read on demand from a [`DataProvider`].
+    ///
+    /// This allows scanning data that can't be entirely loaded or mapped
+    /// into memory, like remote blobs or encrypted containers. The data is
+    /// read window by window while searching for patterns, and the fragments
+    /// that contain matches are read again once the search finishes. See
+    /// [`DataProvider`] for details about how the data is split in windows.
+    ///
+    /// This has the same limitations than [`Scanner::scan_blocks`], except
+    /// that patterns that cross the boundary between two windows are found
+    /// if they are not longer than [`DataProvider::window_overlap`], and
+    /// that modules receive the first window of the data. If the provider
+    /// fails to read some data the scan fails with [`ScanError::ReadError`].
+    pub fn scan_provider<'a, P>(
+        &'a mut self,
+        provider: &'a P,
+    ) -> Result<ScanResults<'a, 'r>, ScanError>
+    where
+        P: DataProvider + Sync,
+    {
+        let mut data = ProviderData::new(provider)?;
+
+        // The first window is read only if some module needs it.
+        if self.wasm_store.data().compiled_rules.imports().next().is_some() {
+            data.read_head()?;
+        }
+
+        self.scan_impl(ScannedData::Provider(data), None)
     }
 
     /// Scans in-memory data.
@@ -332,45 +1206,272 @@ impl<'r> Scanner<'r> {
         &'a mut self,
         data: &'a [u8],
     ) -> Result<ScanResults<'a, 'r>, ScanError> {
-        self.scan_impl(ScannedData::Slice(data))
+        self.scan_impl(ScannedData::Slice(data), None)
+    }
+
+    /// Scans in-memory data asynchronously.
+    ///
+    /// While the rule conditions are being evaluated the scan yields control
+    /// to the async executor once per second, so that long scans don't block
+    /// the executor's worker threads. Notice however that other phases of
+    /// the scan, like the search for patterns or the execution of modules,
+    /// don't yield.
+    #[cfg(feature = "async")]
+    pub async fn scan_async<'a>(
+        &'a mut self,
+        data: &'a [u8],
+    ) -> Result<ScanResults<'a, 'r>, ScanError> {
+        self.scan_impl_async(ScannedData::Slice(data), None, true).await
+    }
+
+    /// Scans in-memory data using the given [`ScanOptions`].
+    ///
+    /// The options apply only to this scan, they override the settings
+    /// established with [`Scanner::set_timeout`] and
+    /// [`Scanner::max_matches_per_pattern`], but they don't modify them.
+    /// If some of the module outputs in the options is not valid, this
+    /// returns an error, and any module output previously set with
+    /// [`Scanner::set_module_output`] is discarded.
+    pub fn scan_with_options<'a>(
+        &'a mut self,
+        data: &'a [u8],
+        mut options: ScanOptions<'_>,
+    ) -> Result<ScanResults<'a, 'r>, ScanError> {
+        let module_outputs = mem::take(&mut options.module_outputs);
+        let module_outputs_raw = mem::take(&mut options.module_outputs_raw);
+
+        let result = module_outputs
+            .into_iter()
+            .try_for_each(|output| self.set_module_output(output))
+            .and_then(|_| {
+                module_outputs_raw.into_iter().try_for_each(|(name, data)| {
+                    self.set_module_output_raw(name, data)
+                })
+            });
+
+        if let Err(err) = result {
+            self.wasm_store.data_mut().user_provided_module_outputs.clear();
+            return Err(err);
+        }
+
+        self.scan_impl(ScannedData::Slice(data), Some(&options))
+    }
+
+    /// Scans in-memory data, using the given values for some global
+    /// variables.
+    ///
+    /// The values apply only to this scan, once the scan finishes the
+    /// variables recover the values they had before, as opposed to
+    /// [`Scanner::set_global`], which changes the value for all subsequent
+    /// scans.
+    pub fn scan_with_globals<'a, I>(
+        &'a mut self,
+        data: &'a [u8],
+        globals: I,
+    ) -> Result<ScanResults<'a, 'r>, ScanError>
+    where
+        I: IntoIterator<Item = (&'a str, Variable)>,
+    {
+        let mut options = ScanOptions::new();
+        for (ident, value) in globals {
+            options.globals.push((ident, value.into()));
+        }
+        self.scan_impl(ScannedData::Slice(data), Some(&options))
     }
 
     /// Sets the value of a global variable.
     ///
     /// The variable must has been previously defined by calling
     /// [`crate::Compiler::define_global`], and the type it has during the
-    /// definition must match the type of the new value (`T`).
+    /// definition must match the type of the new value (`T`). For structures
+    /// this means that the new value must have the same fields, with the
+    /// same types and in the same order.
     ///
     /// The variable will retain the new value in subsequent scans, unless this
     /// function is called again for setting a new value.
     pub fn set_global<T: TryInto<Variable>>(
         &mut self,
-        ident: &str,
-        value: T,
-    ) -> Result<&mut Self, VariableError>
+        ident: &str,
+        value: T,
+    ) -> Result<&mut Self, VariableError>
+    where
+        VariableError: From<<T as TryInto<Variable>>::Error>,
+    {
+        let variable: Variable = value.try_into()?;
+        Self::replace_global(
+            self.wasm_store.data_mut(),
+            ident,
+            variable.into(),
+        )?;
+        Ok(self)
+    }
+
+    /// Resets a global variable to the value it was given when it was
+    /// defined with [`crate::Compiler::define_global`].
+    pub fn reset_global(
+        &mut self,
+        ident: &str,
+    ) -> Result<&mut Self, VariableError> {
+        let globals = self.wasm_store.data().compiled_rules.globals();
+
+        let field = globals
+            .field_by_name(ident)
+            .ok_or_else(|| VariableError::Undefined(ident.to_string()))?;
+
+        Self::replace_global(
+            self.wasm_store.data_mut(),
+            ident,
+            field.type_value.clone(),
+        )?;
+
+        Ok(self)
+    }
+
+    /// Resets all global variables to the values they were given when they
+    /// were defined with [`crate::Compiler::define_global`].
+    pub fn reset_all_globals(&mut self) -> &mut Self {
+        let globals = self.wasm_store.data().compiled_rules.globals();
+        let ctx = self.wasm_store.data_mut();
+
+        for (ident, field) in globals.fields() {
+            Self::replace_global(ctx, ident, field.type_value.clone())
+                .unwrap();
+        }
+
+        self
+    }
+
+    /// Sets the value of a global variable, returning its previous value.
+    fn replace_global(
+        ctx: &mut ScanContext,
+        ident: &str,
+        type_value: TypeValue,
+    ) -> Result<TypeValue, VariableError> {
+        if let Some(field) = ctx.root_struct.field_by_name_mut(ident) {
+            // The new type must match the old one.
+            if type_value.eq_type(&field.type_value) {
+                Ok(mem::replace(&mut field.type_value, type_value))
+            } else {
+                Err(VariableError::InvalidType {
+                    variable: ident.to_string(),
+                    expected_type: field.type_value.ty().to_string(),
+                    actual_type: type_value.ty().to_string(),
+                })
+            }
+        } else {
+            Err(VariableError::Undefined(ident.to_string()))
+        }
+    }
+
+    /// Restores the values of global variables that were replaced with
+    /// [`Scanner::replace_global`]. Values are restored in reverse order, so
+    /// that the original value is restored even if the same variable was
+    /// replaced multiple times.
+    fn restore_globals(
+        ctx: &mut ScanContext,
+        saved_globals: Vec<(&str, TypeValue)>,
+    ) {
+        for (ident, type_value) in saved_globals.into_iter().rev() {
+            Self::replace_global(ctx, ident, type_value).unwrap();
+        }
+    }
+
+    /// Disables the rules with the given identifier.
+    ///
+    /// Disabled rules are not evaluated during the scan, therefore they
+    /// never match, and any other rule that depends on them sees them as
+    /// false. Disabling a global rule means that it doesn't prevent other
+    /// rules in the same namespace from matching. The rule is disabled in
+    /// all the namespaces where it is defined. Rules remain disabled in
+    /// subsequent scans until they are enabled again with
+    /// [`Scanner::enable_rule`] or [`Scanner::enable_all_rules`].
+    ///
+    /// Nothing happens if no rule has the given identifier.
+    pub fn disable_rule(&mut self, ident: &str) -> &mut Self {
+        self.set_rules_disabled(true, |rules, rule| {
+            rules.ident_pool().get(rule.ident_id) == Some(ident)
+        })
+    }
+
+    /// Enables the rules with the given identifier, which were previously
+    /// disabled with [`Scanner::disable_rule`], [`Scanner::disable_namespace`]
+    /// or [`Scanner::disable_tag`].
+    pub fn enable_rule(&mut self, ident: &str) -> &mut Self {
+        self.set_rules_disabled(false, |rules, rule| {
+            rules.ident_pool().get(rule.ident_id) == Some(ident)
+        })
+    }
+
+    /// Disables all the rules in the given namespace.
+    ///
+    /// See [`Scanner::disable_rule`] for details about disabled rules.
+    pub fn disable_namespace(&mut self, namespace: &str) -> &mut Self {
+        self.set_rules_disabled(true, |rules, rule| {
+            rules.ident_pool().get(rule.namespace_ident_id) == Some(namespace)
+        })
+    }
+
+    /// Enables all the rules in the given namespace.
+    pub fn enable_namespace(&mut self, namespace: &str) -> &mut Self {
+        self.set_rules_disabled(false, |rules, rule| {
+            rules.ident_pool().get(rule.namespace_ident_id) == Some(namespace)
+        })
+    }
+
+    /// Disables all the rules that have the given tag.
+    ///
+    /// See [`Scanner::disable_rule`] for details about disabled rules.
+    pub fn disable_tag(&mut self, tag: &str) -> &mut Self {
+        self.set_rules_disabled(true, |rules, rule| {
+            rule.tags.iter().any(|t| rules.ident_pool().get(*t) == Some(tag))
+        })
+    }
+
+    /// Enables all the rules that have the given tag.
+    pub fn enable_tag(&mut self, tag: &str) -> &mut Self {
+        self.set_rules_disabled(false, |rules, rule| {
+            rule.tags.iter().any(|t| rules.ident_pool().get(*t) == Some(tag))
+        })
+    }
+
+    /// Enables all the rules that were previously disabled.
+    pub fn enable_all_rules(&mut self) -> &mut Self {
+        self.set_rules_disabled(false, |_, _| true)
+    }
+
+    /// Sets or clears the bits in the disabled rules bitmap that correspond
+    /// to the rules for which `predicate` returns true.
+    fn set_rules_disabled<P>(
+        &mut self,
+        disabled: bool,
+        predicate: P,
+    ) -> &mut Self
     where
-        VariableError: From<<T as TryInto<Variable>>::Error>,
+        P: Fn(&Rules, &RuleInfo) -> bool,
     {
-        let ctx = self.wasm_store.data_mut();
+        let ctx = self.wasm_store.data();
+        let rules = ctx.compiled_rules;
+        let main_memory = ctx.main_memory.unwrap();
 
-        if let Some(field) = ctx.root_struct.field_by_name_mut(ident) {
-            let variable: Variable = value.try_into()?;
-            let type_value: TypeValue = variable.into();
-            // The new type must match the old one.
-            if type_value.eq_type(&field.type_value) {
-                field.type_value = type_value;
-            } else {
-                return Err(VariableError::InvalidType {
-                    variable: ident.to_string(),
-                    expected_type: field.type_value.ty().to_string(),
-                    actual_type: type_value.ty().to_string(),
-                });
+        // The bitmap of disabled rules is located right after the bitmaps
+        // of matching rules and matching patterns.
+        let base = MATCHING_RULES_BITMAP_BASE as usize
+            + rules.num_rules().div_ceil(8)
+            + rules.num_patterns().div_ceil(8);
+
+        let mem = main_memory.data_mut(self.wasm_store.as_context_mut());
+
+        let bitmap = BitSlice::<_, Lsb0>::from_slice_mut(
+            &mut mem[base..base + rules.num_rules().div_ceil(8)],
+        );
+
+        for (rule_id, rule) in rules.rules().iter().enumerate() {
+            if predicate(rules, rule) {
+                bitmap.set(rule_id, disabled);
             }
-        } else {
-            return Err(VariableError::Undefined(ident.to_string()));
         }
 
-        Ok(self)
+        self
     }
 
     /// Sets the output data for a YARA module.
@@ -477,16 +1578,171 @@ impl<'r> Scanner<'r> {
             })?,
         )
     }
+
+    /// Returns profiling information about the compilation of the rules
+    /// used by this scanner, and about all the scans performed with it.
+    ///
+    /// Only available when the `rules-profiling` feature is enabled.
+    #[cfg(feature = "rules-profiling")]
+    pub fn profiling_data(&self) -> ProfilingData<'r> {
+        let ctx = self.wasm_store.data();
+        let rules = ctx.compiled_rules;
+        let ident = |id| rules.ident_pool().get(id).unwrap();
+
+        let mut modules: Vec<_> = ctx
+            .time_spent_in_module
+            .iter()
+            .map(|(module, time)| (*module, *time))
+            .collect();
+
+        modules.sort_by_key(|(_, time)| cmp::Reverse(*time));
+
+        // Identical patterns are shared by all the rules where they appear,
+        // and the time spent verifying them is split evenly among those
+        // rules, so that it is not counted more than once.
+        let mut pattern_refs: FxHashMap<PatternId, u32> = FxHashMap::default();
+
+        for rule in rules.rules() {
+            for (_, pattern_id) in rule.patterns.iter() {
+                *pattern_refs.entry(*pattern_id).or_default() += 1;
+            }
+        }
+
+        let mut rule_profiles = Vec::new();
+        let mut patterns = Vec::new();
+
+        for (rule_id, rule) in rules.rules().iter().enumerate() {
+            let mut pattern_time = Duration::default();
+
+            for (ident_id, pattern_id) in rule.patterns.iter() {
+                if let Some(time) = ctx.time_spent_in_pattern.get(pattern_id) {
+                    let time = *time / pattern_refs[pattern_id];
+                    pattern_time += time;
+                    patterns.push(PatternProfile {
+                        namespace: ident(rule.namespace_ident_id),
+                        rule: ident(rule.ident_id),
+                        pattern: ident(*ident_id),
+                        time,
+                    });
+                }
+            }
+
+            let condition_time = ctx
+                .time_spent_in_rule
+                .get(&RuleId::from(rule_id))
+                .cloned()
+                .unwrap_or_default();
+
+            // Rules that were never evaluated are not included.
+            if !condition_time.is_zero() || !pattern_time.is_zero() {
+                rule_profiles.push(RuleProfile {
+                    namespace: ident(rule.namespace_ident_id),
+                    rule: ident(rule.ident_id),
+                    condition_time,
+                    pattern_time,
+                });
+            }
+        }
+
+        rule_profiles.sort_by_key(|r| cmp::Reverse(r.total_time()));
+        patterns.sort_by_key(|p| cmp::Reverse(p.time));
+
+        ProfilingData {
+            compilation: rules.compilation_profile().to_vec(),
+            modules,
+            rules: rule_profiles,
+            patterns,
+        }
+    }
 }
 
 impl<'r> Scanner<'r> {
+    /// Starts the heartbeat thread, if not previously started. The heartbeat
+    /// thread increments the WASM engine epoch and HEARTBEAT_COUNTER every
+    /// second. There's a single instance of this thread, independently of
+    /// the number of concurrent scans.
+    fn start_heartbeat() {
+        INIT_HEARTBEAT.call_once(|| {
+            thread::spawn(|| loop {
+                thread::sleep(Duration::from_secs(1));
+                ENGINE.increment_epoch();
+                HEARTBEAT_COUNTER
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| {
+                        Some(x + 1)
+                    })
+                    .unwrap();
+            });
+        });
+    }
+
     fn scan_impl<'a>(
         &'a mut self,
         data: ScannedData<'a>,
+        options: Option<&ScanOptions>,
+    ) -> Result<ScanResults<'a, 'r>, ScanError> {
+        block_on(self.scan_impl_async(data, options, false))
+    }
+
+    async fn scan_impl_async<'a>(
+        &'a mut self,
+        mut data: ScannedData<'a>,
+        options: Option<&ScanOptions<'_>>,
+        yield_to_executor: bool,
     ) -> Result<ScanResults<'a, 'r>, ScanError> {
+        // If the scan was cancelled before it started, there's nothing to
+        // do.
+        if self.wasm_store.data().cancelled.swap(false, Ordering::Relaxed) {
+            return Err(ScanError::Cancelled);
+        }
+
+        // The main memory is created before the memory limit can be set, so
+        // its current size must be checked against the limit here.
+        {
+            let ctx = self.wasm_store.data();
+            if let Some(max_memory) = ctx.memory_limiter.max_memory {
+                let main_memory = ctx.main_memory.unwrap();
+                if main_memory.data_size(self.wasm_store.as_context())
+                    > max_memory
+                {
+                    return Err(ScanError::OutOfMemory);
+                }
+            }
+        }
+
+        // Set the values of global variables that apply only to this scan,
+        // saving the previous values for restoring them later.
+        let mut saved_globals = Vec::new();
+
+        for (ident, type_value) in options.iter().flat_map(|o| &o.globals) {
+            let ctx = self.wasm_store.data_mut();
+            match Self::replace_global(ctx, ident, type_value.clone()) {
+                Ok(prev) => saved_globals.push((*ident, prev)),
+                Err(err) => {
+                    Self::restore_globals(ctx, saved_globals);
+                    return Err(err.into());
+                }
+            }
+        }
+
         // Clear information about matches found in a previous scan, if any.
         self.reset();
 
+        // The timeout in the scan options takes precedence over the one
+        // set for the scanner.
+        let timeout = options.and_then(|o| o.timeout).or(self.timeout);
+
+        // If the scan options specify a maximum number of matches per
+        // pattern, it is used only for this scan. The previous value is
+        // restored once the scan finishes.
+        let prev_max_matches_per_pattern =
+            options.and_then(|o| o.max_matches_per_pattern).map(|n| {
+                let pattern_matches =
+                    &mut self.wasm_store.data_mut().pattern_matches;
+                let prev = pattern_matches.get_max_matches_per_pattern();
+                pattern_matches.max_matches_per_pattern(n);
+                prev
+            });
+
         // Timeout in seconds. This is either the value provided by the user or
         // 315.360.000 which is the number of seconds in a year. Using u64::MAX
         // doesn't work because this value is added to the current epoch, and
@@ -495,66 +1751,69 @@ impl<'r> Scanner<'r> {
         // the user specifies a value larger than 315.360.000 we limit it to
         // 315.360.000 anyway. One year should be enough, I hope you don't plan
         // to run a YARA scan that takes longer.
-        let timeout_secs =
-            self.timeout.map_or(Self::DEFAULT_SCAN_TIMEOUT, |t| {
-                cmp::min(
-                    t.as_secs_f32().ceil() as u64,
-                    Self::DEFAULT_SCAN_TIMEOUT,
-                )
-            });
+        let timeout_secs = timeout.map_or(Self::DEFAULT_SCAN_TIMEOUT, |t| {
+            cmp::min(t.as_secs_f32().ceil() as u64, Self::DEFAULT_SCAN_TIMEOUT)
+        });
 
-        // Sets the deadline for the WASM store. The WASM main function will
-        // abort if the deadline is reached while the function is being
-        // executed.
-        self.wasm_store.set_epoch_deadline(timeout_secs);
-        self.wasm_store
-            .epoch_deadline_callback(|_| Err(ScanError::Timeout.into()));
+        let deadline =
+            HEARTBEAT_COUNTER.load(Ordering::Relaxed) + timeout_secs;
+
+        let cancelled = self.wasm_store.data().cancelled.clone();
+
+        // Sets the deadline for the WASM store. The epoch deadline callback
+        // is invoked every time the heartbeat thread increments the epoch,
+        // and aborts the WASM main function if the scan was cancelled or the
+        // deadline was reached.
+        self.wasm_store.set_epoch_deadline(1);
+        self.wasm_store.epoch_deadline_callback(move |_| {
+            if cancelled.load(Ordering::Relaxed) {
+                Err(ScanError::Cancelled.into())
+            } else if HEARTBEAT_COUNTER.load(Ordering::Relaxed) >= deadline {
+                Err(ScanError::Timeout.into())
+            } else if yield_to_executor {
+                // In asynchronous scans the WASM main function yields control
+                // to the async executor every time the epoch is incremented.
+                Ok(wasmtime::UpdateDeadline::Yield(1))
+            } else {
+                Ok(wasmtime::UpdateDeadline::Continue(1))
+            }
+        });
 
         // If the user specified some timeout, start the heartbeat thread, if
-        // not previously started. The heartbeat thread increments the WASM
-        // engine epoch and HEARTBEAT_COUNTER every second. There's a single
-        // instance of this thread, independently of the number of concurrent
-        // scans.
-        if self.timeout.is_some() {
-            INIT_HEARTBEAT.call_once(|| {
-                thread::spawn(|| loop {
-                    thread::sleep(Duration::from_secs(1));
-                    ENGINE.increment_epoch();
-                    HEARTBEAT_COUNTER
-                        .fetch_update(
-                            Ordering::SeqCst,
-                            Ordering::SeqCst,
-                            |x| Some(x + 1),
-                        )
-                        .unwrap();
-                });
-            });
+        // not previously started. Asynchronous scans always need the thread,
+        // as the epoch determines when the WASM code yields.
+        if timeout.is_some() || yield_to_executor {
+            Self::start_heartbeat();
         }
 
-        // Set the global variable `filesize` to the size of the scanned data.
-        self.filesize
-            .set(
-                self.wasm_store.as_context_mut(),
-                Val::I64(data.as_ref().len() as i64),
-            )
-            .unwrap();
-
         let ctx = self.wasm_store.data_mut();
 
-        ctx.deadline =
-            HEARTBEAT_COUNTER.load(Ordering::Relaxed) + timeout_secs;
-        ctx.scanned_data = data.as_ref().as_ptr();
-        ctx.scanned_data_len = data.as_ref().len();
+        ctx.deadline = deadline;
+        ctx.offset_translator =
+            options.and_then(|o| o.offset_translator.clone());
+        ctx.stats = ScanStats::default();
+        ctx.scanned_data = data.as_ref().into();
 
         // Free all runtime objects left around by previous scans.
         ctx.runtime_objects.clear();
 
         for module_name in ctx.compiled_rules.imports() {
-            // Lookup the module in the list of built-in modules.
-            let module =
-                modules::BUILTIN_MODULES.get(module_name).unwrap_or_else(
-                    || panic!("module `{}` not found", module_name),
-                );
+            // Lookup the module in the list of built-in modules. This
+            // fails only if the rules were compiled with a version of YARA-X
+            // that had a module that is not present in this one.
+            let module = match modules::BUILTIN_MODULES.get(module_name) {
+                Some(module) => module,
+                None => {
+                    ctx.scanned_data = ScanPtr::null();
+                    if let Some(n) = prev_max_matches_per_pattern {
+                        ctx.pattern_matches.max_matches_per_pattern(n);
+                    }
+                    Self::restore_globals(ctx, saved_globals);
+                    return Err(ScanError::UnknownModule {
+                        module: module_name.to_string(),
+                    });
+                }
+            };
 
             let root_struct_name = module.root_struct_descriptor.full_name();
 
@@ -567,7 +1826,20 @@ impl<'r> Scanner<'r> {
             {
                 Some(output)
             } else {
-                module.main_fn.map(|main_fn| main_fn(data.as_ref()))
+                #[cfg(feature = "rules-profiling")]
+                let start = Instant::now();
+
+                let output =
+                    module.main_fn.map(|main_fn| main_fn(data.module_data()));
+
+                #[cfg(feature = "rules-profiling")]
+                {
+                    *ctx.time_spent_in_module
+                        .entry(module_name)
+                        .or_default() += Instant::elapsed(&start);
+                }
+
+                output
             };
 
             if let Some(module_output) = &module_output {
@@ -594,19 +1866,19 @@ impl<'r> Scanner<'r> {
                 );
             }
 
-            // When constant folding is enabled we don't need to generate
-            // structure fields for enums. This is because during the
-            // optimization process symbols like MyEnum.ENUM_ITEM are resolved
-            // to their constant values at compile time. In other words, the
-            // compiler determines that MyEnum.ENUM_ITEM is equal to some value
-            // X, and uses that value in the generated code.
+            // When the rules were compiled with constant folding we don't
+            // need to generate structure fields for enums. This is because
+            // during the optimization process symbols like MyEnum.ENUM_ITEM
+            // are resolved to their constant values at compile time. In other
+            // words, the compiler determines that MyEnum.ENUM_ITEM is equal
+            // to some value X, and uses that value in the generated code.
             //
             // However, without constant folding, enums are treated as any
-            // other field in a struct, and their values are determined at scan
-            // time. For that reason these fields must be generated for enums
-            // when constant folding is disabled.
+            // other field in a struct, and their values are determined at
+            // scan time. For that reason these fields must be generated for
+            // enums when constant folding is disabled (see `OptLevel`).
             let generate_fields_for_enums =
-                !cfg!(feature = "constant-folding");
+                !ctx.compiled_rules.constant_folding();
 
             let module_struct = Struct::from_proto_descriptor_and_msg(
                 &module.root_struct_descriptor,
@@ -624,28 +1896,132 @@ impl<'r> Scanner<'r> {
             // with the new data structure.
             ctx.root_struct.add_field(
                 module_name,
-                TypeValue::Struct(Rc::new(module_struct)),
+                TypeValue::Struct(Arc::new(module_struct)),
             );
         }
 
+        // When the data is split into blocks, the patterns are searched in
+        // each block before evaluating the conditions. The main function
+        // calls ScanContext::search_for_patterns again, but at that point
+        // the scanned data is empty, and no more matches are found. The
+        // unconfirmed matches are discarded before searching in each block,
+        // so that the fragments of a chained pattern are never joined across
+        // blocks.
+        let mut search_result = Ok(());
+
+        if let ScannedData::Blocks(blocks) | ScannedData::Regions(blocks) =
+            &data
+        {
+            let ctx = self.wasm_store.data_mut();
+
+            for (base, block) in blocks {
+                ctx.scanned_data = block.as_ref().into();
+                ctx.scanned_data_base = *base;
+                ctx.unconfirmed_matches.clear();
+                search_result = ctx.search_for_patterns();
+                if search_result.is_err() {
+                    break;
+                }
+            }
+
+            ctx.scanned_data = ScanPtr::null();
+            ctx.scanned_data_base = 0;
+        }
+
+        // When the data comes from a provider, the patterns are searched
+        // window by window, as with blocks. Windows in the same region
+        // overlap, so unconfirmed matches are discarded only when a new
+        // region starts. Once the search finishes, the fragments of data
+        // that contain matches are read again from the provider, so that the
+        // matching data is available in the results.
+        if let ScannedData::Provider(provider)
+        | ScannedData::Process(provider) = &mut data
+        {
+            let ctx = self.wasm_store.data_mut();
+
+            search_result =
+                provider.for_each_window(|base, window, new_region| {
+                    ctx.scanned_data = window.into();
+                    ctx.scanned_data_base = base;
+                    if new_region {
+                        ctx.unconfirmed_matches.clear();
+                    }
+                    ctx.search_for_patterns()
+                });
+
+            ctx.scanned_data = ScanPtr::null();
+            ctx.scanned_data_base = 0;
+
+            if search_result.is_ok() {
+                search_result = provider.fetch(ctx.pattern_matches.ranges());
+            }
+        }
+
+        // Set the global variable `filesize` to the size of the scanned data.
+        // This is done once the search for patterns in blocks and regions
+        // finishes, as regions that can't be read entirely are truncated.
+        self.filesize
+            .set(self.wasm_store.as_context_mut(), Val::I64(data.len() as i64))
+            .unwrap();
+
+        self.wasm_store.data_mut().stats.scanned_bytes = data.scanned_bytes();
+
+        // The pointer is taken once the data is not going to be modified
+        // anymore, `notify_rule_match` uses it while the conditions are
+        // evaluated.
+        self.wasm_store.data_mut().scanned_data_ref =
+            ScanPtr::new(NonNull::from(&data).cast::<ScannedData<'static>>());
+
         // Invoke the main function, which evaluates the rules' conditions. It
         // calls ScanContext::search_for_patterns (which does the Aho-Corasick
         // scanning) only if necessary.
         //
         // This will return Err(ScanError::Timeout), when the scan timeout is
-        // reached while WASM code is being executed. If the timeout occurs
-        // while ScanContext::search_for_patterns is being executed, the result
-        // will be Ok(1). If the scan completes successfully the result is
-        // Ok(0).`
-        let func_result =
-            self.wasm_main_func.call(self.wasm_store.as_context_mut(), ());
+        // reached while WASM code is being executed, or
+        // Err(ScanError::Cancelled) if the scan is cancelled. If the timeout
+        // or the cancellation occurs while ScanContext::search_for_patterns
+        // is being executed, the result will be Ok(1). If the scan completes
+        // successfully the result is Ok(0).
+        let pattern_search_time =
+            self.wasm_store.data().stats.pattern_search_time;
+        let main_start = Instant::now();
+
+        let func_result = match search_result {
+            #[cfg(not(feature = "async"))]
+            Ok(()) => {
+                self.wasm_main_func.call(self.wasm_store.as_context_mut(), ())
+            }
+            #[cfg(feature = "async")]
+            Ok(()) => {
+                self.wasm_main_func
+                    .call_async(self.wasm_store.as_context_mut(), ())
+                    .await
+            }
+            Err(err) => Err(err.into()),
+        };
 
         let ctx = self.wasm_store.data_mut();
 
+        // A cancellation affects only the scan that was in progress.
+        let cancelled = ctx.cancelled.swap(false, Ordering::Relaxed);
+
+        // The main function may call `search_for_patterns`, the time spent
+        // searching for patterns is not part of the time spent evaluating
+        // conditions.
+        ctx.stats.condition_eval_time = Instant::elapsed(&main_start)
+            .saturating_sub(
+                ctx.stats.pattern_search_time - pattern_search_time,
+            );
+
+        if let Some(n) = prev_max_matches_per_pattern {
+            ctx.pattern_matches.max_matches_per_pattern(n);
+        }
+
+        Self::restore_globals(ctx, saved_globals);
+
         // Set pointer to data back to nil. This means that accessing
         // `scanned_data` from within `ScanResults` is not possible.
-        ctx.scanned_data = null();
-        ctx.scanned_data_len = 0;
+        ctx.scanned_data = ScanPtr::null();
 
         // Clear the value of `current_struct` as it may contain a reference
         // to some struct.
@@ -654,27 +2030,62 @@ impl<'r> Scanner<'r> {
         // Move all the in `global_matching_rules` to `private_matching_rules`
         // and `non_private_matching_rules`, leaving `global_matching_rules`
         // empty.
+        let mut global_rules = Vec::new();
+
         for rules in ctx.global_matching_rules.values_mut() {
             for rule_id in rules.drain(0..) {
                 if ctx.compiled_rules.get(rule_id).is_private {
                     ctx.private_matching_rules.push(rule_id);
                 } else {
                     ctx.non_private_matching_rules.push(rule_id);
+                    global_rules.push(rule_id);
                 }
             }
         }
 
-        match func_result {
-            Ok(0) => Ok(ScanResults::new(self.wasm_store.data(), data)),
+        // Rules are evaluated namespace by namespace, with global rules
+        // first, so they can match in a different order than the one in
+        // which they were declared. Matching rules are sorted by RuleId,
+        // which is the declaration order.
+        ctx.private_matching_rules.sort_unstable();
+        ctx.non_private_matching_rules.sort_unstable();
+
+        let result = match func_result {
+            Ok(0) => Ok(()),
+            Ok(1) if cancelled => Err(ScanError::Cancelled),
             Ok(1) => Err(ScanError::Timeout),
             Ok(_) => unreachable!(),
             Err(err) if err.is::<ScanError>() => {
                 Err(err.downcast::<ScanError>().unwrap())
             }
-            Err(err) => panic!(
-                "unexpected error while executing WASM main function: {}",
-                err
-            ),
+            Err(err) => Err(ScanError::WasmError { err }),
+        };
+
+        // When partial results are enabled, a scan that timed out or was
+        // cancelled returns the rules that matched so far.
+        let truncated = self.partial_results
+            && matches!(
+                result,
+                Err(ScanError::Timeout | ScanError::Cancelled)
+            );
+
+        // Non-private global rules are notified only now that we know that
+        // they actually matched.
+        if result.is_ok() || truncated {
+            global_rules.sort_unstable();
+            for rule_id in global_rules {
+                ctx.notify_rule_match(rule_id);
+            }
+        }
+
+        ctx.scanned_data_ref = ScanPtr::null();
+
+        match result {
+            Ok(()) => Ok(ScanResults::new(&self.wasm_store, data, false)),
+            Err(_) if truncated => {
+                Ok(ScanResults::new(&self.wasm_store, data, true))
+            }
+            Err(err) => Err(err),
         }
     }
 
@@ -692,6 +2103,14 @@ impl<'r> Scanner<'r> {
         // Clear the unconfirmed matches.
         ctx.unconfirmed_matches.clear();
 
+        // Clear the global rules that didn't match.
+        ctx.failed_global_rules.clear();
+
+        // Forget about the rules known to match in the previous scan.
+        if let Some(early_termination) = &mut ctx.early_termination {
+            early_termination.reset();
+        }
+
         // If some pattern or rule matched, clear the matches. Notice that a
         // rule may match without any pattern being matched, because there
         // are rules without patterns, or that match if the pattern is not
@@ -724,31 +2143,160 @@ impl<'r> Scanner<'r> {
             // Set to zero all bits in the bitmap.
             bitmap.fill(false);
         }
+
+        // If the memory policy says so, release all the memory retained
+        // from previous scans.
+        self.scans_since_trim += 1;
+
+        if self.trim_every.is_some_and(|n| self.scans_since_trim > n) {
+            let ctx = self.wasm_store.data_mut();
+            ctx.pattern_matches.release();
+            ctx.unconfirmed_matches = FxHashMap::default();
+            ctx.limit_reached = FxHashSet::default();
+            ctx.non_private_matching_rules = Vec::new();
+            ctx.private_matching_rules = Vec::new();
+            ctx.failed_global_rules = Vec::new();
+            ctx.runtime_objects = IndexMap::new();
+            ctx.regexp_cache.get_mut().clear();
+            ctx.regexp_cache.get_mut().shrink_to_fit();
+            self.scans_since_trim = 1;
+        }
     }
 }
 
+/// Statistics about a scan operation.
+///
+/// Returned by [`ScanResults::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Time spent searching for patterns in the scanned data, including the
+    /// verification of the atoms found.
+    pub pattern_search_time: Duration,
+    /// Time spent evaluating the rules' conditions, excluding the time spent
+    /// searching for patterns.
+    pub condition_eval_time: Duration,
+    /// Number of atoms found in the scanned data. Each atom found is a
+    /// potential match that needs to be verified.
+    pub atom_matches: usize,
+    /// Number of pattern matches recorded, for all patterns.
+    pub pattern_matches: usize,
+    /// Number of bytes scanned.
+    pub scanned_bytes: usize,
+}
+
 /// Results of a scan operation.
 ///
 /// Allows iterating over both the matching and non-matching rules.
 pub struct ScanResults<'a, 'r> {
     ctx: &'a ScanContext<'r>,
+    main_memory: &'a [u8],
     data: ScannedData<'a>,
+    truncated: bool,
 }
 
 impl<'a, 'r> ScanResults<'a, 'r> {
-    fn new(ctx: &'a ScanContext<'r>, data: ScannedData<'a>) -> Self {
-        Self { ctx, data }
+    fn new(
+        wasm_store: &'a Store<ScanContext<'r>>,
+        data: ScannedData<'a>,
+        truncated: bool,
+    ) -> Self {
+        let ctx = wasm_store.data();
+        let main_memory = ctx.main_memory.unwrap().data(wasm_store);
+        Self { ctx, main_memory, data, truncated }
+    }
+
+    /// Returns true if the scan was interrupted by a timeout or a
+    /// cancellation before all rules were evaluated.
+    ///
+    /// This only happens when partial results are enabled with
+    /// [`Scanner::partial_results`]. Truncated results contain only the
+    /// rules that matched before the scan was interrupted, rules that
+    /// were not evaluated are reported as non-matching.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
     }
 
-    /// Returns an iterator that yields the matching rules in arbitrary order.
+    /// Returns an iterator that yields the matching rules in the order in
+    /// which they were declared.
+    ///
+    /// Rules are declared in the order in which they were added to the
+    /// compiler, this order is the same across scans and across runs.
     pub fn matching_rules(&'a self) -> MatchingRules<'a, 'r> {
         MatchingRules::new(self.ctx, &self.data)
     }
 
+    /// Returns the matching rules sorted by namespace.
+    ///
+    /// Namespaces are sorted alphabetically, and the rules within each
+    /// namespace appear in the order in which they were declared.
+    pub fn matching_rules_sorted_by_namespace(&'a self) -> Vec<Rule<'a, 'r>> {
+        let mut rules: Vec<_> = self.matching_rules().collect();
+        rules.sort_by_key(|rule| rule.namespace());
+        rules
+    }
+
+    /// Returns statistics about the scan.
+    pub fn stats(&self) -> ScanStats {
+        ScanStats {
+            pattern_matches: self.ctx.pattern_matches.num_matches(),
+            ..self.ctx.stats.clone()
+        }
+    }
+
+    /// Returns the global rules that didn't match.
+    ///
+    /// When a global rule doesn't match, the remaining rules in the same
+    /// namespace are not evaluated and none of them match. This function
+    /// returns the global rule that caused this in each namespace, if any,
+    /// which is useful for understanding why some rule that was expected to
+    /// match didn't. Private global rules are also included.
+    pub fn failed_global_rules(
+        &'a self,
+    ) -> impl ExactSizeIterator<Item = Rule<'a, 'r>> + 'a {
+        let rules = self.ctx.compiled_rules;
+        self.ctx.failed_global_rules.iter().map(move |rule_id| Rule {
+            rule_id: *rule_id,
+            rule_info: rules.get(*rule_id),
+            rules,
+            ctx: self.ctx,
+            data: &self.data,
+        })
+    }
+
     /// Returns an iterator that yields the non-matching rules in arbitrary
     /// order.
     pub fn non_matching_rules(&'a self) -> NonMatchingRules<'a, 'r> {
-        NonMatchingRules::new(self.ctx, &self.data)
+        NonMatchingRules::new(self.ctx, self.main_memory, &self.data)
+    }
+
+    /// Returns the memory regions that were scanned by
+    /// [`Scanner::scan_process`] or [`Scanner::scan_memory_regions`], in
+    /// the order in which they were scanned.
+    ///
+    /// With [`Scanner::scan_blocks`] each block is returned as a region
+    /// whose base is the block's base offset. The result is empty for any
+    /// other kind of scan.
+    pub fn memory_regions(&self) -> Vec<MemoryRegion> {
+        match &self.data {
+            ScannedData::Blocks(blocks) | ScannedData::Regions(blocks) => {
+                blocks
+                    .iter()
+                    .map(|(base, block)| MemoryRegion {
+                        base: *base as u64,
+                        size: block.len(),
+                    })
+                    .collect()
+            }
+            ScannedData::Process(process) => process
+                .regions
+                .iter()
+                .map(|region| MemoryRegion {
+                    base: region.start as u64,
+                    size: region.len(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
     }
 
     /// Returns the protobuf produced by a YARA module after processing the
@@ -783,11 +2331,33 @@ pub struct MatchingRules<'a, 'r> {
     ctx: &'a ScanContext<'r>,
     data: &'a ScannedData<'a>,
     iterator: Iter<'a, RuleId>,
+    private_iterator: Iter<'a, RuleId>,
 }
 
 impl<'a, 'r> MatchingRules<'a, 'r> {
     fn new(ctx: &'a ScanContext<'r>, data: &'a ScannedData<'a>) -> Self {
-        Self { ctx, data, iterator: ctx.non_private_matching_rules.iter() }
+        Self {
+            ctx,
+            data,
+            iterator: ctx.non_private_matching_rules.iter(),
+            private_iterator: [].iter(),
+        }
+    }
+
+    /// Includes private rules in the results.
+    ///
+    /// By default private rules are not returned by this iterator.
+    pub fn include_private(mut self) -> Self {
+        self.private_iterator = self.ctx.private_matching_rules.iter();
+        self
+    }
+
+    /// Returns only the matching rules that have the given tag.
+    pub fn with_tag(
+        self,
+        tag: &'a str,
+    ) -> impl Iterator<Item = Rule<'a, 'r>> + 'a {
+        self.filter(move |rule| rule.tags().any(|t| t == tag))
     }
 }
 
@@ -795,17 +2365,36 @@ impl<'a, 'r> Iterator for MatchingRules<'a, 'r> {
     type Item = Rule<'a, 'r>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let rule_id = *self.iterator.next()?;
+        // Both `iterator` and `private_iterator` are sorted by rule ID, so
+        // the rules are returned in the order in which they were declared.
+        let rule_id = *match (
+            self.iterator.as_slice().first(),
+            self.private_iterator.as_slice().first(),
+        ) {
+            (Some(rule_id), Some(private_rule_id))
+                if private_rule_id < rule_id =>
+            {
+                self.private_iterator.next()
+            }
+            (Some(_), _) => self.iterator.next(),
+            (None, _) => self.private_iterator.next(),
+        }?;
         let rules = self.ctx.compiled_rules;
         let rule_info = rules.get(rule_id);
-        Some(Rule { rule_info, rules, ctx: self.ctx, data: self.data })
+        Some(Rule {
+            rule_id,
+            rule_info,
+            rules,
+            ctx: self.ctx,
+            data: self.data,
+        })
     }
 }
 
 impl<'a, 'r> ExactSizeIterator for MatchingRules<'a, 'r> {
     #[inline]
     fn len(&self) -> usize {
-        self.iterator.len()
+        self.iterator.len() + self.private_iterator.len()
     }
 }
 
@@ -814,14 +2403,17 @@ pub struct NonMatchingRules<'a, 'r> {
     ctx: &'a ScanContext<'r>,
     data: &'a ScannedData<'a>,
     iterator: bitvec::slice::IterZeros<'a, u8, Lsb0>,
+    include_private: bool,
     len: usize,
 }
 
 impl<'a, 'r> NonMatchingRules<'a, 'r> {
-    fn new(ctx: &'a ScanContext<'r>, data: &'a ScannedData<'a>) -> Self {
+    fn new(
+        ctx: &'a ScanContext<'r>,
+        main_memory: &'a [u8],
+        data: &'a ScannedData<'a>,
+    ) -> Self {
         let num_rules = ctx.compiled_rules.num_rules();
-        let main_memory =
-            ctx.main_memory.unwrap().data(unsafe { ctx.wasm_store.as_ref() });
 
         let base = MATCHING_RULES_BITMAP_BASE as usize;
 
@@ -837,17 +2429,28 @@ impl<'a, 'r> NonMatchingRules<'a, 'r> {
         // the BitSlice has exactly as many bits as existing rules.
         let matching_rules_bitmap = &matching_rules_bitmap[0..num_rules];
 
-        Self {
-            ctx,
-            data,
-            iterator: matching_rules_bitmap.iter_zeros(),
-            // The number of non-matching rules is the total number of rules
-            // minus the number of matching rules, both private and
-            // non-private.
-            len: ctx.compiled_rules.num_rules()
-                - ctx.private_matching_rules.len()
-                - ctx.non_private_matching_rules.len(),
+        let iterator = matching_rules_bitmap.iter_zeros();
+
+        // The number of non-matching rules returned by default doesn't
+        // include the private ones.
+        let len = iterator
+            .filter(|rule_id| {
+                !ctx.compiled_rules.get(RuleId::from(*rule_id)).is_private
+            })
+            .count();
+
+        Self { ctx, data, iterator, include_private: false, len }
+    }
+
+    /// Includes private rules in the results.
+    ///
+    /// By default private rules are not returned by this iterator.
+    pub fn include_private(mut self) -> Self {
+        if !self.include_private {
+            self.include_private = true;
+            self.len = self.iterator.len();
         }
+        self
     }
 }
 
@@ -856,14 +2459,16 @@ impl<'a, 'r> Iterator for NonMatchingRules<'a, 'r> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            self.len = self.len.saturating_sub(1);
             let rule_id = RuleId::from(self.iterator.next()?);
             let rules = self.ctx.compiled_rules;
             let rule_info = rules.get(rule_id);
-            // Private rules are not returned, if the current rule is private
-            // keep in the loop and try with the next one.
-            if !rule_info.is_private {
+            // Private rules are not returned unless `include_private` was
+            // used, if the current rule is private keep in the loop and try
+            // with the next one.
+            if self.include_private || !rule_info.is_private {
+                self.len -= 1;
                 return Some(Rule {
+                    rule_id,
                     rule_info,
                     rules,
                     ctx: self.ctx,
@@ -915,10 +2520,16 @@ pub struct Rule<'a, 'r> {
     ctx: &'a ScanContext<'r>,
     data: &'a ScannedData<'a>,
     pub(crate) rules: &'r Rules,
+    pub(crate) rule_id: RuleId,
     pub(crate) rule_info: &'r RuleInfo,
 }
 
 impl<'a, 'r> Rule<'a, 'r> {
+    /// Returns the rule's ID. See [`RuleId`].
+    pub fn id(&self) -> RuleId {
+        self.rule_id
+    }
+
     /// Returns the rule's name.
     pub fn identifier(&self) -> &'r str {
         self.rules.ident_pool().get(self.rule_info.ident_id).unwrap()
@@ -929,6 +2540,36 @@ impl<'a, 'r> Rule<'a, 'r> {
         self.rules.ident_pool().get(self.rule_info.namespace_ident_id).unwrap()
     }
 
+    /// Returns the origin of the source code that contained the rule, as
+    /// set with [`yara_x_parser::SourceCode::with_origin`].
+    ///
+    /// Returns `None` if no origin was set for the source code.
+    pub fn origin(&self) -> Option<&'r str> {
+        self.rule_info
+            .origin
+            .map(|id| self.rules.lit_pool().get_str(id).unwrap())
+    }
+
+    /// Returns true if the rule is private.
+    pub fn is_private(&self) -> bool {
+        self.rule_info.is_private
+    }
+
+    /// Returns true if the rule is global.
+    pub fn is_global(&self) -> bool {
+        self.rule_info.is_global
+    }
+
+    /// Returns the tags associated to this rule.
+    pub fn tags(&self) -> Tags<'r> {
+        Tags::new(self.rules, self.rule_info)
+    }
+
+    /// Returns the metadata associated to this rule.
+    pub fn metadata(&self) -> Metadata<'r> {
+        Metadata::new(self.rules, self.rule_info)
+    }
+
     /// Returns the patterns defined by this rule.
     pub fn patterns(&self) -> Patterns<'a, 'r> {
         Patterns {
@@ -940,6 +2581,103 @@ impl<'a, 'r> Rule<'a, 'r> {
     }
 }
 
+/// An iterator that returns the tags of a rule.
+pub struct Tags<'r> {
+    rules: &'r Rules,
+    iterator: Iter<'r, IdentId>,
+    len: usize,
+}
+
+impl<'r> Tags<'r> {
+    pub(crate) fn new(rules: &'r Rules, rule_info: &'r RuleInfo) -> Self {
+        Self {
+            rules,
+            iterator: rule_info.tags.iter(),
+            len: rule_info.tags.len(),
+        }
+    }
+}
+
+impl<'r> Iterator for Tags<'r> {
+    type Item = &'r str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ident_id = self.iterator.next()?;
+        Some(self.rules.ident_pool().get(*ident_id).unwrap())
+    }
+}
+
+impl<'r> ExactSizeIterator for Tags<'r> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Value of a metadata entry in a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaValue<'r> {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    String(&'r str),
+    /// A string that is not valid UTF-8.
+    Bytes(&'r BStr),
+}
+
+/// An iterator that returns the metadata entries of a rule as
+/// `(identifier, value)` pairs.
+///
+/// Entries are returned in the same order in which they appear in the rule.
+/// Notice that the same identifier can appear multiple times.
+pub struct Metadata<'r> {
+    rules: &'r Rules,
+    iterator: Iter<'r, (IdentId, compiler::MetaValue)>,
+    len: usize,
+}
+
+impl<'r> Metadata<'r> {
+    pub(crate) fn new(rules: &'r Rules, rule_info: &'r RuleInfo) -> Self {
+        Self {
+            rules,
+            iterator: rule_info.metadata.iter(),
+            len: rule_info.metadata.len(),
+        }
+    }
+}
+
+impl<'r> Iterator for Metadata<'r> {
+    type Item = (&'r str, MetaValue<'r>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (ident_id, value) = self.iterator.next()?;
+
+        let ident = self.rules.ident_pool().get(*ident_id).unwrap();
+
+        let value = match value {
+            compiler::MetaValue::Bool(b) => MetaValue::Bool(*b),
+            compiler::MetaValue::Integer(i) => MetaValue::Integer(*i),
+            compiler::MetaValue::Float(f) => MetaValue::Float(*f),
+            compiler::MetaValue::String(id) => {
+                let s = self.rules.lit_pool().get(*id).unwrap();
+                match s.to_str() {
+                    Ok(s) => MetaValue::String(s),
+                    Err(_) => MetaValue::Bytes(s),
+                }
+            }
+        };
+
+        Some((ident, value))
+    }
+}
+
+impl<'r> ExactSizeIterator for Metadata<'r> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
 /// An iterator that returns the patterns defined by a rule.
 pub struct Patterns<'a, 'r> {
     ctx: &'a ScanContext<'r>,
@@ -987,6 +2725,7 @@ impl<'a, 'r> Pattern<'a, 'r> {
     pub fn matches(&self) -> Matches<'a> {
         Matches {
             data: self.data,
+            offset_translator: self.ctx.offset_translator.as_deref(),
             iterator: self
                 .ctx
                 .pattern_matches
@@ -999,6 +2738,7 @@ impl<'a, 'r> Pattern<'a, 'r> {
 /// Iterator that returns the matches for a pattern.
 pub struct Matches<'a> {
     data: &'a ScannedData<'a>,
+    offset_translator: Option<&'a OffsetTranslator>,
     iterator: Option<Iter<'a, matches::Match>>,
 }
 
@@ -1007,7 +2747,11 @@ impl<'a> Iterator for Matches<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let iter = self.iterator.as_mut()?;
-        Some(Match { inner: iter.next()?, data: self.data })
+        Some(Match {
+            inner: iter.next()?,
+            data: self.data,
+            offset_translator: self.offset_translator,
+        })
     }
 }
 
@@ -1015,19 +2759,32 @@ impl<'a> Iterator for Matches<'a> {
 pub struct Match<'a> {
     inner: &'a matches::Match,
     data: &'a ScannedData<'a>,
+    offset_translator: Option<&'a OffsetTranslator>,
 }
 
 impl<'a> Match<'a> {
     /// Range within the original data where the match occurred.
+    ///
+    /// If the scan used an offset translator (see
+    /// [`ScanOptions::offset_translator`]), this is the range returned by
+    /// the translator.
     #[inline]
     pub fn range(&self) -> Range<usize> {
-        self.inner.range.clone()
+        match self.offset_translator {
+            Some(translate) => translate(self.inner.range.clone()),
+            None => self.inner.range.clone(),
+        }
     }
 
     /// Slice containing the data that matched.
+    ///
+    /// With [`Scanner::scan_provider`] and [`Scanner::scan_process`] the
+    /// matching data is read again once the search for patterns finishes.
+    /// If it can't be read, because it changed in the meantime or the
+    /// process memory was unmapped, the slice is empty.
     #[inline]
     pub fn data(&self) -> &'a [u8] {
-        self.data.as_ref().get(self.inner.range.clone()).unwrap()
+        self.data.get(self.inner.range.clone()).unwrap_or_default()
     }
 
     /// XOR key used for decrypting the data if the pattern had the `xor`
@@ -1036,4 +2793,27 @@ impl<'a> Match<'a> {
     pub fn xor_key(&self) -> Option<u8> {
         self.inner.xor_key
     }
+
+    /// Data that matched, after decrypting it with the XOR key, if the
+    /// pattern had the `xor` modifier, or `None` if otherwise.
+    pub fn plaintext(&self) -> Option<Vec<u8>> {
+        let key = self.inner.xor_key?;
+        Some(self.data().iter().map(|b| b ^ key).collect())
+    }
+
+    /// Virtual address where the match occurred, if the data was scanned
+    /// with [`Scanner::scan_process`] or [`Scanner::scan_memory_regions`],
+    /// or `None` if otherwise.
+    ///
+    /// This is the start of [`Match::range`], which for these scans is
+    /// expressed in virtual addresses.
+    #[inline]
+    pub fn address(&self) -> Option<u64> {
+        match self.data {
+            ScannedData::Regions(_) | ScannedData::Process(_) => {
+                Some(self.inner.range.start as u64)
+            }
+            _ => None,
+        }
+    }
 }
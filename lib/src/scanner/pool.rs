@@ -0,0 +1,197 @@
+/*! A pool of scanners that share the same compiled rules.
+
+Creating a [`Scanner`] is relatively expensive, and each scanner can scan a
+single file at a time. [`ScannerPool`] maintains a set of scanners that are
+created on demand and reused across scans, which is the usual approach for
+scanning multiple files in parallel.
+*/
+
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+
+use crate::compiler::Rules;
+use crate::scanner::{ScanError, ScanResults, Scanner};
+
+/// A pool of up to N scanners that use the same [`Rules`].
+///
+/// Scanners are created lazily the first time they are needed, and returned
+/// to the pool once they are not used anymore. When all the scanners in the
+/// pool are in use, [`ScannerPool::get`] blocks until some scanner is
+/// returned to the pool. The pool can be shared by multiple threads.
+///
+/// ```
+/// # use yara_x::ScannerPool;
+/// let rules = yara_x::compile("rule test { strings: $a = \"foo\" condition: $a }").unwrap();
+/// let pool = ScannerPool::new(&rules, 4);
+///
+/// std::thread::scope(|s| {
+///     for data in [b"foo", b"bar"] {
+///         let pool = &pool;
+///         s.spawn(move || {
+///             let mut scanner = pool.get();
+///             let results = scanner.scan(data).unwrap();
+///             println!("{} matching rules", results.matching_rules().len());
+///         });
+///     }
+/// });
+/// ```
+pub struct ScannerPool<'r> {
+    rules: &'r Rules,
+    size: usize,
+    pub(super) state: Mutex<PoolState<'r>>,
+    scanner_returned: Condvar,
+}
+
+pub(super) struct PoolState<'r> {
+    /// Scanners that are not currently in use.
+    pub(super) idle: Vec<Scanner<'r>>,
+    /// Number of scanners created so far.
+    pub(super) created: usize,
+}
+
+impl<'r> ScannerPool<'r> {
+    /// Creates a pool that will contain up to `size` scanners.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is zero.
+    pub fn new(rules: &'r Rules, size: usize) -> Self {
+        assert!(size > 0, "the size of a scanner pool can't be zero");
+        Self {
+            rules,
+            size,
+            state: Mutex::new(PoolState { idle: Vec::new(), created: 0 }),
+            scanner_returned: Condvar::new(),
+        }
+    }
+
+    /// Returns the maximum number of scanners in the pool.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Takes a scanner from the pool.
+    ///
+    /// If there's an idle scanner in the pool it is returned, if not, a new
+    /// scanner is created, unless the pool has reached its maximum size, in
+    /// which case this function blocks until some other scanner is returned
+    /// to the pool. The scanner goes back to the pool when the returned
+    /// [`PooledScanner`] is dropped.
+    ///
+    /// Scanners are reused, so any setting changed in a scanner, like a
+    /// timeout or the value of a global variable, will be present the next
+    /// time the scanner is taken from the pool.
+    pub fn get(&self) -> PooledScanner<'_, 'r> {
+        self.get_with(Scanner::new)
+    }
+
+    /// Like [`ScannerPool::get`], but new scanners are created with
+    /// `new_scanner`.
+    pub(super) fn get_with<F>(&self, new_scanner: F) -> PooledScanner<'_, 'r>
+    where
+        F: FnOnce(&'r Rules) -> Scanner<'r>,
+    {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(scanner) = state.idle.pop() {
+                return PooledScanner { pool: self, scanner: Some(scanner) };
+            }
+            if state.created < self.size {
+                state.created += 1;
+                // The lock is released while the scanner is being created,
+                // as this is a relatively slow operation. If the creation
+                // panics, the guard gives back the slot reserved above, so
+                // that other threads don't wait forever for a scanner that
+                // will never be returned.
+                drop(state);
+                let reservation = Reservation { pool: self };
+                let scanner = new_scanner(self.rules);
+                mem::forget(reservation);
+                return PooledScanner { pool: self, scanner: Some(scanner) };
+            }
+            state = self.scanner_returned.wait(state).unwrap();
+        }
+    }
+
+    /// Scans in-memory data with a scanner from the pool.
+    ///
+    /// The results are passed to `f`, and the value returned by `f` is the
+    /// value returned by this function. The scanner is returned to the pool
+    /// after `f` finishes.
+    pub fn scan<F, T>(&self, data: &[u8], f: F) -> Result<T, ScanError>
+    where
+        F: FnOnce(ScanResults<'_, 'r>) -> T,
+    {
+        let mut scanner = self.get();
+        let results = scanner.scan(data)?;
+        Ok(f(results))
+    }
+
+    /// Scans a file with a scanner from the pool.
+    ///
+    /// This is the same as [`ScannerPool::scan`], but for files.
+    pub fn scan_file<P, F, T>(&self, path: P, f: F) -> Result<T, ScanError>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(ScanResults<'_, 'r>) -> T,
+    {
+        let mut scanner = self.get();
+        let results = scanner.scan_file(path)?;
+        Ok(f(results))
+    }
+}
+
+/// A slot in a [`ScannerPool`] that was reserved for a scanner that is being
+/// created. The slot is released if this is dropped, which only happens when
+/// the creation of the scanner panics.
+struct Reservation<'p, 'r> {
+    pool: &'p ScannerPool<'r>,
+}
+
+impl Drop for Reservation<'_, '_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.pool.state.lock() {
+            state.created -= 1;
+            self.pool.scanner_returned.notify_one();
+        }
+    }
+}
+
+/// A scanner taken from a [`ScannerPool`].
+///
+/// This dereferences to [`Scanner`], and the scanner is returned to the pool
+/// when this is dropped.
+pub struct PooledScanner<'p, 'r> {
+    pool: &'p ScannerPool<'r>,
+    scanner: Option<Scanner<'r>>,
+}
+
+impl<'r> Deref for PooledScanner<'_, 'r> {
+    type Target = Scanner<'r>;
+
+    fn deref(&self) -> &Self::Target {
+        self.scanner.as_ref().unwrap()
+    }
+}
+
+impl<'r> DerefMut for PooledScanner<'_, 'r> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.scanner.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledScanner<'_, '_> {
+    fn drop(&mut self) {
+        if let Some(scanner) = self.scanner.take() {
+            // If the mutex was poisoned by a panic in some other thread the
+            // scanner is simply dropped.
+            if let Ok(mut state) = self.pool.state.lock() {
+                state.idle.push(scanner);
+                self.pool.scanner_returned.notify_one();
+            }
+        }
+    }
+}
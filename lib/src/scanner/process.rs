@@ -0,0 +1,151 @@
+/*! Reading the memory of running processes.
+
+This module implements the platform-specific logic used by
+[`crate::Scanner::scan_process`] for enumerating the memory regions of a
+process and reading their content. Currently, only Linux is supported.
+
+The regions of a process, as well as the ones passed to
+[`crate::Scanner::scan_memory_regions`], are scanned independently, and
+offsets within them are virtual addresses. The memory of a process is read
+window by window while it is scanned, as with
+[`crate::Scanner::scan_provider`].
+*/
+
+use std::borrow::Cow;
+use std::io;
+
+use crate::scanner::provider::{ProviderData, Source};
+use crate::scanner::DataProvider;
+
+/// A memory region scanned by [`crate::Scanner::scan_process`] or
+/// [`crate::Scanner::scan_memory_regions`].
+///
+/// Each region is scanned independently of the others, and the ranges
+/// reported by matches are virtual addresses in the process' address space,
+/// so a match is always contained in a single region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub(crate) base: u64,
+    pub(crate) size: usize,
+}
+
+impl MemoryRegion {
+    /// Virtual address where the region starts.
+    #[inline]
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// Size of the region in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Converts a set of memory regions, each one accompanied by the virtual
+/// address where it starts, into blocks whose base offset is that address.
+/// Empty regions, and regions that are not addressable in the current
+/// platform, are ignored.
+pub(crate) fn regions_to_blocks<'a, I, D>(
+    regions: I,
+) -> Vec<(usize, Cow<'a, [u8]>)>
+where
+    I: IntoIterator<Item = (u64, D)>,
+    D: Into<Cow<'a, [u8]>>,
+{
+    regions
+        .into_iter()
+        .filter_map(|(base, region)| {
+            let region = region.into();
+            let base = usize::try_from(base).ok()?;
+            base.checked_add(region.len())?;
+            (!region.is_empty()).then_some((base, region))
+        })
+        .collect()
+}
+
+/// The memory of a running process.
+///
+/// Reads that fail are treated as if the end of the data was reached, so
+/// that regions that are listed as readable but can't be actually read
+/// (like `[vvar]` in Linux) are skipped, and regions that can be read only
+/// partially are truncated.
+pub(crate) struct ProcessMemory {
+    mem: std::fs::File,
+    len: u64,
+}
+
+impl DataProvider for ProcessMemory {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.mem.read_at(offset, buf).unwrap_or(0))
+    }
+}
+
+/// Opens the memory of the process with the given PID for scanning it.
+///
+/// The returned data consists of all the readable memory regions of the
+/// process, where offsets are virtual addresses. The content of the regions
+/// is read window by window during the scan, so it is never entirely in
+/// memory.
+#[cfg(target_os = "linux")]
+pub(crate) fn open_process_memory(
+    pid: u32,
+) -> io::Result<ProviderData<'static>> {
+    use std::fs;
+
+    let maps = fs::read_to_string(format!("/proc/{pid}/maps"))?;
+    let mem = fs::File::open(format!("/proc/{pid}/mem"))?;
+
+    let mut regions = Vec::new();
+
+    // Each line in `/proc/<pid>/maps` has the following format:
+    //
+    // 55d5c4a4e000-55d5c4a50000 r--p 00000000 08:01 1234  /usr/bin/cat
+    //
+    // The first field is the address range, and the second one contains
+    // the permissions.
+    for line in maps.lines() {
+        let mut fields = line.split_ascii_whitespace();
+
+        let (range, perms) = match (fields.next(), fields.next()) {
+            (Some(range), Some(perms)) => (range, perms),
+            _ => continue,
+        };
+
+        if !perms.starts_with('r') {
+            continue;
+        }
+
+        match range.split_once('-').and_then(|(s, e)| {
+            Some((
+                usize::from_str_radix(s, 16).ok()?,
+                usize::from_str_radix(e, 16).ok()?,
+            ))
+        }) {
+            Some((start, end)) if end > start => regions.push(start..end),
+            _ => continue,
+        };
+    }
+
+    let len = regions.iter().map(|region| region.end).max().unwrap_or(0);
+
+    Ok(ProviderData::with_regions(
+        Source::Process(ProcessMemory { mem, len: len as u64 }),
+        regions,
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn open_process_memory(
+    _pid: u32,
+) -> io::Result<ProviderData<'static>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "scanning processes is not supported in this platform",
+    ))
+}
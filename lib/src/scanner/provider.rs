@@ -0,0 +1,289 @@
+/*! Scanning data provided by a [`DataProvider`].
+
+This module implements the logic used by [`crate::Scanner::scan_provider`]
+for scanning data that is not entirely available in memory, but that can be
+read on demand from some source, like a remote blob or an encrypted
+container.
+*/
+
+use std::cmp;
+use std::fs::File;
+use std::io;
+use std::iter;
+use std::ops::Range;
+
+use crate::scanner::process::ProcessMemory;
+use crate::scanner::ScanError;
+
+/// A source of data that can be scanned with
+/// [`crate::Scanner::scan_provider`].
+///
+/// The data is read in windows of [`DataProvider::window_size`] bytes, which
+/// are searched for patterns one by one. Consecutive windows overlap by
+/// [`DataProvider::window_overlap`] bytes, so that patterns that cross the
+/// boundary between two windows can be found, provided that they are not
+/// longer than the overlap.
+///
+/// Modules receive only the first window of the data, so they can parse the
+/// headers of file formats, but any structure beyond that window is missing
+/// from their output.
+pub trait DataProvider {
+    /// Returns the size of the data, in bytes.
+    fn len(&self) -> io::Result<u64>;
+
+    /// Returns true if the data is empty.
+    fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Reads data starting at `offset`, filling `buf` with it.
+    ///
+    /// Returns the number of bytes read, which can be less than the length
+    /// of `buf`. Reading less bytes than requested is not an error, the
+    /// remaining bytes are requested in subsequent calls. A return value of
+    /// zero indicates that there's no more data at `offset`.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Size of the windows in which the data is read. The default is 16MB.
+    fn window_size(&self) -> usize {
+        16 * 1024 * 1024
+    }
+
+    /// Number of bytes shared by consecutive windows. The default is 64KB.
+    ///
+    /// This must be smaller than [`DataProvider::window_size`], otherwise
+    /// it is ignored.
+    fn window_overlap(&self) -> usize {
+        64 * 1024
+    }
+}
+
+impl DataProvider for File {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+}
+
+/// Source from which the data in [`ProviderData`] is read.
+pub(crate) enum Source<'a> {
+    /// A provider passed to [`crate::Scanner::scan_provider`].
+    Provider(&'a (dyn DataProvider + Sync)),
+    /// The memory of a process scanned with [`crate::Scanner::scan_process`].
+    Process(ProcessMemory),
+}
+
+impl Source<'_> {
+    fn provider(&self) -> &dyn DataProvider {
+        match self {
+            Source::Provider(provider) => *provider,
+            Source::Process(process) => process,
+        }
+    }
+}
+
+/// Data scanned with [`crate::Scanner::scan_provider`] or
+/// [`crate::Scanner::scan_process`].
+///
+/// The scanned data is not kept in memory. Only the fragments that contain
+/// matches are read from the provider once the search for patterns
+/// finishes, so that the data matched by each pattern can be retrieved.
+pub(crate) struct ProviderData<'a> {
+    source: Source<'a>,
+    /// Size of the data, as returned by [`DataProvider::len`].
+    pub len: usize,
+    /// Ranges of the data that are searched for patterns. Sorted by offset
+    /// and non-overlapping. Windows never span more than one region, so
+    /// patterns can't match across the boundary between two regions.
+    pub regions: Vec<Range<usize>>,
+    /// The first window of the first region, which is passed to modules.
+    /// Empty until [`ProviderData::read_head`] is called.
+    pub head: Vec<u8>,
+    /// Fragments of the data that contain matches, together with their
+    /// offsets. Sorted by offset and non-overlapping.
+    pub fragments: Vec<(usize, Vec<u8>)>,
+}
+
+impl<'a> ProviderData<'a> {
+    /// Creates a [`ProviderData`] that consists of a single region that
+    /// spans the whole data.
+    pub fn new(
+        provider: &'a (dyn DataProvider + Sync),
+    ) -> Result<Self, ScanError> {
+        let len = provider
+            .len()
+            .map_err(|source| ScanError::ReadError { source })?;
+
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+
+        Ok(Self::with_regions(
+            Source::Provider(provider),
+            iter::once(0..len).collect(),
+        ))
+    }
+
+    /// Creates a [`ProviderData`] where only the given regions are
+    /// searched for patterns. Empty regions are ignored.
+    pub fn with_regions(
+        source: Source<'a>,
+        mut regions: Vec<Range<usize>>,
+    ) -> Self {
+        regions.retain(|region| !region.is_empty());
+        regions.sort_by_key(|region| region.start);
+
+        Self {
+            source,
+            len: regions.last().map_or(0, |region| region.end),
+            regions,
+            head: Vec::new(),
+            fragments: Vec::new(),
+        }
+    }
+
+    /// Reads the first window of the first region into
+    /// [`ProviderData::head`].
+    pub fn read_head(&mut self) -> Result<(), ScanError> {
+        let region = match self.regions.first() {
+            Some(region) => region.clone(),
+            None => return Ok(()),
+        };
+        let window_size = cmp::max(self.source.provider().window_size(), 1);
+        let mut buf = vec![0; cmp::min(window_size, region.len())];
+        let len =
+            read_exact_at(self.source.provider(), region.start, &mut buf)
+                .map_err(|source| ScanError::ReadError { source })?
+                .len();
+        buf.truncate(len);
+        self.head = buf;
+        Ok(())
+    }
+
+    /// Calls `f` for each window in which the regions are split, passing it
+    /// the window's offset and content, and whether the window is the first
+    /// one in its region. Stops at the first error returned by the provider
+    /// or by `f`.
+    ///
+    /// If some region can't be read entirely, because the provider returns
+    /// less data than expected, the region is truncated.
+    pub fn for_each_window<F>(&mut self, mut f: F) -> Result<(), ScanError>
+    where
+        F: FnMut(usize, &[u8], bool) -> Result<(), ScanError>,
+    {
+        let window_size = cmp::max(self.source.provider().window_size(), 1);
+        let overlap = self.source.provider().window_overlap();
+        let overlap = if overlap < window_size { overlap } else { 0 };
+
+        let max_len = self.regions.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut buf = vec![0; cmp::min(window_size, max_len)];
+
+        for region in self.regions.iter_mut() {
+            let mut offset = region.start;
+
+            while offset < region.end {
+                let size = cmp::min(window_size, region.end - offset);
+                let window = read_exact_at(
+                    self.source.provider(),
+                    offset,
+                    &mut buf[..size],
+                )
+                .map_err(|source| ScanError::ReadError { source })?;
+
+                f(offset, window, offset == region.start)?;
+
+                if window.len() < size {
+                    region.end = offset + window.len();
+                    break;
+                }
+
+                if offset + size >= region.end {
+                    break;
+                }
+
+                offset += size - overlap;
+            }
+        }
+
+        self.regions.retain(|region| !region.is_empty());
+
+        Ok(())
+    }
+
+    /// Reads the given ranges from the provider and stores them as
+    /// fragments. Overlapping and adjacent ranges are merged.
+    pub fn fetch<I>(&mut self, ranges: I) -> Result<(), ScanError>
+    where
+        I: IntoIterator<Item = Range<usize>>,
+    {
+        let mut ranges: Vec<_> = ranges.into_iter().collect();
+
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::new();
+
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => {
+                    last.end = cmp::max(last.end, range.end);
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        self.fragments.clear();
+
+        for range in merged {
+            let mut buf = vec![0; range.len()];
+            let len =
+                read_exact_at(self.source.provider(), range.start, &mut buf)
+                    .map_err(|source| ScanError::ReadError { source })?
+                    .len();
+            buf.truncate(len);
+            self.fragments.push((range.start, buf));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the data in the given range, if it is fully contained in
+    /// one of the fragments read with [`ProviderData::fetch`].
+    pub fn get(&self, range: Range<usize>) -> Option<&[u8]> {
+        let i = self
+            .fragments
+            .partition_point(|(offset, _)| *offset <= range.start)
+            .checked_sub(1)?;
+        let (offset, fragment) = &self.fragments[i];
+        fragment.get(range.start - offset..range.end - offset)
+    }
+}
+
+/// Fills `buf` with data read from `provider` at `offset`, calling
+/// [`DataProvider::read_at`] as many times as necessary. Returns the
+/// portion of `buf` that was filled, which is shorter than `buf` only if
+/// the end of the data was reached.
+fn read_exact_at<'b>(
+    provider: &dyn DataProvider,
+    offset: usize,
+    buf: &'b mut [u8],
+) -> io::Result<&'b [u8]> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match provider.read_at((offset + filled) as u64, &mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(&buf[..filled])
+}
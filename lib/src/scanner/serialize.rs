@@ -0,0 +1,111 @@
+/*! Implementations of [`Serialize`] for scan results.
+
+Scan results are serialized with the following structure (in JSON):
+
+```json
+{
+  "matching_rules": [
+    {
+      "identifier": "my_rule",
+      "namespace": "default",
+      "tags": ["foo", "bar"],
+      "metadata": [["author", "John Doe"], ["score", 10]],
+      "patterns": [
+        {
+          "identifier": "$a",
+          "matches": [{ "range": { "start": 0, "end": 3 }, "xor_key": null }]
+        }
+      ]
+    }
+  ]
+}
+```
+*/
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::scanner::{Match, MetaValue, Pattern, Rule, ScanResults};
+
+impl Serialize for ScanResults<'_, '_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ScanResults", 1)?;
+        s.serialize_field("matching_rules", &Seq(|| self.matching_rules()))?;
+        s.end()
+    }
+}
+
+impl Serialize for Rule<'_, '_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Rule", 5)?;
+        s.serialize_field("identifier", self.identifier())?;
+        s.serialize_field("namespace", self.namespace())?;
+        s.serialize_field("tags", &Seq(|| self.tags()))?;
+        s.serialize_field("metadata", &Seq(|| self.metadata()))?;
+        s.serialize_field("patterns", &Seq(|| self.patterns()))?;
+        s.end()
+    }
+}
+
+impl Serialize for Pattern<'_, '_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Pattern", 2)?;
+        s.serialize_field("identifier", self.identifier())?;
+        s.serialize_field("matches", &Seq(|| self.matches()))?;
+        s.end()
+    }
+}
+
+impl Serialize for Match<'_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Match", 2)?;
+        s.serialize_field("range", &self.range())?;
+        s.serialize_field("xor_key", &self.xor_key())?;
+        s.end()
+    }
+}
+
+impl Serialize for MetaValue<'_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match self {
+            MetaValue::Integer(i) => serializer.serialize_i64(*i),
+            MetaValue::Float(f) => serializer.serialize_f64(*f),
+            MetaValue::Bool(b) => serializer.serialize_bool(*b),
+            MetaValue::String(s) => serializer.serialize_str(s),
+            MetaValue::Bytes(b) => b.serialize(serializer),
+        }
+    }
+}
+
+/// Serializes the items returned by the iterator that the closure creates
+/// as a sequence.
+struct Seq<F>(F);
+
+impl<F, I> Serialize for Seq<F>
+where
+    F: Fn() -> I,
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((self.0)())
+    }
+}
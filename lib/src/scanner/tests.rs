@@ -1,9 +1,15 @@
+use std::time::Duration;
+
+use bstr::ByteSlice;
 use pretty_assertions::assert_eq;
 use protobuf::MessageDyn;
 use protobuf::{Message, MessageFull};
 
 use crate::mods;
-use crate::scanner::Scanner;
+use crate::scanner::{
+    CancelHandle, DataProvider, DirScanOptions, MemoryPolicy, MetaValue,
+    ScanError, ScanOptions, ScanResults, ScannedData, Scanner, ScannerPool,
+};
 use crate::variables::VariableError;
 
 #[test]
@@ -98,16 +104,46 @@ fn xor_matches() {
         .matching_rules()
     {
         for pattern in matching_rules.patterns() {
-            matches.extend(
-                pattern
-                    .matches()
-                    .map(|x| (pattern.identifier(), x.range(), x.xor_key())),
-            )
+            matches.extend(pattern.matches().map(|x| {
+                (pattern.identifier(), x.range(), x.xor_key(), x.plaintext())
+            }))
+        }
+    }
+
+    // The xor key must be 1, and the plaintext the original string.
+    assert_eq!(
+        matches,
+        [("$a", 0..11, Some(1), Some(b"mississippi".to_vec()))]
+    );
+
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "mississippi" xor(0x10-0x7f)
+            condition:
+                $a
+        } 
+        "#,
+    )
+    .unwrap();
+
+    let data: Vec<u8> = b"mississippi".iter().map(|b| b ^ 0x42).collect();
+    let mut matches = vec![];
+
+    for matching_rules in Scanner::new(&rules)
+        .scan(data.as_slice())
+        .expect("scan should not fail")
+        .matching_rules()
+    {
+        for pattern in matching_rules.patterns() {
+            matches.extend(pattern.matches().map(|x| (x.range(), x.xor_key())))
         }
     }
 
-    // The xor key must be 1.
-    assert_eq!(matches, [("$a", 0..11, Some(1))])
+    // The key that produced the match is reported, even when the pattern
+    // accepts a range of keys.
+    assert_eq!(matches, [(0..11, Some(0x42))])
 }
 
 #[cfg(feature = "test_proto2-module")]
@@ -399,6 +435,70 @@ fn global_rules() {
     assert_eq!(non_matching.next().unwrap().identifier(), "global_false");
 
     assert!(non_matching.next().is_none());
+
+    assert_eq!(
+        results
+            .failed_global_rules()
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        ["global_false"]
+    );
+}
+
+#[test]
+fn failed_global_rules() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .add_source(
+            r#"
+        global rule has_foo { strings: $a = "foo" condition: $a }
+        rule has_bar { strings: $a = "bar" condition: $a }
+        "#,
+        )
+        .unwrap()
+        .new_namespace("other")
+        .add_source(
+            r#"
+        global private rule not_empty { condition: filesize > 0 }
+        rule always { condition: true }
+        "#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+
+    let results = scanner.scan(b"").unwrap();
+    assert_eq!(results.matching_rules().len(), 0);
+    assert_eq!(
+        results
+            .failed_global_rules()
+            .map(|rule| (rule.namespace(), rule.identifier()))
+            .collect::<Vec<_>>(),
+        [("default", "has_foo"), ("other", "not_empty")]
+    );
+
+    // `has_bar` is suppressed by `has_foo`, even if it matches.
+    let results = scanner.scan(b"bar").unwrap();
+    assert_eq!(
+        results
+            .matching_rules()
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        ["always"]
+    );
+    assert_eq!(
+        results
+            .failed_global_rules()
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        ["has_foo"]
+    );
+
+    let results = scanner.scan(b"foobar").unwrap();
+    assert_eq!(results.matching_rules().len(), 3);
+    assert_eq!(results.failed_global_rules().len(), 0);
 }
 
 #[test]
@@ -436,6 +536,81 @@ fn private_rules() {
 
     // Only the non-matching, non-private rules should be reported.
     assert_eq!(scan_results.non_matching_rules().len(), 0);
+
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .include_private()
+            .map(|rule| (rule.identifier(), rule.is_private()))
+            .collect::<Vec<_>>(),
+        [("test_1", true), ("test_2", true), ("test_3", false)]
+    );
+}
+
+#[test]
+fn private_non_matching_rules() {
+    let rules = crate::compile(
+        r#"
+        private rule test_1 { condition: false }
+        rule test_2 { condition: test_1 }
+        rule test_3 { condition: false }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(&[]).expect("scan should not fail");
+
+    let non_matching = scan_results.non_matching_rules();
+    assert_eq!(non_matching.len(), 2);
+    assert_eq!(
+        non_matching.map(|rule| rule.identifier()).collect::<Vec<_>>(),
+        ["test_2", "test_3"]
+    );
+
+    let non_matching = scan_results.non_matching_rules().include_private();
+    assert_eq!(non_matching.len(), 3);
+    assert_eq!(
+        non_matching.map(|rule| rule.identifier()).collect::<Vec<_>>(),
+        ["test_1", "test_2", "test_3"]
+    );
+}
+
+#[test]
+fn rule_references() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .add_source(
+            r#"
+            rule foo { strings: $a = "foo" condition: $a }
+            rule bar { condition: foo }
+            "#,
+        )
+        .unwrap()
+        .new_namespace("other")
+        .add_source(
+            r#"
+            rule foo { condition: false }
+            rule bar { condition: not foo }
+            "#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"foo").expect("scan should not fail");
+
+    // References are resolved within the namespace of the referencing rule,
+    // `foo` in namespace `other` is a different rule than the one in the
+    // default namespace.
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .map(|r| (r.namespace(), r.identifier()))
+            .collect::<Vec<_>>(),
+        [("default", "foo"), ("default", "bar"), ("other", "bar")]
+    );
 }
 
 #[test]
@@ -487,6 +662,39 @@ fn max_matches_per_pattern() {
     assert_eq!(scanner.scan(b"foo").unwrap().matching_rules().len(), 1);
 }
 
+#[test]
+fn max_matches_per_pattern_with_offsets() {
+    let rules = crate::compile(
+        r#"
+        rule at_0 { strings: $a = "foo" condition: $a at 0 }
+        rule at_6 { strings: $a = "foo" condition: $a at 6 }
+        rule in_range { strings: $a = "foo" condition: $a in (1..10) }
+        rule count { strings: $a = "foo" condition: #a == 1 }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let matching = |scanner: &mut Scanner| {
+        scanner
+            .scan(b"foofoofoo")
+            .unwrap()
+            .matching_rules()
+            .map(|r| r.identifier().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(matching(&mut scanner), ["at_0", "at_6", "in_range"]);
+
+    // Once the limit is reached further matches are not recorded, so `in`
+    // only sees the matches that were recorded before that. Patterns used
+    // only with `at <const>` are anchored at that offset, the only match
+    // they can produce is the one at the expected offset.
+    scanner.max_matches_per_pattern(1);
+    assert_eq!(matching(&mut scanner), ["at_0", "at_6", "count"]);
+}
+
 #[test]
 fn set_module_output() {
     let mut compiler = crate::Compiler::new();
@@ -564,3 +772,1892 @@ fn set_module_output() {
     let scan_results = scanner.scan(b"").expect("scan should not fail");
     assert_eq!(scan_results.matching_rules().len(), 1);
 }
+
+#[cfg(feature = "vt-module")]
+#[test]
+fn set_module_output_vt() {
+    let rules = crate::compile(
+        r#"
+        import "vt"
+        rule test {
+            condition:
+              vt.metadata.file_type == vt.FileType.PE_EXE and
+              vt.metadata.submitter.country == "ES" and
+              vt.metadata.first_submission_date > 1672531200 and
+              vt.metadata.new_file and
+              vt.metadata.analysis_stats.malicious > 10 and
+              for any tag in vt.metadata.tags : (tag == "signed")
+        }
+        rule no_metadata {
+            condition:
+              not defined vt.metadata.file_type
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let mut metadata = mods::vt::FileMetadata::new();
+    metadata.set_file_type(mods::vt::FileType::PE_EXE);
+    metadata.set_first_submission_date(1700000000);
+    metadata.set_new_file(true);
+    metadata.tags.push("peexe".to_string());
+    metadata.tags.push("signed".to_string());
+    metadata.submitter.mut_or_insert_default().set_country("ES".to_string());
+    metadata.analysis_stats.mut_or_insert_default().set_malicious(42);
+
+    let mut vt_data = Box::new(mods::LiveHuntData::new());
+    vt_data.metadata = Some(metadata).into();
+
+    scanner.set_module_output(vt_data).unwrap();
+
+    let scan_results = scanner.scan(b"").expect("scan should not fail");
+    let matching: Vec<_> =
+        scan_results.matching_rules().map(|r| r.identifier()).collect();
+    assert_eq!(matching, ["test"]);
+
+    // The module doesn't produce any data by itself, if the metadata is not
+    // provided again every field is undefined.
+    let scan_results = scanner.scan(b"").expect("scan should not fail");
+    let matching: Vec<_> =
+        scan_results.matching_rules().map(|r| r.identifier()).collect();
+    assert_eq!(matching, ["no_metadata"]);
+}
+
+#[cfg(all(feature = "rules-profiling", feature = "test_proto2-module"))]
+#[test]
+fn profiling_data() {
+    let rules = crate::compile(
+        r#"
+        import "test_proto2"
+        rule test {
+            strings:
+                $a = /fo+bar/
+            condition:
+                $a and test_proto2.int32_one == 1
+        }
+        rule loop {
+            condition:
+                for all i in (0..1000) : (uint8(i % filesize) != 0xff)
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.scan(b"xxfoooobarxx").expect("scan should not fail");
+
+    let profiling_data = scanner.profiling_data();
+
+    let phases: Vec<_> =
+        profiling_data.compilation.iter().map(|(phase, _)| *phase).collect();
+
+    assert_eq!(
+        phases,
+        [
+            "parse",
+            "compile",
+            "emit_wasm",
+            "compile_wasm",
+            "build_ac_automaton"
+        ]
+    );
+
+    assert_eq!(profiling_data.modules.len(), 1);
+    assert_eq!(profiling_data.modules[0].0, "test_proto2");
+
+    assert_eq!(profiling_data.rules.len(), 2);
+
+    let test = profiling_data.rules.iter().find(|r| r.rule == "test").unwrap();
+    let loop_ =
+        profiling_data.rules.iter().find(|r| r.rule == "loop").unwrap();
+
+    assert_eq!(test.namespace, "default");
+    assert_eq!(test.pattern_time, profiling_data.patterns[0].time);
+    assert!(!loop_.condition_time.is_zero());
+    assert!(loop_.pattern_time.is_zero());
+
+    assert_eq!(profiling_data.patterns.len(), 1);
+    assert_eq!(profiling_data.patterns[0].namespace, "default");
+    assert_eq!(profiling_data.patterns[0].rule, "test");
+    assert_eq!(profiling_data.patterns[0].pattern, "$a");
+
+    let mut folded = Vec::new();
+    profiling_data.write_folded(&mut folded).unwrap();
+
+    for line in String::from_utf8(folded).unwrap().lines() {
+        let (stack, micros) = line.rsplit_once(' ').unwrap();
+        assert!(
+            stack.starts_with("compilation;") || stack.starts_with("scan;")
+        );
+        assert!(micros.parse::<u128>().unwrap() > 0);
+    }
+
+    let mut pprof = Vec::new();
+    profiling_data.write_pprof(&mut pprof).unwrap();
+
+    // The string table contains the sample type and the frame names.
+    assert!(pprof.find(b"nanoseconds").is_some());
+    assert!(pprof.find(b"compile_wasm").is_some());
+}
+
+#[cfg(feature = "rules-profiling")]
+#[test]
+fn profiling_data_shared_patterns() {
+    let rules = crate::compile(
+        r#"
+        rule test_1 {
+            strings:
+                $a = /fo+bar/
+            condition:
+                $a
+        }
+        rule test_2 {
+            strings:
+                $b = /fo+bar/
+            condition:
+                $b
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.scan(b"xxfoooobarxx").expect("scan should not fail");
+
+    let profiling_data = scanner.profiling_data();
+
+    // The pattern is shared by both rules, but it was verified only once,
+    // so the time is split between them instead of being counted twice.
+    let time_spent: Duration =
+        scanner.wasm_store.data().time_spent_in_pattern.values().sum();
+
+    let pattern_time: Duration =
+        profiling_data.patterns.iter().map(|p| p.time).sum();
+
+    assert_eq!(scanner.wasm_store.data().time_spent_in_pattern.len(), 1);
+    assert_eq!(profiling_data.patterns.len(), 2);
+    assert_eq!(
+        profiling_data.patterns[0].time,
+        profiling_data.patterns[1].time
+    );
+    assert!(pattern_time <= time_spent);
+    assert!(time_spent - pattern_time < Duration::from_nanos(2));
+}
+
+#[test]
+fn timeout() {
+    let rules = crate::compile(
+        r#"
+        rule slow {
+            condition:
+                for all i in (0..10000000000) : (
+                    uint8(i % filesize) != 0xff
+                )
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.set_timeout(Duration::from_secs(1));
+
+    assert!(matches!(scanner.scan(b"foobar"), Err(ScanError::Timeout)));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn scan_process() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "Y4R4-X-PR0C3SS-M4RK3R"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    // A copy of the pattern in heap memory owned by this test.
+    let marker = b"Y4R4-X-PR0C3SS-M4RK3R".to_vec();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let scan_results = scanner
+        .scan_process(std::process::id())
+        .expect("scan should not fail");
+
+    let regions = scan_results.memory_regions();
+
+    assert!(!regions.is_empty());
+
+    // Regions that can't be read entirely are truncated, so the scanned
+    // bytes are the sum of the sizes of the regions returned.
+    assert_eq!(
+        scan_results.stats().scanned_bytes,
+        regions.iter().map(|r| r.size()).sum::<usize>()
+    );
+
+    let rule = scan_results.matching_rules().next().unwrap();
+    let pattern = rule.patterns().next().unwrap();
+
+    assert!(pattern
+        .matches()
+        .any(|m| m.address() == Some(marker.as_ptr() as u64)));
+}
+
+#[test]
+fn scan_memory_regions() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                #a == 2 and filesize == 13
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let scan_results = scanner
+        .scan_memory_regions([
+            (0x400000, b"xxfoofo".as_slice()),
+            (0x10000, b"".as_slice()),
+            (0x400007, b"oxxfoo".as_slice()),
+        ])
+        .expect("scan should not fail");
+
+    assert_eq!(
+        scan_results
+            .memory_regions()
+            .iter()
+            .map(|r| (r.base(), r.size()))
+            .collect::<Vec<_>>(),
+        [(0x400000, 7), (0x400007, 6)]
+    );
+
+    let rule = scan_results.matching_rules().next().unwrap();
+    let pattern = rule.patterns().next().unwrap();
+
+    // Each region is scanned independently, the "foo" that crosses the
+    // boundary between the two regions is not found, even if the regions
+    // are contiguous.
+    assert_eq!(
+        pattern
+            .matches()
+            .map(|m| (m.range(), m.address()))
+            .collect::<Vec<_>>(),
+        [
+            (0x400002..0x400005, Some(0x400002)),
+            (0x40000a..0x40000d, Some(0x40000a))
+        ]
+    );
+}
+
+#[test]
+fn scan_blocks() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+                $b = "bar"
+                $c = "foobar"
+            condition:
+                $a at 2 and $b at 1000 and not $c and filesize == 1005
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let blocks: [(usize, &[u8]); 2] = [(0, b"xxfoo"), (1000, b"barxx")];
+
+    let scan_results =
+        scanner.scan_blocks(blocks).expect("scan should not fail");
+
+    let mut matches = vec![];
+
+    for rule in scan_results.matching_rules() {
+        for pattern in rule.patterns() {
+            matches.extend(
+                pattern
+                    .matches()
+                    .map(|m| (pattern.identifier(), m.range(), m.data())),
+            )
+        }
+    }
+
+    assert_eq!(
+        matches,
+        [
+            ("$a", 2..5, b"foo".as_slice()),
+            ("$b", 1000..1003, b"bar".as_slice())
+        ]
+    );
+
+    // Blocks are not memory regions, matches don't have a virtual address.
+    assert!(scan_results
+        .matching_rules()
+        .flat_map(|rule| rule.patterns())
+        .flat_map(|pattern| pattern.matches())
+        .all(|m| m.address().is_none()));
+}
+
+#[test]
+fn scan_blocks_chained_patterns() {
+    // The jump is large enough for the pattern to be split in two chained
+    // fragments, "fooo" and "barr".
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = { 66 6F 6F 6F [0-300] 62 61 72 72 }
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    // The fragments are found in contiguous blocks, but they are not
+    // joined across the boundary between the blocks.
+    let blocks: [(usize, &[u8]); 2] = [(0, b"xxfooo"), (6, b"barrxx")];
+
+    assert_eq!(scanner.scan_blocks(blocks).unwrap().matching_rules().len(), 0);
+
+    assert_eq!(
+        scanner
+            .scan_memory_regions([
+                (0x1000, b"xxfooo".as_slice()),
+                (0x1006, b"barrxx".as_slice()),
+            ])
+            .unwrap()
+            .matching_rules()
+            .len(),
+        0
+    );
+
+    // Both fragments in the same block do match.
+    let blocks: [(usize, &[u8]); 2] = [(0, b"xxfooo"), (6, b"fooobarr")];
+    let scan_results = scanner.scan_blocks(blocks).unwrap();
+    let rule = scan_results.matching_rules().next().unwrap();
+    let pattern = rule.patterns().next().unwrap();
+
+    assert_eq!(
+        pattern.matches().map(|m| (m.range(), m.data())).collect::<Vec<_>>(),
+        [(6..14, b"fooobarr".as_slice())]
+    );
+}
+
+#[cfg(feature = "test_proto2-module")]
+#[test]
+fn scan_blocks_modules() {
+    let rules = crate::compile(
+        r#"
+        import "test_proto2"
+        rule test {
+            condition:
+                test_proto2.file_size == 5
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    // Modules receive the first block.
+    let blocks: [(usize, &[u8]); 2] = [(0, b"xxfoo"), (1000, b"barxxxx")];
+
+    assert_eq!(scanner.scan_blocks(blocks).unwrap().matching_rules().len(), 1);
+
+    assert_eq!(
+        scanner
+            .scan_memory_regions([
+                (0x1000, b"xxfoo".as_slice()),
+                (0x7000, b"barxxxx".as_slice()),
+            ])
+            .unwrap()
+            .matching_rules()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn on_rule_match() {
+    let rules = crate::compile(
+        r#"
+        global rule global_rule {
+            condition:
+                true
+        }
+        private rule private_rule {
+            condition:
+                true
+        }
+        rule rule_1 {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        rule rule_2 {
+            condition:
+                false
+        }
+        rule rule_3 {
+            condition:
+                private_rule
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut notified = vec![];
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.on_rule_match(|rule| {
+        notified.push((
+            rule.identifier().to_string(),
+            rule.patterns().flat_map(|p| p.matches()).count(),
+        ))
+    });
+
+    let scan_results = scanner.scan(b"foofoo").expect("scan should not fail");
+
+    assert_eq!(scan_results.matching_rules().len(), 3);
+
+    drop(scanner);
+
+    assert_eq!(
+        notified,
+        [
+            ("rule_1".to_string(), 2),
+            ("rule_3".to_string(), 0),
+            ("global_rule".to_string(), 0)
+        ]
+    );
+}
+
+#[test]
+fn scan_with_options() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let num_matches = |scan_results: ScanResults| {
+        scan_results
+            .matching_rules()
+            .flat_map(|rule| rule.patterns())
+            .flat_map(|pattern| pattern.matches())
+            .count()
+    };
+
+    let scan_results = scanner
+        .scan_with_options(
+            b"foofoofoo",
+            ScanOptions::new().max_matches_per_pattern(1),
+        )
+        .expect("scan should not fail");
+
+    assert_eq!(num_matches(scan_results), 1);
+
+    // The options used in the previous scan don't affect the next one.
+    let scan_results =
+        scanner.scan(b"foofoofoo").expect("scan should not fail");
+
+    assert_eq!(num_matches(scan_results), 3);
+
+    // An invalid module output makes the scan fail.
+    assert!(matches!(
+        scanner.scan_with_options(
+            b"foofoofoo",
+            ScanOptions::new().module_output_raw("foobar", &[]),
+        ),
+        Err(ScanError::UnknownModule { .. })
+    ));
+}
+
+#[test]
+fn rule_metadata() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            meta:
+                author = "John \"Doe\""
+                severity = 10
+                score = 0.5
+                malicious = true
+                raw = "\xff\x00"
+                author = "Jane"
+            condition:
+                true
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"").expect("scan should not fail");
+    let rule = scan_results.matching_rules().next().unwrap();
+
+    assert_eq!(
+        rule.metadata().collect::<Vec<_>>(),
+        [
+            ("author", MetaValue::String("John \"Doe\"")),
+            ("severity", MetaValue::Integer(10)),
+            ("score", MetaValue::Float(0.5)),
+            ("malicious", MetaValue::Bool(true)),
+            ("raw", MetaValue::Bytes(b"\xff\x00".as_bstr())),
+            ("author", MetaValue::String("Jane")),
+        ]
+    );
+}
+
+#[test]
+fn rule_tags() {
+    let rules = crate::compile(
+        r#"
+        rule foo : tag2 tag1 { condition: true }
+        rule bar : tag1 { condition: true }
+        rule baz { condition: true }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"").expect("scan should not fail");
+
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .map(|rule| (rule.identifier(), rule.tags().collect::<Vec<_>>()))
+            .collect::<Vec<_>>(),
+        [
+            ("foo", vec!["tag2", "tag1"]),
+            ("bar", vec!["tag1"]),
+            ("baz", vec![])
+        ]
+    );
+
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .with_tag("tag1")
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        ["foo", "bar"]
+    );
+
+    assert_eq!(scan_results.matching_rules().with_tag("tag3").count(), 0);
+}
+
+#[cfg(feature = "serde-results")]
+#[test]
+fn serialize_results() {
+    let rules = crate::compile(
+        r#"
+        rule test : tag1 {
+            meta:
+                author = "John Doe"
+                score = 10
+            strings:
+                $a = "foo"
+                $b = "bar" xor
+            condition:
+                $a or $b
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"foo").expect("scan should not fail");
+
+    assert_eq!(
+        serde_json::to_value(&scan_results).unwrap(),
+        serde_json::json!({
+            "matching_rules": [{
+                "identifier": "test",
+                "namespace": "default",
+                "tags": ["tag1"],
+                "metadata": [["author", "John Doe"], ["score", 10]],
+                "patterns": [
+                    {
+                        "identifier": "$a",
+                        "matches": [
+                            {"range": {"start": 0, "end": 3}, "xor_key": null}
+                        ]
+                    },
+                    {
+                        "identifier": "$b",
+                        "matches": []
+                    }
+                ]
+            }]
+        })
+    );
+}
+
+#[test]
+fn wasm_error() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            condition:
+                true
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    // Replace the main function with one that traps, which is what happens
+    // when the code emitted for the conditions fails unexpectedly.
+    scanner.wasm_main_func = wasmtime::Func::wrap(
+        &mut scanner.wasm_store,
+        || -> wasmtime::Result<i32> {
+            Err(wasmtime::Trap::UnreachableCodeReached.into())
+        },
+    )
+    .typed(&scanner.wasm_store)
+    .unwrap();
+
+    assert!(matches!(
+        scanner.scan(b""),
+        Err(ScanError::WasmError { err })
+            if err.downcast_ref() == Some(&wasmtime::Trap::UnreachableCodeReached)
+    ));
+}
+
+#[test]
+fn max_memory() {
+    use wasmtime::AsContextMut;
+
+    let rules = crate::compile(
+        r#"
+        rule test {
+            condition:
+                true
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    // The main memory has a single 64KB page.
+    scanner.max_memory(65536);
+    assert_eq!(scanner.scan(b"").unwrap().matching_rules().len(), 1);
+
+    // Growing the memory beyond the limit fails.
+    let main_memory = scanner.wasm_store.data().main_memory.unwrap();
+    let err = main_memory.grow(scanner.wasm_store.as_context_mut(), 1);
+    assert!(matches!(
+        err.unwrap_err().downcast::<ScanError>(),
+        Ok(ScanError::OutOfMemory)
+    ));
+
+    // A limit smaller than the current memory size makes the scan fail.
+    scanner.max_memory(1024);
+    assert!(matches!(scanner.scan(b""), Err(ScanError::OutOfMemory)));
+}
+
+#[test]
+fn matching_rules_order() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .new_namespace("foo")
+        .add_source("rule r1 { condition: true }")
+        .unwrap()
+        .add_source("global rule r2 { condition: true }")
+        .unwrap()
+        .new_namespace("bar")
+        .add_source("rule r3 { condition: true }")
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"").expect("scan should not fail");
+
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        ["r1", "r2", "r3"]
+    );
+
+    assert_eq!(
+        scan_results
+            .matching_rules_sorted_by_namespace()
+            .iter()
+            .map(|rule| (rule.namespace(), rule.identifier()))
+            .collect::<Vec<_>>(),
+        [("bar", "r3"), ("foo", "r1"), ("foo", "r2")]
+    );
+}
+
+#[test]
+fn scan_reader() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foobar"
+            condition:
+                $a at 3 and filesize == 12
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    // The pattern spans the two chunks returned by the reader.
+    let reader = std::io::Read::chain(&b"xxxfoo"[..], &b"barxxx"[..]);
+
+    assert_eq!(scanner.scan_reader(reader).unwrap().matching_rules().len(), 1);
+
+    struct FailingReader;
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("broken pipe"))
+        }
+    }
+
+    assert!(matches!(
+        scanner.scan_reader(FailingReader),
+        Err(ScanError::ReadError { .. })
+    ));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn scan_async() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    let mut scanner = Scanner::new(&rules);
+    let future = scanner.scan_async(b"foobar");
+
+    // The future can be spawned in multi-threaded executors.
+    assert_send(&future);
+
+    // Asynchronous scans may yield, in which case the waker must be woken
+    // before the future returns `Poll::Pending`, otherwise an executor
+    // would never poll it again. The future must resolve after a bounded
+    // number of polls.
+    use std::future::Future;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct FlagWaker(AtomicBool);
+
+    impl std::task::Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+
+    let flag = Arc::new(FlagWaker::default());
+    let waker = flag.clone().into();
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    let mut scan_results = None;
+
+    for _ in 0..1000 {
+        match future.as_mut().poll(&mut cx) {
+            std::task::Poll::Ready(results) => {
+                scan_results = Some(results.unwrap());
+                break;
+            }
+            std::task::Poll::Pending => {
+                assert!(flag.0.swap(false, Ordering::Relaxed));
+            }
+        }
+    }
+
+    let scan_results = scan_results.expect("scan_async didn't resolve");
+
+    assert_eq!(scan_results.matching_rules().len(), 1);
+}
+
+#[test]
+fn move_scanner_to_thread() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.console_log(|_| {});
+    scanner.on_rule_match(|_| {});
+
+    // The scanner is created in the main thread and used in another one.
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            let scan_results = scanner.scan(b"foobar").unwrap();
+            assert_eq!(scan_results.matching_rules().len(), 1);
+        });
+    });
+}
+
+#[test]
+fn scanner_pool() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    let pool = ScannerPool::new(&rules, 2);
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let num_matching_rules = pool
+                    .scan(b"foobar", |results| results.matching_rules().len())
+                    .unwrap();
+                assert_eq!(num_matching_rules, 1);
+            });
+        }
+    });
+
+    // No more than 2 scanners were created.
+    let first = pool.get();
+    let second = pool.get();
+    assert!(pool.state.lock().unwrap().idle.is_empty());
+    assert_eq!(pool.state.lock().unwrap().created, 2);
+
+    // Scanners are returned to the pool when dropped.
+    drop(first);
+    drop(second);
+    assert_eq!(pool.state.lock().unwrap().idle.len(), 2);
+}
+
+#[test]
+fn scanner_pool_panic() {
+    let rules = crate::compile("rule test { condition: true }").unwrap();
+    let pool = ScannerPool::new(&rules, 1);
+
+    // If the creation of a scanner panics, the slot reserved for it is
+    // released.
+    let result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = pool.get_with(|_| panic!("scanner creation failed"));
+        }));
+
+    assert!(result.is_err());
+    assert_eq!(pool.state.lock().unwrap().created, 0);
+
+    // The pool has a single slot, this would block forever if the slot
+    // wasn't released.
+    let scanner = pool.get();
+    assert_eq!(pool.state.lock().unwrap().created, 1);
+    drop(scanner);
+}
+
+#[test]
+fn scan_with_globals() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .define_global("some_bool", false)
+        .unwrap()
+        .define_global("some_str", "")
+        .unwrap()
+        .add_source(
+            r#"
+        rule test {
+            condition:
+                some_bool and some_str == "foo"
+        }
+        "#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+
+    let globals = [
+        ("some_bool", true.try_into().unwrap()),
+        ("some_str", "foo".try_into().unwrap()),
+    ];
+
+    assert_eq!(
+        scanner
+            .scan_with_globals(&[], globals)
+            .unwrap()
+            .matching_rules()
+            .len(),
+        1
+    );
+
+    // The values are restored after the scan.
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 0);
+
+    let options = ScanOptions::new()
+        .global("some_bool", true)
+        .unwrap()
+        .global("some_str", "foo")
+        .unwrap();
+
+    assert_eq!(
+        scanner
+            .scan_with_options(&[], options)
+            .unwrap()
+            .matching_rules()
+            .len(),
+        1
+    );
+
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 0);
+
+    // If some variable is not valid, none of them is modified.
+    let globals = [
+        ("some_bool", true.try_into().unwrap()),
+        ("some_str", 1.try_into().unwrap()),
+    ];
+
+    assert!(matches!(
+        scanner.scan_with_globals(&[], globals),
+        Err(ScanError::VariableError(VariableError::InvalidType { .. }))
+    ));
+
+    scanner.set_global("some_str", "foo").unwrap();
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 0);
+}
+
+#[test]
+fn reset_globals() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .define_global("some_bool", false)
+        .unwrap()
+        .define_global("some_int", 1)
+        .unwrap()
+        .add_source(
+            r#"
+        rule bool_set { condition: some_bool }
+        rule int_set { condition: some_int == 2 }
+        "#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.set_global("some_bool", true).unwrap();
+    scanner.set_global("some_int", 2).unwrap();
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 2);
+
+    scanner.reset_global("some_bool").unwrap();
+    assert_eq!(
+        scanner
+            .scan(&[])
+            .unwrap()
+            .matching_rules()
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        ["int_set"]
+    );
+
+    scanner.set_global("some_bool", true).unwrap();
+    scanner.reset_all_globals();
+    assert_eq!(scanner.scan(&[]).unwrap().matching_rules().len(), 0);
+
+    assert_eq!(
+        scanner.reset_global("undefined").err().unwrap(),
+        VariableError::Undefined("undefined".to_string())
+    );
+}
+
+#[test]
+fn disable_rules() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .add_source(
+            r#"
+        rule foo : tag_a { strings: $a = "foo" condition: $a }
+        rule bar : tag_a tag_b { condition: foo }
+        "#,
+        )
+        .unwrap()
+        .new_namespace("other")
+        .add_source(
+            r#"
+        global rule always_false { condition: false }
+        rule foo { condition: true }
+        "#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+    let mut scanner = Scanner::new(&rules);
+
+    let matching = |scanner: &mut Scanner| {
+        scanner
+            .scan(b"foo")
+            .unwrap()
+            .matching_rules()
+            .map(|rule| format!("{}:{}", rule.namespace(), rule.identifier()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(matching(&mut scanner), ["default:foo", "default:bar"]);
+
+    // Disabling `foo` disables it in both namespaces, and `bar` doesn't
+    // match either because it depends on `foo`.
+    scanner.disable_rule("foo");
+    assert_eq!(matching(&mut scanner), Vec::<String>::new());
+
+    // Once the global rule is disabled, `other:foo` can match.
+    scanner.enable_rule("foo").disable_rule("always_false");
+    assert_eq!(
+        matching(&mut scanner),
+        ["default:foo", "default:bar", "other:foo"]
+    );
+
+    scanner.disable_namespace("default");
+    assert_eq!(matching(&mut scanner), ["other:foo"]);
+
+    scanner.enable_namespace("default").disable_tag("tag_b");
+    assert_eq!(matching(&mut scanner), ["default:foo", "other:foo"]);
+
+    scanner.enable_all_rules();
+    assert_eq!(matching(&mut scanner), ["default:foo", "default:bar"]);
+}
+
+#[test]
+fn scan_stats() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+                $b = "bar"
+            condition:
+                $a and $b
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let stats = scanner.scan(b"foo bar foo baz").unwrap().stats();
+
+    assert_eq!(stats.scanned_bytes, 15);
+    assert_eq!(stats.pattern_matches, 3);
+    assert!(stats.atom_matches >= 3);
+
+    // Statistics are not accumulated across scans.
+    let stats = scanner.scan(b"foo").unwrap().stats();
+
+    assert_eq!(stats.scanned_bytes, 3);
+    assert_eq!(stats.pattern_matches, 1);
+
+    let blocks = [(0, b"foo".as_slice()), (100, b"bar".as_slice())];
+    let stats = scanner.scan_blocks(blocks).unwrap().stats();
+
+    assert_eq!(stats.scanned_bytes, 6);
+    assert_eq!(stats.pattern_matches, 2);
+}
+
+#[test]
+fn scan_dir() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    let root = std::env::temp_dir()
+        .join(format!("yara-x-scan-dir-{}", std::process::id()));
+
+    std::fs::create_dir_all(root.join("sub/subsub")).unwrap();
+    std::fs::write(root.join("a.txt"), b"foo").unwrap();
+    std::fs::write(root.join("b.bin"), b"foo").unwrap();
+    std::fs::write(root.join("sub/c.TXT"), b"bar").unwrap();
+    std::fs::write(root.join("sub/subsub/d.txt"), b"foofoofoo").unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let mut scan = |options: &DirScanOptions| {
+        let mut results = Vec::new();
+        scanner
+            .scan_dir(&root, options, |path, r| {
+                results.push((
+                    path.strip_prefix(&root).unwrap().to_path_buf(),
+                    r.unwrap().matching_rules().len(),
+                ))
+            })
+            .unwrap();
+        results
+            .into_iter()
+            .map(|(path, n)| (path.to_str().unwrap().replace('\\', "/"), n))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        scan(&DirScanOptions::new()),
+        [
+            ("a.txt".to_string(), 1),
+            ("b.bin".to_string(), 1),
+            ("sub/c.TXT".to_string(), 0),
+            ("sub/subsub/d.txt".to_string(), 1),
+        ]
+    );
+
+    assert_eq!(
+        scan(&DirScanOptions::new().extension("txt").max_depth(1)),
+        [("a.txt".to_string(), 1), ("sub/c.TXT".to_string(), 0)]
+    );
+
+    assert_eq!(
+        scan(&DirScanOptions::new().max_file_size(3).max_depth(0)),
+        [("a.txt".to_string(), 1), ("b.bin".to_string(), 1)]
+    );
+
+    // Symbolic links are ignored unless `follow_symlinks` is used, and
+    // loops are detected.
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("a.txt"), root.join("link.txt"))
+            .unwrap();
+
+        assert_eq!(scan(&DirScanOptions::new().extension("txt")).len(), 3);
+        assert_eq!(
+            scan(
+                &DirScanOptions::new().extension("txt").follow_symlinks(true)
+            ),
+            [
+                ("a.txt".to_string(), 1),
+                ("link.txt".to_string(), 1),
+                ("sub/c.TXT".to_string(), 0),
+                ("sub/subsub/d.txt".to_string(), 1),
+            ]
+        );
+    }
+
+    assert!(matches!(
+        scanner.scan_dir(
+            root.join("missing"),
+            &DirScanOptions::new(),
+            |_, _| {}
+        ),
+        Err(ScanError::OpenError { .. })
+    ));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn offset_translator() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a at 2
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let ranges = |results: ScanResults| {
+        let rule = results.matching_rules().next().unwrap();
+        let pattern = rule.patterns().next().unwrap();
+        pattern
+            .matches()
+            .map(|m| (m.range(), m.data().to_vec()))
+            .collect::<Vec<_>>()
+    };
+
+    // The translation doesn't affect the condition, which still sees the
+    // match at offset 2, nor the matched data.
+    let options = ScanOptions::new()
+        .offset_translator(|range| range.start * 2..range.end * 2);
+
+    assert_eq!(
+        ranges(scanner.scan_with_options(b"xxfoo", options).unwrap()),
+        [(4..10, b"foo".to_vec())]
+    );
+
+    // The translator applies only to the scan where it was used.
+    assert_eq!(
+        ranges(scanner.scan(b"xxfoo").unwrap()),
+        [(2..5, b"foo".to_vec())]
+    );
+}
+
+#[test]
+fn mmap_threshold() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a and filesize == 6
+        }
+        "#,
+    )
+    .unwrap();
+
+    let path = std::env::temp_dir()
+        .join(format!("yara-x-mmap-threshold-{}", std::process::id()));
+
+    std::fs::write(&path, b"foobar").unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    // Returns the number of matching rules, and whether the file was
+    // memory-mapped or not.
+    let scan = |scanner: &mut Scanner| {
+        let results = scanner.scan_file(&path).unwrap();
+        let mapped = matches!(results.data, ScannedData::Mmap(_));
+        (results.matching_rules().len(), mapped)
+    };
+
+    assert_eq!(scan(scanner.always_mmap()), (1, true));
+    assert_eq!(scan(scanner.never_mmap()), (1, false));
+
+    // The file is mapped if its size is equal or above the threshold.
+    assert_eq!(scan(scanner.set_mmap_threshold(5)), (1, true));
+    assert_eq!(scan(scanner.set_mmap_threshold(6)), (1, true));
+    assert_eq!(scan(scanner.set_mmap_threshold(7)), (1, false));
+
+    // Empty files are read, even if they must be memory-mapped.
+    std::fs::write(&path, b"").unwrap();
+
+    assert_eq!(scan(scanner.always_mmap()), (0, false));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn scan_special_files() {
+    let rules =
+        crate::compile("rule test { condition: filesize > 0 }").unwrap();
+    let mut scanner = Scanner::new(&rules);
+
+    let dir = std::env::temp_dir()
+        .join(format!("yara-x-special-files-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    assert!(matches!(
+        scanner.scan_file(&dir),
+        Err(ScanError::IsDirectory { .. })
+    ));
+
+    #[cfg(unix)]
+    {
+        let file = dir.join("file");
+        let link = dir.join("link");
+
+        std::fs::write(&file, b"foo").unwrap();
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        assert_eq!(
+            scanner.scan_file(&link).unwrap().matching_rules().len(),
+            1
+        );
+
+        scanner.follow_symlinks(false);
+
+        assert!(matches!(
+            scanner.scan_file(&link),
+            Err(ScanError::SymbolicLink { .. })
+        ));
+
+        assert_eq!(
+            scanner.scan_file(&file).unwrap().matching_rules().len(),
+            1
+        );
+    }
+
+    // Files in /proc report a size of zero, but they have some content.
+    #[cfg(target_os = "linux")]
+    assert_eq!(
+        scanner
+            .always_mmap()
+            .scan_file("/proc/self/status")
+            .unwrap()
+            .matching_rules()
+            .len(),
+        1
+    );
+
+    #[cfg(target_os = "linux")]
+    assert!(matches!(
+        scanner.scan_file("/dev/null"),
+        Err(ScanError::SpecialFile { .. })
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn try_new() {
+    let rules = crate::compile("rule test { condition: true }").unwrap();
+    let mut scanner = Scanner::try_new(&rules).unwrap();
+
+    assert_eq!(scanner.scan(b"").unwrap().matching_rules().len(), 1);
+}
+
+#[cfg(feature = "pooling-allocator")]
+#[test]
+fn pooling_limits() {
+    // Compiling the rules creates the WASM engine, after that the limits
+    // can't be changed.
+    crate::compile("rule test { condition: true }").unwrap();
+
+    assert!(!crate::set_pooling_limits(
+        crate::PoolingLimits::new().max_instances(10)
+    ));
+}
+
+#[test]
+fn cancel_scan() {
+    let rules = crate::compile(
+        r#"
+        rule slow {
+            condition:
+                for all i in (0..10000000000) : (
+                    uint8(i % filesize) != 0xff
+                )
+        }
+        rule fast {
+            condition:
+                filesize > 0
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let handle = scanner.cancel_handle();
+
+    // Cancel the scan while it is in progress.
+    let canceller = {
+        let handle = handle.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            handle.cancel();
+        })
+    };
+
+    assert!(matches!(scanner.scan(b"foobar"), Err(ScanError::Cancelled)));
+
+    canceller.join().unwrap();
+
+    // Cancelling while no scan is in progress cancels the next scan only.
+    handle.cancel();
+
+    assert!(matches!(scanner.scan(b""), Err(ScanError::Cancelled)));
+    assert_eq!(scanner.scan(b"").unwrap().matching_rules().len(), 0);
+}
+
+#[test]
+fn cancel_pattern_search() {
+    // A provider that cancels the scan when the second window is read.
+    struct Provider(CancelHandle);
+
+    impl DataProvider for Provider {
+        fn len(&self) -> std::io::Result<u64> {
+            Ok(24)
+        }
+
+        fn read_at(
+            &self,
+            offset: u64,
+            buf: &mut [u8],
+        ) -> std::io::Result<usize> {
+            if offset > 0 {
+                self.0.cancel();
+            }
+            let n = buf.len().min(24 - offset as usize);
+            buf[..n].fill(0);
+            Ok(n)
+        }
+
+        fn window_size(&self) -> usize {
+            8
+        }
+    }
+
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let provider = Provider(scanner.cancel_handle());
+
+    // The cancellation is noticed while searching for patterns, even if
+    // no atom is found in the data.
+    assert!(matches!(
+        scanner.scan_provider(&provider),
+        Err(ScanError::Cancelled)
+    ));
+}
+
+#[test]
+fn partial_results() {
+    let rules = crate::compile(
+        r#"
+        rule fast {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        rule slow {
+            condition:
+                for all i in (0..10000000000) : (
+                    uint8(i % filesize) != 0xff
+                )
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.set_timeout(Duration::from_secs(1));
+
+    assert!(matches!(scanner.scan(b"foobar"), Err(ScanError::Timeout)));
+
+    scanner.partial_results(true);
+
+    let scan_results = scanner.scan(b"foobar").unwrap();
+
+    assert!(scan_results.is_truncated());
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .map(|rule| rule.identifier())
+            .collect::<Vec<_>>(),
+        vec!["fast"]
+    );
+
+    let rules = crate::compile(
+        r#"
+        rule fast {
+            condition:
+                filesize > 0
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.partial_results(true);
+
+    let scan_results = scanner.scan(b"foobar").unwrap();
+
+    assert!(!scan_results.is_truncated());
+    assert_eq!(scan_results.matching_rules().len(), 1);
+}
+
+#[test]
+fn memory_policy() {
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+            condition:
+                #a == 2 and "foo" matches /fo+/
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.set_memory_policy(
+        MemoryPolicy::new().max_retained_matches(0).trim_every(2),
+    );
+
+    // Matches must be the same regardless of whether the memory retained
+    // from the previous scan was released or not.
+    for _ in 0..5 {
+        let scan_results = scanner.scan(b"foofoo").unwrap();
+        assert_eq!(scan_results.matching_rules().len(), 1);
+        let scan_results = scanner.scan(b"foo").unwrap();
+        assert_eq!(scan_results.matching_rules().len(), 0);
+    }
+}
+
+#[test]
+fn scan_provider() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Provider {
+        data: Vec<u8>,
+        reads: AtomicUsize,
+        fail: bool,
+        fail_len: bool,
+    }
+
+    impl DataProvider for Provider {
+        fn len(&self) -> std::io::Result<u64> {
+            if self.fail_len {
+                return Err(std::io::Error::other("len failed"));
+            }
+            Ok(self.data.len() as u64)
+        }
+
+        fn read_at(
+            &self,
+            offset: u64,
+            buf: &mut [u8],
+        ) -> std::io::Result<usize> {
+            if self.fail {
+                return Err(std::io::Error::other("read failed"));
+            }
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            // Return at most 3 bytes per read.
+            let data = &self.data[offset as usize..];
+            let n = data.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&data[..n]);
+            Ok(n)
+        }
+
+        fn window_size(&self) -> usize {
+            8
+        }
+
+        fn window_overlap(&self) -> usize {
+            4
+        }
+    }
+
+    let rules = crate::compile(
+        r#"
+        rule test {
+            strings:
+                $a = "foo"
+                $b = "barbaz"
+            condition:
+                #a == 2 and not $b and filesize == 24
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let provider = Provider {
+        data: b"foo...foo..barbaz.......".to_vec(),
+        reads: AtomicUsize::new(0),
+        fail: false,
+        fail_len: false,
+    };
+
+    let scan_results = scanner.scan_provider(&provider).unwrap();
+    let rule = scan_results.matching_rules().next().unwrap();
+    let pattern = rule.patterns().next().unwrap();
+
+    // The second "foo" crosses the end of the first window, but it is found
+    // in the second one. "barbaz" is not found because it doesn't fit
+    // entirely in any window.
+    assert_eq!(
+        pattern
+            .matches()
+            .map(|m| (m.range(), m.data().to_vec()))
+            .collect::<Vec<_>>(),
+        vec![(0..3, b"foo".to_vec()), (6..9, b"foo".to_vec())]
+    );
+
+    assert!(provider.reads.load(Ordering::Relaxed) > 1);
+
+    let provider = Provider { fail: true, ..provider };
+
+    assert!(matches!(
+        scanner.scan_provider(&provider),
+        Err(ScanError::ReadError { .. })
+    ));
+
+    let provider = Provider { fail: false, fail_len: true, ..provider };
+
+    assert!(matches!(
+        scanner.scan_provider(&provider),
+        Err(ScanError::ReadError { .. })
+    ));
+}
+
+#[cfg(feature = "test_proto2-module")]
+#[test]
+fn scan_provider_modules() {
+    struct Provider(Vec<u8>);
+
+    impl DataProvider for Provider {
+        fn len(&self) -> std::io::Result<u64> {
+            Ok(self.0.len() as u64)
+        }
+
+        fn read_at(
+            &self,
+            offset: u64,
+            buf: &mut [u8],
+        ) -> std::io::Result<usize> {
+            let data = &self.0[offset as usize..];
+            let n = data.len().min(buf.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            Ok(n)
+        }
+
+        fn window_size(&self) -> usize {
+            8
+        }
+    }
+
+    let rules = crate::compile(
+        r#"
+        import "test_proto2"
+        rule test {
+            condition:
+                test_proto2.file_size == 8 and filesize == 24
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let provider = Provider(vec![0; 24]);
+
+    // The module receives the first window of the data.
+    assert_eq!(
+        scanner.scan_provider(&provider).unwrap().matching_rules().len(),
+        1
+    );
+}
+
+#[test]
+fn early_termination() {
+    let rules = crate::compile(
+        r#"
+        rule test_1 {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        rule test_2 {
+            strings:
+                $b = "bar"
+                $c = "baz"
+            condition:
+                any of them
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    let num_matches = |scan_results: ScanResults| {
+        scan_results
+            .matching_rules()
+            .flat_map(|rule| rule.patterns())
+            .map(|pattern| pattern.matches().count())
+            .sum::<usize>()
+    };
+
+    let data = b"foo bar foo bar foo";
+
+    assert_eq!(num_matches(scanner.scan(data).unwrap()), 5);
+
+    // Once "foo" and "bar" are found both rules match, and the search
+    // stops.
+    scanner.early_termination(true);
+
+    let scan_results = scanner.scan(data).unwrap();
+    assert_eq!(scan_results.matching_rules().len(), 2);
+    assert_eq!(num_matches(scan_results), 2);
+
+    scanner.early_termination(false);
+
+    assert_eq!(num_matches(scanner.scan(data).unwrap()), 5);
+
+    // The search can't stop early if some rule needs to see all the data.
+    let rules = crate::compile(
+        r#"
+        rule test_1 {
+            strings:
+                $a = "foo"
+            condition:
+                $a
+        }
+        rule test_2 {
+            strings:
+                $b = "bar"
+            condition:
+                #b == 2
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+
+    scanner.early_termination(true);
+
+    let scan_results = scanner.scan(data).unwrap();
+    assert_eq!(scan_results.matching_rules().len(), 2);
+    assert_eq!(num_matches(scan_results), 5);
+}
+
+#[test]
+fn rule_ids() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .add_source(r#"rule foo { condition: true }"#)
+        .unwrap()
+        .new_namespace("bar")
+        .add_source(
+            r#"rule foo { condition: false } rule baz { condition: true }"#,
+        )
+        .unwrap();
+
+    let rules = compiler.build();
+
+    let default_foo = rules.rule_by_name("default", "foo").unwrap();
+    let bar_foo = rules.rule_by_name("bar", "foo").unwrap();
+    let bar_baz = rules.rule_by_name("bar", "baz").unwrap();
+
+    assert_ne!(default_foo, bar_foo);
+    assert!(rules.rule_by_name("bar", "qux").is_none());
+    assert!(rules.rule_by_name("qux", "foo").is_none());
+
+    assert_eq!(rules.rule_name(bar_foo), Some(("bar", "foo")));
+    assert_eq!(rules.rule_name(crate::RuleId::from(3_usize)), None);
+
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"").unwrap();
+
+    assert_eq!(
+        scan_results.matching_rules().map(|r| r.id()).collect::<Vec<_>>(),
+        vec![default_foo, bar_baz]
+    );
+
+    assert_eq!(
+        scan_results.non_matching_rules().map(|r| r.id()).collect::<Vec<_>>(),
+        vec![bar_foo]
+    );
+
+    // Rule IDs are preserved across serialization.
+    let rules = crate::Rules::deserialize(rules.serialize().unwrap()).unwrap();
+
+    assert_eq!(rules.rule_by_name("bar", "baz"), Some(bar_baz));
+}
+
+#[test]
+fn rule_origin() {
+    let mut compiler = crate::Compiler::new();
+
+    compiler
+        .add_source(
+            yara_x_parser::SourceCode::from(r#"rule foo { condition: true }"#)
+                .with_origin("foo.yar"),
+        )
+        .unwrap()
+        .add_source(r#"rule bar { condition: true }"#)
+        .unwrap();
+
+    let rules = compiler.build();
+    let rules = crate::Rules::deserialize(rules.serialize().unwrap()).unwrap();
+
+    let mut scanner = Scanner::new(&rules);
+    let scan_results = scanner.scan(b"").unwrap();
+
+    assert_eq!(
+        scan_results
+            .matching_rules()
+            .map(|r| (r.identifier(), r.origin()))
+            .collect::<Vec<_>>(),
+        vec![("foo", Some("foo.yar")), ("bar", None)]
+    );
+}
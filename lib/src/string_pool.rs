@@ -56,12 +56,28 @@ where
         }
     }
 
+    /// Returns the ID corresponding to the string `s` if it was previously
+    /// interned. If not returns [`None`].
+    #[inline]
+    pub fn get_id(&self, s: &str) -> Option<T> {
+        self.pool.check_interned(s).map(|s| T::from(s.id()))
+    }
+
     /// Returns the string corresponding to a given `id` if it was previously
     /// interned. If not returns [`None`].
     #[inline]
     pub fn get(&self, id: T) -> Option<&str> {
         self.pool.get(Symbol::from(id.into()))
     }
+
+    /// Returns the total size in bytes of the interned strings.
+    ///
+    /// This doesn't include the memory used by the pool's internal hash
+    /// table.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
 }
 
 impl<T> Serialize for StringPool<T>
@@ -197,6 +213,15 @@ where
                     .expect("using BStringPool::get_str with a string that is not valid UTF-8")
             })
     }
+
+    /// Returns the total size in bytes of the interned strings.
+    ///
+    /// This doesn't include the memory used by the pool's internal hash
+    /// table.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
 }
 
 impl<T> Serialize for BStringPool<T>
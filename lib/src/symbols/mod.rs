@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
+use std::sync::Arc;
 
 #[cfg(test)]
 use bstr::{BStr, ByteSlice};
@@ -36,7 +37,7 @@ pub(crate) enum SymbolKind {
     /// The symbol refers to a rule.
     Rule(RuleId),
     /// The symbol refers to a function.
-    Func(Rc<Func>),
+    Func(Arc<Func>),
 }
 
 impl Symbol {
@@ -140,6 +141,15 @@ impl SymbolTable {
         self.map.insert(ident.into(), symbol)
     }
 
+    /// Removes a symbol from the symbol table, returning it if it was
+    /// in the table.
+    pub fn remove<I>(&mut self, ident: I) -> Option<Symbol>
+    where
+        I: AsRef<str>,
+    {
+        self.map.remove(ident.as_ref())
+    }
+
     /// Returns true if the symbol table already contains a symbol with
     /// the given identifier.
     #[inline]
@@ -190,6 +190,48 @@ fn arithmetic_operations() {
     condition_true!("5 \\ 2 \\ 2 == 1");
     condition_true!("7 \\ 2 \\ 2.0 == 1.5");
     condition_true!("7 % 4 % 2 == 1");
+
+    // Folding of constant integer operations don't lose precision with
+    // large numbers.
+    condition_true!("0x7FFFFFFFFFFFFFFF - 1 == 0x7FFFFFFFFFFFFFFE");
+    condition_true!("9007199254740993 + 0 == 9007199254740993");
+    condition_true!("4611686018427387903 * 2 == 0x7FFFFFFFFFFFFFFE");
+}
+
+#[test]
+fn arithmetic_overflow_and_undefined() {
+    // Operations with non-constant operands wrap around on overflow. The
+    // tests use `filesize`, which is zero, for preventing constant folding.
+    condition_true!("filesize + 0x7FFFFFFFFFFFFFFF + 1 < 0");
+    condition_true!("filesize - 0x7FFFFFFFFFFFFFFF - 2 > 0");
+    condition_true!("(filesize + 0x4000000000000000) * 2 < 0");
+
+    // Division by zero is undefined.
+    condition_false!("filesize \\ 0 == 0");
+    condition_false!("filesize \\ 0 != 0");
+    condition_false!("filesize % 0 == 0");
+    condition_false!("filesize % 0 != 0");
+    condition_true!("not defined (1 \\ filesize)");
+    condition_true!("not defined (1 % filesize)");
+
+    // The result of dividing the minimum integer by -1 doesn't fit in a
+    // 64-bits integer, it is undefined too.
+    condition_true!("not defined ((filesize - 0x7FFFFFFFFFFFFFFF - 1) \\ -1)");
+    condition_true!("(filesize - 0x7FFFFFFFFFFFFFFF - 1) % -1 == 0");
+    condition_true!(
+        "(filesize - 0x7FFFFFFFFFFFFFFF) \\ -1 == 0x7FFFFFFFFFFFFFFF"
+    );
+    condition_true!("(filesize + 6) \\ -1 == -6");
+    condition_true!("(filesize - 6) \\ -1 == 6");
+    condition_true!("(filesize - 7) \\ 2 == -3");
+
+    // Float division by zero is not undefined, it produces infinity.
+    condition_true!("(filesize + 1) \\ 0.0 > 1.0");
+
+    // Undefined values are treated as false by boolean operators.
+    condition_true!("filesize \\ 0 == 0 or true");
+    condition_false!("filesize \\ 0 == 0 and true");
+    condition_false!("not (filesize \\ 0 == 0)");
 }
 
 #[test]
@@ -224,6 +266,29 @@ fn bitwise_operations() {
     condition_true!("1 << 65 == 0");
     condition_true!("1 >> 65 == 0");
     condition_true!("1 | 3 ^ 3 != (1 | 3) ^ 3");
+    condition_true!("~0 == -1");
+    condition_true!("~filesize == -1");
+    condition_true!("~(-1) == 0");
+    condition_true!("-1 & 0xFF == 0xFF");
+    condition_true!("-1 ^ 0xFF == -256");
+    condition_true!("-256 | 0xFF == -1");
+    condition_true!("1 << 63 == -9223372036854775807 - 1");
+    condition_true!("(1 << 63) >> 63 == -1");
+    // Right shift is arithmetic, the sign bit is preserved.
+    condition_true!("-8 >> 1 == -4");
+    condition_true!("-1 >> 63 == -1");
+    // Shifting by 64 or more produces 0, regardless of the sign of the
+    // shifted value.
+    condition_true!("-1 >> 64 == 0");
+    condition_true!("-8 << 64 == 0");
+    condition_true!("1 << (filesize + 64) == 0");
+    condition_true!("1 >> (filesize + 64) == 0");
+    // Negative shift amounts are not accepted when they are constant, but
+    // they can be the result of some expression that is evaluated at scan
+    // time. In that case the result is 0.
+    condition_true!("1 << (filesize - 1) == 0");
+    condition_true!("-1 >> (filesize - 1) == 0");
+    condition_true!("0x10 >> (filesize + 4) == 1");
 }
 
 #[test]
@@ -258,6 +323,12 @@ fn string_operations() {
     condition_true!(r#""foo" iequals "FOO""#);
     condition_true!(r#""foo" iequals "FoO""#);
     condition_false!(r#""foo" iequals "bar""#);
+    condition_false!(r#""foo" iequals "FOOO""#);
+    condition_true!(r#""foo" icontains """#);
+    condition_false!(r#""foo" icontains "FOOO""#);
+    condition_false!(r#""foo" istartswith "FOOO""#);
+    condition_false!(r#""foo" iendswith "OOO""#);
+    condition_true!(r#""FOO\x00BAR" iendswith "\x00bar""#);
 
     condition_true!(r#""foo" matches /foo/"#);
     condition_true!(r#""foo" matches /FOO/i"#);
@@ -282,6 +353,12 @@ fn string_operations() {
     // characters.
     condition_false!(r#""🙈🙉🙊" matches /^...$/"#);
     condition_true!(r#""🙈🙉🙊" matches /(?u)^...$/"#);
+
+    // A full DFA for this regexp would have 2^20 states.
+    condition_true!(r#""xxabbbbbbbbbbbbbbbbbbbbbb" matches /[ab]*a[ab]{20}/"#);
+    condition_false!(
+        r#""xxbbbbbbbbbbbbbbbbbbbbbbb" matches /[ab]*a[ab]{20}/"#
+    );
 }
 
 #[test]
@@ -495,6 +572,53 @@ fn for_in() {
     );
 }
 
+#[test]
+fn with() {
+    condition_true!(r#"with a = 1 + 1 : (a == 2)"#);
+    condition_true!(r#"with a = 1, b = a + 1 : (b == 2)"#);
+    condition_true!(r#"with a = 1.5 : (a * 2 == 3.0)"#);
+    condition_true!(r#"with a = "foo" : (a contains "oo")"#);
+    condition_true!(
+        r#"for all i in (1..3) : (with j = i * 2 : (j % 2 == 0))"#
+    );
+
+    // Inner declarations hide outer ones with the same name.
+    condition_true!(r#"with a = 1 : (with a = 2 : (a == 2) and a == 1)"#);
+
+    #[cfg(feature = "test_proto2-module")]
+    condition_true!(
+        r#"with a = test_proto2.int64_undef : (not defined (a == 0))"#
+    );
+
+    #[cfg(feature = "test_proto2-module")]
+    condition_true!(
+        r#"with a = test_proto2.int64_undef, b = 1 : (b == 1 or a == 0)"#
+    );
+
+    #[cfg(feature = "test_proto2-module")]
+    condition_true!(
+        r#"with nested = test_proto2.nested : (nested.nested_int64_one == 1)"#
+    );
+
+    #[cfg(feature = "test_proto2-module")]
+    condition_true!(
+        r#"with arr = test_proto2.array_int64 : (
+             arr[0] == 1 and for any x in arr : (x == 100))"#
+    );
+
+    rule_true!(
+        r#"
+        rule test {
+          strings:
+            $a = "foo"
+          condition:
+            with n = #a, first = @a[1] : (n == 2 and first == 3)
+        }
+        "#,
+        b"barfoofoo"
+    );
+}
+
 #[test]
 fn text_patterns() {
     pattern_true!(r#""issi""#, b"mississippi");
@@ -1272,6 +1396,25 @@ fn regexp_patterns_3() {
 #[test]
 fn regexp_patterns_4() {
     pattern_match!(r"/\babc/", b"abc", b"abc");
+    pattern_match!(r"/\babc\b/", b"x\nabc\ny", b"abc");
+    pattern_match!(
+        r"/\babc\b/ wide",
+        b"x\x00 \x00a\x00b\x00c\x00 \x00",
+        b"a\x00b\x00c\x00"
+    );
+    pattern_false!(r"/\babc\b/ wide", b"x\x00a\x00b\x00c\x00 \x00");
+
+    // Anchors are relative to the scanned data, not to lines, so `^` doesn't
+    // match after a newline and `$` doesn't match before it. This is true
+    // even with the `s` flag, which only affects the `.` metacharacter.
+    pattern_false!(r"/^abc/", b"x\nabc");
+    pattern_false!(r"/abc$/", b"abc\nx");
+    pattern_false!(r"/^abc/s", b"x\nabc");
+    pattern_false!(r"/abc$/s", b"abc\nx");
+    pattern_match!(r"/^abc.*xyz$/s", b"abc\nxyz", b"abc\nxyz");
+    condition_false!(r#""foo\nbar" matches /^bar/"#);
+    condition_false!(r#""foo\nbar" matches /foo$/"#);
+    condition_true!(r#""foo\nbar" matches /^foo\nbar$/"#);
     pattern_match!(r"/abc\b/", b"abc", b"abc");
     pattern_false!(r"/\babc/", b"1abc");
     pattern_false!(r"/\babc/", b"_abc");
@@ -1379,6 +1522,11 @@ fn regexp_nocase() {
     pattern_false!(r#"/abc[^d]/ nocase"#, b"abcd");
     pattern_false!(r#"/abc[^d]/ nocase"#, b"ABCD");
     pattern_match!(r#"/[*-_]+/ nocase"#, b"ABCDabcd1234", b"ABCDabcd1234");
+    pattern_match!(r#"/[A-C]+/ nocase"#, b"aBc", b"aBc");
+    pattern_match!(r#"/\x41\x62c/ nocase"#, b"aBC", b"aBC");
+    pattern_false!(r#"/x[^a]y/ nocase"#, b"xAy");
+    pattern_false!(r#"/x[^A]y/ nocase"#, b"xay");
+    pattern_match!(r#"/x[^a]y/ nocase"#, b"XbY", b"XbY");
 }
 
 #[test]
@@ -2033,6 +2181,30 @@ fn match_count() {
         b"xxxaaaaa"
     );
 
+    rule_true!(
+        r#"
+        rule test {
+            strings:
+                $a = "ab"
+            condition:
+                #a in (0..filesize \ 2) > 3 and #a in (filesize \ 2..filesize) == 1
+        }
+        "#,
+        b"ababababxxxxxxab"
+    );
+
+    rule_false!(
+        r#"
+        rule test {
+            strings:
+                $a = "ab"
+            condition:
+                #a in (0..filesize \ 2) > 3
+        }
+        "#,
+        b"abababxxxxxxxxab"
+    );
+
     #[cfg(feature = "test_proto2-module")]
     rule_false!(
         r#"
@@ -2363,6 +2535,9 @@ fn xor() {
     );
 
     pattern_false!(r#""mississippi" xor(2-255)"#, b"lhrrhrrhqqh");
+    pattern_true!(r#""mississippi" xor(0x01-0xff)"#, b"lhrrhrrhqqh");
+    pattern_false!(r#""mississippi" xor(0x01-0xff)"#, b"mississippi");
+    pattern_true!(r#""mississippi" xor(0x00-0x01)"#, b"mississippi");
     pattern_true!(
         r#""mississippi" xor(255)"#,
         &[0x92, 0x96, 0x8C, 0x8C, 0x96, 0x8C, 0x8C, 0x96, 0x8F, 0x8F, 0x96]
@@ -2494,6 +2669,36 @@ fn fullword() {
         r#"/ip{2}i/ wide fullword"#,
         b"m\x00i\x00s\x00s\x00i\x00s\x00s\x00i\x00p\x00p\x00i\x00"
     );
+
+    pattern_true!(r#""mississippi" nocase fullword"#, b"MISSISSIPPI");
+    pattern_true!(r#""mississippi" nocase fullword"#, b" MiSsIsSiPpI ");
+    pattern_false!(r#""mississippi" nocase fullword"#, b"xMISSISSIPPI");
+    pattern_false!(r#""mississippi" nocase fullword"#, b"MISSISSIPPIX");
+    pattern_false!(r#""miss" nocase fullword"#, b"MISSISSIPPI");
+    pattern_true!(r#"/mis+is+ippi/ nocase fullword"#, b"-MISSISSIPPI-");
+    pattern_false!(r#"/mis+is+ippi/ nocase fullword"#, b"0MISSISSIPPI");
+
+    pattern_true!(
+        r#""mississippi" nocase wide fullword"#,
+        b" \x00M\x00I\x00S\x00S\x00I\x00S\x00S\x00I\x00P\x00P\x00I\x00 \x00"
+    );
+
+    pattern_false!(
+        r#""mississippi" nocase wide fullword"#,
+        b"X\x00M\x00I\x00S\x00S\x00I\x00S\x00S\x00I\x00P\x00P\x00I\x00"
+    );
+
+    pattern_false!(
+        r#""mississippi" nocase wide fullword"#,
+        b"M\x00I\x00S\x00S\x00I\x00S\x00S\x00I\x00P\x00P\x00I\x00x\x00"
+    );
+
+    // As in YARA, the surrounding characters are considered alphanumeric only
+    // if their high byte is zero, so `x\x01` doesn't break the full word.
+    pattern_true!(
+        r#""mississippi" nocase wide fullword"#,
+        b"x\x01M\x00I\x00S\x00S\x00I\x00S\x00S\x00I\x00P\x00P\x00I\x00"
+    );
 }
 
 #[test]
@@ -2860,6 +3065,12 @@ fn filesize() {
             .len(),
         1
     );
+
+    condition_true!("filesize >= 2 and filesize <= 2", b"ab");
+    condition_true!("filesize == 2 and 2 <= filesize", b"ab");
+    condition_false!("filesize < 10 and filesize > 100", b"ab");
+    condition_false!("filesize > 2 and 2 >= filesize", b"abc");
+    condition_false!("filesize < 0 and true", b"ab");
 }
 
 #[test]
@@ -2915,6 +3126,37 @@ fn for_of() {
         "#,
         b"foobarbar"
     );
+
+    rule_true!(
+        r#"
+        rule test {
+          strings:
+            $a1 = "foo"
+            $a2 = "bar"
+            $b = "baz"
+          condition:
+            for any of ($a*) : ( @ == 3 and ! == 3 ) and
+            for all of ($a*) : ( # == 2 ) and
+            for none of ($a*) : ( @[2] < 3 ) and
+            not $b
+        }
+        "#,
+        b"foobarfoobar"
+    );
+
+    rule_false!(
+        r#"
+        rule test {
+          strings:
+            $a1 = "foo"
+            $a2 = "bar"
+            $b = "baz"
+          condition:
+            for 2 of ($a*, $b) : ( @ < 100 )
+        }
+        "#,
+        b"xxxbar"
+    );
 }
 
 #[test]
@@ -3008,6 +3250,66 @@ fn of() {
         b"barbaz"
     );
 
+    // The number of patterns that must match is rounded up, 50% of three
+    // patterns is two patterns.
+    rule_true!(
+        r#"
+        rule test {
+          strings:
+            $ = "foo"
+            $ = "bar"
+            $ = "baz"
+          condition:
+            50% of them
+        }
+        "#,
+        b"barbaz"
+    );
+
+    rule_false!(
+        r#"
+        rule test {
+          strings:
+            $ = "foo"
+            $ = "bar"
+            $ = "baz"
+          condition:
+            50% of them
+        }
+        "#,
+        b"baz"
+    );
+
+    rule_true!(
+        r#"
+        rule test {
+          strings:
+            $s1 = "foo"
+            $s2 = "bar"
+            $s3 = "baz"
+            $x = "qux"
+          condition:
+            30% of ($s*) and not $x
+        }
+        "#,
+        b"baz"
+    );
+
+    rule_false!(
+        r#"
+        rule test {
+          strings:
+            $s1 = "foo"
+            $s2 = "bar"
+            $s3 = "baz"
+            $x = "qux"
+          condition:
+            30% of ($s*) and not $x
+        }
+        "#,
+        b"qux"
+    );
+
     rule_true!(
         r#"
         rule test {
@@ -3212,6 +3514,13 @@ fn test_defined_2() {
     condition_true!(
         r#"defined (for any x in (0..10) : (test_proto2.undef_i64() == 0))"#
     );
+    condition_true!(r#"defined test_proto2.int64_zero"#);
+    condition_false!(r#"defined test_proto2.int64_undef"#);
+    condition_true!(r#"not defined test_proto2.int64_undef"#);
+    condition_false!(r#"defined (test_proto2.int64_undef + 1)"#);
+    condition_true!(
+        r#"defined test_proto2.int64_undef or test_proto2.int64_one == 1"#
+    );
 }
 
 #[test]
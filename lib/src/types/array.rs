@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use bstr::BString;
 use serde::{Deserialize, Serialize};
@@ -10,8 +10,8 @@ pub(crate) enum Array {
     Integers(Vec<i64>),
     Floats(Vec<f64>),
     Bools(Vec<bool>),
-    Strings(Vec<Rc<BString>>),
-    Structs(Vec<Rc<Struct>>),
+    Strings(Vec<Arc<BString>>),
+    Structs(Vec<Arc<Struct>>),
 }
 
 impl Array {
@@ -59,7 +59,7 @@ impl Array {
         }
     }
 
-    pub fn as_string_array(&self) -> &Vec<Rc<BString>> {
+    pub fn as_string_array(&self) -> &Vec<Arc<BString>> {
         if let Self::Strings(v) = self {
             v
         } else {
@@ -67,7 +67,7 @@ impl Array {
         }
     }
 
-    pub fn as_struct_array(&self) -> &Vec<Rc<Struct>> {
+    pub fn as_struct_array(&self) -> &Vec<Arc<Struct>> {
         if let Self::Structs(v) = self {
             v
         } else {
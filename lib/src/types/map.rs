@@ -13,9 +13,8 @@ pub(crate) enum Map {
         // actual data. For example, if the value is an integer it will be
         // Value::Integer(None), if it is a structure, it will have the
         // same fields than actual structures stored in the map, but those
-        // fields will contain no data. The deputy value is optional because
-        // it is present only at compile time, when the `map` field is an
-        // empty map.
+        // fields will contain no data. The deputy value is used only at
+        // compile time, for determining the type of the map's values.
         deputy: Option<TypeValue>,
         // Use IndexMap instead of HashMap because IndexMap allows to get an
         // item not only by key, but also by index. HashMap doesn't offer
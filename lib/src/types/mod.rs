@@ -1,5 +1,5 @@
 use std::fmt::{Debug, Display, Formatter};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use bstr::BString;
 use serde::{Deserialize, Serialize};
@@ -169,12 +169,12 @@ pub(crate) enum TypeValue {
     Integer(Value<i64>),
     Float(Value<f64>),
     Bool(Value<bool>),
-    String(Value<Rc<BString>>),
+    String(Value<Arc<BString>>),
     Regexp(Option<Regexp>),
-    Struct(Rc<Struct>),
-    Array(Rc<Array>),
-    Map(Rc<Map>),
-    Func(Rc<Func>),
+    Struct(Arc<Struct>),
+    Array(Arc<Array>),
+    Map(Arc<Map>),
+    Func(Arc<Func>),
 }
 
 impl TypeValue {
@@ -222,6 +222,7 @@ impl TypeValue {
                 _ => false,
             },
             (Self::Struct(a), Self::Struct(b)) => a.eq(b),
+            (Self::Func(a), Self::Func(b)) => a.signatures() == b.signatures(),
             _ => false,
         }
     }
@@ -230,7 +231,7 @@ impl TypeValue {
     ///
     /// The symbol table contains the methods and/or fields associated to the
     /// type.
-    pub fn symbol_table(&self) -> Rc<dyn SymbolLookup> {
+    pub fn symbol_table(&self) -> Arc<dyn SymbolLookup> {
         match self {
             Self::Struct(s) => s.clone(),
             _ => unreachable!(),
@@ -302,7 +303,7 @@ impl TypeValue {
         }
     }
 
-    pub fn as_array(&self) -> Rc<Array> {
+    pub fn as_array(&self) -> Arc<Array> {
         if let TypeValue::Array(array) = self {
             array.clone()
         } else {
@@ -313,7 +314,7 @@ impl TypeValue {
         }
     }
 
-    pub fn as_struct(&self) -> Rc<Struct> {
+    pub fn as_struct(&self) -> Arc<Struct> {
         if let TypeValue::Struct(structure) = self {
             structure.clone()
         } else {
@@ -324,7 +325,7 @@ impl TypeValue {
         }
     }
 
-    pub fn as_map(&self) -> Rc<Map> {
+    pub fn as_map(&self) -> Arc<Map> {
         if let TypeValue::Map(map) = self {
             map.clone()
         } else {
@@ -335,7 +336,7 @@ impl TypeValue {
         }
     }
 
-    pub fn as_func(&self) -> Rc<Func> {
+    pub fn as_func(&self) -> Arc<Func> {
         if let TypeValue::Func(func) = self {
             func.clone()
         } else {
@@ -364,7 +365,7 @@ impl TypeValue {
     }
 
     #[inline]
-    pub fn as_string(&self) -> Rc<BString> {
+    pub fn as_string(&self) -> Arc<BString> {
         self.try_as_string()
             .expect("TypeValue doesn't have an associated value")
     }
@@ -402,7 +403,7 @@ impl TypeValue {
         }
     }
 
-    pub fn try_as_string(&self) -> Option<Rc<BString>> {
+    pub fn try_as_string(&self) -> Option<Arc<BString>> {
         if let TypeValue::String(value) = self {
             value.extract().cloned()
         } else {
@@ -1,6 +1,6 @@
 use std::iter;
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use bstr::BString;
 use indexmap::{IndexMap, IndexSet};
@@ -101,7 +101,7 @@ impl Struct {
     /// the intermediate fields is not a structure. For example if field
     /// name is "foo.bar.baz" but either "foo" or "bar" is not a structure.
     ///
-    /// If there is some [`Rc`] or [`Weak`] pointer pointing to any of the
+    /// If there is some [`Arc`] or [`Weak`] pointer pointing to any of the
     /// intermediate structures (e.g: the structures in the "foo" and "bar"
     /// fields).
     pub fn add_field<N: Into<String>>(
@@ -115,14 +115,14 @@ impl Struct {
             let field = self
                 .field_entry_by_name(name[0..dot].to_owned())
                 .or_insert_with(|| StructField {
-                    type_value: TypeValue::Struct(Rc::new(Struct::new())),
+                    type_value: TypeValue::Struct(Arc::new(Struct::new())),
                     number: 0,
                 });
 
             if let TypeValue::Struct(ref mut s) = field.type_value {
-                let s = Rc::<Struct>::get_mut(s).unwrap_or_else(|| {
+                let s = Arc::<Struct>::get_mut(s).unwrap_or_else(|| {
                     panic!(
-                        "`add_field` was called while an `Rc` or `Weak` pointer points to field `{}`",
+                        "`add_field` was called while an `Arc` or `Weak` pointer points to field `{}`",
                         (&name[0..dot])
                     )
                 });
@@ -175,6 +175,12 @@ impl Struct {
         self.field_by_name(name).is_some()
     }
 
+    /// Returns an iterator that yields the name and value of each field, in
+    /// the order in which they were added.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &StructField)> {
+        self.fields.iter().map(|(name, field)| (name.as_str(), field))
+    }
+
     /// Get a field by index.
     #[inline]
     pub fn field_by_index(&self, index: usize) -> Option<&StructField> {
@@ -363,7 +369,7 @@ impl Struct {
         // as an existing field.
         for (name, method) in methods {
             if new_struct
-                .add_field(name, TypeValue::Func(Rc::new(method)))
+                .add_field(name, TypeValue::Func(Arc::new(method)))
                 .is_some()
             {
                 panic!(
@@ -595,7 +601,7 @@ impl Struct {
     ///
     /// Here the `foo` field will be named `bar` when the protobuf is converted
     /// into a [`Struct`].
-    fn field_name(field_descriptor: &FieldDescriptor) -> String {
+    pub(crate) fn field_name(field_descriptor: &FieldDescriptor) -> String {
         if let Some(options) =
             field_options.get(&field_descriptor.proto().options)
         {
@@ -614,7 +620,7 @@ impl Struct {
     /// ```text
     /// int64 foo = 1 [(yara.field_options).ignore = true];
     /// ```
-    fn ignore_field(field_descriptor: &FieldDescriptor) -> bool {
+    pub(crate) fn ignore_field(field_descriptor: &FieldDescriptor) -> bool {
         if let Some(options) =
             field_options.get(&field_descriptor.proto().options)
         {
@@ -713,7 +719,7 @@ impl Struct {
                         enum_as_fields,
                     )
                 };
-                TypeValue::Struct(Rc::new(structure))
+                TypeValue::Struct(Arc::new(structure))
             }
         }
     }
@@ -761,7 +767,16 @@ impl Struct {
                 }
             }
             RuntimeType::U64 => {
-                todo!()
+                if let Some(repeated) = repeated {
+                    Array::Integers(
+                        repeated
+                            .into_iter()
+                            .map(|value| value.to_u64().unwrap() as i64)
+                            .collect(),
+                    )
+                } else {
+                    Array::Integers(vec![])
+                }
             }
             RuntimeType::F32 => {
                 if let Some(repeated) = repeated {
@@ -805,7 +820,9 @@ impl Struct {
                         repeated
                             .into_iter()
                             .map(|value| {
-                                Rc::new(BString::from(value.to_str().unwrap()))
+                                Arc::new(BString::from(
+                                    value.to_str().unwrap(),
+                                ))
                             })
                             .collect(),
                     )
@@ -819,7 +836,7 @@ impl Struct {
                         repeated
                             .into_iter()
                             .map(|value| {
-                                Rc::new(BString::from(
+                                Arc::new(BString::from(
                                     value.to_bytes().unwrap(),
                                 ))
                             })
@@ -847,16 +864,18 @@ impl Struct {
                         repeated
                             .into_iter()
                             .map(|value| {
-                                Rc::new(Self::from_proto_descriptor_and_value(
-                                    msg_descriptor,
-                                    value,
-                                    enum_as_fields,
-                                ))
+                                Arc::new(
+                                    Self::from_proto_descriptor_and_value(
+                                        msg_descriptor,
+                                        value,
+                                        enum_as_fields,
+                                    ),
+                                )
                             })
                             .collect(),
                     )
                 } else {
-                    Array::Structs(vec![Rc::new(
+                    Array::Structs(vec![Arc::new(
                         Struct::from_proto_descriptor_and_msg(
                             msg_descriptor,
                             None,
@@ -867,7 +886,7 @@ impl Struct {
             }
         };
 
-        TypeValue::Array(Rc::new(array))
+        TypeValue::Array(Arc::new(array))
     }
 
     fn new_map(
@@ -898,7 +917,7 @@ impl Struct {
             }
         };
 
-        TypeValue::Map(Rc::new(map))
+        TypeValue::Map(Arc::new(map))
     }
 
     fn new_map_with_integer_key(
@@ -907,8 +926,15 @@ impl Struct {
         enum_as_fields: bool,
         syntax: Syntax,
     ) -> Map {
+        // The deputy value is set even if the map has actual values, as
+        // maps created from messages are also used as the initial value of
+        // global variables, which need the deputy during compilation.
+        let deputy =
+            Some(Self::new_value(value_ty, None, enum_as_fields, syntax));
+
+        let mut result = IndexMap::default();
+
         if let Some(map) = map {
-            let mut result = IndexMap::default();
             for (key, value) in map.into_iter() {
                 result.insert(
                     Self::value_as_i64(key),
@@ -920,18 +946,9 @@ impl Struct {
                     ),
                 );
             }
-            Map::IntegerKeys { deputy: None, map: result }
-        } else {
-            Map::IntegerKeys {
-                deputy: Some(Self::new_value(
-                    value_ty,
-                    None,
-                    enum_as_fields,
-                    syntax,
-                )),
-                map: Default::default(),
-            }
         }
+
+        Map::IntegerKeys { deputy, map: result }
     }
 
     fn new_map_with_string_key(
@@ -940,8 +957,15 @@ impl Struct {
         enum_as_fields: bool,
         syntax: Syntax,
     ) -> Map {
+        // The deputy value is set even if the map has actual values, as
+        // maps created from messages are also used as the initial value of
+        // global variables, which need the deputy during compilation.
+        let deputy =
+            Some(Self::new_value(value_ty, None, enum_as_fields, syntax));
+
+        let mut result = IndexMap::default();
+
         if let Some(map) = map {
-            let mut result = IndexMap::default();
             for (key, value) in map.into_iter() {
                 result.insert(
                     BString::from(Self::value_as_string(key)),
@@ -953,18 +977,9 @@ impl Struct {
                     ),
                 );
             }
-            Map::StringKeys { deputy: None, map: result }
-        } else {
-            Map::StringKeys {
-                deputy: Some(Self::new_value(
-                    value_ty,
-                    None,
-                    enum_as_fields,
-                    syntax,
-                )),
-                map: Default::default(),
-            }
         }
+
+        Map::StringKeys { deputy, map: result }
     }
 
     fn from_proto_descriptor_and_value(
@@ -1047,14 +1062,14 @@ impl PartialEq for Struct {
 mod tests {
     use super::Struct;
     use crate::types::{Array, Type, TypeValue, Value};
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     #[test]
     fn test_struct() {
         let mut root = Struct::default();
         let foo = Struct::default();
 
-        root.add_field("foo", TypeValue::Struct(Rc::new(foo)));
+        root.add_field("foo", TypeValue::Struct(Arc::new(foo)));
         root.add_field("bar", TypeValue::Integer(Value::Var(1)));
 
         let field1 = root.field_by_name("foo").unwrap();
@@ -1074,7 +1089,7 @@ mod tests {
         sub.add_field("string", TypeValue::String(Value::Unknown));
         sub.add_field("boolean", TypeValue::Bool(Value::Unknown));
 
-        let sub = Rc::new(sub);
+        let sub = Arc::new(sub);
 
         let mut a = Struct::default();
         let mut b = Struct::default();
@@ -1084,7 +1099,7 @@ mod tests {
         a.add_field("structure", TypeValue::Struct(sub.clone()));
         a.add_field(
             "floats_array",
-            TypeValue::Array(Rc::new(Array::Floats(vec![]))),
+            TypeValue::Array(Arc::new(Array::Floats(vec![]))),
         );
 
         // At this point a != b because b is still empty.
@@ -1095,7 +1110,7 @@ mod tests {
         b.add_field("structure", TypeValue::Struct(sub));
         b.add_field(
             "floats_array",
-            TypeValue::Array(Rc::new(Array::Floats(vec![]))),
+            TypeValue::Array(Arc::new(Array::Floats(vec![]))),
         );
 
         // At this point a == b.
@@ -8,13 +8,14 @@ API functions like [`crate::Compiler::define_global`] expect Rust types that
 implement the [`Into<Variable>`] trait. This module implements the trait for
 multiple commonly used types like `bool`, `i64`, `&str`, etc.
  */
-use std::rc::Rc;
+use std::sync::Arc;
 
 use bstr::BString;
+use protobuf::MessageDyn;
 use thiserror::Error;
 
 use crate::types;
-use crate::types::{Array, TypeValue, Value};
+use crate::types::{Array, Map, TypeValue, Value};
 
 /// Represents a YARA variable.
 ///
@@ -286,7 +287,7 @@ impl TryFrom<&serde_json::Value> for Variable {
                                             ),
                                         );
                                     }
-                                    structs.push(Rc::new(s));
+                                    structs.push(Arc::new(s));
                                 }
                                 None => {
                                     return Err(VariableError::InvalidArray);
@@ -295,7 +296,7 @@ impl TryFrom<&serde_json::Value> for Variable {
                         }
                     }
                 }
-                Ok(Variable(TypeValue::Array(Rc::new(array))))
+                Ok(Variable(TypeValue::Array(Arc::new(array))))
             }
             serde_json::Value::Object(obj) => {
                 let mut s = types::Struct::new();
@@ -305,12 +306,69 @@ impl TryFrom<&serde_json::Value> for Variable {
                         TypeValue::from(Variable::try_from(value)?),
                     );
                 }
-                Ok(Variable(TypeValue::Struct(Rc::new(s))))
+                Ok(Variable(TypeValue::Struct(Arc::new(s))))
             }
         }
     }
 }
 
+/// Creates a structure from a protobuf message.
+///
+/// Each field in the message becomes a field in the structure. Repeated
+/// fields become arrays, map fields become maps, and fields of message type
+/// become nested structures. Enums defined inside the message are also added
+/// as fields, so that their values can be used in conditions. For example,
+/// if the message defines an enum `Verdict` with a value `MALICIOUS`, the
+/// condition can use `my_var.Verdict.MALICIOUS`.
+///
+/// As with JSON arrays, repeated fields of message type can't be empty.
+impl TryFrom<&dyn MessageDyn> for Variable {
+    type Error = VariableError;
+    fn try_from(value: &dyn MessageDyn) -> Result<Self, Self::Error> {
+        let type_value = TypeValue::Struct(Arc::new(
+            types::Struct::from_proto_descriptor_and_msg(
+                &value.descriptor_dyn(),
+                Some(value),
+                true,
+            ),
+        ));
+        // Arrays of structs need at least one item, which is used as the
+        // representative of the array's type.
+        if has_empty_struct_array(&type_value) {
+            return Err(VariableError::InvalidArray);
+        }
+        Ok(Variable(type_value))
+    }
+}
+
+/// Returns true if `type_value` contains an empty array of structs, at any
+/// level of nesting.
+fn has_empty_struct_array(type_value: &TypeValue) -> bool {
+    match type_value {
+        TypeValue::Struct(s) => s
+            .fields()
+            .any(|(_, field)| has_empty_struct_array(&field.type_value)),
+        TypeValue::Array(array) => match array.as_ref() {
+            Array::Structs(structs) => {
+                structs.is_empty()
+                    || structs.iter().any(|s| {
+                        has_empty_struct_array(&TypeValue::Struct(s.clone()))
+                    })
+            }
+            _ => false,
+        },
+        TypeValue::Map(map) => match map.as_ref() {
+            Map::IntegerKeys { map, .. } => {
+                map.values().any(has_empty_struct_array)
+            }
+            Map::StringKeys { map, .. } => {
+                map.values().any(has_empty_struct_array)
+            }
+        },
+        _ => false,
+    }
+}
+
 impl From<Variable> for TypeValue {
     fn from(value: Variable) -> Self {
         value.0
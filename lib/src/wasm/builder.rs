@@ -160,6 +160,7 @@ impl WasmModuleBuilder {
         }
 
         global_const!(module, matching_patterns_bitmap_base, I32);
+        global_const!(module, disabled_rules_bitmap_base, I32);
         global_var!(module, filesize, I64);
         global_var!(module, pattern_search_done, I32);
         global_var!(module, timeout_occurred, I32);
@@ -170,6 +171,7 @@ impl WasmModuleBuilder {
         let wasm_symbols = WasmSymbols {
             main_memory,
             matching_patterns_bitmap_base,
+            disabled_rules_bitmap_base,
             filesize,
             pattern_search_done,
             timeout_occurred,
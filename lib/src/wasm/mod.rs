@@ -79,7 +79,7 @@ See the [`lookup_field`] function.
  */
 use std::any::{type_name, TypeId};
 use std::mem;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use bstr::{BString, ByteSlice};
 use lazy_static::lazy_static;
@@ -352,33 +352,33 @@ impl WasmArg<RuntimeString> for ValRaw {
     }
 }
 
-impl WasmArg<Rc<Array>> for ValRaw {
+impl WasmArg<Arc<Array>> for ValRaw {
     #[inline]
-    fn raw_into(self, ctx: &mut ScanContext) -> Rc<Array> {
+    fn raw_into(self, ctx: &mut ScanContext) -> Arc<Array> {
         let handle = RuntimeObjectHandle::from(self.get_i64());
         ctx.runtime_objects.get(&handle).unwrap().as_array()
     }
 }
 
-impl WasmArg<Rc<Map>> for ValRaw {
+impl WasmArg<Arc<Map>> for ValRaw {
     #[inline]
-    fn raw_into(self, ctx: &mut ScanContext) -> Rc<Map> {
+    fn raw_into(self, ctx: &mut ScanContext) -> Arc<Map> {
         let handle = RuntimeObjectHandle::from(self.get_i64());
         ctx.runtime_objects.get(&handle).unwrap().as_map()
     }
 }
 
-impl WasmArg<Rc<Struct>> for ValRaw {
+impl WasmArg<Arc<Struct>> for ValRaw {
     #[inline]
-    fn raw_into(self, ctx: &mut ScanContext) -> Rc<Struct> {
+    fn raw_into(self, ctx: &mut ScanContext) -> Arc<Struct> {
         let handle = RuntimeObjectHandle::from(self.get_i64());
         ctx.runtime_objects.get(&handle).unwrap().as_struct()
     }
 }
 
-impl WasmArg<Option<Rc<Struct>>> for ValRaw {
+impl WasmArg<Option<Arc<Struct>>> for ValRaw {
     #[inline]
-    fn raw_into(self, ctx: &mut ScanContext) -> Option<Rc<Struct>> {
+    fn raw_into(self, ctx: &mut ScanContext) -> Option<Arc<Struct>> {
         let handle = RuntimeObjectHandle::from(self.get_i64());
         if handle == RuntimeObjectHandle::NULL {
             return None;
@@ -480,9 +480,9 @@ impl WasmResult for RuntimeObjectHandle {
     }
 }
 
-impl WasmResult for Rc<BString> {
+impl WasmResult for Arc<BString> {
     fn values(self, ctx: &mut ScanContext) -> WasmResultArray<ValRaw> {
-        let s = RuntimeString::Rc(self);
+        let s = RuntimeString::Arc(self);
         smallvec![ValRaw::i64(s.into_wasm_with_ctx(ctx))]
     }
 
@@ -491,7 +491,7 @@ impl WasmResult for Rc<BString> {
     }
 }
 
-impl WasmResult for Rc<Struct> {
+impl WasmResult for Arc<Struct> {
     fn values(self, ctx: &mut ScanContext) -> WasmResultArray<ValRaw> {
         let handle = ctx.store_struct(self);
         smallvec![ValRaw::i64(handle.into())]
@@ -583,13 +583,13 @@ fn type_id_to_wasmtime(
         return &[];
     } else if type_id == TypeId::of::<RuntimeString>() {
         return &[wasmtime::ValType::I64];
-    } else if type_id == TypeId::of::<Option<Rc<Struct>>>() {
+    } else if type_id == TypeId::of::<Option<Arc<Struct>>>() {
         return &[wasmtime::ValType::I64];
-    } else if type_id == TypeId::of::<Rc<Struct>>() {
+    } else if type_id == TypeId::of::<Arc<Struct>>() {
         return &[wasmtime::ValType::I64];
-    } else if type_id == TypeId::of::<Rc<Array>>() {
+    } else if type_id == TypeId::of::<Arc<Array>>() {
         return &[wasmtime::ValType::I64];
-    } else if type_id == TypeId::of::<Rc<Map>>() {
+    } else if type_id == TypeId::of::<Arc<Map>>() {
         return &[wasmtime::ValType::I64];
     }
     panic!("type `{}` can't be an argument", type_name)
@@ -683,6 +683,11 @@ pub(crate) struct WasmSymbols {
     /// or not.
     pub matching_patterns_bitmap_base: walrus::GlobalId,
 
+    /// Global variable that contains the offset within the module's main
+    /// memory where resides the bitmap that indicates if a rule is disabled
+    /// or not.
+    pub disabled_rules_bitmap_base: walrus::GlobalId,
+
     /// Global variable that contains the value for `filesize`.
     pub filesize: walrus::GlobalId,
 
@@ -708,12 +713,149 @@ lazy_static! {
         let mut config = Config::default();
         config.cranelift_opt_level(wasmtime::OptLevel::SpeedAndSize);
         config.epoch_interruption(true);
+        #[cfg(feature = "async")]
+        config.async_support(true);
+        #[cfg(feature = "pooling-allocator")]
+        config.allocation_strategy(
+            wasmtime::InstanceAllocationStrategy::Pooling(pooling_config()),
+        );
+        // If the cache configuration can't be loaded, compiled code is not
+        // cached, but that's not a reason for failing.
+        #[cfg(feature = "wasm-cache")]
+        let _ = match std::env::var_os("YRX_WASM_CACHE_CONFIG") {
+            Some(path) => config.cache_config_load(path),
+            None => config.cache_config_load_default(),
+        };
         config
     };
     pub(crate) static ref ENGINE: Engine = Engine::new(&CONFIG).unwrap();
     pub(crate) static ref LINKER: Linker<ScanContext<'static>> = new_linker();
 }
 
+/// Default maximum number of WASM instances (i.e: scanners) that can exist
+/// at the same time when the pooling allocator is used.
+#[cfg(feature = "pooling-allocator")]
+const DEFAULT_POOLING_MAX_INSTANCES: u32 = 1000;
+
+/// Default number of unused slots that the pooling allocator keeps warm
+/// for being reused by new instances.
+#[cfg(feature = "pooling-allocator")]
+const DEFAULT_POOLING_MAX_UNUSED_WARM_SLOTS: u32 = 100;
+
+/// Limits for wasmtime's pooling instance allocator, which is used when
+/// the `pooling-allocator` feature is enabled.
+///
+/// The limits are fixed when the WASM engine is created, which happens
+/// the first time some rules are compiled or deserialized, see
+/// [`set_pooling_limits`].
+#[cfg(feature = "pooling-allocator")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolingLimits {
+    max_instances: u32,
+    max_unused_warm_slots: u32,
+}
+
+#[cfg(feature = "pooling-allocator")]
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            max_instances: DEFAULT_POOLING_MAX_INSTANCES,
+            max_unused_warm_slots: DEFAULT_POOLING_MAX_UNUSED_WARM_SLOTS,
+        }
+    }
+}
+
+#[cfg(feature = "pooling-allocator")]
+impl PoolingLimits {
+    /// Creates the default limits, which allow 1000 scanners to exist at
+    /// the same time and keep 100 unused slots warm.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of scanners that can exist at the same time.
+    pub fn max_instances(mut self, n: u32) -> Self {
+        self.max_instances = n;
+        self
+    }
+
+    /// Maximum number of unused slots that are kept warm for being reused
+    /// by new scanners.
+    pub fn max_unused_warm_slots(mut self, n: u32) -> Self {
+        self.max_unused_warm_slots = n;
+        self
+    }
+
+    /// Returns the default limits, overridden by the following environment
+    /// variables:
+    ///
+    /// * `YRX_POOLING_MAX_INSTANCES`: maximum number of scanners that can
+    ///   exist at the same time.
+    /// * `YRX_POOLING_MAX_UNUSED_WARM_SLOTS`: maximum number of unused slots
+    ///   that are kept warm for reuse.
+    fn from_env() -> Self {
+        let env_limit = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_instances: env_limit(
+                "YRX_POOLING_MAX_INSTANCES",
+                DEFAULT_POOLING_MAX_INSTANCES,
+            ),
+            max_unused_warm_slots: env_limit(
+                "YRX_POOLING_MAX_UNUSED_WARM_SLOTS",
+                DEFAULT_POOLING_MAX_UNUSED_WARM_SLOTS,
+            ),
+        }
+    }
+}
+
+/// Limits set with [`set_pooling_limits`], or read from the environment
+/// when the engine is created if they were not set.
+#[cfg(feature = "pooling-allocator")]
+static POOLING_LIMITS: std::sync::OnceLock<PoolingLimits> =
+    std::sync::OnceLock::new();
+
+/// Sets the limits for wasmtime's pooling instance allocator.
+///
+/// The limits are shared by all scanners, and they must be set before any
+/// rules are compiled or deserialized, as that's when the WASM engine is
+/// created. Returns `false` if that's not the case, or if the limits were
+/// already set, as they can't be changed afterwards. Limits set with this function take precedence over the ones
+/// set with the `YRX_POOLING_MAX_INSTANCES` and
+/// `YRX_POOLING_MAX_UNUSED_WARM_SLOTS` environment variables.
+#[cfg(feature = "pooling-allocator")]
+pub fn set_pooling_limits(limits: PoolingLimits) -> bool {
+    POOLING_LIMITS.set(limits).is_ok()
+}
+
+/// Returns the configuration for wasmtime's pooling instance allocator.
+///
+/// The limits are the ones set with [`set_pooling_limits`], or the ones
+/// read from the environment, see [`PoolingLimits::from_env`].
+///
+/// The main memory is created by the host and imported by the WASM module,
+/// so it is not allocated from the pool and no memory slots are reserved.
+#[cfg(feature = "pooling-allocator")]
+fn pooling_config() -> wasmtime::PoolingAllocationConfig {
+    let limits = POOLING_LIMITS.get_or_init(PoolingLimits::from_env);
+    let mut config = wasmtime::PoolingAllocationConfig::default();
+
+    config
+        .total_core_instances(limits.max_instances)
+        .total_tables(limits.max_instances)
+        .total_stacks(limits.max_instances)
+        .total_memories(0)
+        .max_memories_per_module(0)
+        .max_unused_warm_slots(limits.max_unused_warm_slots);
+
+    config
+}
+
 pub(crate) fn new_linker<'r>() -> Linker<ScanContext<'r>> {
     let mut linker = Linker::<ScanContext<'r>>::new(&ENGINE);
     for export in WASM_EXPORTS {
@@ -751,16 +893,47 @@ pub(crate) fn log_rule_eval_start(
     caller.data_mut().log_rule_eval_start(rule_id);
 }
 
+/// Invoked from WASM before starting the evaluation of the rule identified
+/// by the given [`RuleId`]. This only happens when the "rules-profiling"
+/// feature is enabled.
+#[wasm_export]
+#[cfg(feature = "rules-profiling")]
+pub(crate) fn rule_eval_start(
+    caller: &mut Caller<'_, ScanContext>,
+    rule_id: RuleId,
+) {
+    caller.data_mut().rule_eval_start(rule_id);
+}
+
+/// Invoked from WASM after evaluating the condition of the rule identified
+/// by the given [`RuleId`]. This only happens when the "rules-profiling"
+/// feature is enabled.
+#[wasm_export]
+#[cfg(feature = "rules-profiling")]
+pub(crate) fn rule_eval_end(
+    caller: &mut Caller<'_, ScanContext>,
+    rule_id: RuleId,
+) {
+    caller.data_mut().rule_eval_end(rule_id);
+}
+
 /// Invoked from WASM for triggering the pattern search phase.
 ///
-/// Returns `true` on success and `false` when a timeout occurs.
+/// Returns `true` on success and `false` when a timeout occurs or the scan
+/// is cancelled.
 #[wasm_export]
 pub(crate) fn search_for_patterns(
     caller: &mut Caller<'_, ScanContext>,
 ) -> bool {
-    match caller.data_mut().search_for_patterns() {
+    let main_memory = caller.data().main_memory.unwrap();
+    let (mem, ctx) = main_memory.data_and_store_mut(caller);
+
+    let result = ctx.search_for_patterns();
+    ctx.update_matching_patterns_bitmap(mem);
+
+    match result {
         Ok(_) => true,
-        Err(ScanError::Timeout) => false,
+        Err(ScanError::Timeout | ScanError::Cancelled) => false,
         Err(_) => unreachable!(),
     }
 }
@@ -771,7 +944,9 @@ pub(crate) fn rule_match(
     caller: &mut Caller<'_, ScanContext>,
     rule_id: RuleId,
 ) {
-    caller.data_mut().track_rule_match(rule_id);
+    let main_memory = caller.data().main_memory.unwrap();
+    let (mem, ctx) = main_memory.data_and_store_mut(caller);
+    ctx.track_rule_match(mem, rule_id);
 }
 
 /// Invoked from WASM to notify when a global rule doesn't match.
@@ -780,7 +955,9 @@ pub(crate) fn global_rule_no_match(
     caller: &mut Caller<'_, ScanContext>,
     rule_id: RuleId,
 ) {
-    caller.data_mut().track_global_rule_no_match(rule_id);
+    let main_memory = caller.data().main_memory.unwrap();
+    let (mem, ctx) = main_memory.data_and_store_mut(caller);
+    ctx.track_global_rule_no_match(mem, rule_id);
 }
 
 /// Invoked from WASM to ask whether a pattern matches at a given file
@@ -904,14 +1081,14 @@ pub(crate) fn pat_offset(
 #[wasm_export]
 pub(crate) fn array_len(
     _: &mut Caller<'_, ScanContext>,
-    array: Rc<Array>,
+    array: Arc<Array>,
 ) -> i64 {
     array.len() as i64
 }
 
 /// Called from WASM to obtain the length of a map.
 #[wasm_export]
-pub(crate) fn map_len(_: &mut Caller<'_, ScanContext>, map: Rc<Map>) -> i64 {
+pub(crate) fn map_len(_: &mut Caller<'_, ScanContext>, map: Arc<Map>) -> i64 {
     map.len() as i64
 }
 
@@ -945,7 +1122,7 @@ pub(crate) fn map_len(_: &mut Caller<'_, ScanContext>, map: Rc<Map>) -> i64 {
 /// argument `num_lookup_indexes`.
 fn lookup_field(
     caller: &mut Caller<'_, ScanContext>,
-    structure: Option<Rc<Struct>>,
+    structure: Option<Arc<Struct>>,
     num_lookup_indexes: i32,
 ) -> TypeValue /* TODO: make this a &TypeValue? */ {
     assert!(num_lookup_indexes > 0);
@@ -1008,12 +1185,12 @@ fn lookup_field(
 #[wasm_export]
 pub(crate) fn lookup_string(
     caller: &mut Caller<'_, ScanContext>,
-    structure: Option<Rc<Struct>>,
+    structure: Option<Arc<Struct>>,
     num_lookup_indexes: i32,
 ) -> Option<RuntimeString> {
     match lookup_field(caller, structure, num_lookup_indexes) {
-        TypeValue::String(Value::Var(s)) => Some(RuntimeString::Rc(s)),
-        TypeValue::String(Value::Const(s)) => Some(RuntimeString::Rc(s)),
+        TypeValue::String(Value::Var(s)) => Some(RuntimeString::Arc(s)),
+        TypeValue::String(Value::Const(s)) => Some(RuntimeString::Arc(s)),
         TypeValue::String(Value::Unknown) => None,
         _ => unreachable!(),
     }
@@ -1025,7 +1202,7 @@ pub(crate) fn lookup_string(
 #[wasm_export]
 pub(crate) fn lookup_object(
     caller: &mut Caller<'_, ScanContext>,
-    structure: Option<Rc<Struct>>,
+    structure: Option<Arc<Struct>>,
     num_lookup_indexes: i32,
 ) -> RuntimeObjectHandle {
     let type_value = lookup_field(caller, structure, num_lookup_indexes);
@@ -1043,7 +1220,7 @@ macro_rules! gen_lookup_fn {
         #[wasm_export]
         pub(crate) fn $name(
             caller: &mut Caller<'_, ScanContext>,
-            structure: Option<Rc<Struct>>,
+            structure: Option<Arc<Struct>>,
             num_lookup_indexes: i32,
         ) -> Option<$return_type> {
             if let $type(value) =
@@ -1066,7 +1243,7 @@ macro_rules! gen_array_indexing_fn {
         #[wasm_export]
         pub(crate) fn $name(
             _: &mut Caller<'_, ScanContext>,
-            array: Rc<Array>,
+            array: Arc<Array>,
             index: i64,
         ) -> Option<$return_type> {
             array.$fn().get(index as usize).map(|value| *value)
@@ -1082,9 +1259,9 @@ gen_array_indexing_fn!(array_indexing_bool, as_bool_array, bool);
 #[rustfmt::skip]
 pub(crate) fn array_indexing_string(
     _: &mut Caller<'_, ScanContext>,
-    array: Rc<Array>,
+    array: Arc<Array>,
     index: i64,
-) -> Option<Rc<BString>> {
+) -> Option<Arc<BString>> {
     array
         .as_string_array()
         .get(index as usize)
@@ -1095,9 +1272,9 @@ pub(crate) fn array_indexing_string(
 #[rustfmt::skip]
 pub(crate) fn array_indexing_struct(
     _: &mut Caller<'_, ScanContext>,
-    array: Rc<Array>,
+    array: Arc<Array>,
     index: i64,
-) -> Option<Rc<Struct>> { 
+) -> Option<Arc<Struct>> { 
     array
         .as_struct_array()
         .get(index as usize)
@@ -1145,7 +1322,7 @@ macro_rules! gen_map_lookup_fn {
         #[wasm_export]
         pub(crate) fn $name(
             _: &mut Caller<'_, ScanContext>,
-            map: Rc<Map>,
+            map: Arc<Map>,
             key: i64,
         ) -> Option<$return_type> {
             map.$with().get(&key).map(|v| v.$as())
@@ -1155,7 +1332,7 @@ macro_rules! gen_map_lookup_fn {
         #[wasm_export]
         pub(crate) fn $name(
             caller: &mut Caller<'_, ScanContext>,
-            map: Rc<Map>,
+            map: Arc<Map>,
             key: RuntimeString,
         ) -> Option<$return_type> {
             let key = key.as_bstr(caller.data());
@@ -1209,18 +1386,18 @@ gen_map_lookup_fn!(
 #[wasm_export]
 pub(crate) fn map_lookup_integer_string(
     _: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     key: i64,
-) -> Option<Rc<BString>> {
+) -> Option<Arc<BString>> {
     map.with_integer_keys().get(&key).map(|s| s.as_string())
 }
 
 #[wasm_export]
 pub(crate) fn map_lookup_string_string(
     caller: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     key: RuntimeString,
-) -> Option<Rc<BString>> {
+) -> Option<Arc<BString>> {
     let key = key.as_bstr(caller.data());
     map.with_string_keys().get(key).map(|s| s.as_string())
 }
@@ -1228,18 +1405,18 @@ pub(crate) fn map_lookup_string_string(
 #[wasm_export]
 pub(crate) fn map_lookup_integer_struct(
     _: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     key: i64,
-) -> Option<Rc<Struct>> {
+) -> Option<Arc<Struct>> {
     map.with_integer_keys().get(&key).map(|v| v.as_struct())
 }
 
 #[wasm_export]
 pub(crate) fn map_lookup_string_struct(
     caller: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     key: RuntimeString,
-) -> Option<Rc<Struct>> {
+) -> Option<Arc<Struct>> {
     let key = key.as_bstr(caller.data());
     map.with_string_keys().get(key).map(|v| v.as_struct())
 }
@@ -1249,12 +1426,12 @@ macro_rules! gen_map_lookup_by_index_fn {
         #[wasm_export]
         pub(crate) fn $name(
             _: &mut Caller<'_, ScanContext>,
-            map: Rc<Map>,
+            map: Arc<Map>,
             index: i64,
-        ) -> (Rc<BString>, $val) {
+        ) -> (Arc<BString>, $val) {
             map.with_string_keys()
                 .get_index(index as usize)
-                .map(|(key, value)| (Rc::new(key.clone()), value.$as()))
+                .map(|(key, value)| (Arc::new(key.clone()), value.$as()))
                 .unwrap()
         }
     };
@@ -1262,7 +1439,7 @@ macro_rules! gen_map_lookup_by_index_fn {
         #[wasm_export]
         pub(crate) fn $name(
             _: &mut Caller<'_, ScanContext>,
-            map: Rc<Map>,
+            map: Arc<Map>,
             index: i64,
         ) -> ($key, $val) {
             map.$with()
@@ -1330,9 +1507,9 @@ gen_map_lookup_by_index_fn!(
 #[wasm_export]
 pub(crate) fn map_lookup_by_index_integer_string(
     _: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     index: i64,
-) -> (i64, Rc<BString>) {
+) -> (i64, Arc<BString>) {
     map.with_integer_keys()
         .get_index(index as usize)
         .map(|(key, value)| (*key, value.as_string()))
@@ -1342,13 +1519,13 @@ pub(crate) fn map_lookup_by_index_integer_string(
 #[wasm_export]
 pub(crate) fn map_lookup_by_index_string_string(
     _: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     index: i64,
-) -> (Rc<BString>, Rc<BString>) {
+) -> (Arc<BString>, Arc<BString>) {
     map.with_string_keys()
         .get_index(index as usize)
         .map(|(key, value)| {
-            (Rc::new(key.as_bstr().to_owned()), value.as_string())
+            (Arc::new(key.as_bstr().to_owned()), value.as_string())
         })
         .unwrap()
 }
@@ -1356,9 +1533,9 @@ pub(crate) fn map_lookup_by_index_string_string(
 #[wasm_export]
 pub(crate) fn map_lookup_by_index_integer_struct(
     _: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     index: i64,
-) -> (i64, Rc<Struct>) {
+) -> (i64, Arc<Struct>) {
     map.with_integer_keys()
         .get_index(index as usize)
         .map(|(key, value)| (*key, value.as_struct()))
@@ -1368,13 +1545,13 @@ pub(crate) fn map_lookup_by_index_integer_struct(
 #[wasm_export]
 pub(crate) fn map_lookup_by_index_string_struct(
     _: &mut Caller<'_, ScanContext>,
-    map: Rc<Map>,
+    map: Arc<Map>,
     index: i64,
-) -> (Rc<BString>, Rc<Struct>) {
+) -> (Arc<BString>, Arc<Struct>) {
     map.with_string_keys()
         .get_index(index as usize)
         .map(|(key, value)| {
-            (Rc::new(key.as_bstr().to_owned()), value.as_struct())
+            (Arc::new(key.as_bstr().to_owned()), value.as_struct())
         })
         .unwrap()
 }
@@ -1,5 +1,5 @@
 use bstr::{BStr, BString, ByteSlice, Utf8Error};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::compiler::LiteralId;
 use crate::scanner::{RuntimeObject, RuntimeObjectHandle, ScanContext};
@@ -23,7 +23,7 @@ use crate::utils::cast;
 ///    If the two lower bits are equal to 1, it's a literal string, where the
 ///    remaining bits represent the `LiteralId`.
 ///
-/// * `RuntimeString:Rc`  -> `RuntimeStringId << 2 | 2`
+/// * `RuntimeString:Arc` -> `RuntimeStringId << 2 | 2`
 ///    If the two lower bits are equal to 2, it's a runtime string, where the
 ///    remaining bits represent the handle of a string object.
 ///
@@ -65,7 +65,7 @@ pub(crate) enum RuntimeString {
     /// the data and its length.
     ScannedDataSlice { offset: usize, length: usize },
     /// A reference-counted string.
-    Rc(Rc<BString>),
+    Arc(Arc<BString>),
 }
 
 impl Default for RuntimeString {
@@ -78,7 +78,7 @@ impl RuntimeString {
     /// Creates a [`RuntimeString`] from a [`String`], a [`Vec<u8>`] or any
     /// type that implements [`Into<Vec<u8>>`].
     pub(crate) fn new<S: Into<Vec<u8>>>(s: S) -> Self {
-        Self::Rc(Rc::new(BString::new(s.into())))
+        Self::Arc(Arc::new(BString::new(s.into())))
     }
 
     /// Creates a [`RuntimeString`] from a reference to a byte slice.
@@ -88,7 +88,7 @@ impl RuntimeString {
     /// doesn't need to copy the string data.
     ///
     /// In any other case it makes a copy of the string and return the
-    /// [`RuntimeString::Rc`] variant.
+    /// [`RuntimeString::Arc`] variant.
     pub(crate) fn from_slice(ctx: &ScanContext, s: &[u8]) -> Self {
         let data = ctx.scanned_data();
 
@@ -104,7 +104,7 @@ impl RuntimeString {
                 length: s.len(),
             }
         } else {
-            Self::Rc(Rc::new(BString::from(s)))
+            Self::Arc(Arc::new(BString::from(s)))
         }
     }
 
@@ -118,7 +118,7 @@ impl RuntimeString {
                 let data = ctx.scanned_data();
                 BStr::new(&data[*offset..*offset + *length])
             }
-            Self::Rc(s) => s.as_bstr(),
+            Self::Arc(s) => s.as_bstr(),
         }
     }
 
@@ -140,7 +140,7 @@ impl RuntimeString {
     ) -> RuntimeStringWasm {
         match self {
             Self::Literal(id) => i64::from(id) << 2,
-            Self::Rc(s) => {
+            Self::Arc(s) => {
                 let handle: i64 = ctx.store_string(s).into();
                 handle << 2 | 1
             }
@@ -177,7 +177,7 @@ impl RuntimeString {
                     ctx.runtime_objects.get(&handle).unwrap(),
                     RuntimeObject::String
                 );
-                Self::Rc(s.clone())
+                Self::Arc(s.clone())
             }
             2 => Self::ScannedDataSlice {
                 offset: (s >> 18) as usize,
@@ -16,7 +16,7 @@ pub(crate) fn impl_error_macro(
 ) -> syn::Result<TokenStream> {
     let name = &input.ident;
 
-    let (variants, funcs) = match &input.data {
+    let (variants, funcs, accessors) = match &input.data {
         syn::Data::Struct(_) | syn::Data::Union(_) => {
             return Err(syn::Error::new(
                 name.span(),
@@ -27,6 +27,8 @@ pub(crate) fn impl_error_macro(
         syn::Data::Enum(data_enum) => impl_enum_error_macro(data_enum)?,
     };
 
+    let Accessors { codes, titles, spans, levels } = accessors;
+
     let (impl_generics, ty_generics, where_clause) =
         input.generics.split_for_impl();
 
@@ -36,6 +38,42 @@ pub(crate) fn impl_error_macro(
         #[automatically_derived]
         impl #impl_generics #name #ty_generics #where_clause {
             #(#funcs)*
+
+            /// Returns a code that identifies the kind of error or warning,
+            /// like `duplicate_tag`. This is the name of the enum variant in
+            /// snake-case.
+            pub fn code(&self) -> &'static str {
+                match self {
+                    #(#codes)*
+                }
+            }
+
+            /// Returns the title of the error or warning, like
+            /// "duplicate tag `foo`". This is the first line of the detailed
+            /// report, without the `error:` or `warning:` prefix.
+            #[allow(unused_variables)]
+            pub fn title(&self) -> String {
+                match self {
+                    #(#titles)*
+                }
+            }
+
+            /// Returns the span of the code that caused the error or
+            /// warning. This is the span of the main label in the detailed
+            /// report.
+            pub fn span(&self) -> Span {
+                match self {
+                    #(#spans)*
+                }
+            }
+
+            /// Returns the severity level, which is [`Level::Error`] for
+            /// errors and [`Level::Warning`] for warnings.
+            pub fn level(&self) -> Level {
+                match self {
+                    #(#levels)*
+                }
+            }
         }
 
         #[automatically_derived]
@@ -69,24 +107,98 @@ pub(crate) fn impl_error_macro(
     })
 }
 
+/// Match arms for the accessor methods generated for the enum. Each vector
+/// contains one arm per variant.
+#[derive(Default)]
+struct Accessors {
+    codes: Vec<TokenStream>,
+    titles: Vec<TokenStream>,
+    spans: Vec<TokenStream>,
+    levels: Vec<TokenStream>,
+}
+
 fn impl_enum_error_macro(
     data_enum: &DataEnum,
-) -> syn::Result<(Vec<&Ident>, Vec<TokenStream>)> {
+) -> syn::Result<(Vec<&Ident>, Vec<TokenStream>, Accessors)> {
     // Generate a proto function for each variant in the enum labelled
     // with #[error(...)] or #[warning(...)].
     let mut funcs = Vec::new();
     let mut variants = Vec::new();
+    let mut accessors = Accessors::default();
     // For each variant in the enum...
     for variant in &data_enum.variants {
         // ...look for #[error(...)] or #[warning(...)] attributes.
         for attr in &variant.attrs {
             if let Some((attr_type, attr_args)) = parse_attr(attr)? {
                 variants.push(&variant.ident);
+                gen_accessor_arms(
+                    attr_type,
+                    &attr_args,
+                    variant,
+                    &mut accessors,
+                )?;
                 funcs.push(gen_build_func(attr_type, attr_args, variant)?);
             }
         }
     }
-    Ok((variants, funcs))
+    Ok((variants, funcs, accessors))
+}
+
+// Given an error or warning variant, generates the match arms for the
+// `code`, `title`, `span` and `level` methods that correspond to this
+// variant.
+fn gen_accessor_arms(
+    report_type: &str,
+    attr_args: &AttrArgs,
+    variant: &Variant,
+    accessors: &mut Accessors,
+) -> syn::Result<()> {
+    let variant_ident = &variant.ident;
+    let code = variant_ident.to_string().to_case(Case::Snake);
+
+    // The span of the main label is the span of the error or warning.
+    let labels = get_labels(report_type, variant)?;
+    let main_label_span = &labels
+        .first()
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                variant,
+                "#[error(...)] must be accompanied by at least one instance of #[label(...)}",
+            )
+        })?
+        .0;
+
+    let field_identifiers = variant
+        .fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .filter(|ident| *ident != "detailed_report");
+
+    let level = match report_type {
+        "error" => quote!(Level::Error),
+        "warning" => quote!(Level::Warning),
+        _ => unreachable!(),
+    };
+
+    accessors.codes.push(quote!(
+        Self::#variant_ident { .. } => #code,
+    ));
+
+    accessors.titles.push(quote!(
+        Self::#variant_ident { #( #field_identifiers, )* .. } => {
+            format!(#attr_args)
+        }
+    ));
+
+    accessors.spans.push(quote!(
+        Self::#variant_ident { #main_label_span, .. } => *#main_label_span,
+    ));
+
+    accessors.levels.push(quote!(
+        Self::#variant_ident { .. } => #level,
+    ));
+
+    Ok(())
 }
 
 // Checks if an attribute is #[error(...)] and returns its arguments if that's
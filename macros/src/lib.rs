@@ -145,6 +145,16 @@ pub fn span_macro_derive(input: TokenStream) -> TokenStream {
 ///     tag: String,
 ///     tag_span: Span) -> Error
 /// ```
+///
+/// The macro also generates the following methods, which give access to
+/// the error or warning in a structured way:
+///
+/// - `code(&self) -> &'static str`: the variant name in snake-case (e.g.
+///   `duplicate_tag`).
+/// - `title(&self) -> String`: the title built from `#[error(...)]` or
+///   `#[warning(...)]`.
+/// - `span(&self) -> Span`: the span associated to the first label.
+/// - `level(&self) -> Level`: `Level::Error` or `Level::Warning`.
 #[proc_macro_derive(Error, attributes(error, warning, label, note))]
 pub fn error_macro_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -197,9 +207,9 @@ pub fn module_main(_attr: TokenStream, input: TokenStream) -> TokenStream {
 /// - `RuntimeString`
 /// - `RuleId`
 /// - `PatternId`
-/// - `Rc<Struct>`
-/// - `Rc<Map>`
-/// - `Rc<Array>`
+/// - `Arc<Struct>`
+/// - `Arc<Map>`
+/// - `Arc<Array>`
 ///
 /// # Example
 ///
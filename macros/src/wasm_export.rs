@@ -38,7 +38,7 @@ impl<'ast> FuncSignatureParser<'ast> {
             "RegexpId" => Ok(Cow::Borrowed("r")),
             "RuntimeString" => Ok(Cow::Borrowed("s")),
             "RuntimeObjectHandle" => Ok(Cow::Borrowed("i")),
-            "Rc" => Ok(Cow::Borrowed("i")),
+            "Arc" => Ok(Cow::Borrowed("i")),
             type_ident => Err(syn::Error::new_spanned(
                 type_path,
                 format!(
@@ -424,6 +424,35 @@ pub(crate) fn expr_ascii_tree(expr: &Expr) -> Tree {
 
             Node(node_title, children)
         }
+        Expr::With(w) => {
+            let mut children = Vec::new();
+
+            for (i, declaration) in w.declarations.iter().enumerate() {
+                children.push(Node(
+                    format!("<identifier{i}>"),
+                    vec![Leaf(vec![declaration.identifier.name.to_string()])],
+                ));
+                children.push(Node(
+                    format!("<expr{i}>"),
+                    vec![expr_ascii_tree(&declaration.expression)],
+                ));
+            }
+
+            let comma_sep_labels = (0..w.declarations.len())
+                .map(|i| format!("<identifier{i}> = <expr{i}>"))
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            children.push(Node(
+                "<condition>".to_string(),
+                vec![expr_ascii_tree(&w.condition)],
+            ));
+
+            Node(
+                format!("with {comma_sep_labels} : ( <condition> )"),
+                children,
+            )
+        }
     }
 }
 
@@ -24,7 +24,7 @@ mod span;
 
 use std::borrow::Cow;
 use std::collections::btree_map::Values;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::slice::Iter;
@@ -42,6 +42,8 @@ pub struct AST<'src> {
     pub source: SourceCode<'src>,
     /// The list of imports.
     pub imports: Vec<Import>,
+    /// The list of includes.
+    pub includes: Vec<Include>,
     /// The list of rules in the AST.
     pub rules: Vec<Rule<'src>>,
     /// Warnings generated while building this AST.
@@ -92,12 +94,19 @@ pub struct Import {
     pub module_name: String,
 }
 
+/// An include statement.
+#[derive(Debug, HasSpan)]
+pub struct Include {
+    pub span: Span,
+    pub file_name: String,
+}
+
 /// A YARA rule.
 #[derive(Debug)]
 pub struct Rule<'src> {
     pub flags: RuleFlags,
     pub identifier: Ident<'src>,
-    pub tags: Option<HashSet<&'src str>>,
+    pub tags: Option<Vec<Ident<'src>>>,
     pub meta: Option<Vec<Meta<'src>>>,
     pub patterns: Option<Vec<Pattern<'src>>>,
     pub condition: Expr<'src>,
@@ -116,7 +125,9 @@ pub enum MetaValue<'src> {
     Bool(bool),
     Integer(i64),
     Float(f64),
-    String(&'src str),
+    /// The string's value, with escape sequences already unescaped. Doesn't
+    /// include the quotes.
+    String(Cow<'src, BStr>),
 }
 
 impl<'src> Display for MetaValue<'src> {
@@ -125,7 +136,7 @@ impl<'src> Display for MetaValue<'src> {
             Self::Bool(v) => write!(f, "{}", v),
             Self::Integer(v) => write!(f, "{}", v),
             Self::Float(v) => write!(f, "{:.1}", v),
-            Self::String(v) => write!(f, "{}", v),
+            Self::String(v) => write!(f, "{:?}", v),
         }
     }
 }
@@ -566,6 +577,9 @@ pub enum Expr<'src> {
 
     /// A `for <quantifier> <vars> in ...` expression. (e.g. `for all i in (1..100) : ( ... )`)
     ForIn(Box<ForIn<'src>>),
+
+    /// A `with <declarations> : ...` expression. (e.g. `with foo = 1 + 1 : ( ... )`)
+    With(Box<With<'src>>),
 }
 
 /// A pattern match expression (e.g. `$a`, `$b at 0`, `$c in (0..10)`).
@@ -855,6 +869,22 @@ pub struct ForIn<'src> {
     pub condition: Expr<'src>,
 }
 
+/// A `with` expression (e.g `with foo = 1 + 1 : (foo == 2)`)
+#[derive(Debug, HasSpan)]
+pub struct With<'src> {
+    pub span: Span,
+    pub declarations: Vec<WithDeclaration<'src>>,
+    pub condition: Expr<'src>,
+}
+
+/// Each of the declarations in a `with` expression (e.g. `foo = 1 + 1`).
+#[derive(Debug, HasSpan)]
+pub struct WithDeclaration<'src> {
+    pub span: Span,
+    pub identifier: Ident<'src>,
+    pub expression: Expr<'src>,
+}
+
 /// Items in a `of` expression.
 #[derive(Debug)]
 pub enum OfItems<'src> {
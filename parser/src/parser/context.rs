@@ -35,6 +35,9 @@ pub(crate) struct Context<'src, 'rb> {
     /// While parsing a pattern declaration this holds its identifier.
     pub(crate) current_pattern: Option<Ident<'src>>,
 
+    /// If true, unused patterns produce warnings instead of errors.
+    pub(crate) unused_patterns_as_warnings: bool,
+
     /// Used for building error messages and warnings.
     pub(crate) report_builder: &'rb ReportBuilder,
 
@@ -49,6 +52,7 @@ impl<'src, 'rb> Context<'src, 'rb> {
             declared_patterns: HashMap::new(),
             unused_patterns: HashSet::new(),
             current_pattern: None,
+            unused_patterns_as_warnings: false,
             report_builder,
             warnings: Warnings::default(),
         }
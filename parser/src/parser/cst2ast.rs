@@ -106,6 +106,18 @@ fn create_binary_expr<'src>(
 ) -> Result<Expr<'src>, Error> {
     match op {
         GrammarRule::DOT => {
+            // `filesize` and `entrypoint` are keywords, but at the right of
+            // the field access operator they are field names, like in
+            // `macho.segments[0].filesize`.
+            let rhs = match rhs {
+                Expr::Filesize { span } => {
+                    Expr::Ident(Box::new(Ident::new("filesize", span)))
+                }
+                Expr::Entrypoint { span } => {
+                    Expr::Ident(Box::new(Ident::new("entrypoint", span)))
+                }
+                rhs => rhs,
+            };
             new_n_ary_expr!(Expr::FieldAccess, lhs, rhs)
         }
         // Boolean
@@ -280,16 +292,21 @@ fn check_pattern_modifiers(
     Ok(())
 }
 
+/// Imports, includes and rules declared at the top level of a source file.
+type TopLevelItems<'src> = (Vec<Import>, Vec<Include>, Vec<Rule<'src>>);
+
 pub(crate) fn ast_from_cst<'src>(
     ctx: &mut Context<'src, '_>,
     cst: CST<'src>,
-) -> Result<(Vec<Import>, Vec<Rule<'src>>), Error> {
+) -> Result<TopLevelItems<'src>, Error> {
     let mut imports: Vec<Import> = Vec::new();
+    let mut includes: Vec<Include> = Vec::new();
     let mut rules: Vec<Rule> = Vec::new();
 
     for node in cst {
         match node.as_rule() {
-            // Top level rules are either import statements...
+            // Top level rules are either import statements, include
+            // statements...
             GrammarRule::import_stmt => {
                 let span = ctx.span(&node);
                 let mut children = node.into_inner();
@@ -303,6 +320,17 @@ pub(crate) fn ast_from_cst<'src>(
                     module_name: module_name.to_string(),
                 });
             }
+            GrammarRule::include_stmt => {
+                let span = ctx.span(&node);
+                let mut children = node.into_inner();
+                expect!(children.next().unwrap(), GrammarRule::k_INCLUDE);
+
+                let file_name =
+                    utf8_string_lit_from_cst(ctx, children.next().unwrap())?;
+
+                includes
+                    .push(Include { span, file_name: file_name.to_string() });
+            }
             // .. or rule declarations.
             GrammarRule::rule_decl => {
                 rules.push(rule_from_cst(ctx, node)?);
@@ -314,7 +342,7 @@ pub(crate) fn ast_from_cst<'src>(
             rule => unreachable!("unexpected grammar rule: `{:?}`", rule),
         }
     }
-    Ok((imports, rules))
+    Ok((imports, includes, rules))
 }
 
 /// Given a CST node corresponding to the grammar rule` rule_decl`, returns a
@@ -370,7 +398,8 @@ fn rule_from_cst<'src>(
     // └─ ident "baz"
     //
     let tags = if let GrammarRule::rule_tags = node.as_rule() {
-        let mut tags = HashSet::new();
+        let mut tags = Vec::new();
+        let mut seen = HashSet::new();
 
         // Iterate over all `ident`s that are children of `rule_tags`,
         // ignoring other grammar rules like `COLON`.
@@ -379,13 +408,14 @@ fn rule_from_cst<'src>(
             .filter(|item| item.as_rule() == GrammarRule::ident);
 
         for ident in idents {
-            if !tags.insert(ident.as_str()) {
+            if !seen.insert(ident.as_str()) {
                 return Err(Error::from(ErrorInfo::duplicate_tag(
                     ctx.report_builder,
                     ident.as_str().to_string(),
                     ctx.span(&ident),
                 )));
             }
+            tags.push(ident_from_cst(ctx, ident));
         }
 
         node = children.next().unwrap();
@@ -432,12 +462,29 @@ fn rule_from_cst<'src>(
     node = children.next().unwrap();
 
     // Any identifier left in ctx.unused_pattern is not being
-    // used in the condition.
-    for unused_pattern in ctx.unused_patterns.drain() {
-        let ident = ctx.declared_patterns.get(unused_pattern).unwrap();
-        // Pattern identifiers that start with underscore (e.g: `$_a`) are
-        // allowed to remain unused.
-        if !unused_pattern.starts_with('_') {
+    // used in the condition. Pattern identifiers that start with
+    // underscore (e.g: `$_a`) are allowed to remain unused.
+    let mut unused_patterns: Vec<Ident> = ctx
+        .unused_patterns
+        .drain()
+        .filter(|ident| !ident.starts_with('_'))
+        .map(|ident| ctx.declared_patterns.get(ident).unwrap().clone())
+        .collect();
+
+    // Sort the unused patterns by the order in which they were declared, so
+    // that they are reported in a predictable order.
+    unused_patterns.sort_by_key(|ident| ident.span.start());
+
+    for ident in unused_patterns {
+        if ctx.unused_patterns_as_warnings {
+            ctx.warnings.add(|| {
+                Warning::unused_pattern(
+                    ctx.report_builder,
+                    ident.name.to_string(),
+                    ident.span,
+                )
+            });
+        } else {
             return Err(Error::from(ErrorInfo::unused_pattern(
                 ctx.report_builder,
                 ident.name.to_string(),
@@ -867,7 +914,9 @@ fn meta_from_cst<'src>(
             GrammarRule::float_lit => {
                 MetaValue::Float(float_lit_from_cst(ctx, value_node)?)
             }
-            GrammarRule::string_lit => MetaValue::String(value_node.as_str()),
+            GrammarRule::string_lit => {
+                MetaValue::String(string_lit_from_cst(ctx, value_node, true)?)
+            }
             rule => unreachable!("{:?}", rule),
         };
 
@@ -1092,6 +1141,9 @@ fn boolean_term_from_cst<'src>(
         GrammarRule::for_expr => {
             for_expr_from_cst(ctx, children.next().unwrap())?
         }
+        GrammarRule::with_expr => {
+            with_expr_from_cst(ctx, children.next().unwrap())?
+        }
         _ => unreachable!(),
     };
 
@@ -1544,6 +1596,68 @@ fn for_expr_from_cst<'src>(
     Ok(expr)
 }
 
+/// From a CST node corresponding to the grammar rule `with_expr`, returns
+/// an [`Expr`] describing the `with` statement.
+fn with_expr_from_cst<'src>(
+    ctx: &mut Context<'src, '_>,
+    with_expr: CSTNode<'src>,
+) -> Result<Expr<'src>, Error> {
+    expect!(with_expr, GrammarRule::with_expr);
+
+    let span = ctx.span(&with_expr);
+    let mut children = with_expr.into_inner();
+
+    // The statement starts with the `with` keyword...
+    expect!(children.next().unwrap(), GrammarRule::k_WITH);
+
+    // ...followed by one or more declarations separated by commas, as in..
+    //
+    //   with foo = 1 + 1, bar = foo * 2 : ( ... )
+    //
+    let mut declarations = Vec::new();
+
+    for node in children.by_ref() {
+        match node.as_rule() {
+            GrammarRule::with_declaration => {
+                declarations.push(with_declaration_from_cst(ctx, node)?);
+            }
+            GrammarRule::COMMA => {}
+            GrammarRule::COLON => {
+                break;
+            }
+            rule => unreachable!("{:?}", rule),
+        }
+    }
+
+    expect!(children.next().unwrap(), GrammarRule::LPAREN);
+
+    let condition = boolean_expr_from_cst(ctx, children.next().unwrap())?;
+
+    expect!(children.next().unwrap(), GrammarRule::RPAREN);
+
+    Ok(Expr::With(Box::new(With { span, declarations, condition })))
+}
+
+/// From a CST node corresponding to the grammar rule `with_declaration`,
+/// returns a [`WithDeclaration`].
+fn with_declaration_from_cst<'src>(
+    ctx: &mut Context<'src, '_>,
+    with_declaration: CSTNode<'src>,
+) -> Result<WithDeclaration<'src>, Error> {
+    expect!(with_declaration, GrammarRule::with_declaration);
+
+    let span = ctx.span(&with_declaration);
+    let mut children = with_declaration.into_inner();
+
+    let identifier = ident_from_cst(ctx, children.next().unwrap());
+
+    expect!(children.next().unwrap(), GrammarRule::EQUAL);
+
+    let expression = expr_from_cst(ctx, children.next().unwrap())?;
+
+    Ok(WithDeclaration { span, identifier, expression })
+}
+
 fn anchor_from_cst<'src>(
     ctx: &mut Context<'src, '_>,
     mut iter: impl Iterator<Item = CSTNode<'src>>,
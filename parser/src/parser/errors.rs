@@ -311,6 +311,7 @@ impl ErrorInfo {
             Rule::k_GLOBAL => "`global`",
             Rule::k_IMPORT => "`import`",
             Rule::k_IN => "`in`",
+            Rule::k_INCLUDE => "`include`",
             Rule::k_META => "`meta`",
             Rule::k_NOCASE => "`nocase`",
             Rule::k_NONE => "`none`",
@@ -322,6 +323,7 @@ impl ErrorInfo {
             Rule::k_THEM => "`them`",
             Rule::k_TRUE => "`true`",
             Rule::k_WIDE => "`wide`",
+            Rule::k_WITH => "`with`",
             Rule::k_XOR => "`xor`",
 
             Rule::boolean_expr | Rule::boolean_term => "boolean expression",
@@ -402,6 +404,7 @@ impl ErrorInfo {
             | Rule::block_comment
             | Rule::single_line_comment
             | Rule::import_stmt
+            | Rule::include_stmt
             | Rule::ident_chars
             | Rule::pattern_count
             | Rule::pattern_offset
@@ -419,6 +422,8 @@ impl ErrorInfo {
             | Rule::func_call_expr
             | Rule::of_expr
             | Rule::for_expr
+            | Rule::with_expr
+            | Rule::with_declaration
             | Rule::iterable
             | Rule::quantifier
             | Rule::range
@@ -69,6 +69,12 @@ impl<'src> SourceCode<'src> {
         }
     }
 
+    /// Returns the string that describes the origin of the source code, if
+    /// it was set with [`SourceCode::with_origin`].
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
     /// Returns the source code as a `&str`.
     ///
     /// If the source code is not valid UTF-8 it will return an error.
@@ -118,6 +124,7 @@ impl<'src> From<&'src [u8]> for SourceCode<'src> {
 pub struct Parser<'a> {
     external_report_builder: Option<&'a ReportBuilder>,
     own_report_builder: ReportBuilder,
+    unused_patterns_as_warnings: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -138,6 +145,7 @@ impl<'a> Parser<'a> {
         Self {
             external_report_builder: None,
             own_report_builder: ReportBuilder::new(),
+            unused_patterns_as_warnings: false,
         }
     }
 
@@ -150,6 +158,17 @@ impl<'a> Parser<'a> {
         self
     }
 
+    /// Specifies whether patterns that are not used in the rule's condition
+    /// produce warnings instead of errors. The default setting is `false`,
+    /// which means that unused patterns are errors.
+    ///
+    /// In both cases, patterns with identifiers that start with underscore
+    /// (e.g. `$_a`) are allowed to remain unused.
+    pub fn unused_patterns_as_warnings(&mut self, yes: bool) -> &mut Self {
+        self.unused_patterns_as_warnings = yes;
+        self
+    }
+
     /// Builds the Abstract Syntax Tree (AST) for some YARA source code.
     ///
     /// `src` can be any type that implements [`Into<SourceCode>`], which
@@ -202,9 +221,18 @@ impl<'a> Parser<'a> {
 
         let mut ctx = Context::new(report_builder);
 
-        let (imports, rules) = ast_from_cst(&mut ctx, root.into_inner())?;
+        ctx.unused_patterns_as_warnings = self.unused_patterns_as_warnings;
+
+        let (imports, includes, rules) =
+            ast_from_cst(&mut ctx, root.into_inner())?;
 
-        Ok(AST { source: src, imports, rules, warnings: ctx.warnings })
+        Ok(AST {
+            source: src,
+            imports,
+            includes,
+            rules,
+            warnings: ctx.warnings,
+        })
     }
 
     /// Build the Concrete Syntax Tree (CST) for a YARA source.
@@ -117,6 +117,31 @@ impl ReportBuilder {
         self
     }
 
+    /// Returns the origin of the source code identified by `source_id`, if
+    /// it was registered with an origin.
+    pub fn origin(&self, source_id: SourceId) -> Option<String> {
+        self.cache.borrow().data.get(&source_id)?.origin.clone()
+    }
+
+    /// Returns the line and column that correspond to a byte offset within
+    /// the source code identified by `source_id`. Both line and column
+    /// numbers start at 1, and columns are counted in characters.
+    ///
+    /// Returns `None` if the source code is not registered or the offset is
+    /// not a valid position within the source code.
+    pub fn line_and_column(
+        &self,
+        source_id: SourceId,
+        offset: usize,
+    ) -> Option<(usize, usize)> {
+        let cache = self.cache.borrow();
+        let code = cache.data.get(&source_id)?.code.get(..offset)?;
+        let line_start = code.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+        let line = code.matches('\n').count() + 1;
+        let column = code[line_start..].chars().count() + 1;
+        Some((line, column))
+    }
+
     /// Creates a new error or warning report.
     pub fn create_report(
         &self,
@@ -7,7 +7,7 @@ use crate::report::ReportBuilder;
 
 /// A warning raised while parsing YARA rules.
 #[rustfmt::skip]
-#[derive(Error)]
+#[derive(Error, Eq, PartialEq)]
 pub enum Warning {
     #[warning("consecutive jumps in hex pattern `{pattern_ident}`")]
     #[label("these consecutive jumps will be treated as {coalesced_jump}", jumps_span)]
@@ -73,6 +73,14 @@ pub enum Warning {
         i_span: Span,
     },
 
+    #[warning("unused pattern `{pattern_ident}`")]
+    #[label("this pattern was not used in the condition", pattern_ident_span)]
+    UnusedPattern {
+        detailed_report: String,
+        pattern_ident: String,
+        pattern_ident_span: Span,
+    },
+
     #[warning("slow pattern")]
     #[label("this pattern may slow down the scan", span)]
     SlowPattern {
@@ -90,6 +98,24 @@ pub enum Warning {
         note: Option<String>,
     },
 
+    #[warning("invalid regular expression accepted in relaxed mode")]
+    #[label("{error}", span)]
+    #[note(note)]
+    RelaxedRegexp {
+        detailed_report: String,
+        error: String,
+        span: Span,
+        note: Option<String>,
+    },
+
+    #[warning("rule `{rule_ident}` never matches")]
+    #[label("this condition is always false", condition_span)]
+    DeadRule {
+        detailed_report: String,
+        rule_ident: String,
+        condition_span: Span,
+    },
+
     #[warning("rule `{ignored_rule}` will be ignored due to an indirect dependency on module `{module_name}`")]
     #[label("this other rule depends on module `{module_name}`, which is unsupported", span)]
     IgnoredRule {
@@ -105,11 +131,17 @@ pub enum Warning {
 pub struct Warnings {
     warnings: Vec<Warning>,
     max_warnings: usize,
+    /// Codes of the warnings that are silently discarded.
+    suppressed: Vec<String>,
 }
 
 impl Default for Warnings {
     fn default() -> Self {
-        Self { warnings: Vec::new(), max_warnings: 100 }
+        Self {
+            warnings: Vec::new(),
+            max_warnings: 100,
+            suppressed: Vec::new(),
+        }
     }
 }
 
@@ -127,10 +159,32 @@ impl Warnings {
     #[inline]
     pub fn add(&mut self, f: impl Fn() -> Warning) {
         if self.warnings.len() < self.max_warnings {
-            self.warnings.push(f());
+            let warning = f();
+            if !self.is_suppressed(&warning) {
+                self.warnings.push(warning);
+            }
         }
     }
 
+    /// Suppresses warnings with the given code (e.g: `slow_pattern`).
+    ///
+    /// Suppressed warnings are discarded when added to the list.
+    pub fn suppress<C: Into<String>>(&mut self, code: C) {
+        self.suppressed.push(code.into());
+    }
+
+    /// Returns true if the given warning has been suppressed with
+    /// [`Warnings::suppress`].
+    #[inline]
+    pub fn is_suppressed(&self, warning: &Warning) -> bool {
+        self.suppressed.iter().any(|code| code == warning.code())
+    }
+
+    /// Removes the warnings starting at index `at`, and returns them.
+    pub fn split_off(&mut self, at: usize) -> Vec<Warning> {
+        self.warnings.split_off(at)
+    }
+
     #[inline]
     pub fn as_slice(&self) -> &[Warning] {
         self.warnings.as_slice()
@@ -141,7 +195,9 @@ impl Warnings {
             if self.warnings.len() == self.max_warnings {
                 break;
             }
-            self.warnings.push(w)
+            if !self.is_suppressed(&w) {
+                self.warnings.push(w)
+            }
         }
     }
 }
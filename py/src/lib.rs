@@ -169,7 +169,7 @@ impl Compiler {
 /// rules. The same scanner can be used for scanning multiple files or
 /// in-memory data sequentially, but you need multiple scanners for scanning
 /// in parallel.
-#[pyclass(unsendable)]
+#[pyclass]
 struct Scanner {
     // The only purpose of this field is making sure that the `Rules` object
     // is not freed while the `Scanner` object is still around. This reference